@@ -1,5 +1,5 @@
 use abci::{Application, RequestCheckTx, RequestInitChain};
-use chain_abci::app::ChainNodeApp;
+use chain_abci::app::{ChainNodeApp, ChainNodeState};
 use chain_abci::storage::account::AccountStorage;
 use chain_abci::storage::account::AccountWrapper;
 use chain_abci::storage::tx::StarlingFixedKey;
@@ -9,6 +9,7 @@ use chain_core::compute_app_hash;
 use chain_core::init::config::AccountType;
 use chain_core::init::config::InitNetworkParameters;
 use chain_core::init::config::{InitialValidator, ValidatorKeyType};
+use chain_core::init::config::{RewardsParameters, SlashRatio, SlashingParameters};
 use chain_core::init::{address::RedeemAddress, coin::Coin, config::InitConfig};
 use chain_core::state::account::*;
 use chain_core::tx::fee::{LinearFee, Milli};
@@ -89,6 +90,17 @@ fn init_chain_for(addresses: &Vec<RedeemAddress>) -> ChainNodeApp {
         initial_fee_policy: LinearFee::new(Milli::new(1, 1), Milli::new(1, 1)),
         required_council_node_stake: remaining,
         unbonding_period: 1,
+        slashing_config: SlashingParameters {
+            jail_duration: 1,
+            byzantine_slash_ratio: SlashRatio::new(Milli::new(0, 200)).unwrap(),
+            liveness_slash_ratio: SlashRatio::new(Milli::new(0, 10)).unwrap(),
+            block_signing_window: 100,
+            missed_block_threshold: 50,
+        },
+        rewards_config: RewardsParameters {
+            epoch_length: 100,
+            reward_rate: Milli::new(0, 100),
+        },
     };
     let c = InitConfig::new(
         distribution,
@@ -101,10 +113,12 @@ fn init_chain_for(addresses: &Vec<RedeemAddress>) -> ChainNodeApp {
             consensus_pubkey_type: ValidatorKeyType::Ed25519,
             consensus_pubkey_b64: "MDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDA=".to_string(),
         }],
+        BTreeMap::new(),
+        Vec::new(),
     );
     let t = ::protobuf::well_known_types::Timestamp::new();
     let result = c.validate_config_get_genesis(t.get_seconds());
-    if let Ok((accounts, rp, _nodes)) = result {
+    if let Ok((accounts, rp, nodes)) = result {
         let tx_tree = MerkleTree::empty();
         let mut account_tree =
             AccountStorage::new(Storage::new_db(Arc::new(create(1))), 20).expect("account db");
@@ -121,7 +135,21 @@ fn init_chain_for(addresses: &Vec<RedeemAddress>) -> ChainNodeApp {
             )
             .expect("initial insert");
 
-        let genesis_app_hash = compute_app_hash(&tx_tree, &new_account_root, &rp);
+        let genesis_state = ChainNodeState::genesis(
+            [0u8; 32],
+            t.get_seconds(),
+            new_account_root,
+            rp,
+            c.network_params.clone(),
+            nodes,
+        );
+        let genesis_app_hash = compute_app_hash(
+            &tx_tree,
+            &new_account_root,
+            &genesis_state.rewards_pool,
+            &genesis_state.network_params,
+            &genesis_state.node_state_hash(),
+        );
 
         let example_hash = hex::encode_upper(genesis_app_hash);
         let mut app = ChainNodeApp::new_with_storage(
@@ -1,32 +1,48 @@
+use super::commit::update_utxos_commit;
+use super::peer_filter::PeerFilterFile;
+use super::snapshot::SnapshotConfig;
+use super::state_migration::migrate_app_state;
+use super::{ParamChangeProposal, ValidatorLiveness};
 use crate::enclave_bridge::EnclaveProxy;
+use crate::storage::account::migration::{migrate_accounts, ACCOUNT_STORAGE_VERSION};
+use crate::storage::account::persist_account_nodes;
 use crate::storage::account::AccountStorage;
 use crate::storage::account::AccountWrapper;
 use crate::storage::tx::get_account;
-use crate::storage::tx::StarlingFixedKey;
+use crate::storage::tx::{PendingState, StarlingFixedKey};
+use crate::storage::tx_migration::{migrate_tx_bodies, TX_STORAGE_VERSION};
 use crate::storage::*;
 use abci::*;
-use chain_core::common::MerkleTree;
+use blake2::Blake2s;
 use chain_core::common::Timespec;
-use chain_core::common::{H256, HASH_SIZE_256};
+use chain_core::common::{hash256, MerkleTree, H256, HASH_SIZE_256};
 use chain_core::compute_app_hash;
 use chain_core::init::address::RedeemAddress;
-use chain_core::init::coin::Coin;
+use chain_core::init::coin::{sum_coins, Coin};
 use chain_core::init::config::AccountType;
 use chain_core::init::config::InitConfig;
 use chain_core::init::config::InitNetworkParameters;
+use chain_core::init::config::{RewardsParameters, SlashingParameters};
 use chain_core::state::account::{StakedState, StakedStateAddress};
+use chain_core::state::governance::NetworkParameters;
+use chain_core::state::proof::AppHashParts;
 use chain_core::state::tendermint::{BlockHeight, TendermintVotePower};
 use chain_core::state::CouncilNode;
 use chain_core::state::RewardsPoolState;
-use chain_core::tx::{fee::LinearFee, TxAux};
-use chain_tx_filter::BlockFilter;
+use chain_core::tx::data::input::TxoIndex;
+use chain_core::tx::data::{Tx, TxId};
+use chain_core::tx::{TransactionId, TxAux};
+use chain_tx_filter::{BlockFilter, FilterFormat};
+use chain_tx_validation::TxWithOutputs;
 use enclave_protocol::{EnclaveRequest, EnclaveResponse};
-use kvdb::DBTransaction;
+use kvdb::{DBTransaction, KeyValueDB};
 use log::{info, warn};
 use parity_scale_codec::{Decode, Encode};
 use protobuf::{Message, RepeatedField};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
 
 /// ABCI app state snapshot
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Encode, Decode)]
@@ -41,14 +57,22 @@ pub struct ChainNodeState {
     pub last_account_root_hash: StarlingFixedKey,
     /// last rewards pool state
     pub rewards_pool: RewardsPoolState,
-    /// fee policy to apply -- TODO: change to be against T: FeeAlgorithm
-    pub fee_policy: LinearFee,
-    /// time when unbonded stake can be withdrawn
-    pub unbonding_period: u32,
-    /// (minimal?) amount required to be bonded in validator-associated accounts
-    pub required_council_node_stake: Coin,
+    /// fee policy, unbonding period and required council node stake (changeable by council node proposals)
+    pub network_params: NetworkParameters,
     /// council nodes metadata
     pub council_nodes: Vec<CouncilNode>,
+    /// punishments of council nodes for byzantine / liveness faults
+    pub slashing_config: SlashingParameters,
+    /// recently signed blocks of the active council nodes
+    pub validator_liveness: BTreeMap<StakedStateAddress, ValidatorLiveness>,
+    /// distribution of the rewards pool to council nodes
+    pub rewards_config: RewardsParameters,
+    /// voting power of the blocks signed by council nodes in the current epoch
+    pub rewards_weights: BTreeMap<StakedStateAddress, u64>,
+    /// stake delegated to council nodes (council node -> delegator -> delegated amount)
+    pub delegations: BTreeMap<StakedStateAddress, BTreeMap<StakedStateAddress, Coin>>,
+    /// pending network parameter changes (by the proposal transaction ID)
+    pub param_change_proposals: BTreeMap<TxId, ParamChangeProposal>,
 }
 
 impl ChainNodeState {
@@ -66,12 +90,60 @@ impl ChainNodeState {
             block_time: genesis_time,
             last_account_root_hash,
             rewards_pool,
-            fee_policy: network_params.initial_fee_policy,
-            unbonding_period: network_params.unbonding_period,
-            required_council_node_stake: network_params.required_council_node_stake,
+            network_params: NetworkParameters::from(&network_params),
             council_nodes,
+            slashing_config: network_params.slashing_config,
+            validator_liveness: BTreeMap::new(),
+            rewards_config: network_params.rewards_config,
+            rewards_weights: BTreeMap::new(),
+            delegations: BTreeMap::new(),
+            param_change_proposals: BTreeMap::new(),
         }
     }
+
+    /// total stake delegated to the council node
+    pub fn delegated_stake(&self, council_node: &StakedStateAddress) -> Coin {
+        self.delegations
+            .get(council_node)
+            .map_or(Coin::zero(), |delegators| {
+                sum_coins(delegators.values().cloned()).expect("should not be over the max supply")
+            })
+    }
+
+    /// records the amount currently delegated by the delegator to the council node (zero removes the delegation)
+    pub fn set_delegation(
+        &mut self,
+        council_node: StakedStateAddress,
+        delegator: StakedStateAddress,
+        amount: Coin,
+    ) {
+        if amount == Coin::zero() {
+            if let Some(delegators) = self.delegations.get_mut(&council_node) {
+                delegators.remove(&delegator);
+                if delegators.is_empty() {
+                    self.delegations.remove(&council_node);
+                }
+            }
+        } else {
+            self.delegations
+                .entry(council_node)
+                .or_insert_with(BTreeMap::new)
+                .insert(delegator, amount);
+        }
+    }
+
+    /// hash of the consensus state that isn't committed to by the other app hash components
+    /// (see `AppHashParts::node_state_hash`)
+    pub fn node_state_hash(&self) -> H256 {
+        let mut bs = self.council_nodes.encode();
+        self.slashing_config.encode_to(&mut bs);
+        self.validator_liveness.encode_to(&mut bs);
+        self.rewards_config.encode_to(&mut bs);
+        self.rewards_weights.encode_to(&mut bs);
+        self.delegations.encode_to(&mut bs);
+        self.param_change_proposals.encode_to(&mut bs);
+        hash256::<Blake2s>(&bs)
+    }
 }
 
 /// The global ABCI state
@@ -82,10 +154,20 @@ pub struct ChainNodeApp<T: EnclaveProxy> {
     pub accounts: AccountStorage,
     /// valid transactions after DeliverTx before EndBlock/Commit
     pub delivered_txs: Vec<TxAux>,
+    /// inputs spent and staked states updated by the transactions accepted in CheckTx since the last commit
+    pub mempool_state: PendingState,
+    /// inputs spent by the transactions delivered in the current block (they are marked as spent in Commit)
+    pub delivered_state: PendingState,
     /// current block filter
     pub filter: BlockFilter,
     /// root hash of the sparse merkle patricia trie of staking account states after DeliverTx before EndBlock/Commit
     pub uncommitted_account_root_hash: StarlingFixedKey,
+    /// account trie roots inserted in the current block (the replaced ones are pruned in Commit)
+    pub uncommitted_account_roots: Vec<StarlingFixedKey>,
+    /// the number of blocks the replaced committed account trie roots are retained for (all are retained if None)
+    pub account_root_retention: Option<BlockHeight>,
+    /// the number of the last committed blocks whose undo journal entries are kept (none are recorded if None)
+    pub undo_depth: Option<BlockHeight>,
     /// a reference to genesis (used when there is no committed state)
     pub genesis_app_hash: H256,
     /// last two hex digits in chain_id
@@ -98,11 +180,26 @@ pub struct ChainNodeApp<T: EnclaveProxy> {
     pub validator_pubkeys: BTreeMap<StakedStateAddress, PubKey>,
     /// validator addresses whose bonded amount changed in the current block
     pub power_changed_in_block: BTreeMap<StakedStateAddress, TendermintVotePower>,
+    /// whether a network parameter change was activated in the current block
+    pub network_params_changed_in_block: bool,
+    /// the node state hash committed to by the last app hash (see `ChainNodeState::node_state_hash`)
+    pub committed_node_state_hash: H256,
+    /// whether the last committed app hash isn't the one computed from the last app state
+    /// (e.g. after its account trie was migrated to a newer encoding), so it's recomputed in the next commit
+    pub app_hash_outdated: bool,
     /// proxy for processing transaction validation requests
     pub tx_validator: T,
+    /// periodic snapshots of the committed state (if enabled)
+    pub snapshot_config: Option<SnapshotConfig>,
+    /// set while a snapshot of the committed state is written in the background
+    pub snapshot_in_progress: Arc<AtomicBool>,
+    /// expired account trie roots whose pruning is deferred until the snapshot being written is finished
+    pub deferred_account_roots: Vec<StarlingFixedKey>,
+    /// rules for the peers Tendermint connects to (all peers are allowed if None)
+    pub peer_filter: Option<Mutex<PeerFilterFile>>,
 }
 
-fn get_validator_key(node: &CouncilNode) -> PubKey {
+pub fn get_validator_key(node: &CouncilNode) -> PubKey {
     let mut pk = PubKey::new();
     let (keytype, key) = node.consensus_pubkey.to_validator_update();
     pk.set_field_type(keytype);
@@ -128,15 +225,20 @@ fn get_validator_mapping(
             accounts,
         )
         .expect("council node staking account should be in the account state");
-        if account.bonded < last_app_state.required_council_node_stake {
+        if account.is_jailed()
+            || account.bonded < last_app_state.network_params.required_council_node_stake
+        {
             validator_voting_power.insert(
                 node.staking_account_address,
                 TendermintVotePower::from(Coin::zero()),
             );
         } else {
+            let delegated = last_app_state.delegated_stake(&node.staking_account_address);
             validator_voting_power.insert(
                 node.staking_account_address,
-                TendermintVotePower::from(account.bonded),
+                TendermintVotePower::from(
+                    (account.bonded + delegated).expect("should not be over the max supply"),
+                ),
             );
         }
     }
@@ -180,27 +282,159 @@ fn get_voting_power(
     }
 }
 
-fn store_valid_genesis_state(
-    genesis_time: Timespec,
-    genesis_app_hash: H256,
-    rewards_pool: RewardsPoolState,
-    network_params: InitNetworkParameters,
-    last_account_root_hash: StarlingFixedKey,
-    council_nodes: Vec<CouncilNode>,
-    inittx: &mut DBTransaction,
-) -> ChainNodeState {
-    let last_state = ChainNodeState::genesis(
-        genesis_app_hash,
-        genesis_time,
-        last_account_root_hash,
-        rewards_pool,
-        network_params,
-        council_nodes,
-    );
+fn store_valid_genesis_state(last_state: &ChainNodeState, inittx: &mut DBTransaction) {
     let encoded = last_state.encode();
+    inittx.put(
+        COL_NODE_INFO,
+        &app_state_key(last_state.last_block_height),
+        &encoded,
+    );
     inittx.put(COL_NODE_INFO, LAST_STATE_KEY, &encoded);
     inittx.put(COL_EXTRA, b"init_chain_state", &encoded);
-    last_state
+}
+
+/// Stores the synthetic genesis transaction (body + unspent outputs)
+/// and passes it to the enclave, so that the initial UTXOs can be spent
+/// (together with the genesis app hash components, as the enclave only accepts the committed genesis transaction)
+fn store_genesis_tx<T: EnclaveProxy>(
+    tx_validator: &mut T,
+    db: Arc<dyn KeyValueDB>,
+    tx: Tx,
+    account_root: H256,
+    app_hash_parts: AppHashParts,
+    inittx: &mut DBTransaction,
+) {
+    let txid = tx.id();
+    inittx.put(
+        COL_BODIES,
+        &txid[..],
+        &TxWithOutputs::Transfer(tx.clone()).encode(),
+    );
+    update_utxos_commit(&[], tx.outputs.len() as TxoIndex, txid, db, inittx);
+    // the only transaction in the genesis transaction tree
+    inittx.put(COL_TX_INCLUSION, &txid[..], &(0i64, 0u32).encode());
+    match tx_validator.process_request(EnclaveRequest::StoreGenesisTx {
+        tx,
+        account_root,
+        app_hash_parts,
+    }) {
+        EnclaveResponse::StoreGenesisTx(Ok(_)) => {
+            info!("genesis transaction stored: {}", hex::encode(&txid[..]));
+        }
+        _ => {
+            panic!("storing the genesis transaction in the enclave failed");
+        }
+    }
+}
+
+/// Migrates the staked states in the last committed account trie to the current encoding
+/// if the storage was created by an older version
+/// (as the next app hash is computed from the migrated trie root, all nodes need to upgrade at the same height;
+/// the last committed app hash is left as it is, but it's recomputed in the next commit, see `recompute_app_hash`)
+fn migrate_account_storage(storage: &Storage, account_db: &Storage, accounts: &mut AccountStorage) {
+    let stored_version = storage
+        .db
+        .get(COL_NODE_INFO, ACCOUNT_STORAGE_VERSION_KEY)
+        .expect("account storage version lookup")
+        .map_or(0, |version| version[0]);
+    if stored_version >= ACCOUNT_STORAGE_VERSION {
+        return;
+    }
+    let mut inittx = storage.db.transaction();
+    if let Some(last_app_state) = storage
+        .db
+        .get(COL_NODE_INFO, LAST_STATE_KEY)
+        .expect("app state lookup")
+    {
+        info!(
+            "migrating account storage from version {} to {}",
+            stored_version, ACCOUNT_STORAGE_VERSION
+        );
+        let data = last_app_state.to_vec();
+        let mut last_state =
+            ChainNodeState::decode(&mut data.as_slice()).expect("deserialize app state");
+        last_state.last_account_root_hash = migrate_accounts(
+            account_db,
+            accounts,
+            &last_state.last_account_root_hash,
+            stored_version,
+        )
+        .expect("account storage migration");
+        persist_account_nodes(accounts).expect("migrated account trie should be stored");
+        // the states committed at the earlier heights keep the account trie roots of the older encoding
+        let encoded = last_state.encode();
+        inittx.put(
+            COL_NODE_INFO,
+            &app_state_key(last_state.last_block_height),
+            &encoded,
+        );
+        inittx.put(COL_NODE_INFO, LAST_STATE_KEY, &encoded);
+    }
+    inittx.put(
+        COL_NODE_INFO,
+        ACCOUNT_STORAGE_VERSION_KEY,
+        &[ACCOUNT_STORAGE_VERSION],
+    );
+    storage
+        .db
+        .write(inittx)
+        .expect("account storage version should be stored");
+}
+
+/// Migrates the stored transaction bodies to the current transaction encoding
+/// if the storage was created before the transaction format was versioned
+/// (it doesn't change the app hash, so nodes can upgrade it independently)
+fn migrate_tx_storage(storage: &Storage) {
+    let stored_version = storage
+        .db
+        .get(COL_NODE_INFO, TX_STORAGE_VERSION_KEY)
+        .expect("transaction storage version lookup")
+        .map_or(0, |version| version[0]);
+    if stored_version >= TX_STORAGE_VERSION {
+        return;
+    }
+    let migrated = migrate_tx_bodies(storage).expect("transaction storage migration");
+    info!(
+        "migrated {} transaction bodies to storage version {}",
+        migrated, TX_STORAGE_VERSION
+    );
+}
+
+/// Migrates the node storage and the account trie storage created by an older version to the current encodings
+///
+/// # Arguments
+///
+/// * `storage` - the node storage
+/// * `account_db` - the database underlying `accounts`
+/// * `accounts` - the account trie storage
+fn migrate_storage(storage: &Storage, account_db: &Storage, accounts: &mut AccountStorage) {
+    if migrate_app_state(storage).expect("app state migration") {
+        info!("migrated the last app state to the current encoding");
+    }
+    migrate_account_storage(storage, account_db, accounts);
+    migrate_tx_storage(storage);
+}
+
+/// Recomputes the app hash from the app state and the transaction tree stored for its app hash
+/// (it differs from the committed one if the app state was migrated since)
+fn recompute_app_hash(storage: &Storage, state: &ChainNodeState) -> H256 {
+    // the tree isn't stored if there were no transactions before (e.g. the genesis without any UTXOs)
+    let tree = match storage
+        .db
+        .get(COL_MERKLE_PROOFS, &state.last_apphash[..])
+        .expect("transaction tree lookup")
+    {
+        Some(encoded) => MerkleTree::decode(&mut encoded.to_vec().as_slice())
+            .expect("deserialize transaction tree"),
+        None => MerkleTree::new(Vec::new()),
+    };
+    compute_app_hash(
+        &tree,
+        &state.last_account_root_hash,
+        &state.rewards_pool,
+        &state.network_params,
+        &state.node_state_hash(),
+    )
 }
 
 impl<T: EnclaveProxy> ChainNodeApp<T> {
@@ -240,26 +474,44 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
         let chain_hex_id = hex::decode(&chain_id[chain_id.len() - 2..])
             .expect("failed to decode two last hex digits in chain ID")[0];
 
+        let app_hash_outdated =
+            recompute_app_hash(&storage, &last_app_state) != last_app_state.last_apphash;
+        if app_hash_outdated {
+            info!("the last app state was migrated: its app hash will be recomputed in the next commit");
+        }
         let (validator_voting_power, validator_pubkeys) =
             get_validator_mapping(&accounts, &last_app_state);
         ChainNodeApp {
             storage,
             accounts,
             delivered_txs: Vec::new(),
+            mempool_state: PendingState::default(),
+            delivered_state: PendingState::default(),
             filter: BlockFilter::default(),
             uncommitted_account_root_hash: last_app_state.last_account_root_hash,
+            uncommitted_account_roots: Vec::new(),
+            account_root_retention: None,
+            undo_depth: None,
             chain_hex_id,
             genesis_app_hash,
             last_state: Some(last_app_state),
             validator_voting_power,
             validator_pubkeys,
             power_changed_in_block: BTreeMap::new(),
+            network_params_changed_in_block: false,
+            committed_node_state_hash: last_app_state.node_state_hash(),
+            app_hash_outdated,
             tx_validator,
+            snapshot_config: None,
+            snapshot_in_progress: Arc::new(AtomicBool::new(false)),
+            deferred_account_roots: Vec::new(),
+            peer_filter: None,
         }
     }
 
     /// Creates a new App initialized with a given storage (could be in-mem or persistent).
-    /// If persistent storage is used, it'll try to recove stored arguments (e.g. last app hash / block height) from it.
+    /// If persistent storage is used, it'll try to recove stored arguments (e.g. last app hash / block height) from it
+    /// (the storage needs to be in the current encoding, i.e. migrated as in `new`).
     ///
     /// # Arguments
     ///
@@ -290,9 +542,9 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
             let data = last_app_state.to_vec();
             let last_state =
                 ChainNodeState::decode(&mut data.as_slice()).expect("deserialize app state");
-            // TODO: genesis app hash check when embedded in enclave binary
             let enclave_sanity_check = tx_validator.process_request(EnclaveRequest::CheckChain {
                 chain_hex_id,
+                genesis_app_hash,
                 last_app_hash: Some(last_state.last_apphash),
             });
             match enclave_sanity_check {
@@ -316,9 +568,9 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
             )
         } else {
             info!("no last app state stored");
-            // TODO: genesis app hash check when embedded in enclave binary
             let enclave_sanity_check = tx_validator.process_request(EnclaveRequest::CheckChain {
                 chain_hex_id,
+                genesis_app_hash,
                 last_app_hash: None,
             });
             match enclave_sanity_check {
@@ -342,15 +594,27 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
                 storage,
                 accounts,
                 delivered_txs: Vec::new(),
+                mempool_state: PendingState::default(),
+                delivered_state: PendingState::default(),
                 filter: BlockFilter::default(),
                 uncommitted_account_root_hash: [0u8; 32],
+                uncommitted_account_roots: Vec::new(),
+                account_root_retention: None,
+                undo_depth: None,
                 chain_hex_id,
                 genesis_app_hash,
                 last_state: None,
                 validator_voting_power: BTreeMap::new(),
                 validator_pubkeys: BTreeMap::new(),
                 power_changed_in_block: BTreeMap::new(),
+                network_params_changed_in_block: false,
+                committed_node_state_hash: [0u8; 32],
+                app_hash_outdated: false,
                 tx_validator,
+                snapshot_config: None,
+                snapshot_in_progress: Arc::new(AtomicBool::new(false)),
+                deferred_account_roots: Vec::new(),
+                peer_filter: None,
             }
         }
     }
@@ -371,13 +635,43 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
         node_storage_config: &StorageConfig<'_>,
         account_storage_config: &StorageConfig<'_>,
     ) -> ChainNodeApp<T> {
-        ChainNodeApp::new_with_storage(
-            tx_validator,
-            gah,
-            chain_id,
-            Storage::new(node_storage_config),
-            AccountStorage::new(Storage::new(account_storage_config), 20).expect("account db"),
-        )
+        let storage = Storage::new(node_storage_config);
+        let account_db = Storage::new(account_storage_config);
+        let mut accounts =
+            AccountStorage::new(Storage::new_db(account_db.db.clone()), 20).expect("account db");
+        migrate_storage(&storage, &account_db, &mut accounts);
+        ChainNodeApp::new_with_storage(tx_validator, gah, chain_id, storage, accounts)
+    }
+
+    /// Sets the format of the block filters emitted in the end block events (ethbloom by default)
+    pub fn with_block_filter_format(mut self, format: FilterFormat) -> Self {
+        self.filter = BlockFilter::new(format);
+        self
+    }
+
+    /// Enables writing snapshots of the committed state (written in the background, see `snapshot::spawn_snapshot_writer`)
+    pub fn with_snapshots(mut self, config: SnapshotConfig) -> Self {
+        self.snapshot_config = Some(config);
+        self
+    }
+
+    /// Sets the number of blocks the replaced committed account trie roots are retained for
+    /// (older roots are pruned, so the account states at the earlier heights can't be queried)
+    pub fn with_account_root_retention(mut self, blocks: BlockHeight) -> Self {
+        self.account_root_retention = Some(blocks);
+        self
+    }
+
+    /// Sets the number of the last committed blocks that can be rolled back (see `rollback`)
+    pub fn with_undo_depth(mut self, blocks: BlockHeight) -> Self {
+        self.undo_depth = Some(blocks);
+        self
+    }
+
+    /// Enables filtering of the peers Tendermint connects to (see `peer_filter`)
+    pub fn with_peer_filter(mut self, filter: PeerFilterFile) -> Self {
+        self.peer_filter = Some(Mutex::new(filter));
+        self
     }
 
     /// Handles InitChain requests:
@@ -401,7 +695,7 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
                 );
             }
 
-            let tx_tree = MerkleTree::empty();
+            let tx_tree = conf.get_genesis_tx_tree();
 
             let mut keys: Vec<StarlingFixedKey> = accounts.iter().map(StakedState::key).collect();
             // TODO: get rid of the extra allocations
@@ -412,12 +706,39 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
                 .insert(None, &mut keys, &wrapped)
                 .expect("initial insert");
 
-            let genesis_app_hash = compute_app_hash(&tx_tree, &new_account_root, &rp);
+            // the app hash is set below (the genesis app state is committed to by its node state hash)
+            let mut last_state = ChainNodeState::genesis(
+                [0u8; 32],
+                genesis_time,
+                new_account_root,
+                rp,
+                conf.network_params.clone(),
+                nodes.clone(),
+            );
+            let app_hash_parts = AppHashParts::new(
+                &tx_tree,
+                &last_state.rewards_pool,
+                &last_state.network_params,
+                &last_state.node_state_hash(),
+            );
+            let genesis_app_hash = app_hash_parts.app_hash(&new_account_root);
+            last_state.last_apphash = genesis_app_hash;
             if self.genesis_app_hash != genesis_app_hash {
                 panic!("initchain resulting genesis app hash: {:?} does not match the expected genesis app hash: {:?}", genesis_app_hash, self.genesis_app_hash);
             }
 
             let mut inittx = db.transaction();
+            if let Some(tx) = conf.get_genesis_tx() {
+                store_genesis_tx(
+                    &mut self.tx_validator,
+                    db.clone(),
+                    tx,
+                    new_account_root,
+                    app_hash_parts,
+                    &mut inittx,
+                );
+                inittx.put(COL_MERKLE_PROOFS, &genesis_app_hash[..], &tx_tree.encode());
+            }
             check_and_store_consensus_params(
                 _req.consensus_params.as_ref(),
                 &nodes,
@@ -430,6 +751,8 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
                 let mut validator = ValidatorUpdate::default();
                 let power = get_voting_power(&conf.distribution, &node.staking_account_address);
                 validator.set_power(power.into());
+                self.validator_voting_power
+                    .insert(node.staking_account_address, power);
                 let pk = get_validator_key(&node);
                 self.validator_pubkeys
                     .insert(node.staking_account_address, pk.clone());
@@ -438,21 +761,15 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
             }
             let mut resp = ResponseInitChain::new();
             resp.set_validators(RepeatedField::from(validators));
-            let last_state = store_valid_genesis_state(
-                genesis_time,
-                genesis_app_hash,
-                rp,
-                conf.network_params,
-                new_account_root,
-                nodes,
-                &mut inittx,
-            );
+            store_valid_genesis_state(&last_state, &mut inittx);
 
+            persist_account_nodes(&self.accounts).expect("genesis account trie should be stored");
             let wr = db.write(inittx);
             if wr.is_err() {
                 panic!("db write error: {}", wr.err().unwrap());
             } else {
                 self.uncommitted_account_root_hash = last_state.last_account_root_hash;
+                self.committed_node_state_hash = last_state.node_state_hash();
                 self.last_state = Some(last_state);
             }
 
@@ -465,3 +782,172 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::super::snapshot::{restore_snapshot, write_snapshot};
+    use super::super::state_migration::APP_STATE_VERSION;
+    use super::*;
+    use crate::enclave_bridge::mock::MockClient;
+    use crate::storage::account::tree::HashTree;
+    use chain_core::init::config::SlashRatio;
+    use chain_core::state::account::{to_stake_key, UnbondingEntry};
+    use chain_core::state::proof::AccountProof;
+    use chain_core::tx::fee::{LinearFee, Milli};
+    use kvdb_memorydb::create;
+    use starling::traits::{Decode as TreeDecode, Encode as TreeEncode, Exception};
+    use std::io::Cursor;
+
+    /// a staked state in the encoding of the initial release
+    struct StakedStateV0Bytes(Vec<u8>);
+
+    impl TreeEncode for StakedStateV0Bytes {
+        fn encode(&self) -> Result<Vec<u8>, Exception> {
+            Ok(self.0.clone())
+        }
+    }
+
+    impl TreeDecode for StakedStateV0Bytes {
+        fn decode(buffer: &[u8]) -> Result<Self, Exception> {
+            Ok(StakedStateV0Bytes(buffer.to_vec()))
+        }
+    }
+
+    /// stores the app state and the account trie of a node created by the initial release
+    fn store_initial_release_state(storage: &Storage, account_db: &Storage) -> ChainNodeState {
+        let address = StakedStateAddress::BasicRedeem(RedeemAddress::from([0x11; 20]));
+        // nonce, bonded, unbonded, unbonded_from, address
+        let mut encoded = Vec::new();
+        encoded.extend_from_slice(&1u64.to_le_bytes());
+        encoded.extend_from_slice(&100u64.to_le_bytes());
+        encoded.extend_from_slice(&20u64.to_le_bytes());
+        encoded.extend_from_slice(&1_000u64.to_le_bytes());
+        encoded.push(0);
+        encoded.extend_from_slice(&[0x11; 20]);
+        let mut old_tree: HashTree<StakedStateV0Bytes, Storage> =
+            HashTree::new(Storage::new_db(account_db.db.clone()), 20).expect("account db");
+        let old_root = old_tree
+            .insert(
+                None,
+                &mut [to_stake_key(&address)],
+                &[StakedStateV0Bytes(encoded)],
+            )
+            .expect("insert");
+
+        let no_slashing = SlashRatio::new(Milli::integral(0)).expect("zero slash ratio");
+        let mut state = ChainNodeState {
+            last_block_height: 0,
+            last_apphash: [0u8; 32],
+            block_time: 0,
+            last_account_root_hash: old_root,
+            rewards_pool: RewardsPoolState::new(Coin::unit(), 0),
+            network_params: NetworkParameters {
+                fee_policy: LinearFee::new(Milli::new(1, 1), Milli::new(1, 1)),
+                unbonding_period: 1,
+                required_council_node_stake: Coin::unit(),
+            },
+            council_nodes: vec![],
+            slashing_config: SlashingParameters {
+                jail_duration: 0,
+                byzantine_slash_ratio: no_slashing,
+                liveness_slash_ratio: no_slashing,
+                block_signing_window: 100,
+                missed_block_threshold: 99,
+            },
+            validator_liveness: BTreeMap::new(),
+            rewards_config: RewardsParameters {
+                epoch_length: 1,
+                reward_rate: Milli::integral(0),
+            },
+            rewards_weights: BTreeMap::new(),
+            delegations: BTreeMap::new(),
+            param_change_proposals: BTreeMap::new(),
+        };
+        state.last_apphash = recompute_app_hash(storage, &state);
+        let mut inittx = storage.db.transaction();
+        inittx.put(COL_NODE_INFO, GENESIS_APP_HASH_KEY, &state.last_apphash);
+        inittx.put(COL_EXTRA, CHAIN_ID_KEY, b"test-00");
+        inittx.put(COL_NODE_INFO, APP_STATE_VERSION_KEY, &[APP_STATE_VERSION]);
+        inittx.put(COL_NODE_INFO, LAST_STATE_KEY, &state.encode());
+        storage.db.write(inittx).expect("write");
+        state
+    }
+
+    #[test]
+    fn app_hash_should_be_recomputed_after_account_storage_migration() {
+        let storage = Storage::new_db(Arc::new(create(NUM_COLUMNS.unwrap())));
+        let account_db = Storage::new_db(Arc::new(create(1)));
+        let old_state = store_initial_release_state(&storage, &account_db);
+        let mut accounts =
+            AccountStorage::new(Storage::new_db(account_db.db.clone()), 20).expect("account db");
+        migrate_storage(&storage, &account_db, &mut accounts);
+
+        let mut app = ChainNodeApp::new_with_storage(
+            MockClient::new(0),
+            &hex::encode(old_state.last_apphash),
+            "test-00",
+            storage,
+            accounts,
+        );
+        let migrated_state = app.last_state.clone().expect("app state");
+        assert_ne!(
+            old_state.last_account_root_hash,
+            migrated_state.last_account_root_hash
+        );
+        // the migrated height keeps the committed app hash
+        assert_eq!(old_state.last_apphash, migrated_state.last_apphash);
+        assert!(app.app_hash_outdated);
+
+        let mut bbreq = RequestBeginBlock::default();
+        let mut header = Header::default();
+        header.set_height(1);
+        header.set_time(::protobuf::well_known_types::Timestamp::new());
+        bbreq.set_header(header);
+        app.begin_block(&bbreq);
+        let mut ebreq = RequestEndBlock::default();
+        ebreq.set_height(1);
+        app.end_block(&ebreq);
+        let cresp = app.commit(&RequestCommit::default());
+
+        let state = app.last_state.clone().expect("app state");
+        assert!(!app.app_hash_outdated);
+        assert_ne!(old_state.last_apphash, state.last_apphash);
+        assert_eq!(&state.last_apphash[..], &cresp.data[..]);
+        assert_eq!(recompute_app_hash(&app.storage, &state), state.last_apphash);
+
+        // the migrated state is verified against the recomputed app hash when it's restored from a snapshot
+        let mut snapshot = Vec::new();
+        write_snapshot(&mut snapshot, &app.storage, &app.accounts).expect("snapshot");
+        let mut restored_accounts =
+            AccountStorage::new(Storage::new_db(Arc::new(create(1))), 20).expect("account db");
+        let restored = restore_snapshot(
+            Cursor::new(&snapshot),
+            &Storage::new_db(Arc::new(create(NUM_COLUMNS.unwrap()))),
+            &mut restored_accounts,
+            "test-00",
+            &old_state.last_apphash,
+            &state.last_apphash,
+        )
+        .expect("restore");
+        assert_eq!(state, restored);
+
+        // and the migrated staked states are proven against it
+        let mut qreq = RequestQuery::new();
+        qreq.data = vec![0x11; 20];
+        qreq.path = "account".into();
+        qreq.prove = true;
+        let qresp = app.query(&qreq);
+        assert_eq!(0, qresp.code);
+        let account = StakedState::decode(&mut qresp.value.as_slice()).expect("account");
+        assert_eq!(
+            account.unbonding,
+            vec![UnbondingEntry {
+                amount: Coin::new(20).unwrap(),
+                release_time: 1_000,
+            }]
+        );
+        let proof = AccountProof::decode(&mut qresp.get_proof().get_ops()[0].get_data())
+            .expect("account proof");
+        assert!(proof.verify(&account, &state.last_apphash));
+    }
+}
@@ -1,22 +1,25 @@
+use super::snapshot::spawn_snapshot_writer;
 use super::ChainNodeApp;
 use crate::app::spend_utxos;
 use crate::enclave_bridge::EnclaveProxy;
+use crate::storage::account::persist_account_nodes;
 use crate::storage::*;
 use abci::*;
 use bit_vec::BitVec;
 use chain_core::common::MerkleTree;
 use chain_core::compute_app_hash;
+use chain_core::state::tendermint::BlockHeight;
 use chain_core::tx::data::input::{TxoIndex, TxoPointer};
 use chain_core::tx::data::TxId;
 use chain_core::tx::PlainTxAux;
 use chain_core::tx::TxAux;
-use chain_core::tx::TxObfuscated;
 use chain_tx_validation::TxWithOutputs;
 use enclave_protocol::{EnclaveRequest, EnclaveResponse};
 use integer_encoding::VarInt;
 use kvdb::{DBTransaction, KeyValueDB};
-use log::debug;
-use parity_scale_codec::{Decode, Encode};
+use log::{debug, info, warn};
+use parity_scale_codec::Encode;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 /// Given a db and a DB transaction, it will go through TX inputs and mark them as spent
@@ -40,28 +43,50 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
     /// Commits delivered TX: flushes updates to the underlying storage
     pub fn commit_handler(&mut self, _req: &RequestCommit) -> ResponseCommit {
         let orig_state = self.last_state.clone();
-        let mut new_state = orig_state.expect("executing block commit, but no app state stored (i.e. no initchain or recovery was executed)");
+        let previous_state = orig_state.expect("executing block commit, but no app state stored (i.e. no initchain or recovery was executed)");
+        let mut new_state = previous_state.clone();
         let mut resp = ResponseCommit::new();
         let mut inittx = self.storage.db.transaction();
-        if !self.delivered_txs.is_empty() {
+        let node_state_hash = new_state.node_state_hash();
+        // staked states may also be updated without transactions (e.g. slashed in begin_block),
+        // network parameters by activated proposals in end_block, the account trie by a storage migration
+        // and the rest of the node state e.g. by the signatures of the last block
+        if !self.delivered_txs.is_empty()
+            || self.uncommitted_account_root_hash != new_state.last_account_root_hash
+            || self.network_params_changed_in_block
+            || self.app_hash_outdated
+            || self.committed_node_state_hash != node_state_hash
+        {
             let ids: Vec<TxId> = self
                 .delivered_txs
                 .iter()
                 .map(chain_core::tx::TxAux::tx_id)
                 .collect();
+            // all inputs spent in the block are marked at once
+            // (several transactions may spend the outputs of the same transaction)
+            let mut spent_inputs = Vec::new();
             let tree = MerkleTree::new(ids);
-            for txaux in self.delivered_txs.iter() {
+            for (index, txaux) in self.delivered_txs.iter().enumerate() {
                 let txid: TxId = txaux.tx_id();
+                inittx.put(
+                    COL_TX_INCLUSION,
+                    &txid[..],
+                    &(new_state.last_block_height, index as u32).encode(),
+                );
                 match &txaux {
                     TxAux::TransferTx {
                         inputs,
                         no_of_outputs,
-                        payload: TxObfuscated { txpayload, .. },
+                        payload,
                         ..
                     } => {
                         // FIXME: temporary hack / this shouldn't be here
-                        let plain_tx = PlainTxAux::decode(&mut txpayload.as_slice());
-                        if let Ok(PlainTxAux::TransferTx(tx, witness)) = plain_tx {
+                        let plain_tx = self.tx_validator.decrypt_payload(
+                            &txid,
+                            payload,
+                            new_state.last_block_height,
+                        );
+                        if let Some(PlainTxAux::TransferTx(tx, witness)) = plain_tx {
                             inittx.put(
                                 COL_BODIES,
                                 &txid[..],
@@ -69,19 +94,17 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
                             );
                             inittx.put(COL_WITNESS, &txid[..], &witness.encode());
                         }
-                        update_utxos_commit(
-                            &inputs,
-                            *no_of_outputs,
-                            txid,
-                            self.storage.db.clone(),
-                            &mut inittx,
+                        spent_inputs.extend(inputs.iter().cloned());
+                        inittx.put(
+                            COL_TX_META,
+                            &txid[..],
+                            &BitVec::from_elem(*no_of_outputs as usize, false).to_bytes(),
                         );
                     }
                     TxAux::DepositStakeTx { tx, .. } => {
                         inittx.put(COL_BODIES, &txid[..], &tx.encode());
                         // witness is obfuscated -- TODO: could be stored on the enclave side or thrown away?
-                        // this is not necessary (as they are spent in deliver_tx) and more of a sanity check (as update_utxos_commit does it)
-                        spend_utxos(&tx.inputs, self.storage.db.clone(), &mut inittx);
+                        spent_inputs.extend(tx.inputs.iter().cloned());
                         // account should be already updated in deliver_tx
                     }
                     TxAux::UnbondStakeTx(tx, witness) => {
@@ -89,15 +112,54 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
                         inittx.put(COL_WITNESS, &txid[..], &witness.encode());
                         // account should be already updated in deliver_tx
                     }
+                    TxAux::NodeJoinTx(tx, witness) => {
+                        inittx.put(COL_BODIES, &txid[..], &tx.encode());
+                        inittx.put(COL_WITNESS, &txid[..], &witness.encode());
+                        // account and council nodes should be already updated in deliver_tx
+                    }
+                    TxAux::UnjailTx(tx, witness) => {
+                        inittx.put(COL_BODIES, &txid[..], &tx.encode());
+                        inittx.put(COL_WITNESS, &txid[..], &witness.encode());
+                        // account should be already updated in deliver_tx
+                    }
+                    TxAux::RebondStakeTx(tx, witness) => {
+                        inittx.put(COL_BODIES, &txid[..], &tx.encode());
+                        inittx.put(COL_WITNESS, &txid[..], &witness.encode());
+                        // account should be already updated in deliver_tx
+                    }
+                    TxAux::DelegateStakeTx(tx, witness) => {
+                        inittx.put(COL_BODIES, &txid[..], &tx.encode());
+                        inittx.put(COL_WITNESS, &txid[..], &witness.encode());
+                        // account and delegations should be already updated in deliver_tx
+                    }
+                    TxAux::UndelegateStakeTx(tx, witness) => {
+                        inittx.put(COL_BODIES, &txid[..], &tx.encode());
+                        inittx.put(COL_WITNESS, &txid[..], &witness.encode());
+                        // account and delegations should be already updated in deliver_tx
+                    }
+                    TxAux::ParamChangeProposalTx(tx, witness) => {
+                        inittx.put(COL_BODIES, &txid[..], &tx.encode());
+                        inittx.put(COL_WITNESS, &txid[..], &witness.encode());
+                        // account and proposals should be already updated in deliver_tx
+                    }
+                    TxAux::ParamChangeVoteTx(tx, witness) => {
+                        inittx.put(COL_BODIES, &txid[..], &tx.encode());
+                        inittx.put(COL_WITNESS, &txid[..], &witness.encode());
+                        // account and proposal votes should be already updated in deliver_tx
+                    }
                     TxAux::WithdrawUnbondedStakeTx {
                         witness,
                         no_of_outputs,
-                        payload: TxObfuscated { txpayload, .. },
+                        payload,
                         ..
                     } => {
                         // FIXME: temporary hack / this shouldn't be here
-                        let plain_tx = PlainTxAux::decode(&mut txpayload.as_slice());
-                        if let Ok(PlainTxAux::WithdrawUnbondedStakeTx(tx)) = plain_tx {
+                        let plain_tx = self.tx_validator.decrypt_payload(
+                            &txid,
+                            payload,
+                            new_state.last_block_height,
+                        );
+                        if let Some(PlainTxAux::WithdrawUnbondedStakeTx(tx)) = plain_tx {
                             inittx.put(
                                 COL_BODIES,
                                 &txid[..],
@@ -115,12 +177,15 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
                     }
                 }
             }
+            spend_utxos(&spent_inputs, self.storage.db.clone(), &mut inittx);
             new_state.rewards_pool.last_block_height = new_state.last_block_height;
             new_state.last_account_root_hash = self.uncommitted_account_root_hash;
             let app_hash = compute_app_hash(
                 &tree,
                 &new_state.last_account_root_hash,
                 &new_state.rewards_pool,
+                &new_state.network_params,
+                &node_state_hash,
             );
             inittx.put(COL_MERKLE_PROOFS, &app_hash[..], &tree.encode());
             new_state.last_apphash = app_hash;
@@ -142,16 +207,63 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
             &i64::encode_var_vec(new_state.last_block_height),
             &new_state.last_apphash,
         );
-        inittx.put(COL_NODE_INFO, LAST_STATE_KEY, &new_state.encode());
+        let expired_account_roots =
+            self.expire_account_roots(&previous_state, &new_state, &mut inittx);
+        let encoded_state = new_state.encode();
+        inittx.put(
+            COL_NODE_INFO,
+            &app_state_key(new_state.last_block_height),
+            &encoded_state,
+        );
+        inittx.put(COL_NODE_INFO, LAST_STATE_KEY, &encoded_state);
+        self.record_block_undo(new_state.last_block_height, &mut inittx);
+        // the account trie nodes of the block are persisted first: if the app stops before the state is written,
+        // the block is replayed on top of the previous state (and the same nodes are inserted again)
+        persist_account_nodes(&self.accounts).expect("failed to persist the account trie");
         let wr = self.storage.db.write(inittx);
         if wr.is_err() {
             panic!("db write error: {}", wr.err().unwrap());
         } else {
             resp.data = new_state.last_apphash.to_vec();
+            let height = new_state.last_block_height;
             self.last_state = Some(new_state);
             self.delivered_txs.clear();
+            self.mempool_state.clear();
+            self.delivered_state.clear();
+            self.network_params_changed_in_block = false;
+            self.app_hash_outdated = false;
+            self.committed_node_state_hash = node_state_hash;
+            self.prune_account_roots(
+                &previous_state.last_account_root_hash,
+                &expired_account_roots,
+            );
+            self.write_periodic_snapshot(height);
         }
 
         resp
     }
+
+    /// Starts writing the snapshot of the just committed state in the background if the height is at the configured interval
+    /// (it's skipped if the previous snapshot is still being written)
+    fn write_periodic_snapshot(&self, height: BlockHeight) {
+        if let Some(config) = &self.snapshot_config {
+            if config.interval <= 0 || height % config.interval != 0 {
+                return;
+            }
+            if self.snapshot_in_progress.swap(true, Ordering::SeqCst) {
+                warn!(
+                    "skipping the snapshot at height {}: the previous one is still being written",
+                    height
+                );
+                return;
+            }
+            info!("writing the snapshot at height {}", height);
+            spawn_snapshot_writer(
+                config.clone(),
+                self.storage.clone(),
+                self.accounts.db().clone(),
+                self.snapshot_in_progress.clone(),
+            );
+        }
+    }
 }
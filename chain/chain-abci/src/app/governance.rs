@@ -0,0 +1,128 @@
+use super::ChainNodeApp;
+use crate::enclave_bridge::EnclaveProxy;
+use crate::storage::tx::get_account;
+use chain_core::state::account::StakedStateAddress;
+use chain_core::state::governance::{NetworkParameterChange, ParamChangeProposalTx};
+use chain_core::state::tendermint::BlockHeight;
+use chain_core::tx::data::TxId;
+use log::{info, warn};
+use parity_scale_codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+/// a pending network parameter change
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Encode, Decode)]
+pub struct ParamChangeProposal {
+    /// the new value of the network parameter
+    pub change: NetworkParameterChange,
+    /// the block height at the end of which the votes are counted (and the change is activated if approved)
+    pub activation_height: BlockHeight,
+    /// council nodes that voted for the change (including the proposer)
+    pub votes: Vec<StakedStateAddress>,
+}
+
+impl ParamChangeProposal {
+    pub fn new(tx: &ParamChangeProposalTx) -> Self {
+        ParamChangeProposal {
+            change: tx.change,
+            activation_height: tx.activation_height,
+            votes: vec![tx.address],
+        }
+    }
+}
+
+/// a change is approved if the council nodes that voted for it have more than 2/3 of the total voting power
+fn is_approved(votes_power: i64, total_power: i64) -> bool {
+    votes_power * 3 > total_power * 2
+}
+
+impl<T: EnclaveProxy> ChainNodeApp<T> {
+    /// Records a new network parameter change proposal (delivered in the transaction with the given ID)
+    pub fn add_param_change_proposal(&mut self, proposal_id: TxId, tx: &ParamChangeProposalTx) {
+        self.last_state
+            .as_mut()
+            .expect("executing deliver tx, but no app state stored")
+            .param_change_proposals
+            .insert(proposal_id, ParamChangeProposal::new(tx));
+    }
+
+    /// Records the council node's vote for the pending network parameter change proposal
+    pub fn add_param_change_vote(&mut self, proposal_id: &TxId, voter: StakedStateAddress) {
+        let state = self
+            .last_state
+            .as_mut()
+            .expect("executing deliver tx, but no app state stored");
+        match state.param_change_proposals.get_mut(proposal_id) {
+            Some(proposal) if !proposal.votes.contains(&voter) => proposal.votes.push(voter),
+            _ => warn!("vote for a missing proposal or a duplicate vote passed verification"),
+        }
+    }
+
+    /// At the end of the block, counts the votes of the proposals to be activated at its height:
+    /// the approved network parameter changes take effect from the next block, the rest are dropped
+    /// (the voting power is taken from the start of the block)
+    pub fn activate_param_changes(&mut self, height: BlockHeight) {
+        let state = self
+            .last_state
+            .as_mut()
+            .expect("executing end block, but no app state stored");
+        let due: Vec<TxId> = state
+            .param_change_proposals
+            .iter()
+            .filter(|(_, proposal)| proposal.activation_height <= height)
+            .map(|(proposal_id, _)| *proposal_id)
+            .collect();
+        if due.is_empty() {
+            return;
+        }
+        let total_power: i64 = self
+            .validator_voting_power
+            .values()
+            .map(|power| i64::from(*power))
+            .sum();
+        let mut activated = false;
+        for proposal_id in due {
+            let proposal = state
+                .param_change_proposals
+                .remove(&proposal_id)
+                .expect("due proposal is stored");
+            let votes_power: i64 = proposal
+                .votes
+                .iter()
+                .filter_map(|voter| self.validator_voting_power.get(voter))
+                .map(|power| i64::from(*power))
+                .sum();
+            if is_approved(votes_power, total_power) {
+                info!(
+                    "activating network parameter change (proposal {}): {}",
+                    hex::encode(&proposal_id[..]),
+                    proposal.change
+                );
+                state.network_params.apply(&proposal.change);
+                activated = true;
+            } else {
+                info!(
+                    "network parameter change proposal {} rejected (votes: {} / {})",
+                    hex::encode(&proposal_id[..]),
+                    votes_power,
+                    total_power
+                );
+            }
+        }
+        if activated {
+            self.network_params_changed_in_block = true;
+            // the required council node stake may have changed
+            let council_nodes: Vec<StakedStateAddress> =
+                self.validator_voting_power.keys().cloned().collect();
+            for address in council_nodes {
+                match get_account(
+                    &address,
+                    &self.uncommitted_account_root_hash,
+                    &self.accounts,
+                ) {
+                    Ok(account) => self.update_voting_power(&account),
+                    Err(e) => warn!("council node staked state not found: {}", e),
+                }
+            }
+        }
+    }
+}
@@ -1,27 +1,38 @@
 mod app_init;
 mod commit;
+mod governance;
+pub mod peer_filter;
+pub mod pruning;
 mod query;
+mod rewards;
+pub mod rollback;
+mod slashing;
+pub mod snapshot;
+mod state_migration;
 mod validate_tx;
 
 use abci::*;
-use chain_tx_filter::BlockFilter;
-use log::info;
+use chain_tx_filter::{BlockFilter, TxFilters};
+use log::{info, warn};
 
+use self::app_init::get_validator_key;
 pub use self::app_init::{ChainNodeApp, ChainNodeState};
+pub use self::governance::ParamChangeProposal;
+pub use self::slashing::ValidatorLiveness;
 use crate::enclave_bridge::EnclaveProxy;
 use crate::storage::account::AccountStorage;
 use crate::storage::account::AccountWrapper;
-use crate::storage::tx::StarlingFixedKey;
+use crate::storage::tx::{get_account, StarlingFixedKey};
 use crate::storage::COL_TX_META;
 use bit_vec::BitVec;
 use chain_core::common::TendermintEventType;
-use chain_core::state::account::StakedState;
+use chain_core::init::coin::Coin;
+use chain_core::state::account::{StakedState, StakedStateAddress};
 use chain_core::state::tendermint::TendermintVotePower;
+use chain_core::state::CouncilNode;
 use chain_core::tx::data::input::TxoPointer;
-use chain_core::tx::TxObfuscated;
 use chain_core::tx::{PlainTxAux, TxAux};
 use kvdb::{DBTransaction, KeyValueDB};
-use parity_scale_codec::Decode;
 use protobuf::RepeatedField;
 use std::collections::BTreeMap;
 use std::sync::Arc;
@@ -62,6 +73,55 @@ pub fn update_account(
     )
 }
 
+impl<T: EnclaveProxy> ChainNodeApp<T> {
+    /// Records the voting power change (emitted in end_block) if the updated staked state belongs to a council node
+    /// (the voting power includes the stake delegated to it; it's zero if the council node is jailed
+    /// or its own bonded amount is below the required council node stake)
+    pub fn update_voting_power(&mut self, account: &StakedState) {
+        if !self.validator_voting_power.contains_key(&account.address) {
+            return;
+        }
+        let state = self
+            .last_state
+            .as_ref()
+            .expect("updating voting power, but no app state stored");
+        let active = !account.is_jailed()
+            && account.bonded >= state.network_params.required_council_node_stake;
+        let new_power = if active {
+            let delegated = state.delegated_stake(&account.address);
+            TendermintVotePower::from(
+                (account.bonded + delegated).expect("should not be over the max supply"),
+            )
+        } else {
+            TendermintVotePower::zero()
+        };
+        // end_block skips the changes that cancelled out during the block
+        self.power_changed_in_block
+            .insert(account.address, new_power);
+    }
+
+    /// Records the stake the updated staked state delegates to the council node
+    /// and the council node's voting power change
+    fn update_delegation(&mut self, council_node: StakedStateAddress, delegator: &StakedState) {
+        let delegated = match delegator.delegation {
+            Some(delegation) if delegation.council_node == council_node => delegation.amount,
+            _ => Coin::zero(),
+        };
+        self.last_state
+            .as_mut()
+            .expect("updating delegations, but no app state stored")
+            .set_delegation(council_node, delegator.address, delegated);
+        match get_account(
+            &council_node,
+            &self.uncommitted_account_root_hash,
+            &self.accounts,
+        ) {
+            Ok(account) => self.update_voting_power(&account),
+            Err(e) => warn!("council node staked state not found: {}", e),
+        }
+    }
+}
+
 /// TODO: sanity checks in abci https://github.com/tendermint/rust-abci/issues/49
 impl<T: EnclaveProxy> abci::Application for ChainNodeApp<T> {
     /// Query Connection: Called on startup from Tendermint.  The application should normally
@@ -112,7 +172,7 @@ impl<T: EnclaveProxy> abci::Application for ChainNodeApp<T> {
     /// commit()
     fn begin_block(&mut self, req: &RequestBeginBlock) -> ResponseBeginBlock {
         info!("received beginblock request");
-        // TODO: process RequestBeginBlock -- e.g. rewards for validators? + punishment for malicious ByzantineValidators
+        // TODO: process RequestBeginBlock -- e.g. rewards for validators?
         // TODO: Check security implications once https://github.com/tendermint/tendermint/issues/2653 is closed
         let block_time = req
             .header
@@ -124,6 +184,8 @@ impl<T: EnclaveProxy> abci::Application for ChainNodeApp<T> {
             .seconds;
         self.last_state.as_mut().map(|mut x| x.block_time = block_time)
             .expect("executing begin block, but no app state stored (i.e. no initchain or recovery was executed)");
+        self.slash_faulty_council_nodes(req);
+        self.record_signed_blocks(req);
         ResponseBeginBlock::new()
     }
 
@@ -134,35 +196,88 @@ impl<T: EnclaveProxy> abci::Application for ChainNodeApp<T> {
         let mut resp = ResponseDeliverTx::new();
         let mtxaux = ChainNodeApp::validate_tx_req(self, _req, &mut resp);
         if let (0, Some((txaux, fee_acc))) = (resp.code, mtxaux) {
-            let mut inittx = self.storage.db.transaction();
+            // the spent inputs are only recorded in `delivered_state` (they are marked as spent in commit,
+            // so nothing is written to the node storage before the whole block is committed)
             let (next_account_root, maccount) = match &txaux {
-                TxAux::TransferTx { inputs, .. } => {
-                    // here the original idea was "conservative" that it "spent" utxos here
-                    // but it didn't create utxos for this TX (they are created in commit)
-                    spend_utxos(&inputs, self.storage.db.clone(), &mut inittx);
-                    (self.uncommitted_account_root_hash, None)
-                }
-                TxAux::DepositStakeTx { tx, .. } => {
-                    spend_utxos(&tx.inputs, self.storage.db.clone(), &mut inittx);
+                TxAux::TransferTx { .. } => (self.uncommitted_account_root_hash, None),
+                TxAux::DepositStakeTx { .. } => update_account(
+                    fee_acc
+                        .1
+                        .expect("account returned in deposit stake verification"),
+                    &self.uncommitted_account_root_hash,
+                    &mut self.accounts,
+                ),
+                TxAux::UnbondStakeTx(_, _) => update_account(
+                    fee_acc
+                        .1
+                        .expect("account returned in unbond stake verification"),
+                    &self.uncommitted_account_root_hash,
+                    &mut self.accounts,
+                ),
+                TxAux::WithdrawUnbondedStakeTx { .. } => update_account(
+                    fee_acc
+                        .1
+                        .expect("account returned in withdraw unbonded stake verification"),
+                    &self.uncommitted_account_root_hash,
+                    &mut self.accounts,
+                ),
+                TxAux::NodeJoinTx(tx, _) => {
+                    // registered with zero power, so that the validator update below is emitted in end_block
+                    let node = CouncilNode::new(tx.address, tx.consensus_pubkey.clone());
+                    self.validator_pubkeys
+                        .insert(tx.address, get_validator_key(&node));
+                    self.validator_voting_power
+                        .insert(tx.address, TendermintVotePower::zero());
+                    self.last_state
+                        .as_mut()
+                        .expect("delivertx should have app state")
+                        .council_nodes
+                        .push(node);
                     update_account(
                         fee_acc
                             .1
-                            .expect("account returned in deposit stake verification"),
+                            .expect("account returned in node join verification"),
                         &self.uncommitted_account_root_hash,
                         &mut self.accounts,
                     )
                 }
-                TxAux::UnbondStakeTx(_, _) => update_account(
+                TxAux::UnjailTx(_, _) => update_account(
+                    fee_acc.1.expect("account returned in unjail verification"),
+                    &self.uncommitted_account_root_hash,
+                    &mut self.accounts,
+                ),
+                TxAux::RebondStakeTx(_, _) => update_account(
                     fee_acc
                         .1
-                        .expect("account returned in unbond stake verification"),
+                        .expect("account returned in rebond stake verification"),
                     &self.uncommitted_account_root_hash,
                     &mut self.accounts,
                 ),
-                TxAux::WithdrawUnbondedStakeTx { .. } => update_account(
+                TxAux::DelegateStakeTx(_, _) => update_account(
                     fee_acc
                         .1
-                        .expect("account returned in withdraw unbonded stake verification"),
+                        .expect("account returned in delegate stake verification"),
+                    &self.uncommitted_account_root_hash,
+                    &mut self.accounts,
+                ),
+                TxAux::UndelegateStakeTx(_, _) => update_account(
+                    fee_acc
+                        .1
+                        .expect("account returned in undelegate stake verification"),
+                    &self.uncommitted_account_root_hash,
+                    &mut self.accounts,
+                ),
+                TxAux::ParamChangeProposalTx(_, _) => update_account(
+                    fee_acc
+                        .1
+                        .expect("account returned in param change proposal verification"),
+                    &self.uncommitted_account_root_hash,
+                    &mut self.accounts,
+                ),
+                TxAux::ParamChangeVoteTx(_, _) => update_account(
+                    fee_acc
+                        .1
+                        .expect("account returned in param change vote verification"),
                     &self.uncommitted_account_root_hash,
                     &mut self.accounts,
                 ),
@@ -170,35 +285,32 @@ impl<T: EnclaveProxy> abci::Application for ChainNodeApp<T> {
             if let Some(ref account) = maccount {
                 self.filter.add_staked_state_address(&account.address);
             }
-            match maccount {
-                Some(ref account) if self.validator_voting_power.contains_key(&account.address) => {
-                    let min_power = TendermintVotePower::from(
-                        self.last_state
-                            .as_ref()
-                            .expect("delivertx should have app state")
-                            .required_council_node_stake,
-                    );
-                    let new_power = TendermintVotePower::from(account.bonded);
-                    let old_power = self.validator_voting_power[&account.address];
-                    if new_power > old_power && new_power >= min_power {
-                        self.power_changed_in_block
-                            .insert(account.address, new_power);
-                    } else if old_power >= min_power && new_power < old_power {
-                        self.power_changed_in_block
-                            .insert(account.address, TendermintVotePower::zero());
-                    }
+            if let Some(ref account) = maccount {
+                self.update_voting_power(account);
+            }
+            // as self.accounts allows querying against different tree roots
+            // the modifications done with "update_account" are safe: the new trie nodes are only buffered
+            // until commit (see `persist_account_nodes`) and if the app stops before that,
+            // the replayed block inserts the same (content-addressed) nodes again
+            // the intermediate uncommitted tree roots aren't useful (not exposed for querying) -- they are pruned in commit
+            self.set_uncommitted_account_root(next_account_root);
+            let delegated_to = match &txaux {
+                TxAux::DelegateStakeTx(tx, _) => Some(tx.council_node),
+                TxAux::UndelegateStakeTx(tx, _) => Some(tx.council_node),
+                _ => None,
+            };
+            if let (Some(council_node), Some(account)) = (delegated_to, &maccount) {
+                self.update_delegation(council_node, account);
+            }
+            match &txaux {
+                TxAux::ParamChangeProposalTx(tx, _) => {
+                    self.add_param_change_proposal(txaux.tx_id(), tx)
+                }
+                TxAux::ParamChangeVoteTx(tx, _) => {
+                    self.add_param_change_vote(&tx.proposal_id, tx.address)
                 }
                 _ => {}
-            };
-            // as self.accounts allows querying against different tree roots
-            // the modifications done with "update_account" _should_ be safe, as the final tree root will
-            // be persisted in commit.
-            // The question is whether it really is -- e.g. if Tendermint/ABCI app crashes during DeliverTX
-            // and then it tries to replay the block on the restart, will it cause problems
-            // with the account storage (starling / MerkleBIT), because it already persisted those "future" / not-yet-committed account states?
-            // TODO: check-verify / test starling persistence safety?
-            // TODO: most of these intermediate uncommitted tree roots aren't useful (not exposed for querying) -- prune them / the account storage?
-            self.uncommitted_account_root_hash = next_account_root;
+            }
             let mut kvpair = KVPair::new();
             kvpair.key = Vec::from(&b"txid"[..]);
             // TODO: "Keys and values in tags must be UTF-8 encoded strings" ?
@@ -216,9 +328,6 @@ impl<T: EnclaveProxy> abci::Application for ChainNodeApp<T> {
             let new_remaining = (rewards_pool.remaining + fee_acc.0.to_coin())
                 .expect("rewards pool + fee greater than max coin?");
             rewards_pool.remaining = new_remaining;
-            // this "buffered write" shouldn't persist (persistence done in commit)
-            // but should change it in-memory -- TODO: check
-            self.storage.db.write_buffered(inittx);
         }
         resp
     }
@@ -227,30 +336,41 @@ impl<T: EnclaveProxy> abci::Application for ChainNodeApp<T> {
     fn end_block(&mut self, _req: &RequestEndBlock) -> ResponseEndBlock {
         info!("received endblock request");
         let mut resp = ResponseEndBlock::new();
+        self.distribute_epoch_rewards(_req.height);
+        self.activate_param_changes(_req.height);
+        // view key filters of the individual transactions (so that clients only request the matching ones)
+        let mut tx_filters = TxFilters::default();
+        let last_block_height = self.last_state.as_ref().map_or(0, |x| x.last_block_height);
         for txaux in self.delivered_txs.iter() {
             match txaux {
-                TxAux::TransferTx {
-                    payload: TxObfuscated { txpayload, .. },
-                    ..
-                } => {
+                TxAux::TransferTx { txid, payload, .. } => {
                     // FIXME: temporary hack / this shouldn't be here
-                    let plain_tx = PlainTxAux::decode(&mut txpayload.as_slice());
-                    if let Ok(PlainTxAux::TransferTx(tx, _)) = plain_tx {
+                    let plain_tx =
+                        self.tx_validator
+                            .decrypt_payload(txid, payload, last_block_height);
+                    if let Some(PlainTxAux::TransferTx(tx, _)) = plain_tx {
                         for view in tx.attributes.allowed_view.iter() {
                             self.filter.add_view_key(&view.view_key);
                         }
+                        tx_filters.add_transaction(
+                            *txid,
+                            tx.attributes.allowed_view.iter().map(|view| &view.view_key),
+                        );
                     }
                 }
-                TxAux::WithdrawUnbondedStakeTx {
-                    payload: TxObfuscated { txpayload, .. },
-                    ..
-                } => {
+                TxAux::WithdrawUnbondedStakeTx { txid, payload, .. } => {
                     // FIXME: temporary hack / this shouldn't be here
-                    let plain_tx = PlainTxAux::decode(&mut txpayload.as_slice());
-                    if let Ok(PlainTxAux::WithdrawUnbondedStakeTx(tx)) = plain_tx {
+                    let plain_tx =
+                        self.tx_validator
+                            .decrypt_payload(txid, payload, last_block_height);
+                    if let Some(PlainTxAux::WithdrawUnbondedStakeTx(tx)) = plain_tx {
                         for view in tx.attributes.allowed_view.iter() {
                             self.filter.add_view_key(&view.view_key);
                         }
+                        tx_filters.add_transaction(
+                            *txid,
+                            tx.attributes.allowed_view.iter().map(|view| &view.view_key),
+                        );
                     }
                 }
                 _ => {}
@@ -263,9 +383,14 @@ impl<T: EnclaveProxy> abci::Application for ChainNodeApp<T> {
             let mut event = Event::new();
             event.field_type = TendermintEventType::BlockFilter.to_string();
             event.attributes.push(kvpair);
+            let (key, value) = tx_filters.get_tendermint_kv();
+            let mut kvpair = KVPair::new();
+            kvpair.key = key;
+            kvpair.value = value;
+            event.attributes.push(kvpair);
             resp.events.push(event);
         }
-        self.filter = BlockFilter::default();
+        self.filter = BlockFilter::new(self.filter.format());
         // TODO: skipchain-based validator changes?
         if !self.power_changed_in_block.is_empty() {
             let mut validators = Vec::with_capacity(self.power_changed_in_block.len());
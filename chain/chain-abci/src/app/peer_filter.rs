@@ -0,0 +1,305 @@
+//! Peer filtering: Tendermint (with `filter_peers = true` in its config.toml) queries the application
+//! before connecting to a peer (see `ChainNodeApp::query_handler`), and the connection is refused
+//! if the peer's node ID or IP address is denied or not allowed by the rules in the peer filter file.
+//!
+//! The file is JSON with the (optional) lists of node IDs and IP addresses / CIDR ranges, e.g.
+//! `{"allowed_ids": ["<hex node ID>"], "allowed_addrs": ["10.0.0.0/8"], "denied_addrs": ["10.0.0.13"]}`.
+//! It's reread when a peer is checked after the file was modified, so the rules can be changed
+//! while the node is running (the already connected peers aren't disconnected).
+//! NOTE: council node metadata doesn't contain the peers' node IDs / addresses yet, so the rules are only configured locally
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// the query path prefix for filtering peers by their addresses
+const FILTER_ADDR_PATH: &str = "p2p/filter/addr/";
+/// the query path prefix for filtering peers by their node IDs
+const FILTER_ID_PATH: &str = "p2p/filter/id/";
+
+/// IP address range in the CIDR notation (e.g. 10.0.0.0/8) or a single IP address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNetwork {
+    /// true if the address is in the range (IPv4-mapped IPv6 addresses are matched against IPv4 ranges)
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.address, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = (!0u32)
+                    .checked_shl(32 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = (!0u128)
+                    .checked_shl(128 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(*ip) & mask
+            }
+            (IpAddr::V4(_), IpAddr::V6(ip)) => match ip.to_ipv4() {
+                Some(ip) => self.contains(&IpAddr::V4(ip)),
+                None => false,
+            },
+            (IpAddr::V6(_), IpAddr::V4(_)) => false,
+        }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '/');
+        let address = parts
+            .next()
+            .unwrap_or_default()
+            .parse::<IpAddr>()
+            .map_err(|e| format!("invalid IP address in {}: {}", s, e))?;
+        let max_prefix_len = if address.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match parts.next() {
+            Some(prefix_len) => prefix_len
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max_prefix_len)
+                .ok_or_else(|| format!("invalid prefix length in {}", s))?,
+            None => max_prefix_len,
+        };
+        Ok(IpNetwork {
+            address,
+            prefix_len,
+        })
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+/// The peer filter rules as they are written in the peer filter file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PeerFilterConfig {
+    /// if not empty, only the peers with these node IDs are allowed
+    #[serde(default)]
+    pub allowed_ids: Vec<String>,
+    /// the peers with these node IDs are refused
+    #[serde(default)]
+    pub denied_ids: Vec<String>,
+    /// if not empty, only the peers with IP addresses in these ranges are allowed
+    #[serde(default)]
+    pub allowed_addrs: Vec<String>,
+    /// the peers with IP addresses in these ranges are refused
+    #[serde(default)]
+    pub denied_addrs: Vec<String>,
+}
+
+/// Allowlist / denylist of the peers' node IDs and IP addresses (the denylists take precedence)
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PeerFilter {
+    allowed_ids: BTreeSet<String>,
+    denied_ids: BTreeSet<String>,
+    allowed_addrs: Vec<IpNetwork>,
+    denied_addrs: Vec<IpNetwork>,
+}
+
+fn parse_ids(ids: &[String]) -> BTreeSet<String> {
+    ids.iter().map(|id| id.trim().to_lowercase()).collect()
+}
+
+fn parse_addrs(addrs: &[String]) -> Result<Vec<IpNetwork>, String> {
+    addrs.iter().map(|addr| addr.trim().parse()).collect()
+}
+
+impl PeerFilter {
+    /// checks and normalizes the configured rules
+    pub fn from_config(config: &PeerFilterConfig) -> Result<Self, String> {
+        Ok(PeerFilter {
+            allowed_ids: parse_ids(&config.allowed_ids),
+            denied_ids: parse_ids(&config.denied_ids),
+            allowed_addrs: parse_addrs(&config.allowed_addrs)?,
+            denied_addrs: parse_addrs(&config.denied_addrs)?,
+        })
+    }
+
+    /// true if the peer node ID (hex-encoded) isn't denied and is allowed (or the ID allowlist is empty)
+    pub fn allows_id(&self, id: &str) -> bool {
+        let id = id.to_lowercase();
+        !self.denied_ids.contains(&id)
+            && (self.allowed_ids.is_empty() || self.allowed_ids.contains(&id))
+    }
+
+    /// true if the peer IP address isn't denied and is allowed (or the address allowlist is empty)
+    pub fn allows_addr(&self, ip: &IpAddr) -> bool {
+        !self.denied_addrs.iter().any(|network| network.contains(ip))
+            && (self.allowed_addrs.is_empty()
+                || self
+                    .allowed_addrs
+                    .iter()
+                    .any(|network| network.contains(ip)))
+    }
+
+    /// checks the peer in the Tendermint filter query path
+    /// ("/p2p/filter/addr/<IP:PORT>" or "/p2p/filter/id/<ID>") and returns the reason if it's refused
+    pub fn check_query_path(&self, path: &str) -> Result<(), String> {
+        let path = path.trim_start_matches('/');
+        if path.starts_with(FILTER_ADDR_PATH) {
+            let addr = &path[FILTER_ADDR_PATH.len()..];
+            let socket_addr = addr
+                .parse::<SocketAddr>()
+                .map_err(|e| format!("invalid peer address {}: {}", addr, e))?;
+            if self.allows_addr(&socket_addr.ip()) {
+                Ok(())
+            } else {
+                Err(format!("peer address {} is not allowed", addr))
+            }
+        } else if path.starts_with(FILTER_ID_PATH) {
+            let id = &path[FILTER_ID_PATH.len()..];
+            if self.allows_id(id) {
+                Ok(())
+            } else {
+                Err(format!("peer ID {} is not allowed", id))
+            }
+        } else {
+            Err(format!("unknown peer filter query: {}", path))
+        }
+    }
+}
+
+/// Peer filter loaded from a file (the rules are reread if the file was modified since they were read)
+#[derive(Debug)]
+pub struct PeerFilterFile {
+    path: PathBuf,
+    filter: PeerFilter,
+    /// the modification time of the file when the rules were read
+    modified: Option<SystemTime>,
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn read_peer_filter(path: &Path) -> Result<PeerFilter, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read the peer filter file {:?}: {}", path, e))?;
+    let config: PeerFilterConfig = serde_json::from_str(&contents)
+        .map_err(|e| format!("invalid peer filter file {:?}: {}", path, e))?;
+    PeerFilter::from_config(&config)
+}
+
+impl PeerFilterFile {
+    /// reads the peer filter rules from the file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref().to_path_buf();
+        let modified = modified_time(&path);
+        let filter = read_peer_filter(&path)?;
+        Ok(PeerFilterFile {
+            path,
+            filter,
+            modified,
+        })
+    }
+
+    /// rereads the rules if the file was modified and returns true if they were changed
+    /// (if the new rules can't be read, the previous ones are kept and the error is returned)
+    pub fn reload(&mut self) -> Result<bool, String> {
+        let modified = modified_time(&self.path);
+        if modified.is_some() && modified == self.modified {
+            return Ok(false);
+        }
+        // an invalid modification is only reported once
+        self.modified = modified;
+        let filter = read_peer_filter(&self.path)?;
+        if filter == self.filter {
+            return Ok(false);
+        }
+        self.filter = filter;
+        Ok(true)
+    }
+
+    /// the current rules
+    pub fn filter(&self) -> &PeerFilter {
+        &self.filter
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn to_strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn addr_filter(allowed: &[&str], denied: &[&str]) -> PeerFilter {
+        PeerFilter::from_config(&PeerFilterConfig {
+            allowed_addrs: to_strings(allowed),
+            denied_addrs: to_strings(denied),
+            ..Default::default()
+        })
+        .expect("valid rules")
+    }
+
+    fn id_filter(allowed: &[&str], denied: &[&str]) -> PeerFilter {
+        PeerFilter::from_config(&PeerFilterConfig {
+            allowed_ids: to_strings(allowed),
+            denied_ids: to_strings(denied),
+            ..Default::default()
+        })
+        .expect("valid rules")
+    }
+
+    #[test]
+    fn ip_networks_should_contain_their_addresses() {
+        let network: IpNetwork = "10.1.0.0/16".parse().unwrap();
+        assert!(network.contains(&"10.1.200.3".parse().unwrap()));
+        assert!(network.contains(&"::ffff:10.1.0.1".parse().unwrap()));
+        assert!(!network.contains(&"10.2.0.1".parse().unwrap()));
+        let any: IpNetwork = "0.0.0.0/0".parse().unwrap();
+        assert!(any.contains(&"192.168.0.1".parse().unwrap()));
+        let single: IpNetwork = "fd00::1".parse().unwrap();
+        assert_eq!("fd00::1/128", single.to_string());
+        assert!(single.contains(&"fd00::1".parse().unwrap()));
+        assert!(!single.contains(&"fd00::2".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+        assert!("10.0.0/8".parse::<IpNetwork>().is_err());
+    }
+
+    #[test]
+    fn denied_peers_and_peers_not_allowed_should_be_refused() {
+        let filter = addr_filter(&["10.0.0.0/8"], &["10.0.0.13"]);
+        assert!(filter
+            .check_query_path("/p2p/filter/addr/10.0.0.1:26656")
+            .is_ok());
+        assert!(filter
+            .check_query_path("p2p/filter/addr/10.0.0.13:26656")
+            .is_err());
+        assert!(filter
+            .check_query_path("/p2p/filter/addr/192.168.0.1:26656")
+            .is_err());
+        assert!(filter
+            .check_query_path("/p2p/filter/addr/not-an-address")
+            .is_err());
+        // no ID rules
+        assert!(filter.check_query_path("/p2p/filter/id/ABCD").is_ok());
+
+        let filter = id_filter(&[], &["abcd"]);
+        assert!(filter.check_query_path("/p2p/filter/id/ABCD").is_err());
+        assert!(filter.check_query_path("/p2p/filter/id/1234").is_ok());
+        let filter = id_filter(&["abcd"], &[]);
+        assert!(filter.check_query_path("/p2p/filter/id/abcd").is_ok());
+        assert!(filter.check_query_path("/p2p/filter/id/1234").is_err());
+        assert!(filter.check_query_path("/p2p/other").is_err());
+    }
+}
@@ -0,0 +1,185 @@
+//! Pruning of the account trie: every staked state update inserts a new root,
+//! but only the committed roots are needed (and only the recent ones if the history isn't queried)
+use super::{ChainNodeApp, ChainNodeState};
+use crate::enclave_bridge::EnclaveProxy;
+use crate::storage::account::{
+    collect_values, persist_account_nodes, AccountStorage, AccountWrapper,
+};
+use crate::storage::tx::StarlingFixedKey;
+use crate::storage::*;
+use chain_core::state::account::StakedState;
+use chain_core::state::tendermint::BlockHeight;
+use kvdb::DBTransaction;
+use log::warn;
+use parity_scale_codec::{Decode, Encode};
+use starling::traits::Exception;
+use std::collections::BTreeSet;
+use std::sync::atomic::Ordering;
+
+/// the committed account trie roots recorded for pruning (by the height they were committed at)
+pub fn get_committed_account_roots(
+    storage: &Storage,
+) -> Result<Vec<(BlockHeight, StarlingFixedKey)>, Exception> {
+    match storage
+        .db
+        .get(COL_NODE_INFO, COMMITTED_ACCOUNT_ROOTS_KEY)
+        .map_err(|e| Exception::new(&e.to_string()))?
+    {
+        Some(encoded) => {
+            Vec::decode(&mut encoded.to_vec().as_slice()).map_err(|e| Exception::new(e.what()))
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+impl<T: EnclaveProxy> ChainNodeApp<T> {
+    /// Sets the account trie root after a staked state update in the current block
+    /// (the replaced roots are pruned after the block is committed)
+    pub fn set_uncommitted_account_root(&mut self, root: StarlingFixedKey) {
+        self.uncommitted_account_roots.push(root);
+        self.uncommitted_account_root_hash = root;
+    }
+
+    /// Records the newly committed account trie root (if the committed roots are only retained for a number of blocks)
+    /// and returns the roots that are out of the retention window
+    /// (the app state committed at the height that left the window is removed as well)
+    ///
+    /// # Arguments
+    ///
+    /// * `previous` - the previously committed state
+    /// * `committed` - the state being committed
+    /// * `dbtx` - the commit's DB transaction the retained roots are stored in
+    pub(crate) fn expire_account_roots(
+        &self,
+        previous: &ChainNodeState,
+        committed: &ChainNodeState,
+        dbtx: &mut DBTransaction,
+    ) -> Vec<StarlingFixedKey> {
+        let retention = match self.account_root_retention {
+            Some(retention) => retention,
+            None => return Vec::new(),
+        };
+        let mut roots =
+            get_committed_account_roots(&self.storage).expect("committed account roots lookup");
+        if roots.is_empty() {
+            roots.push((previous.last_block_height, previous.last_account_root_hash));
+        }
+        if roots.last().map(|(_, root)| root) != Some(&committed.last_account_root_hash) {
+            roots.push((
+                committed.last_block_height,
+                committed.last_account_root_hash,
+            ));
+        }
+        // a root holds the account states until the height the next root was committed at
+        let first_retained_height = committed.last_block_height - retention;
+        let mut expired = Vec::new();
+        while roots.len() > 1 && roots[1].0 <= first_retained_height {
+            expired.push(roots.remove(0).1);
+        }
+        if first_retained_height > 0 {
+            dbtx.delete(COL_NODE_INFO, &app_state_key(first_retained_height - 1));
+        }
+        expired.retain(|root| roots.iter().all(|(_, retained)| retained != root));
+        dbtx.put(COL_NODE_INFO, COMMITTED_ACCOUNT_ROOTS_KEY, &roots.encode());
+        expired
+    }
+
+    /// Removes the intermediate roots created in the committed block and the expired committed roots from the account trie
+    /// (a failure only leaves unused trie nodes behind, so it's logged).
+    /// While a snapshot is written in the background, the expired roots are only removed after it finishes
+    /// (the snapshotted root may be among them; if the node stops before that, they are left behind as unused trie nodes)
+    pub(crate) fn prune_account_roots(
+        &mut self,
+        previous_root: &StarlingFixedKey,
+        expired_roots: &[StarlingFixedKey],
+    ) {
+        let committed_root = self.uncommitted_account_root_hash;
+        let intermediate_roots: BTreeSet<StarlingFixedKey> = self
+            .uncommitted_account_roots
+            .drain(..)
+            .filter(|root| *root != committed_root && root != previous_root)
+            .collect();
+        let expired_roots = if self.snapshot_in_progress.load(Ordering::SeqCst) {
+            self.deferred_account_roots.extend_from_slice(expired_roots);
+            Vec::new()
+        } else {
+            let mut expired = std::mem::replace(&mut self.deferred_account_roots, Vec::new());
+            expired.extend_from_slice(expired_roots);
+            expired
+        };
+        for root in intermediate_roots.iter().chain(expired_roots.iter()) {
+            if let Err(e) = self.accounts.remove(root) {
+                warn!(
+                    "failed to prune the account trie root {}: {}",
+                    hex::encode(root),
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// Rebuilds the account trie with only the last committed root and the retained committed roots
+/// (e.g. to reclaim the space of the intermediate roots in data directories created before pruning)
+/// and removes the app states committed with the other roots; returns the number of the roots in the rebuilt trie
+///
+/// # Arguments
+///
+/// * `storage` - the node storage
+/// * `account_db` - the database of the current account trie
+/// * `compacted` - the (empty) account trie storage the roots are rebuilt in
+pub fn compact_account_storage(
+    storage: &Storage,
+    account_db: &Storage,
+    compacted: &mut AccountStorage,
+) -> Result<usize, Exception> {
+    let encoded_state = storage
+        .db
+        .get(COL_NODE_INFO, LAST_STATE_KEY)
+        .map_err(|e| Exception::new(&e.to_string()))?
+        .ok_or_else(|| Exception::new("no committed app state stored"))?;
+    let state = ChainNodeState::decode(&mut encoded_state.to_vec().as_slice())
+        .map_err(|e| Exception::new(e.what()))?;
+    let mut roots: Vec<StarlingFixedKey> = get_committed_account_roots(storage)?
+        .into_iter()
+        .map(|(_, root)| root)
+        .collect();
+    if roots.last() != Some(&state.last_account_root_hash) {
+        roots.push(state.last_account_root_hash);
+    }
+    let mut previous_root = None;
+    for root in roots.iter() {
+        let mut values = Vec::new();
+        collect_values(account_db, root, &mut values)?;
+        let mut keys = Vec::with_capacity(values.len());
+        let mut wrapped = Vec::with_capacity(values.len());
+        for (key, value) in values {
+            keys.push(key);
+            let account = StakedState::decode(&mut value.as_slice())
+                .map_err(|e| Exception::new(&format!("failed to decode: {}", e.what())))?;
+            wrapped.push(AccountWrapper(account));
+        }
+        let compacted_root = compacted.insert(previous_root.as_ref(), &mut keys, &wrapped)?;
+        if compacted_root != *root {
+            return Err(Exception::new("rebuilt account trie root mismatch"));
+        }
+        previous_root = Some(compacted_root);
+    }
+    persist_account_nodes(compacted)?;
+    let mut dbtx = storage.db.transaction();
+    let stored_states = storage
+        .db
+        .iter_from_prefix(COL_NODE_INFO, APP_STATE_AT_HEIGHT_PREFIX)
+        .take_while(|(key, _)| key.starts_with(APP_STATE_AT_HEIGHT_PREFIX));
+    for (key, value) in stored_states {
+        match ChainNodeState::decode(&mut value.to_vec().as_slice()) {
+            Ok(state) if roots.contains(&state.last_account_root_hash) => {}
+            _ => dbtx.delete(COL_NODE_INFO, &key),
+        }
+    }
+    storage
+        .db
+        .write(dbtx)
+        .map_err(|e| Exception::new(&e.to_string()))?;
+    Ok(roots.len())
+}
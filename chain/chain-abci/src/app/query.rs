@@ -1,23 +1,27 @@
-use super::ChainNodeApp;
+use super::{ChainNodeApp, ChainNodeState};
 use crate::enclave_bridge::EnclaveProxy;
+use crate::storage::account::get_sibling_path;
 use crate::storage::tx::get_account;
 use crate::storage::*;
 use abci::*;
+use bit_vec::BitVec;
 use chain_core::common::{MerkleTree, Proof as MerkleProof, H256, HASH_SIZE_256};
-use chain_core::state::account::StakedStateAddress;
+use chain_core::state::account::{to_stake_key, StakedStateAddress};
+use chain_core::state::proof::{AccountProof, AppHashParts};
+use chain_core::state::tendermint::BlockHeight;
 use chain_core::tx::data::input::TxoIndex;
 use chain_core::tx::data::{txid_hash, TXID_HASH_ID};
 use chain_core::tx::TransactionId;
-use chain_core::tx::TxObfuscated;
 use chain_core::tx::{PlainTxAux, TxAux};
 use chain_tx_validation::TxWithOutputs;
 use enclave_protocol::{
-    DecryptionRequest, DecryptionRequestBody, DecryptionResponse, EncryptionRequest,
+    DecryptedTx, DecryptionRequest, DecryptionRequestBody, DecryptionResponse, EncryptionRequest,
     EncryptionResponse,
 };
 use integer_encoding::VarInt;
-use log::warn;
+use log::{info, warn};
 use parity_scale_codec::{Decode, Encode};
+use secp256k1::Secp256k1;
 use std::convert::TryFrom;
 
 /// Generate generic ABCI ProofOp for the witness
@@ -30,15 +34,29 @@ fn get_witness_proof_op(witness: &[u8]) -> ProofOp {
 }
 
 #[cfg(not(feature = "mock-enc-dec"))]
-fn handle_enc_dec(_req: &RequestQuery, resp: &mut ResponseQuery, storage: &Storage) {
+fn handle_enc_dec<T: EnclaveProxy>(
+    _req: &RequestQuery,
+    resp: &mut ResponseQuery,
+    storage: &Storage,
+    _tx_validator: &T,
+    _key_from: BlockHeight,
+) {
     let msg = "received a temporary *mock* encryption/decryption query in abci (use the dedicated enclaves instead)";
     warn!(msg);
     resp.log += msg;
     resp.code = 1;
 }
 
+/// the payloads are obfuscated by the enclave proxy (plain for the mock, AEAD for the software one)
+/// with the key of the last committed block
 #[cfg(feature = "mock-enc-dec")]
-fn handle_enc_dec(_req: &RequestQuery, resp: &mut ResponseQuery, storage: &Storage) {
+fn handle_enc_dec<T: EnclaveProxy>(
+    _req: &RequestQuery,
+    resp: &mut ResponseQuery,
+    storage: &Storage,
+    tx_validator: &T,
+    key_from: BlockHeight,
+) {
     warn!("received a temporary *mock* encryption/decryption query in abci");
     match _req.path.as_ref() {
         // FIXME: temporary mock
@@ -46,47 +64,38 @@ fn handle_enc_dec(_req: &RequestQuery, resp: &mut ResponseQuery, storage: &Stora
             let request = EncryptionRequest::decode(&mut _req.data.as_slice());
             match request {
                 Ok(EncryptionRequest::TransferTx(tx, witness)) => {
+                    let txid = tx.id();
                     let plain = PlainTxAux::TransferTx(tx.clone(), witness);
                     let mock = EncryptionResponse {
                         tx: TxAux::TransferTx {
-                            txid: tx.id(),
+                            txid,
                             inputs: tx.inputs.clone(),
                             no_of_outputs: tx.outputs.len() as TxoIndex,
-                            payload: TxObfuscated {
-                                key_from: 0,
-                                nonce: [0u8; 12],
-                                txpayload: plain.encode(),
-                            },
+                            payload: tx_validator.encrypt_payload(&txid, &plain, key_from),
                         },
                     };
                     resp.value = mock.encode();
                 }
                 Ok(EncryptionRequest::DepositStake(maintx, witness)) => {
                     let plain = PlainTxAux::DepositStakeTx(witness);
+                    let payload = tx_validator.encrypt_payload(&maintx.id(), &plain, key_from);
                     let mock = EncryptionResponse {
                         tx: TxAux::DepositStakeTx {
                             tx: maintx,
-                            payload: TxObfuscated {
-                                key_from: 0,
-                                nonce: [0u8; 12],
-                                txpayload: plain.encode(),
-                            },
+                            payload,
                         },
                     };
                     resp.value = mock.encode();
                 }
                 Ok(EncryptionRequest::WithdrawStake(tx, _, witness)) => {
+                    let txid = tx.id();
                     let plain = PlainTxAux::WithdrawUnbondedStakeTx(tx.clone());
                     let mock = EncryptionResponse {
                         tx: TxAux::WithdrawUnbondedStakeTx {
-                            txid: tx.id(),
+                            txid,
                             no_of_outputs: tx.outputs.len() as TxoIndex,
                             witness,
-                            payload: TxObfuscated {
-                                key_from: 0,
-                                nonce: [0u8; 12],
-                                txpayload: plain.encode(),
-                            },
+                            payload: tx_validator.encrypt_payload(&txid, &plain, key_from),
                         },
                     };
                     resp.value = mock.encode();
@@ -100,18 +109,27 @@ fn handle_enc_dec(_req: &RequestQuery, resp: &mut ResponseQuery, storage: &Stora
         // FIXME: temporary mock
         "mockdecrypt" => {
             let request = DecryptionRequest::decode(&mut _req.data.as_slice());
+            // the mock has no challenge (the client signs a zero one)
+            let verified = request.and_then(|request| {
+                request
+                    .verify(&Secp256k1::verification_only(), [0u8; 32])
+                    .map(|_| request)
+                    .map_err(|_| "Invalid request signature".into())
+            });
             if let Ok(DecryptionRequest {
-                body: DecryptionRequestBody { txs, .. },
+                body: DecryptionRequestBody { txs, view_key, .. },
                 ..
-            }) = request
+            }) = verified
             {
                 let mut resp_txs = Vec::with_capacity(txs.len());
                 let looked_up = txs.iter().map(|txid| storage.db.get(COL_BODIES, txid));
                 for found in looked_up {
                     if let Ok(Some(uv)) = found {
-                        let tx = TxWithOutputs::decode(&mut uv.to_vec().as_slice());
+                        let tx = TxWithOutputs::decode_stored(&mut uv.to_vec().as_slice());
                         if let Ok(ttx) = tx {
-                            resp_txs.push(ttx);
+                            if let Some(decrypted) = DecryptedTx::filter(ttx, &view_key) {
+                                resp_txs.push(decrypted);
+                            }
                         }
                     }
                 }
@@ -144,6 +162,133 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
         }
     }
 
+    /// Gets the app state committed at the requested height
+    /// (the last committed one if the height is 0 or it wasn't committed yet)
+    fn get_state_at(&self, height: BlockHeight) -> Result<ChainNodeState, String> {
+        let last_state = self
+            .last_state
+            .as_ref()
+            .ok_or_else(|| "node not correctly restored / initialized".to_string())?;
+        if height <= 0 || height >= last_state.last_block_height {
+            return Ok(last_state.clone());
+        }
+        if let Some(retention) = self.account_root_retention {
+            if height < last_state.last_block_height - retention {
+                return Err(format!("state at height {} was pruned", height));
+            }
+        }
+        match self.storage.db.get(COL_NODE_INFO, &app_state_key(height)) {
+            Ok(Some(data)) => ChainNodeState::decode(&mut data.to_vec().as_slice())
+                .map_err(|e| format!("invalid state at height {}: {}", height, e.what())),
+            _ => Err(format!("state at height {} not found", height)),
+        }
+    }
+
+    /// Generates the proof of the account's inclusion in the committed state's app hash
+    fn get_account_proof(
+        &self,
+        state: &ChainNodeState,
+        address: &StakedStateAddress,
+    ) -> Result<AccountProof, String> {
+        let siblings = get_sibling_path(
+            self.accounts.db(),
+            &state.last_account_root_hash,
+            &to_stake_key(address),
+        )
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "account not found in the account trie".to_string())?;
+        let tree = match self
+            .storage
+            .db
+            .get(COL_MERKLE_PROOFS, &state.last_apphash[..])
+        {
+            Ok(Some(data)) => MerkleTree::decode(&mut data.to_vec().as_slice())
+                .map_err(|e| format!("invalid merkle tree: {}", e.what()))?,
+            _ => return Err("merkle tree of the committed state not found".to_string()),
+        };
+        Ok(AccountProof {
+            siblings,
+            app_hash_parts: AppHashParts::new(
+                &tree,
+                &state.rewards_pool,
+                &state.network_params,
+                &state.node_state_hash(),
+            ),
+        })
+    }
+
+    /// Gets the height of the block the transaction was committed in and its index in the block's transaction tree
+    fn get_tx_inclusion(&self, txid: &[u8]) -> Result<(BlockHeight, u32), String> {
+        match self.storage.db.get(COL_TX_INCLUSION, txid) {
+            Ok(Some(data)) => <(BlockHeight, u32)>::decode(&mut data.to_vec().as_slice())
+                .map_err(|e| format!("invalid inclusion record: {}", e.what())),
+            _ => Err("tx not found".to_string()),
+        }
+    }
+
+    /// Generates the proof of the transaction's inclusion in the transaction tree of the block it was committed in
+    /// (the tree root is a part of the app hash committed at that height)
+    fn get_tx_inclusion_proof(&self, txid: &[u8], height: BlockHeight) -> Result<ProofOp, String> {
+        let app_hash = if height == 0 {
+            self.genesis_app_hash.to_vec()
+        } else {
+            match self
+                .storage
+                .db
+                .get(COL_APP_STATES, &i64::encode_var_vec(height))
+            {
+                Ok(Some(app_hash)) => app_hash.to_vec(),
+                _ => return Err(format!("app hash at height {} not found", height)),
+            }
+        };
+        let tree: MerkleTree<H256> = match self.storage.db.get(COL_MERKLE_PROOFS, &app_hash) {
+            Ok(Some(data)) => MerkleTree::decode(&mut data.to_vec().as_slice())
+                .map_err(|e| format!("invalid merkle tree: {}", e.what()))?,
+            _ => return Err(format!("merkle tree at height {} not found", height)),
+        };
+        let mut id = [0u8; HASH_SIZE_256];
+        id.copy_from_slice(txid);
+        match tree.generate_proof(id) {
+            Some(merkle_proof) => Ok(into_proof_op(tree.root_hash(), merkle_proof)),
+            None => Err("tx not found in the block's transaction tree".to_string()),
+        }
+    }
+
+    /// Sets the response height to the inclusion height of the transaction and adds its inclusion proof if requested
+    fn set_tx_inclusion(&self, req: &RequestQuery, resp: &mut ResponseQuery, height: BlockHeight) {
+        resp.height = height;
+        if req.prove {
+            match self.get_tx_inclusion_proof(&req.data[..], height) {
+                Ok(proof_op) => {
+                    let mut proof = Proof::new();
+                    proof.set_ops(vec![proof_op].into());
+                    resp.set_proof(proof);
+                }
+                Err(e) => {
+                    resp.log += format!("proof error: {}", e).as_ref();
+                    resp.code = 2;
+                }
+            }
+        }
+    }
+
+    /// Gets the spent status of each output of the transaction (the number of outputs is taken from its stored body,
+    /// as the bit vector in TX_META is padded to whole bytes)
+    fn get_output_status(&self, txid: &[u8]) -> Result<Vec<bool>, String> {
+        let meta = match self.storage.db.get(COL_TX_META, txid) {
+            Ok(Some(meta)) => BitVec::from_bytes(&meta),
+            _ => return Err("tx outputs not found".to_string()),
+        };
+        let no_of_outputs = match self.storage.db.get(COL_BODIES, txid) {
+            Ok(Some(body)) => TxWithOutputs::decode_stored(&mut body.to_vec().as_slice())
+                .map_err(|e| format!("invalid tx body: {}", e.what()))?
+                .outputs()
+                .len(),
+            _ => return Err("tx body not found".to_string()),
+        };
+        Ok(meta.iter().take(no_of_outputs).collect())
+    }
+
     /// Responds to query requests -- note that path is hex-encoded in the original request on the client side
     /// e.g. "store" == 0x73746f7265.
     pub fn query_handler(&self, _req: &RequestQuery) -> ResponseQuery {
@@ -154,18 +299,32 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
         // * p2p/filter/id/<ID>, where <ID> is the peer node ID (ie. the pubkey.Address() for the peer's PubKey)
         // If either of these queries return a non-zero ABCI code, Tendermint will refuse to connect to the peer."
         if _req.path.starts_with("/p2p") || _req.path.starts_with("p2p") {
-            // TODO: peer filtering
+            if let Some(peer_filter) = self.peer_filter.as_ref() {
+                let mut peer_filter = peer_filter.lock().expect("peer filter lock");
+                match peer_filter.reload() {
+                    Ok(true) => info!("reloaded the peer filter rules"),
+                    Ok(false) => {}
+                    Err(e) => warn!("keeping the previous peer filter rules: {}", e),
+                }
+                if let Err(e) = peer_filter.filter().check_query_path(&_req.path) {
+                    resp.code = 1;
+                    resp.log += &e;
+                }
+            }
             return resp;
         }
 
         match _req.path.as_ref() {
             // FIXME: remove when no longer necessary
-            "mockencrypt" => {
-                handle_enc_dec(&_req, &mut resp, &self.storage);
-            }
-            // FIXME: remove when no longer necessary
-            "mockdecrypt" => {
-                handle_enc_dec(&_req, &mut resp, &self.storage);
+            "mockencrypt" | "mockdecrypt" => {
+                let key_from = self.last_state.as_ref().map_or(0, |x| x.last_block_height);
+                handle_enc_dec(
+                    &_req,
+                    &mut resp,
+                    &self.storage,
+                    &self.tx_validator,
+                    key_from,
+                );
             }
             "store" => {
                 self.lookup(&mut resp, COL_BODIES, &_req.data[..], "tx not found");
@@ -225,6 +384,50 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
             "witness" => {
                 self.lookup(&mut resp, COL_WITNESS, &_req.data[..], "tx not found");
             }
+            "inclusion" => match self.get_tx_inclusion(&_req.data[..]) {
+                Ok((height, index)) => {
+                    resp.value = (height, index).encode();
+                    resp.info = format!(
+                        "committed at height {} (index {} in the block)",
+                        height, index
+                    );
+                    self.set_tx_inclusion(_req, &mut resp, height);
+                }
+                Err(e) => {
+                    resp.log += format!("inclusion lookup failed: {}", e).as_ref();
+                    resp.code = 1;
+                }
+            },
+            // the spent status isn't a part of the app hash: the proof is only of the transaction's inclusion
+            "output_status" => match self.get_output_status(&_req.data[..]) {
+                Ok(spent) => {
+                    resp.value = spent.encode();
+                    resp.info = spent
+                        .iter()
+                        .enumerate()
+                        .map(|(index, spent)| {
+                            format!(
+                                "output {}: {}",
+                                index,
+                                if *spent { "spent" } else { "unspent" }
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    match self.get_tx_inclusion(&_req.data[..]) {
+                        Ok((height, _)) => self.set_tx_inclusion(_req, &mut resp, height),
+                        Err(e) if _req.prove => {
+                            resp.log += format!("proof error: {}", e).as_ref();
+                            resp.code = 2;
+                        }
+                        Err(_) => {}
+                    }
+                }
+                Err(e) => {
+                    resp.log += format!("output status lookup failed: {}", e).as_ref();
+                    resp.code = 1;
+                }
+            },
             "merkle" => {
                 self.lookup(
                     &mut resp,
@@ -235,24 +438,70 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
             }
             "account" => {
                 let account_address = StakedStateAddress::try_from(_req.data.as_slice());
-                if let (Some(state), Ok(address)) = (&self.last_state, account_address) {
-                    let account =
-                        get_account(&address, &state.last_account_root_hash, &self.accounts);
-                    match account {
-                        Ok(a) => {
-                            resp.value = a.encode();
-                            // TODO: inclusion proof
-                        }
-                        Err(e) => {
-                            resp.log += format!("account lookup failed: {}", e).as_ref();
-                            resp.code = 1;
+                match (self.get_state_at(_req.height), account_address) {
+                    (Ok(state), Ok(address)) => {
+                        let account =
+                            get_account(&address, &state.last_account_root_hash, &self.accounts);
+                        match account {
+                            Ok(a) => {
+                                resp.value = a.encode();
+                                resp.height = state.last_block_height;
+                                if _req.prove {
+                                    match self.get_account_proof(&state, &address) {
+                                        Ok(account_proof) => {
+                                            let mut proof = Proof::new();
+                                            proof.set_ops(
+                                                vec![into_account_proof_op(
+                                                    &address,
+                                                    account_proof,
+                                                )]
+                                                .into(),
+                                            );
+                                            resp.set_proof(proof);
+                                        }
+                                        Err(e) => {
+                                            resp.log += format!("proof error: {}", e).as_ref();
+                                            resp.code = 2;
+                                        }
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                resp.log += format!("account lookup failed: {}", e).as_ref();
+                                resp.code = 1;
+                            }
                         }
                     }
-                } else {
-                    resp.log += "account lookup failed (either invalid address or node not correctly restored / initialized)";
-                    resp.code = 3;
+                    (Err(e), _) => {
+                        resp.log += format!("account lookup failed: {}", e).as_ref();
+                        resp.code = 3;
+                    }
+                    (_, Err(_)) => {
+                        resp.log += "account lookup failed: invalid address";
+                        resp.code = 3;
+                    }
                 }
             }
+            "rewards_pool" => match self.get_state_at(_req.height) {
+                Ok(state) => {
+                    resp.value = state.rewards_pool.encode();
+                    resp.height = state.last_block_height;
+                }
+                Err(e) => {
+                    resp.log += format!("rewards pool lookup failed: {}", e).as_ref();
+                    resp.code = 3;
+                }
+            },
+            "council_nodes" => match self.get_state_at(_req.height) {
+                Ok(state) => {
+                    resp.value = state.council_nodes.encode();
+                    resp.height = state.last_block_height;
+                }
+                Err(e) => {
+                    resp.log += format!("council nodes lookup failed: {}", e).as_ref();
+                    resp.code = 3;
+                }
+            },
             _ => {
                 resp.log += "invalid path";
                 resp.code = 1;
@@ -271,3 +520,13 @@ fn into_proof_op<T: Encode>(root_hash: H256, proof: MerkleProof<T>) -> ProofOp {
 
     proof_op
 }
+
+fn into_account_proof_op(address: &StakedStateAddress, proof: AccountProof) -> ProofOp {
+    let mut proof_op = ProofOp::new();
+
+    proof_op.set_field_type("account".into());
+    proof_op.set_key(to_stake_key(address).to_vec());
+    proof_op.set_data(proof.encode());
+
+    proof_op
+}
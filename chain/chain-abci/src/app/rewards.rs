@@ -0,0 +1,150 @@
+use super::slashing::find_council_node;
+use super::{update_account, ChainNodeApp};
+use crate::enclave_bridge::EnclaveProxy;
+use crate::storage::tx::get_account;
+use abci::*;
+use chain_core::init::coin::Coin;
+use chain_core::state::account::StakedStateAddress;
+use chain_core::state::tendermint::{BlockHeight, TendermintVotePower};
+use log::{info, warn};
+use std::collections::BTreeMap;
+
+impl<T: EnclaveProxy> ChainNodeApp<T> {
+    /// Adds the voting power of council nodes that signed the previous block to their rewards weights
+    pub fn record_signed_blocks(&mut self, req: &RequestBeginBlock) {
+        let state = self
+            .last_state
+            .as_mut()
+            .expect("executing begin block, but no app state stored");
+        for vote in req.get_last_commit_info().votes.iter() {
+            if !vote.signed_last_block {
+                continue;
+            }
+            let validator_address = &vote.get_validator().address[..];
+            if let Some(node) = find_council_node(&state.council_nodes, validator_address) {
+                let power = self
+                    .validator_voting_power
+                    .get(&node.staking_account_address)
+                    .cloned()
+                    .unwrap_or_else(TendermintVotePower::zero);
+                let weight = state
+                    .rewards_weights
+                    .entry(node.staking_account_address)
+                    .or_insert(0);
+                *weight = weight.saturating_add(i64::from(power) as u64);
+            }
+        }
+    }
+
+    /// At the end of each epoch, distributes a part of the rewards pool to the council nodes' bonded amounts
+    /// (proportionally to their voting power in the blocks they signed during the epoch);
+    /// the council node's share is split with its delegators proportionally to the delegated stake
+    pub fn distribute_epoch_rewards(&mut self, height: BlockHeight) {
+        let state = self
+            .last_state
+            .as_mut()
+            .expect("executing end block, but no app state stored");
+        if height % BlockHeight::from(state.rewards_config.epoch_length) != 0 {
+            return;
+        }
+        let weights = std::mem::replace(&mut state.rewards_weights, BTreeMap::new());
+        let total_weight: u128 = weights.values().map(|weight| u128::from(*weight)).sum();
+        if total_weight == 0 {
+            return;
+        }
+        let rewards = u128::from(u64::from(
+            state
+                .rewards_config
+                .epoch_rewards(state.rewards_pool.remaining),
+        ));
+        let mut distributed = Coin::zero();
+        for (address, weight) in weights {
+            let share = Coin::new((rewards * u128::from(weight) / total_weight) as u64)
+                .expect("share is below the epoch rewards");
+            if share == Coin::zero() {
+                continue;
+            }
+            let account = match get_account(
+                &address,
+                &self.uncommitted_account_root_hash,
+                &self.accounts,
+            ) {
+                Ok(account) => account,
+                Err(e) => {
+                    warn!("council node staked state not found: {}", e);
+                    continue;
+                }
+            };
+            // jailed council nodes (and their delegators) forfeit their rewards (they stay in the pool)
+            if account.is_jailed() {
+                continue;
+            }
+            let delegators = self
+                .last_state
+                .as_ref()
+                .expect("executing end block, but no app state stored")
+                .delegations
+                .get(&address)
+                .cloned()
+                .unwrap_or_default();
+            let total_stake = u128::from(u64::from(account.bonded))
+                + delegators
+                    .values()
+                    .map(|amount| u128::from(u64::from(*amount)))
+                    .sum::<u128>();
+            let mut node_share = share;
+            for (delegator, amount) in delegators {
+                let delegator_share = Coin::new(
+                    (u128::from(u64::from(share)) * u128::from(u64::from(amount)) / total_stake)
+                        as u64,
+                )
+                .expect("delegator's share is below the council node's share");
+                if delegator_share == Coin::zero() {
+                    continue;
+                }
+                if self.pay_reward(&delegator, delegator_share) {
+                    node_share = (node_share - delegator_share)
+                        .expect("delegators' shares are below the council node's share");
+                    distributed = (distributed + delegator_share)
+                        .expect("distributed rewards below the pool");
+                }
+            }
+            if node_share > Coin::zero() && self.pay_reward(&address, node_share) {
+                distributed =
+                    (distributed + node_share).expect("distributed rewards below the pool");
+            }
+        }
+        info!("distributed {} from the rewards pool", distributed);
+        let rewards_pool = &mut self
+            .last_state
+            .as_mut()
+            .expect("executing end block, but no app state stored")
+            .rewards_pool;
+        rewards_pool.remaining =
+            (rewards_pool.remaining - distributed).expect("distributed rewards below the pool");
+        rewards_pool.distributed = (rewards_pool.distributed + distributed)
+            .expect("distributed rewards greater than max coin?");
+    }
+
+    /// Adds the reward to the staked state's bonded amount (returns false if the staked state wasn't found)
+    fn pay_reward(&mut self, address: &StakedStateAddress, reward: Coin) -> bool {
+        let mut account =
+            match get_account(address, &self.uncommitted_account_root_hash, &self.accounts) {
+                Ok(account) => account,
+                Err(e) => {
+                    warn!("rewarded staked state not found: {}", e);
+                    return false;
+                }
+            };
+        account.add_reward(reward);
+        let (next_account_root, _) = update_account(
+            account.clone(),
+            &self.uncommitted_account_root_hash,
+            &mut self.accounts,
+        );
+        self.set_uncommitted_account_root(next_account_root);
+        self.filter.add_staked_state_address(&account.address);
+        self.update_voting_power(&account);
+        true
+    }
+}
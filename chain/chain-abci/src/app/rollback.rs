@@ -0,0 +1,211 @@
+//! Rollback of the last committed blocks (e.g. to re-execute them after a faulty binary was run):
+//! when enabled, each commit records an undo journal entry with the previous values of all the node storage keys
+//! it writes -- the spent bits in COL_TX_META, the inserted transaction bodies / witnesses / merkle trees,
+//! the committed app hash and the previous `ChainNodeState` (with its account trie root) --
+//! so that the state before the block can be restored exactly.
+//! NOTE: the enclave storage isn't rolled back, and the account trie nodes of the rolled back roots
+//! are left behind (they can be reclaimed with `compact`); the account states of the restored height
+//! need to be retained (i.e. the account root retention needs to be at least the undo depth)
+use super::{ChainNodeApp, ChainNodeState};
+use crate::enclave_bridge::EnclaveProxy;
+use crate::storage::*;
+use chain_core::common::{MerkleTree, H256};
+use chain_core::compute_app_hash;
+use chain_core::state::tendermint::BlockHeight;
+use integer_encoding::VarInt;
+use kvdb::DBTransaction;
+use parity_scale_codec::{Decode, Encode};
+use starling::traits::Database;
+use std::collections::BTreeSet;
+
+/// The changes of the node storage made when a block was committed
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct BlockUndo {
+    /// (column, key, the value before the block -- None if the key was inserted in the block)
+    pub changes: Vec<(Option<u32>, Vec<u8>, Option<Vec<u8>>)>,
+}
+
+impl BlockUndo {
+    /// the state committed before the block (if the block's commit wrote the last state)
+    fn previous_state(&self) -> Result<Option<ChainNodeState>, String> {
+        let previous = self
+            .changes
+            .iter()
+            .find(|(col, key, _)| *col == COL_NODE_INFO && key.as_slice() == LAST_STATE_KEY)
+            .and_then(|(_, _, previous)| previous.as_ref());
+        match previous {
+            Some(encoded) => ChainNodeState::decode(&mut encoded.as_slice())
+                .map(Some)
+                .map_err(|e| format!("failed to decode the previous state: {}", e.what())),
+            None => Ok(None),
+        }
+    }
+}
+
+/// the undo journal entry of the block committed at the given height (if it was recorded and not expired)
+pub fn get_block_undo(storage: &Storage, height: BlockHeight) -> Result<Option<BlockUndo>, String> {
+    match storage
+        .db
+        .get(COL_NODE_INFO, &undo_key(height))
+        .map_err(|e| e.to_string())?
+    {
+        Some(encoded) => BlockUndo::decode(&mut encoded.to_vec().as_slice())
+            .map(Some)
+            .map_err(|e| format!("failed to decode the undo journal entry: {}", e.what())),
+        None => Ok(None),
+    }
+}
+
+impl<T: EnclaveProxy> ChainNodeApp<T> {
+    /// Records the undo journal entry of the block committed at the given height (if the undo depth is set)
+    /// with the previous values of all the keys written in the commit's DB transaction
+    /// and removes the entry of the block that left the undo depth
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - the height of the block being committed
+    /// * `dbtx` - the commit's DB transaction (with all its other changes already added)
+    pub(crate) fn record_block_undo(&self, height: BlockHeight, dbtx: &mut DBTransaction) {
+        let depth = match self.undo_depth {
+            Some(depth) => depth,
+            None => return,
+        };
+        let mut recorded = BTreeSet::new();
+        let mut changes = Vec::new();
+        for op in dbtx.ops.iter() {
+            if recorded.insert((op.col(), op.key().to_vec())) {
+                let previous = self
+                    .storage
+                    .db
+                    .get(op.col(), op.key())
+                    .expect("undo journal lookup")
+                    .map(|value| value.to_vec());
+                changes.push((op.col(), op.key().to_vec(), previous));
+            }
+        }
+        dbtx.put(
+            COL_NODE_INFO,
+            &undo_key(height),
+            &BlockUndo { changes }.encode(),
+        );
+        if height > depth {
+            dbtx.delete(COL_NODE_INFO, &undo_key(height - depth));
+        }
+    }
+}
+
+/// Checks the restored state against the app hash committed at its height:
+/// the app hash is recomputed from the stored transaction tree and the state, and its account trie root needs to be present
+fn check_restored_state(
+    storage: &Storage,
+    account_db: &Storage,
+    state: &ChainNodeState,
+    height: BlockHeight,
+) -> Result<(), String> {
+    if state.last_block_height != height {
+        return Err(format!(
+            "the restored state is at height {} instead of {}",
+            state.last_block_height, height
+        ));
+    }
+    // the app hash after InitChain is only stored as the genesis one
+    let committed_app_hash = if height == 0 {
+        storage.db.get(COL_NODE_INFO, GENESIS_APP_HASH_KEY)
+    } else {
+        storage.db.get(COL_APP_STATES, &height.encode_var_vec())
+    }
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("no app hash committed at height {}", height))?;
+    if committed_app_hash[..] != state.last_apphash[..] {
+        return Err(format!(
+            "the restored app hash doesn't match the one committed at height {}",
+            height
+        ));
+    }
+    // the tree isn't stored if there were no transactions before (e.g. the genesis without any UTXOs)
+    let tree = match storage
+        .db
+        .get(COL_MERKLE_PROOFS, &state.last_apphash[..])
+        .map_err(|e| e.to_string())?
+    {
+        Some(encoded) => MerkleTree::<H256>::decode(&mut encoded.to_vec().as_slice())
+            .map_err(|e| format!("failed to decode the transaction tree: {}", e.what()))?,
+        None => MerkleTree::new(Vec::new()),
+    };
+    let app_hash = compute_app_hash(
+        &tree,
+        &state.last_account_root_hash,
+        &state.rewards_pool,
+        &state.network_params,
+        &state.node_state_hash(),
+    );
+    if app_hash != state.last_apphash {
+        return Err("the app hash recomputed from the restored state doesn't match".to_string());
+    }
+    if state.last_account_root_hash != [0u8; 32]
+        && account_db
+            .get_node(state.last_account_root_hash)
+            .map_err(|e| e.to_string())?
+            .is_none()
+    {
+        return Err(format!(
+            "the account states at height {} were pruned",
+            height
+        ));
+    }
+    Ok(())
+}
+
+/// Rolls back the last `blocks` committed blocks using their undo journal entries
+/// and returns the restored state (nothing is changed if it doesn't match the app hash committed at its height)
+///
+/// # Arguments
+///
+/// * `storage` - the node storage
+/// * `account_db` - the database of the account trie
+/// * `blocks` - the number of the blocks to roll back
+pub fn rollback_blocks(
+    storage: &Storage,
+    account_db: &Storage,
+    blocks: BlockHeight,
+) -> Result<ChainNodeState, String> {
+    let encoded_state = storage
+        .db
+        .get(COL_NODE_INFO, LAST_STATE_KEY)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "no committed app state stored".to_string())?;
+    let last_state = ChainNodeState::decode(&mut encoded_state.to_vec().as_slice())
+        .map_err(|e| format!("failed to decode the last state: {}", e.what()))?;
+    let last_height = last_state.last_block_height;
+    if blocks <= 0 || blocks > last_height {
+        return Err(format!(
+            "can't roll back {} blocks at height {}",
+            blocks, last_height
+        ));
+    }
+    let target_height = last_height - blocks;
+    let mut dbtx = storage.db.transaction();
+    let mut restored_state = None;
+    // the entries are applied from the last block, so the values before the earliest block are written last
+    for height in (target_height + 1..=last_height).rev() {
+        let undo = get_block_undo(storage, height)?.ok_or_else(|| {
+            format!(
+                "no undo journal entry for the block at height {} (only the blocks in the undo depth can be rolled back)",
+                height
+            )
+        })?;
+        restored_state = undo.previous_state()?;
+        for (col, key, previous) in undo.changes {
+            match previous {
+                Some(value) => dbtx.put(col, &key, &value),
+                None => dbtx.delete(col, &key),
+            }
+        }
+        dbtx.delete(COL_NODE_INFO, &undo_key(height));
+    }
+    let restored_state = restored_state
+        .ok_or_else(|| format!("no state committed before height {}", target_height + 1))?;
+    check_restored_state(storage, account_db, &restored_state, target_height)?;
+    storage.db.write(dbtx).map_err(|e| e.to_string())?;
+    Ok(restored_state)
+}
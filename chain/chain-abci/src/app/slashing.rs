@@ -0,0 +1,142 @@
+use super::{update_account, ChainNodeApp};
+use crate::enclave_bridge::EnclaveProxy;
+use crate::storage::tx::get_account;
+use abci::*;
+use chain_core::init::config::SlashRatio;
+use chain_core::state::account::StakedStateAddress;
+use chain_core::state::tendermint::{BlockHeight, TendermintVotePower};
+use chain_core::state::CouncilNode;
+use log::{info, warn};
+use parity_scale_codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+/// signatures of a council node in the recent blocks (a ring buffer over the block signing window)
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Encode, Decode)]
+pub struct ValidatorLiveness {
+    /// whether the block at `height % window` was signed
+    signed: Vec<bool>,
+}
+
+impl ValidatorLiveness {
+    /// starts with all blocks in the window marked as signed
+    pub fn new(block_signing_window: u16) -> Self {
+        ValidatorLiveness {
+            signed: vec![true; usize::from(block_signing_window)],
+        }
+    }
+
+    /// records whether the block at the given height was signed
+    pub fn update(&mut self, height: BlockHeight, signed: bool) {
+        let window = self.signed.len() as BlockHeight;
+        if window > 0 {
+            self.signed[(height % window) as usize] = signed;
+        }
+    }
+
+    /// number of blocks in the window that weren't signed
+    pub fn missed(&self) -> usize {
+        self.signed.iter().filter(|signed| !**signed).count()
+    }
+}
+
+/// finds the council node with the given Tendermint validator address
+pub fn find_council_node<'a>(
+    council_nodes: &'a [CouncilNode],
+    validator_address: &[u8],
+) -> Option<&'a CouncilNode> {
+    council_nodes
+        .iter()
+        .find(|node| node.consensus_pubkey.validator_address()[..] == *validator_address)
+}
+
+impl<T: EnclaveProxy> ChainNodeApp<T> {
+    /// Punishes council nodes in BeginBlock: the ones with evidence of byzantine faults
+    /// and the ones that missed too many blocks in the signing window
+    pub fn slash_faulty_council_nodes(&mut self, req: &RequestBeginBlock) {
+        let state = self
+            .last_state
+            .as_ref()
+            .expect("executing begin block, but no app state stored");
+        let slashing_config = state.slashing_config.clone();
+        let mut punished = Vec::new();
+        for evidence in req.byzantine_validators.iter() {
+            let validator_address = &evidence.get_validator().address[..];
+            if let Some(node) = find_council_node(&state.council_nodes, validator_address) {
+                punished.push((
+                    node.staking_account_address,
+                    slashing_config.byzantine_slash_ratio,
+                ));
+            }
+        }
+        // votes in the last commit are for the previous block
+        let signed_height = req.get_header().height - 1;
+        let mut liveness_updates = Vec::new();
+        for vote in req.get_last_commit_info().votes.iter() {
+            let validator_address = &vote.get_validator().address[..];
+            if let Some(node) = find_council_node(&state.council_nodes, validator_address) {
+                // jailed or not-yet-active council nodes aren't tracked
+                let active = self
+                    .validator_voting_power
+                    .get(&node.staking_account_address)
+                    .map_or(false, |power| *power > TendermintVotePower::zero());
+                if active {
+                    liveness_updates.push((node.staking_account_address, vote.signed_last_block));
+                }
+            }
+        }
+        let state = self
+            .last_state
+            .as_mut()
+            .expect("executing begin block, but no app state stored");
+        for (address, signed) in liveness_updates {
+            let liveness = state
+                .validator_liveness
+                .entry(address)
+                .or_insert_with(|| ValidatorLiveness::new(slashing_config.block_signing_window));
+            liveness.update(signed_height, signed);
+            if liveness.missed() > usize::from(slashing_config.missed_block_threshold) {
+                punished.push((address, slashing_config.liveness_slash_ratio));
+            }
+        }
+        for (address, ratio) in punished {
+            self.slash_and_jail(&address, ratio);
+        }
+    }
+
+    /// Slashes the bonded amount of the council node's staked state into the rewards pool,
+    /// jails it until the jail period passes and sets its voting power to zero
+    fn slash_and_jail(&mut self, address: &StakedStateAddress, ratio: SlashRatio) {
+        let mut account =
+            match get_account(address, &self.uncommitted_account_root_hash, &self.accounts) {
+                Ok(account) => account,
+                Err(e) => {
+                    warn!("council node staked state not found: {}", e);
+                    return;
+                }
+            };
+        if account.is_jailed() {
+            return;
+        }
+        let state = self
+            .last_state
+            .as_mut()
+            .expect("executing begin block, but no app state stored");
+        let jailed_until = state.block_time + i64::from(state.slashing_config.jail_duration);
+        let slashed = account.slash_and_jail(ratio, jailed_until);
+        info!(
+            "jailing council node {} until {} (slashed: {})",
+            address, jailed_until, slashed
+        );
+        state.rewards_pool.remaining = (state.rewards_pool.remaining + slashed)
+            .expect("rewards pool + slashed amount greater than max coin?");
+        state.validator_liveness.remove(address);
+        let (next_account_root, _) = update_account(
+            account,
+            &self.uncommitted_account_root_hash,
+            &mut self.accounts,
+        );
+        self.set_uncommitted_account_root(next_account_root);
+        self.power_changed_in_block
+            .insert(*address, TendermintVotePower::zero());
+    }
+}
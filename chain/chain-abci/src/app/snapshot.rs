@@ -0,0 +1,590 @@
+//! State snapshots: the last committed app state, the staked states in its account trie
+//! and the UTXO metadata / transaction bodies / witnesses / extra information columns of the node storage,
+//! so that a new node can be bootstrapped without replaying all blocks since genesis.
+//!
+//! The file is a sequence of records (4-byte little endian length + SCALE-encoded data):
+//! a `SnapshotHeader`, the entries and the final entry with the checksum of all the previous records.
+//!
+//! The app hash commits to the account trie, the IDs of the last block's transactions and the rest of the app state
+//! (the rewards pool, the network parameters and the node state, see `ChainNodeState::node_state_hash`),
+//! so the snapshotted app state is verified against the trusted app hash. The snapshotted node storage columns
+//! (UTXO metadata, bodies, witnesses...) aren't committed to by the app hash: they are only covered by the checksum,
+//! which detects corruption, but not tampering -- i.e. they are trusted to come from an honest node.
+//!
+//! NOTE: the enclave storage (sealed transaction data) is not included, as it's sealed to the enclave
+//! of the node that wrote it: it needs to be restored separately (e.g. from a backup of the same machine),
+//! otherwise the enclave can't validate the transactions spending the outputs created before the snapshot height
+//! (the import command checks it with the enclave)
+use super::state_migration::APP_STATE_VERSION;
+use super::ChainNodeState;
+use crate::storage::account::migration::ACCOUNT_STORAGE_VERSION;
+use crate::storage::account::{
+    collect_values, persist_account_nodes, AccountStorage, AccountWrapper,
+};
+use crate::storage::tx::StarlingFixedKey;
+use crate::storage::tx_migration::TX_STORAGE_VERSION;
+use crate::storage::*;
+use blake2::{Blake2s, Digest};
+use chain_core::common::{MerkleTree, H256};
+use chain_core::compute_app_hash;
+use chain_core::state::account::StakedState;
+use chain_core::state::tendermint::BlockHeight;
+use integer_encoding::VarInt;
+use log::{error, info};
+use parity_scale_codec::{Decode, Encode};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+/// version of the snapshot file format
+pub const SNAPSHOT_FORMAT_VERSION: u8 = 1;
+
+/// the node storage columns included in snapshots
+const SNAPSHOT_COLUMNS: [Option<u32>; 5] = [
+    COL_TX_META,
+    COL_WITNESS,
+    COL_BODIES,
+    COL_EXTRA,
+    COL_TX_INCLUSION,
+];
+
+/// upper bound on the size of a single record (to reject corrupted lengths before allocating)
+const MAX_RECORD_SIZE: usize = 64 * 1024 * 1024;
+
+/// the number of node storage entries written to the database at once during the import
+const IMPORT_BATCH_SIZE: usize = 10_000;
+
+/// Metadata of the snapshotted state
+#[derive(Debug, Encode, Decode)]
+pub struct SnapshotHeader {
+    /// version of the snapshot file format
+    pub format_version: u8,
+    /// the chain ID set in Tendermint genesis.json
+    pub chain_id: Vec<u8>,
+    /// the app hash after InitChain
+    pub genesis_app_hash: H256,
+    /// version of the staked state encoding in the account trie
+    pub account_storage_version: u8,
+    /// the last committed app state
+    pub state: ChainNodeState,
+    /// merkle tree of the transaction IDs the last app hash was computed from
+    pub tx_tree: MerkleTree<H256>,
+}
+
+#[derive(Encode, Decode)]
+enum SnapshotEntry {
+    /// staked state in the account trie (the trie key and the encoded staked state)
+    Account(StarlingFixedKey, Vec<u8>),
+    /// key-value pair in one of the snapshotted node storage columns
+    Column {
+        column: u32,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+    /// the number of the preceding entries and the checksum of all the preceding records
+    End { entries: u64, checksum: H256 },
+}
+
+/// Errors in writing / restoring snapshots
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// reading or writing the snapshot file failed
+    Io(io::Error),
+    /// the snapshot file is malformed or truncated
+    InvalidFormat(String),
+    /// the node storage or the account trie storage couldn't be read or written
+    Storage(String),
+    /// there is no committed state to be snapshotted
+    NoCommittedState,
+    /// snapshots are only restored to an empty data directory
+    StateAlreadyStored,
+    /// the snapshot doesn't match the node's network or the trusted app hash
+    Mismatch(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "snapshot I/O error: {}", e),
+            SnapshotError::InvalidFormat(e) => write!(f, "invalid snapshot: {}", e),
+            SnapshotError::Storage(e) => write!(f, "storage error: {}", e),
+            SnapshotError::NoCommittedState => write!(f, "no committed app state stored"),
+            SnapshotError::StateAlreadyStored => write!(
+                f,
+                "app state already stored (snapshots can only be restored to an empty data directory)"
+            ),
+            SnapshotError::Mismatch(e) => write!(f, "snapshot mismatch: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+/// When and where the node writes snapshots of its committed state
+#[derive(Debug, Clone)]
+pub struct SnapshotConfig {
+    /// the directory the snapshot files are written to
+    pub dir: PathBuf,
+    /// a snapshot is written after each block whose height is a multiple of this interval
+    pub interval: BlockHeight,
+}
+
+impl SnapshotConfig {
+    /// the path of the snapshot file of the state committed at the given height
+    pub fn snapshot_path(&self, height: BlockHeight) -> PathBuf {
+        self.dir.join(format!("snapshot-{}.bin", height))
+    }
+}
+
+/// writes SCALE-encoded records and computes the checksum of the written data
+struct RecordWriter<W: Write> {
+    writer: W,
+    hasher: Blake2s,
+}
+
+impl<W: Write> RecordWriter<W> {
+    fn write<T: Encode>(&mut self, record: &T) -> io::Result<()> {
+        let encoded = record.encode();
+        let length = (encoded.len() as u32).to_le_bytes();
+        self.hasher.input(&length);
+        self.hasher.input(&encoded);
+        self.writer.write_all(&length)?;
+        self.writer.write_all(&encoded)
+    }
+}
+
+/// reads SCALE-encoded records and computes the checksum of the read data
+struct RecordReader<R: Read> {
+    reader: R,
+    hasher: Blake2s,
+    /// the number of the snapshot entries read so far
+    entries: u64,
+}
+
+impl<R: Read> RecordReader<R> {
+    fn new(reader: R) -> Self {
+        RecordReader {
+            reader,
+            hasher: Blake2s::new(),
+            entries: 0,
+        }
+    }
+
+    fn read<T: Decode>(&mut self) -> Result<T, SnapshotError> {
+        let mut length = [0u8; 4];
+        self.reader.read_exact(&mut length)?;
+        let size = u32::from_le_bytes(length) as usize;
+        if size > MAX_RECORD_SIZE {
+            return Err(SnapshotError::InvalidFormat(format!(
+                "record size {} over the limit",
+                size
+            )));
+        }
+        let mut encoded = vec![0u8; size];
+        self.reader.read_exact(&mut encoded)?;
+        self.hasher.input(&length);
+        self.hasher.input(&encoded);
+        T::decode(&mut encoded.as_slice())
+            .map_err(|e| SnapshotError::InvalidFormat(e.what().to_string()))
+    }
+
+    /// reads the next snapshot entry; None after the final entry (if the number of entries and the checksum match)
+    fn read_entry(&mut self) -> Result<Option<SnapshotEntry>, SnapshotError> {
+        let checksum = checksum(&self.hasher);
+        match self.read()? {
+            SnapshotEntry::End {
+                entries: expected_entries,
+                checksum: expected_checksum,
+            } => {
+                if self.entries != expected_entries || checksum != expected_checksum {
+                    return Err(SnapshotError::InvalidFormat("checksum mismatch".to_owned()));
+                }
+                Ok(None)
+            }
+            entry => {
+                self.entries += 1;
+                Ok(Some(entry))
+            }
+        }
+    }
+}
+
+/// the checksum of the records written / read so far
+fn checksum(hasher: &Blake2s) -> H256 {
+    let mut checksum = [0u8; 32];
+    checksum.copy_from_slice(&hasher.clone().result());
+    checksum
+}
+
+fn check_column(column: u32) -> Result<(), SnapshotError> {
+    if SNAPSHOT_COLUMNS.contains(&Some(column)) {
+        Ok(())
+    } else {
+        Err(SnapshotError::InvalidFormat(format!(
+            "unexpected column {}",
+            column
+        )))
+    }
+}
+
+fn storage_error<E: fmt::Display>(e: E) -> SnapshotError {
+    SnapshotError::Storage(e.to_string())
+}
+
+/// the key-value pairs of a node storage column
+type ColumnIterator<'a> = Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a>;
+
+/// The last committed state and the iterators over the snapshotted columns: the iterators are positioned
+/// at the storage state they were created in, so the view stays consistent while the following blocks are committed
+struct SnapshotView<'a> {
+    header: SnapshotHeader,
+    columns: Vec<(u32, ColumnIterator<'a>)>,
+}
+
+/// Takes the view of the last committed state in the node storage
+fn take_snapshot_view(storage: &Storage) -> Result<SnapshotView<'_>, SnapshotError> {
+    let db = &storage.db;
+    let state = match db
+        .get(COL_NODE_INFO, LAST_STATE_KEY)
+        .map_err(storage_error)?
+    {
+        Some(encoded) => ChainNodeState::decode(&mut encoded.to_vec().as_slice())
+            .map_err(|e| SnapshotError::Storage(e.what().to_string()))?,
+        None => return Err(SnapshotError::NoCommittedState),
+    };
+    let chain_id = db
+        .get(COL_EXTRA, CHAIN_ID_KEY)
+        .map_err(storage_error)?
+        .ok_or_else(|| SnapshotError::Storage("no chain id stored".to_owned()))?
+        .to_vec();
+    let mut genesis_app_hash = [0u8; 32];
+    genesis_app_hash.copy_from_slice(
+        &db.get(COL_NODE_INFO, GENESIS_APP_HASH_KEY)
+            .map_err(storage_error)?
+            .ok_or_else(|| SnapshotError::Storage("no genesis app hash stored".to_owned()))?,
+    );
+    // the tree isn't stored if the last app hash is the genesis one without the genesis transaction
+    let tx_tree: MerkleTree<H256> = match db
+        .get(COL_MERKLE_PROOFS, &state.last_apphash[..])
+        .map_err(storage_error)?
+    {
+        Some(encoded) => MerkleTree::decode(&mut encoded.to_vec().as_slice())
+            .map_err(|e| SnapshotError::Storage(e.what().to_string()))?,
+        None => MerkleTree::new(vec![]),
+    };
+    let columns = SNAPSHOT_COLUMNS
+        .iter()
+        .map(move |column| (column.expect("snapshot column"), db.iter(*column)))
+        .collect();
+    Ok(SnapshotView {
+        header: SnapshotHeader {
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            chain_id,
+            genesis_app_hash,
+            account_storage_version: ACCOUNT_STORAGE_VERSION,
+            state,
+            tx_tree,
+        },
+        columns,
+    })
+}
+
+/// Writes the snapshot of the viewed state; returns the snapshotted state
+/// (the account trie nodes of the committed root are immutable, so they are read from the current account trie storage)
+fn write_snapshot_view<W: Write>(
+    writer: W,
+    view: SnapshotView<'_>,
+    account_db: &Storage,
+) -> Result<ChainNodeState, SnapshotError> {
+    let mut values = Vec::new();
+    collect_values(
+        account_db,
+        &view.header.state.last_account_root_hash,
+        &mut values,
+    )
+    .map_err(storage_error)?;
+
+    let mut records = RecordWriter {
+        writer: BufWriter::new(writer),
+        hasher: Blake2s::new(),
+    };
+    records.write(&view.header)?;
+    let mut entries: u64 = 0;
+    for (key, value) in values {
+        records.write(&SnapshotEntry::Account(key, value))?;
+        entries += 1;
+    }
+    for (column, iter) in view.columns {
+        for (key, value) in iter {
+            records.write(&SnapshotEntry::Column {
+                column,
+                key: key.to_vec(),
+                value: value.to_vec(),
+            })?;
+            entries += 1;
+        }
+    }
+    let checksum = checksum(&records.hasher);
+    records.write(&SnapshotEntry::End { entries, checksum })?;
+    records.writer.flush()?;
+    Ok(view.header.state)
+}
+
+/// Writes the snapshot of the last committed state in the storage; returns the snapshotted state
+///
+/// # Arguments
+///
+/// * `writer` - where the snapshot is written to
+/// * `storage` - the node storage
+/// * `accounts` - the account trie storage
+pub fn write_snapshot<W: Write>(
+    writer: W,
+    storage: &Storage,
+    accounts: &AccountStorage,
+) -> Result<ChainNodeState, SnapshotError> {
+    let view = take_snapshot_view(storage)?;
+    write_snapshot_view(writer, view, accounts.db())
+}
+
+/// Writes the snapshot of the viewed state to the snapshot directory
+/// (to a temporary file first, so that only complete snapshots are found there); returns its path
+fn write_snapshot_file(
+    config: &SnapshotConfig,
+    view: SnapshotView<'_>,
+    account_db: &Storage,
+) -> Result<PathBuf, SnapshotError> {
+    fs::create_dir_all(&config.dir)?;
+    let tmp_path = config.dir.join("snapshot.tmp");
+    let state = write_snapshot_view(fs::File::create(&tmp_path)?, view, account_db)?;
+    let path = config.snapshot_path(state.last_block_height);
+    fs::rename(&tmp_path, &path)?;
+    Ok(path)
+}
+
+/// resets the in-progress flag when the snapshot thread finishes (even if it panics)
+struct InProgressGuard(Arc<AtomicBool>);
+
+impl Drop for InProgressGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Writes the snapshot of the last committed state to the snapshot directory in a background thread
+/// (the result is only logged, as the node can continue without it).
+/// It returns once the thread has taken the view of the committed state, so the following blocks
+/// can be committed while the snapshot is written; `in_progress` is reset when the thread finishes.
+///
+/// # Arguments
+///
+/// * `config` - where the snapshot is written to
+/// * `storage` - the node storage
+/// * `account_db` - the account trie storage (the trie nodes of the snapshotted root mustn't be pruned while `in_progress` is set)
+/// * `in_progress` - the flag set while the snapshot is being written
+pub fn spawn_snapshot_writer(
+    config: SnapshotConfig,
+    storage: Storage,
+    account_db: Storage,
+    in_progress: Arc<AtomicBool>,
+) {
+    let (view_taken, view_ready) = mpsc::channel();
+    thread::spawn(move || {
+        let _in_progress = InProgressGuard(in_progress);
+        let view = take_snapshot_view(&storage);
+        let _ = view_taken.send(());
+        match view.and_then(|view| write_snapshot_file(&config, view, &account_db)) {
+            Ok(path) => info!("snapshot written to {:?}", path),
+            Err(e) => error!("failed to write the snapshot: {}", e),
+        }
+    });
+    // the channel is also closed if the thread panicked before taking the view
+    let _ = view_ready.recv();
+}
+
+/// Restores the snapshotted state to empty storages: the state is only stored
+/// if its app hash is computed from the snapshotted account trie and app state and matches the trusted app hash.
+/// The snapshot is read twice: the node storage is only written to in the second pass,
+/// after the checksum and the app hash were verified in the first one.
+/// Returns the restored state.
+///
+/// NOTE: the snapshotted node storage columns (UTXO metadata, bodies, witnesses...) aren't committed to by the app hash,
+/// so they are trusted to come from an honest node (see the module documentation)
+///
+/// # Arguments
+///
+/// * `reader` - the snapshot data
+/// * `storage` - the (empty) node storage
+/// * `accounts` - the (empty) account trie storage
+/// * `chain_id` - the chain ID set in Tendermint genesis.json
+/// * `genesis_app_hash` - the expected app hash after InitChain
+/// * `trusted_app_hash` - the app hash committed at the snapshot height (e.g. in the header of the next block)
+pub fn restore_snapshot<R: Read + Seek>(
+    mut reader: R,
+    storage: &Storage,
+    accounts: &mut AccountStorage,
+    chain_id: &str,
+    genesis_app_hash: &H256,
+    trusted_app_hash: &H256,
+) -> Result<ChainNodeState, SnapshotError> {
+    let db = &storage.db;
+    if db
+        .get(COL_NODE_INFO, LAST_STATE_KEY)
+        .map_err(storage_error)?
+        .is_some()
+    {
+        return Err(SnapshotError::StateAlreadyStored);
+    }
+    let start = reader.seek(SeekFrom::Current(0))?;
+    let mut records = RecordReader::new(BufReader::new(&mut reader));
+    let header: SnapshotHeader = records.read()?;
+    if header.format_version != SNAPSHOT_FORMAT_VERSION {
+        return Err(SnapshotError::InvalidFormat(format!(
+            "unsupported format version {}",
+            header.format_version
+        )));
+    }
+    if header.chain_id != chain_id.as_bytes() {
+        return Err(SnapshotError::Mismatch("different chain id".to_owned()));
+    }
+    if header.genesis_app_hash != *genesis_app_hash {
+        return Err(SnapshotError::Mismatch(
+            "different genesis app hash".to_owned(),
+        ));
+    }
+    if header.account_storage_version != ACCOUNT_STORAGE_VERSION {
+        return Err(SnapshotError::Mismatch(format!(
+            "account storage version {} (expected {})",
+            header.account_storage_version, ACCOUNT_STORAGE_VERSION
+        )));
+    }
+    let state = header.state;
+    if state.last_apphash != *trusted_app_hash {
+        return Err(SnapshotError::Mismatch(format!(
+            "snapshot app hash {} is not the trusted one",
+            hex::encode(&state.last_apphash)
+        )));
+    }
+
+    // the first pass: the staked states are inserted into the account trie (persisted only once all is verified)
+    let mut keys = Vec::new();
+    let mut wrapped = Vec::new();
+    while let Some(entry) = records.read_entry()? {
+        match entry {
+            SnapshotEntry::Account(key, value) => {
+                let account = StakedState::decode(&mut value.as_slice())
+                    .map_err(|e| SnapshotError::InvalidFormat(e.what().to_string()))?;
+                keys.push(key);
+                wrapped.push(AccountWrapper(account));
+            }
+            SnapshotEntry::Column { column, .. } => check_column(column)?,
+            SnapshotEntry::End { .. } => unreachable!("the final entry is checked when read"),
+        }
+    }
+    let verified_checksum = checksum(&records.hasher);
+
+    let account_root = if keys.is_empty() {
+        [0u8; 32]
+    } else {
+        accounts
+            .insert(None, &mut keys, &wrapped)
+            .map_err(storage_error)?
+    };
+    if account_root != state.last_account_root_hash {
+        return Err(SnapshotError::Mismatch(
+            "staked states don't match the account trie root".to_owned(),
+        ));
+    }
+    let app_hash = compute_app_hash(
+        &header.tx_tree,
+        &account_root,
+        &state.rewards_pool,
+        &state.network_params,
+        &state.node_state_hash(),
+    );
+    if app_hash != state.last_apphash {
+        return Err(SnapshotError::Mismatch(
+            "computed app hash doesn't match the snapshotted state".to_owned(),
+        ));
+    }
+
+    // the second pass: the verified node storage columns are written
+    drop(records);
+    reader.seek(SeekFrom::Start(start))?;
+    let mut records = RecordReader::new(BufReader::new(&mut reader));
+    let _: SnapshotHeader = records.read()?;
+    let mut dbtx = db.transaction();
+    let mut batch_size = 0;
+    while let Some(entry) = records.read_entry()? {
+        if let SnapshotEntry::Column { column, key, value } = entry {
+            check_column(column)?;
+            dbtx.put(Some(column), &key, &value);
+            batch_size += 1;
+            if batch_size == IMPORT_BATCH_SIZE {
+                db.write(dbtx).map_err(storage_error)?;
+                dbtx = db.transaction();
+                batch_size = 0;
+            }
+        }
+    }
+    // (if the snapshot was modified during the import, the storage isn't marked as initialized
+    // and needs to be removed before the next attempt)
+    if checksum(&records.hasher) != verified_checksum {
+        return Err(SnapshotError::InvalidFormat(
+            "snapshot modified during the import".to_owned(),
+        ));
+    }
+    persist_account_nodes(accounts).map_err(storage_error)?;
+
+    dbtx.put(COL_EXTRA, CHAIN_ID_KEY, chain_id.as_bytes());
+    dbtx.put(COL_NODE_INFO, GENESIS_APP_HASH_KEY, genesis_app_hash);
+    dbtx.put(
+        COL_NODE_INFO,
+        ACCOUNT_STORAGE_VERSION_KEY,
+        &[ACCOUNT_STORAGE_VERSION],
+    );
+    dbtx.put(COL_NODE_INFO, TX_STORAGE_VERSION_KEY, &[TX_STORAGE_VERSION]);
+    dbtx.put(COL_NODE_INFO, APP_STATE_VERSION_KEY, &[APP_STATE_VERSION]);
+    dbtx.put(COL_MERKLE_PROOFS, &app_hash[..], &header.tx_tree.encode());
+    dbtx.put(
+        COL_APP_STATES,
+        &i64::encode_var_vec(state.last_block_height),
+        &app_hash,
+    );
+    let encoded_state = state.encode();
+    dbtx.put(
+        COL_NODE_INFO,
+        &app_state_key(state.last_block_height),
+        &encoded_state,
+    );
+    // the app state is stored last: its presence marks the storage as initialized
+    dbtx.put(COL_NODE_INFO, LAST_STATE_KEY, &encoded_state);
+    db.write(dbtx).map_err(storage_error)?;
+    Ok(state)
+}
+
+/// Restores the snapshot file (see `restore_snapshot`)
+pub fn restore_snapshot_file(
+    path: &Path,
+    storage: &Storage,
+    accounts: &mut AccountStorage,
+    chain_id: &str,
+    genesis_app_hash: &H256,
+    trusted_app_hash: &H256,
+) -> Result<ChainNodeState, SnapshotError> {
+    restore_snapshot(
+        fs::File::open(path)?,
+        storage,
+        accounts,
+        chain_id,
+        genesis_app_hash,
+        trusted_app_hash,
+    )
+}
@@ -0,0 +1,204 @@
+//! Migration of the last committed app state (`ChainNodeState`) stored by the initial release:
+//! it didn't have the network parameters changeable by proposals, punishments, rewards distribution and delegations,
+//! so they are initialized (the punishments and the rewards distribution with the parameters in `upgraded_state`).
+use super::ChainNodeState;
+use crate::storage::tx::StarlingFixedKey;
+use crate::storage::*;
+use chain_core::common::{Timespec, H256};
+use chain_core::init::coin::Coin;
+use chain_core::init::config::{RewardsParameters, SlashRatio, SlashingParameters};
+use chain_core::state::governance::NetworkParameters;
+use chain_core::state::tendermint::BlockHeight;
+use chain_core::state::{CouncilNode, RewardsPoolState};
+use chain_core::tx::fee::{LinearFee, Milli};
+use parity_scale_codec::{Decode, Encode};
+use std::collections::BTreeMap;
+
+/// version of the app state encoding:
+/// 0 -- the initial encoding (not stored, as it was the initial encoding)
+/// 1 -- network parameters, punishments, rewards distribution, delegations and parameter change proposals
+pub const APP_STATE_VERSION: u8 = 1;
+
+/// the rewards pool state in version 0 of the app state
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+struct RewardsPoolStateV0 {
+    remaining: Coin,
+    last_block_height: BlockHeight,
+}
+
+/// the app state encoding in version 0
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+struct ChainNodeStateV0 {
+    last_block_height: BlockHeight,
+    last_apphash: H256,
+    block_time: Timespec,
+    last_account_root_hash: StarlingFixedKey,
+    rewards_pool: RewardsPoolStateV0,
+    fee_policy: LinearFee,
+    unbonding_period: u32,
+    required_council_node_stake: Coin,
+    council_nodes: Vec<CouncilNode>,
+}
+
+/// The state of a chain upgraded from the initial release, which didn't punish council nodes or distribute the rewards pool:
+/// nothing is slashed and jailed council nodes can unjail right away (a council node is only jailed for byzantine faults
+/// or if it missed all the blocks in the signing window), and nothing is distributed from the rewards pool.
+fn upgraded_state(state: ChainNodeStateV0) -> ChainNodeState {
+    let no_slashing = SlashRatio::new(Milli::integral(0)).expect("zero slash ratio");
+    ChainNodeState {
+        last_block_height: state.last_block_height,
+        last_apphash: state.last_apphash,
+        block_time: state.block_time,
+        last_account_root_hash: state.last_account_root_hash,
+        rewards_pool: RewardsPoolState::new(
+            state.rewards_pool.remaining,
+            state.rewards_pool.last_block_height,
+        ),
+        network_params: NetworkParameters {
+            fee_policy: state.fee_policy,
+            unbonding_period: state.unbonding_period,
+            required_council_node_stake: state.required_council_node_stake,
+        },
+        council_nodes: state.council_nodes,
+        slashing_config: SlashingParameters {
+            jail_duration: 0,
+            byzantine_slash_ratio: no_slashing,
+            liveness_slash_ratio: no_slashing,
+            block_signing_window: 100,
+            missed_block_threshold: 99,
+        },
+        validator_liveness: BTreeMap::new(),
+        rewards_config: RewardsParameters {
+            epoch_length: 1,
+            reward_rate: Milli::integral(0),
+        },
+        rewards_weights: BTreeMap::new(),
+        delegations: BTreeMap::new(),
+        param_change_proposals: BTreeMap::new(),
+    }
+}
+
+/// decodes the app state stored in the given version of its encoding
+fn decode_app_state(version: u8, value: &[u8]) -> Result<ChainNodeState, String> {
+    let mut data = value;
+    let decoded = match version {
+        0 => ChainNodeStateV0::decode(&mut data).map(upgraded_state),
+        _ => ChainNodeState::decode(&mut data),
+    };
+    decoded.map_err(|e| format!("failed to decode the app state: {}", e.what()))
+}
+
+/// Re-encodes the last committed app state in the current encoding if it was stored by an older version
+/// and stores the app state version (in the same DB transaction).
+/// Returns whether a stored app state was migrated.
+///
+/// # Arguments
+///
+/// * `storage` - the node storage
+pub fn migrate_app_state(storage: &Storage) -> Result<bool, String> {
+    let stored_version = storage
+        .db
+        .get(COL_NODE_INFO, APP_STATE_VERSION_KEY)
+        .map_err(|e| e.to_string())?
+        .map_or(0, |version| version[0]);
+    if stored_version >= APP_STATE_VERSION {
+        return Ok(false);
+    }
+    let mut dbtx = storage.db.transaction();
+    let stored_state = storage
+        .db
+        .get(COL_NODE_INFO, LAST_STATE_KEY)
+        .map_err(|e| e.to_string())?;
+    if let Some(ref encoded) = stored_state {
+        let last_state = decode_app_state(stored_version, &encoded)?;
+        let encoded = last_state.encode();
+        dbtx.put(
+            COL_NODE_INFO,
+            &app_state_key(last_state.last_block_height),
+            &encoded,
+        );
+        dbtx.put(COL_NODE_INFO, LAST_STATE_KEY, &encoded);
+    }
+    dbtx.put(COL_NODE_INFO, APP_STATE_VERSION_KEY, &[APP_STATE_VERSION]);
+    storage.db.write(dbtx).map_err(|e| e.to_string())?;
+    Ok(stored_state.is_some())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chain_core::init::address::RedeemAddress;
+    use chain_core::state::account::StakedStateAddress;
+    use chain_core::state::tendermint::TendermintValidatorPubKey;
+    use kvdb_memorydb::create;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_migrate_initial_app_state() {
+        let storage = Storage::new_db(Arc::new(create(NUM_COLUMNS.unwrap())));
+        // as encoded by the initial release:
+        // height, app hash, block time, account root, rewards pool (remaining, height),
+        // fee policy (constant, coefficient), unbonding period, required stake, one council node
+        let mut encoded = Vec::new();
+        encoded.extend_from_slice(&7i64.to_le_bytes());
+        encoded.extend_from_slice(&[0xaa; 32]);
+        encoded.extend_from_slice(&1_000i64.to_le_bytes());
+        encoded.extend_from_slice(&[0xbb; 32]);
+        encoded.extend_from_slice(&500u64.to_le_bytes());
+        encoded.extend_from_slice(&6i64.to_le_bytes());
+        encoded.extend_from_slice(&1_100u64.to_le_bytes());
+        encoded.extend_from_slice(&1_250u64.to_le_bytes());
+        encoded.extend_from_slice(&60u32.to_le_bytes());
+        encoded.extend_from_slice(&50u64.to_le_bytes());
+        encoded.push(0x04);
+        encoded.push(0x00);
+        encoded.extend_from_slice(&[0x11; 20]);
+        encoded.push(0x00);
+        encoded.extend_from_slice(&[0x22; 32]);
+        encoded.extend_from_slice(&3u64.to_le_bytes());
+        let mut inittx = storage.db.transaction();
+        inittx.put(COL_NODE_INFO, LAST_STATE_KEY, &encoded);
+        storage.db.write(inittx).unwrap();
+
+        assert_eq!(migrate_app_state(&storage), Ok(true));
+        let stored = storage
+            .db
+            .get(COL_NODE_INFO, LAST_STATE_KEY)
+            .unwrap()
+            .unwrap();
+        let state = ChainNodeState::decode(&mut stored.to_vec().as_slice()).unwrap();
+        assert_eq!(state.last_block_height, 7);
+        assert_eq!(state.last_apphash, [0xaa; 32]);
+        assert_eq!(state.block_time, 1_000);
+        assert_eq!(state.last_account_root_hash, [0xbb; 32]);
+        assert_eq!(
+            state.rewards_pool,
+            RewardsPoolState::new(Coin::new(500).unwrap(), 6)
+        );
+        assert_eq!(
+            state.network_params,
+            NetworkParameters {
+                fee_policy: LinearFee::new(Milli::new(1, 100), Milli::new(1, 250)),
+                unbonding_period: 60,
+                required_council_node_stake: Coin::new(50).unwrap(),
+            }
+        );
+        let mut council_node = CouncilNode::new(
+            StakedStateAddress::BasicRedeem(RedeemAddress::from([0x11; 20])),
+            TendermintValidatorPubKey::Ed25519([0x22; 32]),
+        );
+        council_node.nonce = 3;
+        assert_eq!(state.council_nodes, vec![council_node]);
+        assert!(state.delegations.is_empty());
+        assert!(state.param_change_proposals.is_empty());
+        let at_height = storage
+            .db
+            .get(COL_NODE_INFO, &app_state_key(7))
+            .unwrap()
+            .unwrap();
+        assert_eq!(at_height.to_vec(), stored.to_vec());
+
+        // already migrated
+        assert_eq!(migrate_app_state(&storage), Ok(false));
+    }
+}
@@ -1,28 +1,40 @@
-use super::ChainNodeApp;
+use super::{ChainNodeApp, ChainNodeState};
 use crate::enclave_bridge::EnclaveProxy;
-use crate::storage::tx::verify;
+use crate::storage::tx::verify_with_pending;
 use abci::*;
-use chain_core::state::account::StakedState;
+use chain_core::state::account::{DelegateTx, NodeJoinRequestTx, StakedState, StakedStateAddress};
+use chain_core::state::governance::{ParamChangeProposalTx, ParamChangeVoteTx};
 use chain_core::tx::fee::{Fee, FeeAlgorithm};
 use chain_core::tx::TxAux;
-use chain_tx_validation::ChainInfo;
+use chain_tx_validation::{ChainInfo, Error};
 use parity_scale_codec::Decode;
 
 /// Wrapper to astract over CheckTx and DeliverTx requests
 pub trait RequestWithTx {
     fn tx(&self) -> &[u8];
+    /// true if the transaction is checked for the mempool (against the pending mempool transactions)
+    /// rather than delivered in a block (against the transactions delivered before it)
+    fn is_mempool_check(&self) -> bool;
 }
 
 impl RequestWithTx for RequestCheckTx {
     fn tx(&self) -> &[u8] {
         &self.tx[..]
     }
+
+    fn is_mempool_check(&self) -> bool {
+        true
+    }
 }
 
 impl RequestWithTx for RequestDeliverTx {
     fn tx(&self) -> &[u8] {
         &self.tx[..]
     }
+
+    fn is_mempool_check(&self) -> bool {
+        false
+    }
 }
 
 /// Wrapper to astract over CheckTx and DeliverTx responses
@@ -51,6 +63,70 @@ impl ResponseWithCodeAndLog for ResponseDeliverTx {
     }
 }
 
+/// checks the node join request against the current council nodes (its staking address and consensus key
+/// must not be used yet) and that the bonded amount after paying the fee is at least the required stake
+fn check_node_join(
+    state: &ChainNodeState,
+    maintx: &NodeJoinRequestTx,
+    account: &StakedState,
+) -> Result<(), Error> {
+    if state.council_nodes.iter().any(|node| {
+        node.staking_account_address == maintx.address
+            || node.consensus_pubkey == maintx.consensus_pubkey
+    }) {
+        return Err(Error::DuplicateCouncilNode);
+    }
+    if account.bonded < state.network_params.required_council_node_stake {
+        return Err(Error::NotEnoughStake);
+    }
+    Ok(())
+}
+
+fn is_council_node(state: &ChainNodeState, address: &StakedStateAddress) -> bool {
+    state
+        .council_nodes
+        .iter()
+        .any(|node| node.staking_account_address == *address)
+}
+
+/// checks that the delegation target is a registered council node
+fn check_delegation(state: &ChainNodeState, maintx: &DelegateTx) -> Result<(), Error> {
+    if !is_council_node(state, &maintx.council_node) {
+        return Err(Error::NotCouncilNode);
+    }
+    Ok(())
+}
+
+/// checks that the proposer is a registered council node and that the change is activated
+/// after the block the proposal is included in (so that the other council nodes can vote)
+fn check_param_change_proposal(
+    state: &ChainNodeState,
+    maintx: &ParamChangeProposalTx,
+) -> Result<(), Error> {
+    if !is_council_node(state, &maintx.address) {
+        return Err(Error::NotCouncilNode);
+    }
+    if maintx.activation_height <= state.last_block_height + 1 {
+        return Err(Error::InvalidActivationHeight);
+    }
+    Ok(())
+}
+
+/// checks that the voter is a registered council node that hasn't voted for the pending proposal yet
+fn check_param_change_vote(
+    state: &ChainNodeState,
+    maintx: &ParamChangeVoteTx,
+) -> Result<(), Error> {
+    if !is_council_node(state, &maintx.address) {
+        return Err(Error::NotCouncilNode);
+    }
+    match state.param_change_proposals.get(&maintx.proposal_id) {
+        None => Err(Error::ProposalNotFound),
+        Some(proposal) if proposal.votes.contains(&maintx.address) => Err(Error::AlreadyVoted),
+        Some(_) => Ok(()),
+    }
+}
+
 impl<T: EnclaveProxy> ChainNodeApp<T> {
     /// Gets CheckTx or DeliverTx requests, tries to parse its data into TxAux and validate that TxAux.
     /// Returns Some(parsed txaux, (paid fee, updated staking account)) if OK, or None if some problems (and sets log + error code in the passed in response).
@@ -69,26 +145,67 @@ impl<T: EnclaveProxy> ChainNodeApp<T> {
             }
             Ok(txaux) => {
                 let state = self.last_state.as_ref().expect("the app state is expected");
-                let min_fee = state
+                let min_fee = match state
+                    .network_params
                     .fee_policy
                     .calculate_fee(_req.tx().len())
-                    .expect("invalid fee policy");
-                let fee_paid = verify(
+                {
+                    Ok(fee) => fee,
+                    Err(e) => {
+                        resp.set_code(1);
+                        resp.add_log(&format!("failed to compute the fee: {}", e));
+                        return None;
+                    }
+                };
+                let pending = if _req.is_mempool_check() {
+                    &self.mempool_state
+                } else {
+                    &self.delivered_state
+                };
+                let fee_paid = verify_with_pending(
                     &mut self.tx_validator,
                     &txaux,
                     ChainInfo {
                         min_fee_computed: min_fee,
                         chain_hex_id: self.chain_hex_id,
                         previous_block_time: state.block_time,
-                        unbonding_period: state.unbonding_period,
+                        last_block_height: state.last_block_height,
+                        unbonding_period: state.network_params.unbonding_period,
                     },
                     &self.uncommitted_account_root_hash,
                     self.storage.db.clone(),
                     &self.accounts,
-                );
+                    pending,
+                )
+                .and_then(|(fee, maccount)| {
+                    if let TxAux::NodeJoinTx(maintx, _) = &txaux {
+                        let account = maccount
+                            .as_ref()
+                            .expect("account returned in node join verification");
+                        check_node_join(state, maintx, account)?;
+                    }
+                    match &txaux {
+                        TxAux::DelegateStakeTx(maintx, _) => check_delegation(state, maintx)?,
+                        TxAux::ParamChangeProposalTx(maintx, _) => {
+                            check_param_change_proposal(state, maintx)?
+                        }
+                        TxAux::ParamChangeVoteTx(maintx, _) => {
+                            check_param_change_vote(state, maintx)?
+                        }
+                        _ => {}
+                    }
+                    Ok((fee, maccount))
+                });
                 if fee_paid.is_ok() {
                     resp.set_code(0);
-                    Some((txaux, fee_paid.unwrap()))
+                    let (fee, maccount) = fee_paid.unwrap();
+                    if _req.is_mempool_check() {
+                        self.mempool_state.add(&txaux, maccount.as_ref());
+                    } else {
+                        // the delivered staked states are looked up in the uncommitted account trie
+                        self.delivered_state.add(&txaux, None);
+                    }
+                    Some((txaux, (fee, maccount)))
                 } else {
                     resp.set_code(1);
                     resp.add_log(&format!("verification failed: {}", fee_paid.unwrap_err()));
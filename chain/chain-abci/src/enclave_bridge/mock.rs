@@ -102,6 +102,11 @@ impl EnclaveProxy for MockClient {
                     _ => EnclaveResponse::UnknownRequest,
                 }
             }
+            EnclaveRequest::StoreGenesisTx { tx, .. } => {
+                self.local_tx_store
+                    .insert(tx.id(), TxWithOutputs::Transfer(tx));
+                EnclaveResponse::StoreGenesisTx(Ok(()))
+            }
             _ => EnclaveResponse::UnknownRequest,
         }
     }
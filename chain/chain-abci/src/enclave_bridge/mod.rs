@@ -1,3 +1,6 @@
+use chain_core::state::tendermint::BlockHeight;
+use chain_core::tx::data::TxId;
+use chain_core::tx::{PlainTxAux, TxObfuscated};
 use enclave_protocol::{EnclaveRequest, EnclaveResponse, FLAGS};
 use parity_scale_codec::{Decode, Encode};
 use std::sync::{Arc, Mutex};
@@ -5,10 +8,38 @@ use zmq::Socket;
 
 /// TODO: feature-guard when workspaces can be built with --features flag: https://github.com/rust-lang/cargo/issues/5015
 pub mod mock;
+/// TODO: feature-guard when workspaces can be built with --features flag: https://github.com/rust-lang/cargo/issues/5015
+pub mod software;
 
 /// Abstracts over communication with an external process that does enclave calls
 pub trait EnclaveProxy: Sync + Send + Sized {
     fn process_request(&mut self, request: EnclaveRequest) -> EnclaveResponse;
+
+    /// FIXME: temporary -- chain-abci shouldn't see plain payloads (bodies / view keys should come from the enclave)
+    /// by default, payloads are expected to be plain SCALE-encoded `PlainTxAux`
+    /// (`last_block_height` is the last committed block height: the later keys aren't established yet)
+    fn decrypt_payload(
+        &self,
+        _txid: &TxId,
+        payload: &TxObfuscated,
+        _last_block_height: BlockHeight,
+    ) -> Option<PlainTxAux> {
+        PlainTxAux::decode(&mut payload.txpayload.as_slice()).ok()
+    }
+
+    /// FIXME: temporary -- only for the mock encryption query (should be done by the encryption enclave)
+    fn encrypt_payload(
+        &self,
+        _txid: &TxId,
+        plain: &PlainTxAux,
+        _key_from: BlockHeight,
+    ) -> TxObfuscated {
+        TxObfuscated {
+            key_from: 0,
+            nonce: [0u8; 12],
+            txpayload: plain.encode(),
+        }
+    }
 }
 
 /// Provides communication with the enclave wrapper app over ZMQ
@@ -0,0 +1,188 @@
+//! Software (non-SGX) stand-in for the transaction obfuscation done by the enclaves.
+//! Payloads are really AEAD-encrypted (AES-128-PMAC-SIV) with a height-keyed symmetric key,
+//! so that development / CI networks exercise the whole confidential flow without SGX hardware.
+//! WARNING: the keys are derived from public data (network ID + height), so this provides NO confidentiality.
+use super::mock::MockClient;
+use super::EnclaveProxy;
+use blake2::{Blake2s, Digest};
+use chain_core::state::tendermint::BlockHeight;
+use chain_core::tx::data::TxId;
+use chain_core::tx::{PlainTxAux, TxAux, TxObfuscated};
+use enclave_protocol::{EnclaveRequest, EnclaveResponse};
+use miscreant::{Aead, Aes128PmacSivAead};
+use parity_scale_codec::{Decode, Encode};
+
+const KEY_DERIVATION_CONTEXT: &[u8] = b"chain-abci software tx obfuscation";
+
+/// Encrypts / decrypts transaction payloads with the key established at `key_from` height
+pub struct SoftwareObfuscation {
+    chain_hex_id: u8,
+}
+
+impl SoftwareObfuscation {
+    pub fn new(chain_hex_id: u8) -> Self {
+        SoftwareObfuscation { chain_hex_id }
+    }
+
+    fn cipher(&self, key_from: BlockHeight) -> Aes128PmacSivAead {
+        let mut hasher = Blake2s::new();
+        hasher.input(KEY_DERIVATION_CONTEXT);
+        hasher.input(&[self.chain_hex_id]);
+        hasher.input(&key_from.to_le_bytes());
+        Aes128PmacSivAead::new(&hasher.result())
+    }
+
+    /// the payload is bound to the transaction ID (associated data);
+    /// the nonce is derived from it, as SIV is nonce-misuse resistant
+    pub fn encrypt(&self, txid: &TxId, plain: &PlainTxAux, key_from: BlockHeight) -> TxObfuscated {
+        let mut nonce = [0u8; 12];
+        nonce.copy_from_slice(&txid[..12]);
+        let txpayload = self
+            .cipher(key_from)
+            .seal(&nonce, &txid[..], &plain.encode());
+        TxObfuscated {
+            key_from,
+            nonce,
+            txpayload,
+        }
+    }
+
+    /// returns None if the payload wasn't encrypted for this transaction with the `key_from` key
+    /// or if `key_from` is above the last committed block height (its key wasn't established yet)
+    pub fn decrypt(
+        &self,
+        txid: &TxId,
+        payload: &TxObfuscated,
+        last_block_height: BlockHeight,
+    ) -> Option<PlainTxAux> {
+        if payload.key_from > last_block_height {
+            return None;
+        }
+        let plain = self
+            .cipher(payload.key_from)
+            .open(&payload.nonce, &txid[..], &payload.txpayload)
+            .ok()?;
+        PlainTxAux::decode(&mut plain.as_slice()).ok()
+    }
+}
+
+/// Decrypts the payloads with `SoftwareObfuscation` and validates the plain transactions with `MockClient`
+pub struct SoftwareEnclaveClient {
+    inner: MockClient,
+    obfuscation: SoftwareObfuscation,
+}
+
+impl SoftwareEnclaveClient {
+    pub fn new(chain_hex_id: u8) -> Self {
+        SoftwareEnclaveClient {
+            inner: MockClient::new(chain_hex_id),
+            obfuscation: SoftwareObfuscation::new(chain_hex_id),
+        }
+    }
+
+    /// replaces the encrypted payload with the plain one (as expected by `MockClient`)
+    fn decrypt_in_place(&self, tx: &mut TxAux, last_block_height: BlockHeight) -> bool {
+        let txid = tx.tx_id();
+        let payload = match tx {
+            TxAux::TransferTx { payload, .. }
+            | TxAux::DepositStakeTx { payload, .. }
+            | TxAux::WithdrawUnbondedStakeTx { payload, .. } => payload,
+            TxAux::UnbondStakeTx(..)
+            | TxAux::NodeJoinTx(..)
+            | TxAux::UnjailTx(..)
+            | TxAux::RebondStakeTx(..)
+            | TxAux::DelegateStakeTx(..)
+            | TxAux::UndelegateStakeTx(..)
+            | TxAux::ParamChangeProposalTx(..)
+            | TxAux::ParamChangeVoteTx(..) => {
+                return true;
+            }
+        };
+        match self.obfuscation.decrypt(&txid, payload, last_block_height) {
+            Some(plain) => {
+                payload.txpayload = plain.encode();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl EnclaveProxy for SoftwareEnclaveClient {
+    fn process_request(&mut self, request: EnclaveRequest) -> EnclaveResponse {
+        match request {
+            EnclaveRequest::VerifyTx {
+                mut tx,
+                account,
+                info,
+            } => {
+                if self.decrypt_in_place(&mut tx, info.last_block_height) {
+                    self.inner
+                        .process_request(EnclaveRequest::VerifyTx { tx, account, info })
+                } else {
+                    EnclaveResponse::VerifyTx(Err(chain_tx_validation::Error::EnclaveRejected))
+                }
+            }
+            request => self.inner.process_request(request),
+        }
+    }
+
+    fn decrypt_payload(
+        &self,
+        txid: &TxId,
+        payload: &TxObfuscated,
+        last_block_height: BlockHeight,
+    ) -> Option<PlainTxAux> {
+        self.obfuscation.decrypt(txid, payload, last_block_height)
+    }
+
+    fn encrypt_payload(
+        &self,
+        txid: &TxId,
+        plain: &PlainTxAux,
+        key_from: BlockHeight,
+    ) -> TxObfuscated {
+        self.obfuscation.encrypt(txid, plain, key_from)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chain_core::tx::data::Tx;
+    use chain_core::tx::witness::TxWitness;
+    use chain_core::tx::TransactionId;
+
+    #[test]
+    fn payload_should_only_decrypt_with_its_txid_and_key() {
+        let obfuscation = SoftwareObfuscation::new(0xab);
+        let tx = Tx::new();
+        let txid = tx.id();
+        let plain = PlainTxAux::new(tx, TxWitness::new());
+
+        let payload = obfuscation.encrypt(&txid, &plain, 10);
+        assert_eq!(10, payload.key_from);
+        assert_ne!(plain.encode(), payload.txpayload);
+        assert_eq!(Some(plain), obfuscation.decrypt(&txid, &payload, 10));
+
+        assert!(obfuscation.decrypt(&[0u8; 32], &payload, 10).is_none());
+        let mut other_key = payload.clone();
+        other_key.key_from = 11;
+        assert!(obfuscation.decrypt(&txid, &other_key, 11).is_none());
+        assert!(SoftwareObfuscation::new(0xac)
+            .decrypt(&txid, &payload, 10)
+            .is_none());
+    }
+
+    #[test]
+    fn payload_should_not_decrypt_before_its_key_height() {
+        let obfuscation = SoftwareObfuscation::new(0xab);
+        let tx = Tx::new();
+        let txid = tx.id();
+        let plain = PlainTxAux::new(tx, TxWitness::new());
+
+        let payload = obfuscation.encrypt(&txid, &plain, 10);
+        assert!(obfuscation.decrypt(&txid, &payload, 9).is_none());
+        assert_eq!(Some(plain), obfuscation.decrypt(&txid, &payload, 11));
+    }
+}
@@ -2,14 +2,27 @@ mod app;
 mod enclave_bridge;
 mod storage;
 
-use log::info;
+use log::{info, warn};
+use std::fs;
 use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
 use zmq::{Context, REQ};
 
+use crate::app::peer_filter::PeerFilterFile;
+use crate::app::pruning::compact_account_storage;
+use crate::app::rollback::rollback_blocks;
+use crate::app::snapshot::{restore_snapshot_file, SnapshotConfig};
 use crate::app::ChainNodeApp;
-use crate::enclave_bridge::ZmqEnclaveClient;
+use crate::enclave_bridge::software::SoftwareEnclaveClient;
+use crate::enclave_bridge::{EnclaveProxy, ZmqEnclaveClient};
+use crate::storage::account::AccountStorage;
 use crate::storage::*;
-use chain_core::init::network::{get_network, get_network_id, init_chain_id};
+use chain_core::common::{H256, HASH_SIZE_256};
+use chain_core::init::network::{init_chain_params, ChainParams};
+use chain_core::state::tendermint::BlockHeight;
+use chain_tx_filter::gcs::MAX_FP_RATE_BITS;
+use chain_tx_filter::FilterFormat;
+use enclave_protocol::{EnclaveRequest, EnclaveResponse};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -54,38 +67,317 @@ struct AbciOpt {
     #[structopt(
         short = "e",
         long = "enclave_server",
-        help = "Connection string (e.g. ipc://enclave.socket or tcp://127.0.0.1:25933) for ZeroMQ server wrapper around the transaction validation enclave."
+        help = "Connection string (e.g. ipc://enclave.socket or tcp://127.0.0.1:25933) for ZeroMQ server wrapper around the transaction validation enclave (required unless --software_enclave is used)."
     )]
-    enclave_server: String,
+    enclave_server: Option<String>,
+    #[structopt(
+        long = "software_enclave",
+        help = "Development / testing only: validates and obfuscates transactions in software instead of connecting to the enclave (NOT confidential)"
+    )]
+    software_enclave: bool,
+    #[structopt(
+        long = "gcs_filter_fp_bits",
+        help = "Emits block filters as Golomb-coded sets (instead of ethbloom) with the false positive rate of 1 / 2^<value> (1-32)"
+    )]
+    gcs_filter_fp_bits: Option<u8>,
+    #[structopt(
+        long = "snapshot_interval",
+        help = "Writes a snapshot of the committed state after each block whose height is a multiple of this value"
+    )]
+    snapshot_interval: Option<BlockHeight>,
+    #[structopt(
+        long = "snapshot_dir",
+        help = "Sets the directory the snapshots are written to (the \"snapshots\" subdirectory of the data storage directory by default)"
+    )]
+    snapshot_dir: Option<String>,
+    #[structopt(
+        long = "retain_account_roots",
+        help = "Only keeps the account states committed in the last <value> blocks (all committed account states are kept by default)"
+    )]
+    retain_account_roots: Option<BlockHeight>,
+    #[structopt(
+        long = "undo_depth",
+        help = "Keeps the undo journal of the last <value> committed blocks, so that they can be rolled back with the rollback command"
+    )]
+    undo_depth: Option<BlockHeight>,
+    #[structopt(
+        long = "peer_filter",
+        help = "Refuses the peers not allowed by the rules in this JSON file (reloaded when it's modified; requires filter_peers = true in Tendermint config)"
+    )]
+    peer_filter: Option<String>,
+    #[structopt(subcommand)]
+    command: Option<AbciCommand>,
 }
 
-fn main() {
-    env_logger::init();
-    let opt = AbciOpt::from_args();
+#[derive(Debug, StructOpt)]
+enum AbciCommand {
+    #[structopt(
+        name = "import-snapshot",
+        about = "Bootstraps the empty data storage directory from a snapshot (verified against the trusted app hash) and exits (the enclave storage isn't included: unless --software_enclave is used, it needs to be restored from a backup at the snapshot height first, which is checked with the enclave at --enclave_server)"
+    )]
+    ImportSnapshot {
+        #[structopt(short = "f", long = "file", help = "The snapshot file")]
+        file: String,
+        #[structopt(
+            short = "a",
+            long = "app_hash",
+            help = "The trusted app hash committed at the snapshot height (e.g. in the header of the next block)"
+        )]
+        app_hash: String,
+    },
+    #[structopt(
+        name = "compact",
+        about = "Rebuilds the account storage with only the last and the retained committed account states and exits (the node must be stopped; if it's interrupted, the account storage is recovered at the next start)"
+    )]
+    Compact,
+    #[structopt(
+        name = "rollback",
+        about = "Restores the state before the last committed blocks (recorded with --undo_depth), verifies it against the committed app hash and exits"
+    )]
+    Rollback {
+        #[structopt(
+            short = "b",
+            long = "blocks",
+            help = "The number of blocks to roll back"
+        )]
+        blocks: BlockHeight,
+    },
+}
+
+fn decode_app_hash(hex_hash: &str) -> H256 {
+    let decoded = hex::decode(hex_hash).expect("failed to decode app hash");
+    let mut app_hash = [0u8; HASH_SIZE_256];
+    app_hash.copy_from_slice(&decoded[..]);
+    app_hash
+}
+
+/// connects to the ZeroMQ server wrapper around the transaction validation enclave
+fn connect_enclave(opt: &AbciOpt) -> ZmqEnclaveClient {
     let ctx = Context::new();
     let socket = ctx.socket(REQ).expect("failed to init zmq context");
     socket
-        .connect(&opt.enclave_server)
+        .connect(
+            opt.enclave_server
+                .as_ref()
+                .expect("enclave server connection string (unless --software_enclave is used)"),
+        )
         .expect("failed to connect to enclave zmq wrapper");
-    let proxy = ZmqEnclaveClient::new(socket);
+    ZmqEnclaveClient::new(socket)
+}
+
+/// Checks that the enclave storage was restored at the trusted app hash
+/// (it isn't included in snapshots, as it's sealed to the enclave of the node that wrote it,
+/// so without it the enclave couldn't validate the transactions spending the snapshotted UTXOs)
+fn check_enclave_storage(opt: &AbciOpt, chain_hex_id: u8, app_hash: &H256) {
+    if opt.software_enclave {
+        warn!("using the software enclave: the enclave storage isn't checked");
+        return;
+    }
+    match connect_enclave(opt).process_request(EnclaveRequest::CheckChain {
+        chain_hex_id,
+        genesis_app_hash: decode_app_hash(&opt.genesis_app_hash),
+        last_app_hash: Some(*app_hash),
+    }) {
+        EnclaveResponse::CheckChain(Ok(_)) => {
+            info!("the enclave storage is at the trusted app hash");
+        }
+        EnclaveResponse::CheckChain(Err(enclave_app_hash)) => {
+            panic!("the enclave storage isn't at the trusted app hash (enclave app hash: {:?}): restore it from a backup at the snapshot height first", enclave_app_hash);
+        }
+        _ => unreachable!("unexpected enclave response"),
+    }
+}
 
-    init_chain_id(&opt.chain_id);
+fn import_snapshot(opt: &AbciOpt, chain_hex_id: u8, file: &str, app_hash: &str) {
+    let trusted_app_hash = decode_app_hash(app_hash);
+    check_enclave_storage(opt, chain_hex_id, &trusted_app_hash);
+    let storage = Storage::new(&StorageConfig::new(&opt.data, StorageType::Node));
+    let account_db = Storage::new(&StorageConfig::new(&opt.data, StorageType::AccountTrie));
+    let mut accounts = AccountStorage::new(account_db, 20).expect("account db");
+    let state = restore_snapshot_file(
+        Path::new(file),
+        &storage,
+        &mut accounts,
+        &opt.chain_id,
+        &decode_app_hash(&opt.genesis_app_hash),
+        &trusted_app_hash,
+    )
+    .unwrap_or_else(|e| panic!("failed to import the snapshot: {}", e));
     info!(
-        "network={:?} network_id={:X}",
-        get_network(),
-        get_network_id()
+        "imported the snapshot at height {} (app hash: {})",
+        state.last_block_height,
+        hex::encode_upper(&state.last_apphash)
+    );
+}
+
+/// Renames a database directory and syncs the parent directory, so that the rename is durable
+fn rename_db(from: &str, to: &str) {
+    fs::rename(from, to).unwrap_or_else(|e| panic!("failed to move {} to {}: {}", from, to, e));
+    if let Some(parent) = Path::new(to).parent() {
+        fs::File::open(parent)
+            .and_then(|dir| dir.sync_all())
+            .unwrap_or_else(|e| panic!("failed to sync {:?}: {}", parent, e));
+    }
+}
+
+/// Finishes the swap of the account db interrupted during `compact`
+/// (or discards the compacted account db if it wasn't completely written)
+fn recover_compaction(opt: &AbciOpt) {
+    let account_path = StorageConfig::new(&opt.data, StorageType::AccountTrie).db_path();
+    let compacting_path = format!("{}-compacting", account_path);
+    let compacted_path = format!("{}-compacted", account_path);
+    let old_path = format!("{}-old", account_path);
+    if Path::new(&compacting_path).exists() {
+        warn!("removing the account db of an interrupted compaction");
+        fs::remove_dir_all(&compacting_path)
+            .expect("failed to remove the incomplete compacted account db");
+    }
+    if Path::new(&compacted_path).exists() {
+        warn!("finishing the swap of the account db of an interrupted compaction");
+        if Path::new(&account_path).exists() {
+            rename_db(&account_path, &old_path);
+        }
+        rename_db(&compacted_path, &account_path);
+    }
+    if Path::new(&old_path).exists() {
+        if Path::new(&account_path).exists() {
+            fs::remove_dir_all(&old_path).expect("failed to remove the old account db");
+        } else {
+            rename_db(&old_path, &account_path);
+        }
+    }
+}
+
+/// Rebuilds the account db next to the current one and swaps them, so that each step can be recovered
+/// after a crash by `recover_compaction`: the new account db is written to `<path>-compacting`,
+/// which is renamed to `<path>-compacted` once complete; then the current one is moved to `<path>-old`
+/// and replaced by the compacted one.
+/// The node must be stopped (the account db can't be opened while the node uses it).
+fn compact(opt: &AbciOpt) {
+    let storage = Storage::new(&StorageConfig::new(&opt.data, StorageType::Node));
+    let account_path = StorageConfig::new(&opt.data, StorageType::AccountTrie).db_path();
+    let compacting_path = format!("{}-compacting", account_path);
+    let compacted_path = format!("{}-compacted", account_path);
+    let old_path = format!("{}-old", account_path);
+    let roots = {
+        let account_db = Storage::open_path(&account_path);
+        let mut compacted =
+            AccountStorage::new(Storage::open_path(&compacting_path), 20).expect("account db");
+        compact_account_storage(&storage, &account_db, &mut compacted)
+            .unwrap_or_else(|e| panic!("failed to compact the account storage: {}", e))
+    };
+    rename_db(&compacting_path, &compacted_path);
+    rename_db(&account_path, &old_path);
+    rename_db(&compacted_path, &account_path);
+    fs::remove_dir_all(&old_path).expect("failed to remove the old account db");
+    info!(
+        "compacted the account storage ({} committed roots kept)",
+        roots
     );
+}
 
+fn rollback(opt: &AbciOpt, blocks: BlockHeight) {
+    let storage = Storage::new(&StorageConfig::new(&opt.data, StorageType::Node));
+    let account_db = Storage::new(&StorageConfig::new(&opt.data, StorageType::AccountTrie));
+    let state = rollback_blocks(&storage, &account_db, blocks)
+        .unwrap_or_else(|e| panic!("failed to roll back the blocks: {}", e));
+    info!(
+        "rolled back to height {} (app hash: {})",
+        state.last_block_height,
+        hex::encode_upper(&state.last_apphash)
+    );
+}
+
+fn run<T: EnclaveProxy + 'static>(proxy: T, opt: &AbciOpt) {
     let addr = SocketAddr::new(opt.host, opt.port);
+    let filter_format = match opt.gcs_filter_fp_bits {
+        Some(fp_rate_bits) if fp_rate_bits > 0 && fp_rate_bits <= MAX_FP_RATE_BITS => {
+            FilterFormat::Gcs { fp_rate_bits }
+        }
+        Some(_) => panic!("invalid block filter false positive rate"),
+        None => FilterFormat::EthBloom,
+    };
     info!("starting up");
-    abci::run(
-        addr,
-        ChainNodeApp::new(
-            proxy,
-            &opt.genesis_app_hash,
-            &opt.chain_id,
-            &StorageConfig::new(&opt.data, StorageType::Node),
-            &StorageConfig::new(&opt.data, StorageType::AccountTrie),
-        ),
+    let mut app = ChainNodeApp::new(
+        proxy,
+        &opt.genesis_app_hash,
+        &opt.chain_id,
+        &StorageConfig::new(&opt.data, StorageType::Node),
+        &StorageConfig::new(&opt.data, StorageType::AccountTrie),
+    )
+    .with_block_filter_format(filter_format);
+    if let Some(interval) = opt.snapshot_interval {
+        if interval <= 0 {
+            panic!("invalid snapshot interval");
+        }
+        let dir = opt
+            .snapshot_dir
+            .as_ref()
+            .map_or_else(|| Path::new(&opt.data).join("snapshots"), PathBuf::from);
+        info!("writing snapshots every {} blocks to {:?}", interval, dir);
+        app = app.with_snapshots(SnapshotConfig { dir, interval });
+    }
+    if let Some(blocks) = opt.retain_account_roots {
+        if blocks <= 0 {
+            panic!("invalid number of blocks to retain the account states for");
+        }
+        info!("keeping the account states of the last {} blocks", blocks);
+        app = app.with_account_root_retention(blocks);
+    }
+    if let Some(blocks) = opt.undo_depth {
+        if blocks <= 0 {
+            panic!("invalid undo depth");
+        }
+        if opt
+            .retain_account_roots
+            .map_or(false, |retained| retained < blocks)
+        {
+            panic!("the account states need to be retained for at least the undo depth");
+        }
+        info!("keeping the undo journal of the last {} blocks", blocks);
+        app = app.with_undo_depth(blocks);
+    }
+    if let Some(path) = opt.peer_filter.as_ref() {
+        let filter = PeerFilterFile::load(path)
+            .unwrap_or_else(|e| panic!("failed to load the peer filter: {}", e));
+        info!("filtering peers by the rules in {}", path);
+        app = app.with_peer_filter(filter);
+    }
+    abci::run(addr, app);
+}
+
+fn main() {
+    env_logger::init();
+    let opt = AbciOpt::from_args();
+
+    let chain_params = ChainParams::from_chain_id(&opt.chain_id).expect("valid chain id");
+    init_chain_params(chain_params);
+    info!(
+        "network={:?} network_id={:X}",
+        chain_params.network(),
+        chain_params.network_id()
     );
+
+    recover_compaction(&opt);
+    match &opt.command {
+        Some(AbciCommand::ImportSnapshot { file, app_hash }) => {
+            import_snapshot(&opt, chain_params.network_id(), file, app_hash);
+            return;
+        }
+        Some(AbciCommand::Compact) => {
+            compact(&opt);
+            return;
+        }
+        Some(AbciCommand::Rollback { blocks }) => {
+            rollback(&opt, *blocks);
+            return;
+        }
+        None => {}
+    }
+    if opt.software_enclave {
+        warn!("using the software enclave: transactions are NOT confidential");
+        run(SoftwareEnclaveClient::new(chain_params.network_id()), &opt);
+    } else {
+        run(connect_enclave(&opt), &opt);
+    }
 }
@@ -0,0 +1,261 @@
+//! Migration of the staked states stored in the account trie to the current encoding
+use super::{collect_values, AccountStorage, AccountWrapper};
+use crate::storage::Storage;
+use chain_core::common::{Timespec, H256};
+use chain_core::init::coin::Coin;
+use chain_core::state::account::{
+    Delegation, Nonce, StakedState, StakedStateAddress, UnbondingEntry,
+};
+use parity_scale_codec::{Decode, Encode};
+use starling::traits::Exception;
+
+/// version of the staked state encoding in the account trie:
+/// 0 -- a single unbonded amount with one `unbonded_from` time, without jailing (not stored, as it was the initial encoding)
+/// 1 -- a list of unbonding entries
+/// 2 -- an optional delegation to a council node
+/// 3 -- an optional genesis vesting schedule
+pub const ACCOUNT_STORAGE_VERSION: u8 = 3;
+
+/// the staked state encoding in version 0 of the account trie storage
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+struct StakedStateV0 {
+    nonce: Nonce,
+    bonded: Coin,
+    unbonded: Coin,
+    unbonded_from: Timespec,
+    address: StakedStateAddress,
+}
+
+impl From<StakedStateV0> for StakedState {
+    fn from(state: StakedStateV0) -> Self {
+        let unbonding = if state.unbonded == Coin::zero() {
+            Vec::new()
+        } else {
+            vec![UnbondingEntry {
+                amount: state.unbonded,
+                release_time: state.unbonded_from,
+            }]
+        };
+        // nodes couldn't be jailed yet
+        StakedState::new(state.nonce, state.bonded, unbonding, state.address)
+    }
+}
+
+/// the staked state encoding in version 1 of the account trie storage
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+struct StakedStateV1 {
+    nonce: Nonce,
+    bonded: Coin,
+    unbonding: Vec<UnbondingEntry>,
+    address: StakedStateAddress,
+    jailed_until: Option<Timespec>,
+}
+
+impl From<StakedStateV1> for StakedState {
+    fn from(state: StakedStateV1) -> Self {
+        let mut account =
+            StakedState::new(state.nonce, state.bonded, state.unbonding, state.address);
+        account.jailed_until = state.jailed_until;
+        account
+    }
+}
+
+/// the staked state encoding in version 2 of the account trie storage
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+struct StakedStateV2 {
+    nonce: Nonce,
+    bonded: Coin,
+    unbonding: Vec<UnbondingEntry>,
+    address: StakedStateAddress,
+    jailed_until: Option<Timespec>,
+    delegation: Option<Delegation>,
+}
+
+impl From<StakedStateV2> for StakedState {
+    fn from(state: StakedStateV2) -> Self {
+        let mut account =
+            StakedState::new(state.nonce, state.bonded, state.unbonding, state.address);
+        account.jailed_until = state.jailed_until;
+        account.delegation = state.delegation;
+        account
+    }
+}
+
+/// decodes the staked state stored in the given version of the account trie storage
+fn decode_staked_state(version: u8, value: &[u8]) -> Result<StakedState, Exception> {
+    let mut data = value;
+    let decoded = match version {
+        0 => StakedStateV0::decode(&mut data).map(StakedState::from),
+        1 => StakedStateV1::decode(&mut data).map(StakedState::from),
+        2 => StakedStateV2::decode(&mut data).map(StakedState::from),
+        _ => StakedState::decode(&mut data),
+    };
+    decoded.map_err(|e| Exception::new(&format!("failed to decode: {}", e.what())))
+}
+
+/// Re-inserts all staked states under the given root (stored in the given version's encoding) in the current encoding.
+/// Returns the new account trie root (only the given root is migrated, older roots stay in the old encoding).
+///
+/// # Arguments
+///
+/// * `account_db` - the database underlying `accounts` (to traverse the trie nodes)
+/// * `accounts` - the account trie storage the migrated staked states are inserted to
+/// * `root` - the account trie root to migrate (usually the last committed one)
+/// * `version` - the account storage version the staked states under `root` are stored in
+pub fn migrate_accounts(
+    account_db: &Storage,
+    accounts: &mut AccountStorage,
+    root: &H256,
+    version: u8,
+) -> Result<H256, Exception> {
+    let mut values = Vec::new();
+    collect_values(account_db, root, &mut values)?;
+    if values.is_empty() {
+        return Ok(*root);
+    }
+    let mut keys = Vec::with_capacity(values.len());
+    let mut wrapped = Vec::with_capacity(values.len());
+    for (key, value) in values {
+        keys.push(key);
+        wrapped.push(AccountWrapper(decode_staked_state(version, &value)?));
+    }
+    accounts.insert(Some(root), &mut keys, &wrapped)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::tree::HashTree;
+    use super::*;
+    use chain_core::init::address::RedeemAddress;
+    use kvdb_memorydb::create;
+    use starling::traits::{Decode as TreeDecode, Encode as TreeEncode};
+    use std::sync::Arc;
+
+    struct StakedStateV0Wrapper(StakedStateV0);
+
+    impl TreeEncode for StakedStateV0Wrapper {
+        fn encode(&self) -> Result<Vec<u8>, Exception> {
+            Ok(self.0.encode())
+        }
+    }
+
+    impl TreeDecode for StakedStateV0Wrapper {
+        fn decode(buffer: &[u8]) -> Result<Self, Exception> {
+            let data = Vec::from(buffer);
+            let state = StakedStateV0::decode(&mut data.as_slice())
+                .map_err(|e| Exception::new(e.what()))?;
+            Ok(StakedStateV0Wrapper(state))
+        }
+    }
+
+    fn get_state_v0(address: RedeemAddress, unbonded: Coin) -> StakedStateV0 {
+        StakedStateV0 {
+            nonce: 2,
+            bonded: Coin::one(),
+            unbonded,
+            unbonded_from: 10,
+            address: address.into(),
+        }
+    }
+
+    #[test]
+    fn test_migrate_accounts_to_unbonding_entries() {
+        let db = Arc::new(create(1));
+        let mut old_tree: HashTree<StakedStateV0Wrapper, Storage> =
+            HashTree::new(Storage::new_db(db.clone()), 20).expect("account db");
+        let unbonded = get_state_v0([0x11; 20].into(), Coin::unit());
+        let bonded = get_state_v0([0x22; 20].into(), Coin::zero());
+        let unbonded_key = StakedState::from(unbonded.clone()).key();
+        let bonded_key = StakedState::from(bonded.clone()).key();
+        let old_root = old_tree
+            .insert(
+                None,
+                &mut [unbonded_key, bonded_key],
+                &mut vec![StakedStateV0Wrapper(unbonded), StakedStateV0Wrapper(bonded)],
+            )
+            .expect("insert");
+
+        let mut accounts =
+            AccountStorage::new(Storage::new_db(db.clone()), 20).expect("account db");
+        let new_root =
+            migrate_accounts(&Storage::new_db(db), &mut accounts, &old_root, 0).expect("migrate");
+        assert_ne!(old_root, new_root);
+        let items = accounts
+            .get(&new_root, &mut [unbonded_key, bonded_key])
+            .expect("get");
+        let unbonded = items[&unbonded_key].clone().expect("unbonded account").0;
+        assert_eq!(
+            unbonded.unbonding,
+            vec![UnbondingEntry {
+                amount: Coin::unit(),
+                release_time: 10,
+            }]
+        );
+        assert_eq!(unbonded.nonce, 2);
+        assert_eq!(unbonded.bonded, Coin::one());
+        let bonded = items[&bonded_key].clone().expect("bonded account").0;
+        assert!(bonded.unbonding.is_empty());
+    }
+
+    #[test]
+    fn test_decode_initial_staked_state() {
+        // nonce, bonded, unbonded, unbonded_from, address (as encoded by the initial release)
+        let mut encoded = Vec::new();
+        encoded.extend_from_slice(&3u64.to_le_bytes());
+        encoded.extend_from_slice(&100u64.to_le_bytes());
+        encoded.extend_from_slice(&20u64.to_le_bytes());
+        encoded.extend_from_slice(&1_000u64.to_le_bytes());
+        encoded.push(0);
+        encoded.extend_from_slice(&[0x11; 20]);
+        let decoded = decode_staked_state(0, &encoded).expect("decode");
+        assert_eq!(decoded.nonce, 3);
+        assert_eq!(decoded.bonded, Coin::new(100).unwrap());
+        assert_eq!(
+            decoded.unbonding,
+            vec![UnbondingEntry {
+                amount: Coin::new(20).unwrap(),
+                release_time: 1_000,
+            }]
+        );
+        assert_eq!(decoded.address, RedeemAddress::from([0x11; 20]).into());
+        assert_eq!(decoded.jailed_until, None);
+    }
+
+    #[test]
+    fn test_decode_staked_state_without_delegation() {
+        let state = StakedStateV1 {
+            nonce: 1,
+            bonded: Coin::one(),
+            unbonding: vec![UnbondingEntry {
+                amount: Coin::unit(),
+                release_time: 10,
+            }],
+            address: RedeemAddress::from([0x11; 20]).into(),
+            jailed_until: Some(5),
+        };
+        let decoded = decode_staked_state(1, &state.encode()).expect("decode");
+        assert_eq!(decoded.unbonding, state.unbonding);
+        assert_eq!(decoded.jailed_until, Some(5));
+        assert_eq!(decoded.delegation, None);
+    }
+
+    #[test]
+    fn test_decode_staked_state_without_vesting() {
+        let delegation = Delegation {
+            council_node: RedeemAddress::from([0x22; 20]).into(),
+            amount: Coin::unit(),
+        };
+        let state = StakedStateV2 {
+            nonce: 1,
+            bonded: Coin::one(),
+            unbonding: Vec::new(),
+            address: RedeemAddress::from([0x11; 20]).into(),
+            jailed_until: None,
+            delegation: Some(delegation),
+        };
+        let decoded = decode_staked_state(2, &state.encode()).expect("decode");
+        assert_eq!(decoded.bonded, Coin::one());
+        assert_eq!(decoded.delegation, Some(delegation));
+        assert_eq!(decoded.vesting, None);
+    }
+}
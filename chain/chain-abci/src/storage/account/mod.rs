@@ -1,14 +1,17 @@
 #![allow(dead_code)]
+/// Migrations of the staked state encoding
+pub mod migration;
 /// TODO: WIP usage -- disallow dead_code when new TX types are added to work with accounts and use this
 /// Internal definitions
-mod tree;
+pub(crate) mod tree;
 
 use crate::storage::Storage;
 use chain_core::common::H256;
 use chain_core::state::account::StakedState;
+use chain_core::state::proof::TrieSide;
 use parity_scale_codec::{Decode as ScaleDecode, Encode as ScaleEncode};
 use starling::constants::KEY_LEN;
-use starling::traits::{Database, Decode, Encode, Exception};
+use starling::traits::{Branch, Data, Database, Decode, Encode, Exception, Leaf, NodeVariant};
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -63,8 +66,7 @@ impl Database<H256> for Storage {
         let serialized = value.encode();
         let mut insert_tx = self.db.transaction();
         insert_tx.put(None, &key, &serialized);
-        // this "buffered write" shouldn't persist (persistence done in batch write)
-        // but should change it in-memory -- TODO: check
+        // the "buffered write" changes it in-memory; it's persisted in `persist_account_nodes`
         self.db.write_buffered(insert_tx);
         Ok(())
     }
@@ -77,9 +79,86 @@ impl Database<H256> for Storage {
         Ok(())
     }
 
+    /// called after each trie update: the nodes are kept buffered, so that the uncommitted roots
+    /// of a block aren't persisted before it's committed (see `persist_account_nodes`)
     #[inline]
     fn batch_write(&mut self) -> Result<(), Exception> {
-        self.db.flush().map_err(tree::convert_io_err)
+        Ok(())
+    }
+}
+
+/// Persists the trie nodes buffered since the last call
+/// (in Commit or after the account storage was initialized / rebuilt)
+pub fn persist_account_nodes(accounts: &AccountStorage) -> Result<(), Exception> {
+    accounts.db().db.flush().map_err(tree::convert_io_err)
+}
+
+fn get_node(db: &Storage, key: &H256) -> Result<tree::TreeNode, Exception> {
+    db.get_node(*key)?
+        .ok_or_else(|| Exception::new("account trie node not found"))
+}
+
+/// collects the (key, raw value) pairs of all data nodes reachable from the given trie node
+pub fn collect_values(
+    db: &Storage,
+    node_key: &H256,
+    values: &mut Vec<(H256, Vec<u8>)>,
+) -> Result<(), Exception> {
+    match get_node(db, node_key)?.node {
+        NodeVariant::Branch(branch) => {
+            let (_, zero, one, _, _) = branch.decompose();
+            collect_values(db, &zero, values)?;
+            collect_values(db, &one, values)
+        }
+        NodeVariant::Leaf(leaf) => {
+            let (key, data) = leaf.decompose();
+            match get_node(db, &data)?.node {
+                NodeVariant::Data(data) => {
+                    values.push((key, data.get_value().to_vec()));
+                    Ok(())
+                }
+                _ => Err(Exception::new("account trie leaf doesn't point to data")),
+            }
+        }
+        _ => Err(Exception::new("unexpected account trie node")),
+    }
+}
+
+/// the bit at the index (from the most significant one) decides which branch side the key goes down
+fn choose_zero(key: &H256, index: usize) -> bool {
+    (key[index / 8] >> (7 - index % 8)) & 1 == 0
+}
+
+/// returns the sibling subtrees on the path from the key's leaf up to the trie root
+/// (as they are included in `AccountProof`) or None if the key isn't in the trie
+pub fn get_sibling_path(
+    db: &Storage,
+    root: &H256,
+    key: &H256,
+) -> Result<Option<Vec<(TrieSide, H256)>>, Exception> {
+    let mut siblings = Vec::new();
+    let mut node_key = *root;
+    loop {
+        match get_node(db, &node_key)?.node {
+            NodeVariant::Branch(branch) => {
+                let (_, zero, one, split_index, _) = branch.decompose();
+                if choose_zero(key, split_index) {
+                    siblings.push((TrieSide::One, one));
+                    node_key = zero;
+                } else {
+                    siblings.push((TrieSide::Zero, zero));
+                    node_key = one;
+                }
+            }
+            NodeVariant::Leaf(leaf) => {
+                if leaf.get_key() != key {
+                    return Ok(None);
+                }
+                siblings.reverse();
+                return Ok(Some(siblings));
+            }
+            _ => return Err(Exception::new("unexpected account trie node")),
+        }
     }
 }
 
@@ -89,7 +168,8 @@ mod test {
     use super::*;
     use chain_core::init::address::RedeemAddress;
     use chain_core::init::coin::Coin;
-    use chain_core::state::account::StakedState;
+    use chain_core::state::account::{StakedState, UnbondingEntry};
+    use chain_core::state::proof::{AccountProof, AppHashParts};
     use kvdb_memorydb::create;
     use std::sync::Arc;
 
@@ -122,8 +202,10 @@ mod test {
         let updated_account = StakedState::new(
             1,
             Coin::unit(),
-            Coin::unit(),
-            1,
+            vec![UnbondingEntry {
+                amount: Coin::unit(),
+                release_time: 1,
+            }],
             RedeemAddress::default().into(),
         );
         let wrapped_updated = AccountWrapper(updated_account);
@@ -154,8 +236,10 @@ mod test {
         let updated_account = StakedState::new(
             1,
             Coin::unit(),
-            Coin::unit(),
-            1,
+            vec![UnbondingEntry {
+                amount: Coin::unit(),
+                release_time: 1,
+            }],
             RedeemAddress::default().into(),
         );
         let wrapped_updated = AccountWrapper(updated_account);
@@ -172,4 +256,38 @@ mod test {
         let old_items = tree.get(&old_root, &mut [key]).expect("get 2");
         assert_eq!(old_items[&key], None);
     }
+
+    #[test]
+    fn test_sibling_path_proves_account_root() {
+        let mut tree = AccountStorage::new(create_db(), 20).expect("account db");
+        let mut accounts: Vec<StakedState> = (0..5u8)
+            .map(|i| StakedState::new(0, Coin::unit(), vec![], RedeemAddress::from([i; 20]).into()))
+            .collect();
+        accounts.sort_by_key(StakedState::key);
+        let mut keys: Vec<H256> = accounts.iter().map(StakedState::key).collect();
+        let wrapped: Vec<AccountWrapper> = accounts.iter().cloned().map(AccountWrapper).collect();
+        let root = tree.insert(None, &mut keys, &wrapped).expect("insert");
+        let app_hash_parts = AppHashParts {
+            valid_tx_root: [0; 32],
+            rewards_pool_hash: [1; 32],
+            network_params_hash: [2; 32],
+            node_state_hash: [3; 32],
+        };
+        for account in accounts.iter() {
+            let siblings = get_sibling_path(tree.db(), &root, &account.key())
+                .expect("trie lookup")
+                .expect("account in the trie");
+            let proof = AccountProof {
+                siblings,
+                app_hash_parts: app_hash_parts.clone(),
+            };
+            assert_eq!(root, proof.account_root(account));
+            assert!(proof.verify(account, &app_hash_parts.app_hash(&root)));
+        }
+        let missing =
+            StakedState::new(0, Coin::unit(), vec![], RedeemAddress::from([9; 20]).into());
+        assert!(get_sibling_path(tree.db(), &root, &missing.key())
+            .expect("trie lookup")
+            .is_none());
+    }
 }
@@ -345,18 +345,26 @@ where
         ValueType,
         H256,
     >,
+    /// a handle to the database underlying the tree (e.g. for traversing the trie nodes)
+    db: DatabaseType,
 }
 
 impl<ValueType, DatabaseType> HashTree<ValueType, DatabaseType>
 where
     ValueType: Encode + Decode + Sync + Send,
-    DatabaseType: Database<H256, NodeType = TreeNode>,
+    DatabaseType: Database<H256, NodeType = TreeNode> + Clone,
 {
     /// Creates a new `HashTree`.
     #[inline]
     pub fn new(db: DatabaseType, depth: usize) -> BinaryMerkleTreeResult<Self> {
-        let tree = MerkleBIT::from_db(db, depth)?;
-        Ok(Self { tree })
+        let tree = MerkleBIT::from_db(db.clone(), depth)?;
+        Ok(Self { tree, db })
+    }
+
+    /// Gets the database underlying the tree.
+    #[inline]
+    pub fn db(&self) -> &DatabaseType {
+        &self.db
     }
 
     /// Gets the values associated with `keys` from the tree.
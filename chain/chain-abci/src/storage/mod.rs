@@ -1,6 +1,9 @@
 pub mod account;
 pub mod tx;
+pub mod tx_migration;
 
+use chain_core::state::tendermint::BlockHeight;
+use integer_encoding::VarInt;
 use kvdb::KeyValueDB;
 use std::path::Path;
 use std::sync::Arc;
@@ -20,12 +23,36 @@ pub const COL_NODE_INFO: Option<u32> = Some(4);
 pub const COL_MERKLE_PROOFS: Option<u32> = Some(5);
 /// Column for tracking app states: height => root hash
 pub const COL_APP_STATES: Option<u32> = Some(6);
+/// Column for the inclusion of committed TXs: TxId => (BlockHeight, u32) (the block height and the index in its transaction tree)
+pub const COL_TX_INCLUSION: Option<u32> = Some(7);
 /// Number of columns in DB
-pub const NUM_COLUMNS: Option<u32> = Some(7);
+pub const NUM_COLUMNS: Option<u32> = Some(8);
 
 pub const CHAIN_ID_KEY: &[u8] = b"chain_id";
 pub const GENESIS_APP_HASH_KEY: &[u8] = b"genesis_app_hash";
 pub const LAST_STATE_KEY: &[u8] = b"last_state";
+pub const ACCOUNT_STORAGE_VERSION_KEY: &[u8] = b"account_storage_version";
+pub const TX_STORAGE_VERSION_KEY: &[u8] = b"tx_storage_version";
+pub const APP_STATE_VERSION_KEY: &[u8] = b"app_state_version";
+pub const COMMITTED_ACCOUNT_ROOTS_KEY: &[u8] = b"committed_account_roots";
+/// Prefix of the keys of the app states committed at each height (in COL_NODE_INFO): prefix || varint(height) => ChainNodeState
+pub const APP_STATE_AT_HEIGHT_PREFIX: &[u8] = b"app_state_at_";
+/// Prefix of the keys of the undo journal entries of the recent blocks (in COL_NODE_INFO): prefix || varint(height) => BlockUndo
+pub const UNDO_AT_HEIGHT_PREFIX: &[u8] = b"undo_at_";
+
+/// the key of the app state committed at the given height (in COL_NODE_INFO)
+pub fn app_state_key(height: BlockHeight) -> Vec<u8> {
+    let mut key = APP_STATE_AT_HEIGHT_PREFIX.to_vec();
+    key.extend(height.encode_var_vec());
+    key
+}
+
+/// the key of the undo journal entry of the block committed at the given height (in COL_NODE_INFO)
+pub fn undo_key(height: BlockHeight) -> Vec<u8> {
+    let mut key = UNDO_AT_HEIGHT_PREFIX.to_vec();
+    key.extend(height.encode_var_vec());
+    key
+}
 
 pub enum StorageType {
     Node,
@@ -65,6 +92,7 @@ impl<'a> StorageConfig<'a> {
 
 /// Storage wrapper -- currently only holds the reference to KV DB.
 /// It may hold caches or other look ups (TODO: reconsider whether necessary and if db could be moved up to App)
+#[derive(Clone)]
 pub struct Storage {
     pub db: Arc<dyn KeyValueDB>,
 }
@@ -78,10 +106,15 @@ impl Storage {
 
     /// inititalizes Storage based on the provided config
     pub fn new(config: &StorageConfig<'_>) -> Self {
+        Storage::open_path(&config.db_path())
+    }
+
+    /// inititalizes Storage in the provided RocksDB directory
+    pub fn open_path(db_path: &str) -> Self {
         let db = Arc::new(
             kvdb_rocksdb::Database::open(
                 &kvdb_rocksdb::DatabaseConfig::with_columns(NUM_COLUMNS),
-                &config.db_path(),
+                db_path,
             )
             .expect("failed to open db"),
         );
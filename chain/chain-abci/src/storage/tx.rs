@@ -8,10 +8,15 @@ use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::fee::Fee;
 use chain_core::tx::TransactionId;
 use chain_core::tx::TxAux;
-use chain_tx_validation::{verify_unbonding, witness::verify_tx_recover_address, ChainInfo, Error};
+use chain_tx_validation::{
+    verify_delegation, verify_node_join, verify_param_change_proposal, verify_param_change_vote,
+    verify_rebonding, verify_unbonding, verify_undelegation, verify_unjailing,
+    witness::verify_tx_recover_address, ChainInfo, Error,
+};
 use enclave_protocol::{EnclaveRequest, EnclaveResponse};
 use kvdb::KeyValueDB;
 use starling::constants::KEY_LEN;
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
 
 /// key type for looking up accounts/staked states in the merkle tree storage
@@ -65,6 +70,61 @@ fn check_spent_input_lookup(inputs: &[TxoPointer], db: Arc<dyn KeyValueDB>) -> R
     Ok(())
 }
 
+/// Changes made by the valid transactions that aren't committed yet (pending in the mempool or delivered in the current block),
+/// so that the transactions conflicting with them are rejected
+#[derive(Debug, Default)]
+pub struct PendingState {
+    /// the transaction outputs spent by the pending transactions
+    spent_inputs: BTreeSet<TxoPointer>,
+    /// the staked states after the pending transactions (with their projected nonces and balances)
+    accounts: BTreeMap<StakedStateAddress, StakedState>,
+}
+
+impl PendingState {
+    /// records the inputs spent and the staked state updated by the accepted transaction
+    pub fn add(&mut self, txaux: &TxAux, maccount: Option<&StakedState>) {
+        match txaux {
+            TxAux::TransferTx { inputs, .. } => self.spent_inputs.extend(inputs.iter().cloned()),
+            TxAux::DepositStakeTx { tx, .. } => self.spent_inputs.extend(tx.inputs.iter().cloned()),
+            _ => {}
+        }
+        if let Some(account) = maccount {
+            self.accounts.insert(account.address, account.clone());
+        }
+    }
+
+    /// forgets the pending transactions (after the block is committed)
+    pub fn clear(&mut self) {
+        self.spent_inputs.clear();
+        self.accounts.clear();
+    }
+
+    /// true if no transactions are pending
+    pub fn is_empty(&self) -> bool {
+        self.spent_inputs.is_empty() && self.accounts.is_empty()
+    }
+
+    fn check_inputs(&self, inputs: &[TxoPointer]) -> Result<(), Error> {
+        if inputs.iter().any(|txin| self.spent_inputs.contains(txin)) {
+            return Err(Error::InputSpent);
+        }
+        Ok(())
+    }
+
+    /// the projected staked state (or the one in the account trie if no pending transaction updated it)
+    fn get_account(
+        &self,
+        account_address: &StakedStateAddress,
+        last_root: &StarlingFixedKey,
+        accounts: &AccountStorage,
+    ) -> Result<StakedState, Error> {
+        match self.accounts.get(account_address) {
+            Some(account) => Ok(account.clone()),
+            None => get_account(account_address, last_root, accounts),
+        }
+    }
+}
+
 /// Checks TX against the current DB and returns an `Error` if something fails.
 /// If OK, returns the paid fee.
 pub fn verify<T: EnclaveProxy>(
@@ -74,10 +134,32 @@ pub fn verify<T: EnclaveProxy>(
     last_account_root_hash: &StarlingFixedKey,
     db: Arc<dyn KeyValueDB>,
     accounts: &AccountStorage,
+) -> Result<(Fee, Option<StakedState>), Error> {
+    verify_with_pending(
+        tx_validator,
+        txaux,
+        extra_info,
+        last_account_root_hash,
+        db,
+        accounts,
+        &PendingState::default(),
+    )
+}
+
+/// Checks TX against the current DB and the pending transactions (see `verify`)
+pub fn verify_with_pending<T: EnclaveProxy>(
+    tx_validator: &mut T,
+    txaux: &TxAux,
+    extra_info: ChainInfo,
+    last_account_root_hash: &StarlingFixedKey,
+    db: Arc<dyn KeyValueDB>,
+    accounts: &AccountStorage,
+    pending: &PendingState,
 ) -> Result<(Fee, Option<StakedState>), Error> {
     let paid_fee = match txaux {
         TxAux::TransferTx { inputs, .. } => {
             check_spent_input_lookup(&inputs, db)?;
+            pending.check_inputs(&inputs)?;
             let response = tx_validator.process_request(EnclaveRequest::VerifyTx {
                 tx: txaux.clone(),
                 account: None,
@@ -91,7 +173,8 @@ pub fn verify<T: EnclaveProxy>(
             }
         }
         TxAux::DepositStakeTx { tx, .. } => {
-            let maccount = get_account(&tx.to_staked_account, last_account_root_hash, accounts);
+            let maccount =
+                pending.get_account(&tx.to_staked_account, last_account_root_hash, accounts);
             let account = match maccount {
                 Ok(a) => Some(a),
                 Err(Error::AccountNotFound) => None,
@@ -100,6 +183,7 @@ pub fn verify<T: EnclaveProxy>(
                 }
             };
             check_spent_input_lookup(&tx.inputs, db)?;
+            pending.check_inputs(&tx.inputs)?;
             let response = tx_validator.process_request(EnclaveRequest::VerifyTx {
                 tx: txaux.clone(),
                 account,
@@ -117,7 +201,8 @@ pub fn verify<T: EnclaveProxy>(
             if let Err(_e) = account_address {
                 return Err(Error::EcdsaCrypto); // FIXME: Err(Error::EcdsaCrypto(e));
             }
-            let account = get_account(&account_address.unwrap(), last_account_root_hash, accounts)?;
+            let account =
+                pending.get_account(&account_address.unwrap(), last_account_root_hash, accounts)?;
             verify_unbonding(maintx, extra_info, account)?
         }
         TxAux::WithdrawUnbondedStakeTx { txid, witness, .. } => {
@@ -125,7 +210,8 @@ pub fn verify<T: EnclaveProxy>(
             if let Err(_e) = account_address {
                 return Err(Error::EcdsaCrypto); // FIXME: Err(Error::EcdsaCrypto(e));
             }
-            let account = get_account(&account_address.unwrap(), last_account_root_hash, accounts)?;
+            let account =
+                pending.get_account(&account_address.unwrap(), last_account_root_hash, accounts)?;
             let response = tx_validator.process_request(EnclaveRequest::VerifyTx {
                 tx: txaux.clone(),
                 account: Some(account),
@@ -138,6 +224,69 @@ pub fn verify<T: EnclaveProxy>(
                 }
             }
         }
+        TxAux::NodeJoinTx(maintx, witness) => {
+            let account_address = verify_tx_recover_address(&witness, &maintx.id());
+            if let Err(_e) = account_address {
+                return Err(Error::EcdsaCrypto); // FIXME: Err(Error::EcdsaCrypto(e));
+            }
+            let account =
+                pending.get_account(&account_address.unwrap(), last_account_root_hash, accounts)?;
+            verify_node_join(maintx, extra_info, account)?
+        }
+        TxAux::UnjailTx(maintx, witness) => {
+            let account_address = verify_tx_recover_address(&witness, &maintx.id());
+            if let Err(_e) = account_address {
+                return Err(Error::EcdsaCrypto); // FIXME: Err(Error::EcdsaCrypto(e));
+            }
+            let account =
+                pending.get_account(&account_address.unwrap(), last_account_root_hash, accounts)?;
+            verify_unjailing(maintx, extra_info, account)?
+        }
+        TxAux::RebondStakeTx(maintx, witness) => {
+            let account_address = verify_tx_recover_address(&witness, &maintx.id());
+            if let Err(_e) = account_address {
+                return Err(Error::EcdsaCrypto); // FIXME: Err(Error::EcdsaCrypto(e));
+            }
+            let account =
+                pending.get_account(&account_address.unwrap(), last_account_root_hash, accounts)?;
+            verify_rebonding(maintx, extra_info, account)?
+        }
+        TxAux::DelegateStakeTx(maintx, witness) => {
+            let account_address = verify_tx_recover_address(&witness, &maintx.id());
+            if let Err(_e) = account_address {
+                return Err(Error::EcdsaCrypto); // FIXME: Err(Error::EcdsaCrypto(e));
+            }
+            let account =
+                pending.get_account(&account_address.unwrap(), last_account_root_hash, accounts)?;
+            verify_delegation(maintx, extra_info, account)?
+        }
+        TxAux::UndelegateStakeTx(maintx, witness) => {
+            let account_address = verify_tx_recover_address(&witness, &maintx.id());
+            if let Err(_e) = account_address {
+                return Err(Error::EcdsaCrypto); // FIXME: Err(Error::EcdsaCrypto(e));
+            }
+            let account =
+                pending.get_account(&account_address.unwrap(), last_account_root_hash, accounts)?;
+            verify_undelegation(maintx, extra_info, account)?
+        }
+        TxAux::ParamChangeProposalTx(maintx, witness) => {
+            let account_address = verify_tx_recover_address(&witness, &maintx.id());
+            if let Err(_e) = account_address {
+                return Err(Error::EcdsaCrypto); // FIXME: Err(Error::EcdsaCrypto(e));
+            }
+            let account =
+                pending.get_account(&account_address.unwrap(), last_account_root_hash, accounts)?;
+            verify_param_change_proposal(maintx, extra_info, account)?
+        }
+        TxAux::ParamChangeVoteTx(maintx, witness) => {
+            let account_address = verify_tx_recover_address(&witness, &maintx.id());
+            if let Err(_e) = account_address {
+                return Err(Error::EcdsaCrypto); // FIXME: Err(Error::EcdsaCrypto(e));
+            }
+            let account =
+                pending.get_account(&account_address.unwrap(), last_account_root_hash, accounts)?;
+            verify_param_change_vote(maintx, extra_info, account)?
+        }
     };
     Ok(paid_fee)
 }
@@ -153,11 +302,15 @@ pub mod tests {
     use chain_core::init::coin::Coin;
     use chain_core::state::account::StakedStateOpAttributes;
     use chain_core::state::account::{
-        DepositBondTx, StakedStateOpWitness, UnbondTx, WithdrawUnbondedTx,
+        DelegateTx, Delegation, DepositBondTx, NodeJoinRequestTx, RebondTx, StakedStateOpWitness,
+        UnbondTx, UnbondingEntry, UndelegateTx, UnjailTx, Vesting, WithdrawUnbondedTx,
+        MAX_UNBONDING_ENTRIES,
     };
+    use chain_core::state::tendermint::TendermintValidatorPubKey;
     use chain_core::tx::data::{
         address::ExtendedAddr,
         attribute::TxAttributes,
+        htlc::{hash_lock, HashTimeLock},
         input::{TxoIndex, TxoPointer},
         output::TxOut,
     };
@@ -322,7 +475,7 @@ pub mod tests {
         let public_key = PublicKey::from_secret_key(&secp, &secret_key);
 
         let addr = RedeemAddress::from(&public_key);
-        let account = StakedState::new(1, Coin::one(), Coin::zero(), 0, addr.into());
+        let account = StakedState::new(1, Coin::one(), Vec::new(), addr.into());
         let key = account.key();
         let wrapped = AccountWrapper(account);
         let new_root = tree
@@ -347,6 +500,7 @@ pub mod tests {
                 .expect("invalid fee policy"),
             chain_hex_id: DEFAULT_CHAIN_ID,
             previous_block_time: 0,
+            last_block_height: 0,
             unbonding_period: 1,
         };
         let result = verify(
@@ -371,6 +525,7 @@ pub mod tests {
                 .expect("invalid fee policy"),
             chain_hex_id: DEFAULT_CHAIN_ID,
             previous_block_time: 0,
+            last_block_height: 0,
             unbonding_period: 1,
         };
         let mut mock_bridge = get_enclave_bridge_mock();
@@ -454,10 +609,340 @@ pub mod tests {
             );
             expect_error(&result, Error::InputOutputDoNotMatch);
         }
+        // TooManyUnbondingEntries
+        {
+            let public_key = PublicKey::from_secret_key(&Secp256k1::new(), &secret_key);
+            let entry = UnbondingEntry {
+                amount: Coin::one(),
+                release_time: 1,
+            };
+            let account = StakedState::new(
+                1,
+                Coin::one(),
+                vec![entry; MAX_UNBONDING_ENTRIES],
+                RedeemAddress::from(&public_key).into(),
+            );
+            let result = verify_unbonding(&tx, extra_info, account);
+            expect_error(&result, Error::TooManyUnbondingEntries);
+        }
+    }
+
+    #[test]
+    fn unbond_and_undelegate_tx_should_keep_vesting_locked_stake() {
+        let (txaux, tx, secret_key, _, _) = prepare_app_valid_unbond_tx();
+        let extra_info = ChainInfo {
+            min_fee_computed: LinearFee::new(Milli::new(1, 1), Milli::new(1, 1))
+                .calculate_for_txaux(&txaux)
+                .expect("invalid fee policy"),
+            chain_hex_id: DEFAULT_CHAIN_ID,
+            previous_block_time: 50,
+            last_block_height: 0,
+            unbonding_period: 1,
+        };
+        let public_key = PublicKey::from_secret_key(&Secp256k1::new(), &secret_key);
+        let mut account = StakedState::new(
+            1,
+            Coin::one(),
+            Vec::new(),
+            RedeemAddress::from(&public_key).into(),
+        );
+        account.vesting = Some(Vesting {
+            amount: Coin::one(),
+            start: 0,
+            cliff: 0,
+            end: 100,
+        });
+        // half of the stake is still locked at the block time
+        let half = Coin::new(u64::from(Coin::one()) / 2).unwrap();
+        assert_eq!(account.locked(extra_info.previous_block_time), half);
+        assert!(verify_unbonding(&tx, extra_info, account.clone()).is_ok());
+        {
+            let mut tx = tx.clone();
+            tx.value = half;
+            let result = verify_unbonding(&tx, extra_info, account.clone());
+            expect_error(&result, Error::VestingLocked);
+        }
+        // the delegated stake counts towards the locked amount
+        let council_node = RedeemAddress::from([0x33; 20]).into();
+        account.bonded = Coin::zero();
+        account.delegation = Some(Delegation {
+            council_node,
+            amount: Coin::one(),
+        });
+        let tx = UndelegateTx::new(
+            half,
+            1,
+            council_node,
+            StakedStateOpAttributes::new(DEFAULT_CHAIN_ID),
+        );
+        assert!(verify_undelegation(&tx, extra_info, account.clone()).is_ok());
+        let tx = UndelegateTx::new(
+            (half + Coin::unit()).unwrap(),
+            1,
+            council_node,
+            StakedStateOpAttributes::new(DEFAULT_CHAIN_ID),
+        );
+        let result = verify_undelegation(&tx, extra_info, account);
+        expect_error(&result, Error::VestingLocked);
+    }
+
+    #[test]
+    fn delegate_and_undelegate_tx_should_verify() {
+        let (_, _, secret_key, accounts, last_account_root_hash) = prepare_app_valid_unbond_tx();
+        let council_node = RedeemAddress::from([0x33; 20]).into();
+        let half = Coin::new(u64::from(Coin::one()) / 2).unwrap();
+        let tx = DelegateTx::new(
+            half,
+            1,
+            council_node,
+            StakedStateOpAttributes::new(DEFAULT_CHAIN_ID),
+        );
+        let txaux = TxAux::DelegateStakeTx(
+            tx.clone(),
+            get_account_op_witness(Secp256k1::new(), &tx.id(), &secret_key),
+        );
+        let extra_info = ChainInfo {
+            min_fee_computed: LinearFee::new(Milli::new(1, 1), Milli::new(1, 1))
+                .calculate_for_txaux(&txaux)
+                .expect("invalid fee policy"),
+            chain_hex_id: DEFAULT_CHAIN_ID,
+            previous_block_time: 0,
+            last_block_height: 0,
+            unbonding_period: 1,
+        };
+        let (fee, account) = verify(
+            &mut get_enclave_bridge_mock(),
+            &txaux,
+            extra_info,
+            &last_account_root_hash,
+            create_db(),
+            &accounts,
+        )
+        .expect("valid delegation");
+        let account = account.expect("updated account");
+        assert_eq!(
+            account.delegation,
+            Some(Delegation {
+                council_node,
+                amount: half,
+            })
+        );
+        assert_eq!(
+            account.bonded,
+            (Coin::one() - half)
+                .and_then(|x| x - fee.to_coin())
+                .unwrap()
+        );
+        assert_eq!(account.nonce, 2);
+        // AlreadyDelegated
+        {
+            let tx = DelegateTx::new(
+                half,
+                2,
+                RedeemAddress::from([0x44; 20]).into(),
+                StakedStateOpAttributes::new(DEFAULT_CHAIN_ID),
+            );
+            let result = verify_delegation(&tx, extra_info, account.clone());
+            expect_error(&result, Error::AlreadyDelegated);
+        }
+        // NotEnoughDelegated
+        {
+            let tx = UndelegateTx::new(
+                (half + Coin::unit()).unwrap(),
+                2,
+                council_node,
+                StakedStateOpAttributes::new(DEFAULT_CHAIN_ID),
+            );
+            let result = verify_undelegation(&tx, extra_info, account.clone());
+            expect_error(&result, Error::NotEnoughDelegated);
+        }
+        // the whole amount would be paid as the fee
+        {
+            let tx = UndelegateTx::new(
+                extra_info.min_fee_computed.to_coin(),
+                2,
+                council_node,
+                StakedStateOpAttributes::new(DEFAULT_CHAIN_ID),
+            );
+            let result = verify_undelegation(&tx, extra_info, account.clone());
+            expect_error(&result, Error::InputOutputDoNotMatch);
+        }
+        let tx = UndelegateTx::new(
+            half,
+            2,
+            council_node,
+            StakedStateOpAttributes::new(DEFAULT_CHAIN_ID),
+        );
+        let (fee, account) = verify_undelegation(&tx, extra_info, account).expect("undelegation");
+        let account = account.expect("updated account");
+        assert_eq!(account.delegation, None);
+        assert_eq!(
+            account.unbonding,
+            vec![UnbondingEntry {
+                amount: (half - fee.to_coin()).unwrap(),
+                release_time: 1,
+            }]
+        );
+        assert_eq!(account.nonce, 3);
+    }
+
+    #[test]
+    fn node_join_tx_should_pay_fee_from_bonded() {
+        let (_, _, secret_key, accounts, last_account_root_hash) = prepare_app_valid_unbond_tx();
+        let secp = Secp256k1::new();
+        let address = RedeemAddress::from(&PublicKey::from_secret_key(&secp, &secret_key));
+        let tx = NodeJoinRequestTx::new(
+            1,
+            address.into(),
+            TendermintValidatorPubKey::Ed25519([0xee; 32]),
+            StakedStateOpAttributes::new(DEFAULT_CHAIN_ID),
+        );
+        let witness = get_account_op_witness(secp, &tx.id(), &secret_key);
+        let txaux = TxAux::NodeJoinTx(tx.clone(), witness);
+        let extra_info = ChainInfo {
+            min_fee_computed: LinearFee::new(Milli::new(1, 1), Milli::new(1, 1))
+                .calculate_for_txaux(&txaux)
+                .expect("invalid fee policy"),
+            chain_hex_id: DEFAULT_CHAIN_ID,
+            previous_block_time: 0,
+            last_block_height: 0,
+            unbonding_period: 1,
+        };
+        let mut mock_bridge = get_enclave_bridge_mock();
+        let (fee, account) = verify(
+            &mut mock_bridge,
+            &txaux,
+            extra_info,
+            &last_account_root_hash,
+            create_db(),
+            &accounts,
+        )
+        .expect("valid node join");
+        let account = account.expect("updated account");
+        assert_eq!(account.nonce, 2);
+        assert_eq!(
+            account.bonded,
+            (Coin::one() - fee.to_coin()).expect("fee below bonded")
+        );
+        // MismatchAccountAddress
+        {
+            let mut tx = tx.clone();
+            tx.address = RedeemAddress::from([0xdd; 20]).into();
+            let txaux = TxAux::NodeJoinTx(
+                tx.clone(),
+                get_account_op_witness(Secp256k1::new(), &tx.id(), &secret_key),
+            );
+            let result = verify(
+                &mut mock_bridge,
+                &txaux,
+                extra_info,
+                &last_account_root_hash,
+                create_db(),
+                &accounts,
+            );
+            expect_error(&result, Error::MismatchAccountAddress);
+        }
+        // AccountIncorrectNonce
+        {
+            let mut tx = tx.clone();
+            tx.nonce = 0;
+            let txaux = TxAux::NodeJoinTx(
+                tx.clone(),
+                get_account_op_witness(Secp256k1::new(), &tx.id(), &secret_key),
+            );
+            let result = verify(
+                &mut mock_bridge,
+                &txaux,
+                extra_info,
+                &last_account_root_hash,
+                create_db(),
+                &accounts,
+            );
+            expect_error(&result, Error::AccountIncorrectNonce);
+        }
+    }
+
+    #[test]
+    fn unjail_tx_should_verify_after_jail_period() {
+        let mut accounts =
+            AccountStorage::new(Storage::new_db(create_db()), 20).expect("account db");
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let address = RedeemAddress::from(&PublicKey::from_secret_key(&secp, &secret_key));
+        let mut account = StakedState::new(1, Coin::one(), Vec::new(), address.into());
+        account.jailed_until = Some(10);
+        let key = account.key();
+        let last_account_root_hash = accounts
+            .insert(None, &mut [key], &mut vec![AccountWrapper(account)])
+            .expect("insert");
+        let tx = UnjailTx::new(
+            1,
+            address.into(),
+            StakedStateOpAttributes::new(DEFAULT_CHAIN_ID),
+        );
+        let witness = get_account_op_witness(secp, &tx.id(), &secret_key);
+        let txaux = TxAux::UnjailTx(tx.clone(), witness);
+        let mut extra_info = ChainInfo {
+            min_fee_computed: LinearFee::new(Milli::new(1, 1), Milli::new(1, 1))
+                .calculate_for_txaux(&txaux)
+                .expect("invalid fee policy"),
+            chain_hex_id: DEFAULT_CHAIN_ID,
+            previous_block_time: 9,
+            last_block_height: 0,
+            unbonding_period: 1,
+        };
+        let mut mock_bridge = get_enclave_bridge_mock();
+        // AccountJailed
+        {
+            let result = verify(
+                &mut mock_bridge,
+                &txaux,
+                extra_info,
+                &last_account_root_hash,
+                create_db(),
+                &accounts,
+            );
+            expect_error(&result, Error::AccountJailed);
+        }
+        extra_info.previous_block_time = 10;
+        let (fee, account) = verify(
+            &mut mock_bridge,
+            &txaux,
+            extra_info,
+            &last_account_root_hash,
+            create_db(),
+            &accounts,
+        )
+        .expect("valid unjail");
+        let account = account.expect("updated account");
+        assert!(!account.is_jailed());
+        assert_eq!(account.nonce, 2);
+        assert_eq!(
+            account.bonded,
+            (Coin::one() - fee.to_coin()).expect("fee below bonded")
+        );
+        // AccountNotJailed
+        {
+            let (_, _, secret_key, accounts, last_account_root_hash) =
+                prepare_app_valid_unbond_tx();
+            let txaux = TxAux::UnjailTx(
+                tx.clone(),
+                get_account_op_witness(Secp256k1::new(), &tx.id(), &secret_key),
+            );
+            let result = verify(
+                &mut mock_bridge,
+                &txaux,
+                extra_info,
+                &last_account_root_hash,
+                create_db(),
+                &accounts,
+            );
+            expect_error(&result, Error::AccountNotJailed);
+        }
     }
 
     fn prepare_app_valid_withdraw_tx(
-        unbonded_from: Timespec,
+        unbonding: Vec<UnbondingEntry>,
     ) -> (
         TxAux,
         WithdrawUnbondedTx,
@@ -473,7 +958,7 @@ pub mod tests {
         let public_key = PublicKey::from_secret_key(&secp, &secret_key);
 
         let addr = RedeemAddress::from(&public_key);
-        let account = StakedState::new(1, Coin::zero(), Coin::one(), unbonded_from, addr.into());
+        let account = StakedState::new(1, Coin::zero(), unbonding, addr.into());
         let key = account.key();
         let wrapped = AccountWrapper(account.clone());
         let new_root = tree
@@ -514,16 +999,152 @@ pub mod tests {
         )
     }
 
+    fn get_unbonding_entry(release_time: Timespec) -> UnbondingEntry {
+        UnbondingEntry {
+            amount: Coin::one(),
+            release_time,
+        }
+    }
+
+    #[test]
+    fn withdraw_tx_should_release_only_matured_entries() {
+        let (txaux, tx, _, account, _, _, _) =
+            prepare_app_valid_withdraw_tx(vec![get_unbonding_entry(0), get_unbonding_entry(20)]);
+        let extra_info = ChainInfo {
+            min_fee_computed: LinearFee::new(Milli::new(1, 1), Milli::new(1, 1))
+                .calculate_for_txaux(&txaux)
+                .expect("invalid fee policy"),
+            chain_hex_id: DEFAULT_CHAIN_ID,
+            previous_block_time: 10,
+            last_block_height: 0,
+            unbonding_period: 1,
+        };
+        let (_, updated) = verify_unbonded_withdraw(&tx, extra_info, account).expect("withdraw");
+        let updated = updated.expect("updated account");
+        assert_eq!(updated.unbonding, vec![get_unbonding_entry(20)]);
+        assert_eq!(updated.unbonded(), Coin::one());
+        assert_eq!(updated.nonce, 2);
+    }
+
+    #[test]
+    fn rebond_tx_should_take_the_latest_unbonding_entries_first() {
+        let mut accounts =
+            AccountStorage::new(Storage::new_db(create_db()), 20).expect("account db");
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let address = RedeemAddress::from(&PublicKey::from_secret_key(&secp, &secret_key));
+        let account = StakedState::new(
+            1,
+            Coin::zero(),
+            vec![get_unbonding_entry(0), get_unbonding_entry(20)],
+            address.into(),
+        );
+        let key = account.key();
+        let last_account_root_hash = accounts
+            .insert(None, &mut [key], &mut vec![AccountWrapper(account)])
+            .expect("insert");
+        let half = Coin::new(u64::from(Coin::one()) / 2).unwrap();
+        let tx = RebondTx::new(
+            (Coin::one() + half).unwrap(),
+            1,
+            StakedStateOpAttributes::new(DEFAULT_CHAIN_ID),
+        );
+        let txaux = TxAux::RebondStakeTx(
+            tx.clone(),
+            get_account_op_witness(secp, &tx.id(), &secret_key),
+        );
+        let extra_info = ChainInfo {
+            min_fee_computed: LinearFee::new(Milli::new(1, 1), Milli::new(1, 1))
+                .calculate_for_txaux(&txaux)
+                .expect("invalid fee policy"),
+            chain_hex_id: DEFAULT_CHAIN_ID,
+            previous_block_time: 10,
+            last_block_height: 0,
+            unbonding_period: 1,
+        };
+        let mut mock_bridge = get_enclave_bridge_mock();
+        let (fee, account) = verify(
+            &mut mock_bridge,
+            &txaux,
+            extra_info,
+            &last_account_root_hash,
+            create_db(),
+            &accounts,
+        )
+        .expect("valid rebond");
+        let account = account.expect("updated account");
+        assert_eq!(
+            account.unbonding,
+            vec![UnbondingEntry {
+                amount: half,
+                release_time: 0,
+            }]
+        );
+        assert_eq!(account.bonded, (tx.value - fee.to_coin()).unwrap());
+        assert_eq!(account.nonce, 2);
+        // ZeroCoin
+        {
+            let mut tx = tx.clone();
+            tx.value = Coin::zero();
+            let txaux = TxAux::RebondStakeTx(
+                tx.clone(),
+                get_account_op_witness(Secp256k1::new(), &tx.id(), &secret_key),
+            );
+            let result = verify(
+                &mut mock_bridge,
+                &txaux,
+                extra_info,
+                &last_account_root_hash,
+                create_db(),
+                &accounts,
+            );
+            expect_error(&result, Error::ZeroCoin);
+        }
+        // NotEnoughUnbonded
+        {
+            let mut tx = tx.clone();
+            tx.value = (Coin::one() + Coin::one())
+                .and_then(|x| x + Coin::unit())
+                .unwrap();
+            let txaux = TxAux::RebondStakeTx(
+                tx.clone(),
+                get_account_op_witness(Secp256k1::new(), &tx.id(), &secret_key),
+            );
+            let result = verify(
+                &mut mock_bridge,
+                &txaux,
+                extra_info,
+                &last_account_root_hash,
+                create_db(),
+                &accounts,
+            );
+            expect_error(&result, Error::NotEnoughUnbonded);
+        }
+        // AccountJailed
+        {
+            let mut account = StakedState::new(
+                1,
+                Coin::zero(),
+                vec![get_unbonding_entry(0)],
+                address.into(),
+            );
+            account.jailed_until = Some(20);
+            let result = verify_rebonding(&tx, extra_info, account);
+            expect_error(&result, Error::AccountJailed);
+        }
+    }
+
     #[test]
     fn existing_account_withdraw_tx_should_verify() {
         let (txaux, _, _, _, _, accounts, last_account_root_hash) =
-            prepare_app_valid_withdraw_tx(0);
+            prepare_app_valid_withdraw_tx(vec![get_unbonding_entry(0)]);
         let extra_info = ChainInfo {
             min_fee_computed: LinearFee::new(Milli::new(1, 1), Milli::new(1, 1))
                 .calculate_for_txaux(&txaux)
                 .expect("invalid fee policy"),
             chain_hex_id: DEFAULT_CHAIN_ID,
             previous_block_time: 0,
+            last_block_height: 0,
             unbonding_period: 1,
         };
         let result = verify(
@@ -541,13 +1162,14 @@ pub mod tests {
     fn test_account_withdraw_verify_fail() {
         let db = create_db();
         let (txaux, tx, _, account, secret_key, accounts, last_account_root_hash) =
-            prepare_app_valid_withdraw_tx(0);
+            prepare_app_valid_withdraw_tx(vec![get_unbonding_entry(0)]);
         let extra_info = ChainInfo {
             min_fee_computed: LinearFee::new(Milli::new(1, 1), Milli::new(1, 1))
                 .calculate_for_txaux(&txaux)
                 .expect("invalid fee policy"),
             chain_hex_id: DEFAULT_CHAIN_ID,
             previous_block_time: 0,
+            last_block_height: 0,
             unbonding_period: 1,
         };
         let mut mock_bridge = get_enclave_bridge_mock();
@@ -725,7 +1347,7 @@ pub mod tests {
         // AccountNotUnbonded
         {
             let (txaux, _, _, account, _, accounts, last_account_root_hash) =
-                prepare_app_valid_withdraw_tx(20);
+                prepare_app_valid_withdraw_tx(vec![get_unbonding_entry(20)]);
             let result = verify(
                 &mut mock_bridge,
                 &txaux,
@@ -795,6 +1417,7 @@ pub mod tests {
                 .expect("invalid fee policy"),
             chain_hex_id: DEFAULT_CHAIN_ID,
             previous_block_time: 0,
+            last_block_height: 0,
             unbonding_period: 1,
         };
         let last_account_root_hash = [0u8; 32];
@@ -819,6 +1442,56 @@ pub mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn inputs_spent_by_pending_tx_should_not_verify() {
+        let mut mock_bridge = get_enclave_bridge_mock();
+        let (db, txaux, _, _, _, _, accounts) =
+            prepare_app_valid_transfer_tx(false, &mut mock_bridge);
+        let extra_info = ChainInfo {
+            min_fee_computed: LinearFee::new(Milli::new(1, 1), Milli::new(1, 1))
+                .calculate_for_txaux(&txaux)
+                .expect("invalid fee policy"),
+            chain_hex_id: DEFAULT_CHAIN_ID,
+            previous_block_time: 0,
+            last_block_height: 0,
+            unbonding_period: 1,
+        };
+        let last_account_root_hash = [0u8; 32];
+        let mut pending = PendingState::default();
+        let (_, maccount) = verify_with_pending(
+            &mut mock_bridge,
+            &txaux,
+            extra_info,
+            &last_account_root_hash,
+            db.clone(),
+            &accounts,
+            &pending,
+        )
+        .expect("valid transfer");
+        pending.add(&txaux, maccount.as_ref());
+        let result = verify_with_pending(
+            &mut mock_bridge,
+            &txaux,
+            extra_info,
+            &last_account_root_hash,
+            db.clone(),
+            &accounts,
+            &pending,
+        );
+        expect_error(&result, Error::InputSpent);
+        pending.clear();
+        let result = verify_with_pending(
+            &mut mock_bridge,
+            &txaux,
+            extra_info,
+            &last_account_root_hash,
+            db,
+            &accounts,
+            &pending,
+        );
+        assert!(result.is_ok());
+    }
+
     fn expect_error<T, Error>(res: &Result<T, Error>, expected: Error)
     where
         Error: Debug,
@@ -841,6 +1514,7 @@ pub mod tests {
                 .expect("invalid fee policy"),
             chain_hex_id: DEFAULT_CHAIN_ID,
             previous_block_time: 0,
+            last_block_height: 0,
             unbonding_period: 1,
         };
         let last_account_root_hash = [0u8; 32];
@@ -1130,6 +1804,7 @@ pub mod tests {
                 .expect("invalid fee policy"),
             chain_hex_id: DEFAULT_CHAIN_ID,
             previous_block_time: 0,
+            last_block_height: 0,
             unbonding_period: 1,
         };
         let last_account_root_hash = [0u8; 32];
@@ -1442,4 +2117,77 @@ pub mod tests {
             assert!(result.is_err());
         }
     }
+
+    #[test]
+    fn hash_time_locked_output_claim_and_refund() {
+        let secp = Secp256k1::new();
+        let claim_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let refund_key = SecretKey::from_slice(&[0x11; 32]).expect("32 bytes, within curve order");
+        let (claim_addr, claim_tree) = get_address(&secp, &claim_key);
+        let (refund_addr, refund_tree) = get_address(&secp, &refund_key);
+        let preimage = [0x42; 32];
+        let htlc = HashTimeLock::new(
+            hash_lock(&preimage),
+            claim_tree.root_hash(),
+            refund_tree.root_hash(),
+            10,
+        );
+        let mut input_tx = Tx::new();
+        input_tx.add_output(TxOut::new(htlc.address(), Coin::new(10).unwrap()));
+        let input = vec![TxWithOutputs::Transfer(input_tx.clone())];
+
+        let spend_to = |addr: ExtendedAddr| {
+            let mut tx = Tx::new();
+            tx.add_input(TxoPointer::new(input_tx.id(), 0));
+            tx.add_output(TxOut::new(addr, Coin::new(9).unwrap()));
+            tx.attributes = TxAttributes::new(DEFAULT_CHAIN_ID);
+            tx
+        };
+        let claim_tx = spend_to(claim_addr);
+        let claim_witness: TxWitness =
+            match get_tx_witness(secp.clone(), &claim_tx.id(), &claim_key, &claim_tree) {
+                TxInWitness::TreeSig(sig, proof) => vec![TxInWitness::HashTimeLockClaim(
+                    sig,
+                    proof,
+                    htlc.clone(),
+                    preimage,
+                )]
+                .into(),
+                _ => unreachable!(),
+            };
+        let refund_tx = spend_to(refund_addr);
+        let refund_witness: TxWitness =
+            match get_tx_witness(secp.clone(), &refund_tx.id(), &refund_key, &refund_tree) {
+                TxInWitness::TreeSig(sig, proof) => {
+                    vec![TxInWitness::HashTimeLockRefund(sig, proof, htlc.clone())].into()
+                }
+                _ => unreachable!(),
+            };
+
+        let mut extra_info = ChainInfo {
+            min_fee_computed: Fee::new(Coin::one()),
+            chain_hex_id: DEFAULT_CHAIN_ID,
+            previous_block_time: 9,
+            last_block_height: 0,
+            unbonding_period: 1,
+        };
+        // before the timeout: only claims
+        let result = verify_transfer(&claim_tx, &claim_witness, extra_info, input.clone());
+        assert!(result.is_ok());
+        let result = verify_transfer(&refund_tx, &refund_witness, extra_info, input.clone());
+        expect_error(&result, Error::HashTimeLockNotExpired);
+        // wrong preimage
+        let mut wrong_witness = claim_witness.clone();
+        if let TxInWitness::HashTimeLockClaim(_, _, _, ref mut preimage) = wrong_witness[0] {
+            *preimage = [0x43; 32];
+        }
+        let result = verify_transfer(&claim_tx, &wrong_witness, extra_info, input.clone());
+        expect_error(&result, Error::EcdsaCrypto);
+        // after the timeout: only refunds
+        extra_info.previous_block_time = 10;
+        let result = verify_transfer(&claim_tx, &claim_witness, extra_info, input.clone());
+        expect_error(&result, Error::HashTimeLockExpired);
+        let result = verify_transfer(&refund_tx, &refund_witness, extra_info, input);
+        assert!(result.is_ok());
+    }
 }
@@ -0,0 +1,110 @@
+//! Migration of the transaction bodies stored before the transaction format was versioned:
+//! they are in the legacy layout without the version tag (see `LEGACY_TX_VERSION` in chain-core),
+//! so they are re-encoded with the tag (legacy transactions keep their IDs).
+use crate::storage::*;
+use chain_core::state::account::{DepositBondTx, UnbondTx};
+use chain_core::tx::data::TxId;
+use chain_core::tx::TransactionId;
+use chain_tx_validation::TxWithOutputs;
+use parity_scale_codec::{Encode, Error};
+
+/// Version of the stored transaction bodies (COL_BODIES) -- version 0 is the legacy untagged encoding
+pub const TX_STORAGE_VERSION: u8 = 1;
+
+/// re-encodes the body if it's fully decoded by the legacy decoder and it has the expected ID
+fn reencode_legacy<'a, T: Encode>(
+    txid: &[u8],
+    mut data: &'a [u8],
+    decode_legacy: impl Fn(&mut &'a [u8]) -> Result<T, Error>,
+    id: impl Fn(&T) -> TxId,
+) -> Option<Vec<u8>> {
+    let tx = decode_legacy(&mut data).ok()?;
+    if data.is_empty() && id(&tx)[..] == txid[..] {
+        Some(tx.encode())
+    } else {
+        None
+    }
+}
+
+/// the body in the current encoding: transactions with outputs are stored as `TxWithOutputs`,
+/// deposits and unbonding transactions directly (the other transaction types didn't exist)
+fn migrate_tx_body(txid: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    reencode_legacy(txid, data, TxWithOutputs::decode_legacy, TxWithOutputs::id)
+        .or_else(|| reencode_legacy(txid, data, DepositBondTx::decode_legacy, DepositBondTx::id))
+        .or_else(|| reencode_legacy(txid, data, UnbondTx::decode_legacy, UnbondTx::id))
+}
+
+/// Migrates all the transaction bodies stored in the legacy encoding to the current one
+/// and stores the transaction storage version (in the same DB transaction).
+/// Returns the number of migrated bodies.
+///
+/// # Arguments
+///
+/// * `storage` - the node storage created before the transaction format was versioned
+pub fn migrate_tx_bodies(storage: &Storage) -> Result<usize, String> {
+    let mut dbtx = storage.db.transaction();
+    let mut migrated = 0;
+    for (txid, data) in storage.db.iter(COL_BODIES) {
+        let body = migrate_tx_body(&txid, &data).ok_or_else(|| {
+            format!(
+                "the stored body of {} isn't a legacy transaction",
+                hex::encode(&txid)
+            )
+        })?;
+        dbtx.put(COL_BODIES, &txid, &body);
+        migrated += 1;
+    }
+    dbtx.put(COL_NODE_INFO, TX_STORAGE_VERSION_KEY, &[TX_STORAGE_VERSION]);
+    storage.db.write(dbtx).map_err(|e| e.to_string())?;
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chain_core::tx::data::txid_hash;
+    use chain_core::tx::data::Tx;
+    use kvdb_memorydb::create;
+    use parity_scale_codec::Decode;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_migrate_legacy_tx_bodies() {
+        let storage = Storage::new_db(Arc::new(create(NUM_COLUMNS.unwrap())));
+        // one input, no outputs, chain id, no view keys
+        let mut transfer = vec![0x04];
+        transfer.extend_from_slice(&[0x01; 32]);
+        transfer.extend_from_slice(&[0x01, 0x00, 0x00, 0xab, 0x00]);
+        let transfer_id = txid_hash(&transfer);
+        // stored in the transfer variant of `TxWithOutputs`
+        let mut stored_transfer = vec![0u8];
+        stored_transfer.extend_from_slice(&transfer);
+        // value, nonce, chain id
+        let mut unbond = Vec::new();
+        unbond.extend_from_slice(&1u64.to_le_bytes());
+        unbond.extend_from_slice(&2u64.to_le_bytes());
+        unbond.push(0xab);
+        let unbond_id = txid_hash(&unbond);
+        let mut inittx = storage.db.transaction();
+        inittx.put(COL_BODIES, &transfer_id, &stored_transfer);
+        inittx.put(COL_BODIES, &unbond_id, &unbond);
+        storage.db.write(inittx).unwrap();
+
+        assert_eq!(migrate_tx_bodies(&storage), Ok(2));
+        let body = storage.db.get(COL_BODIES, &transfer_id).unwrap().unwrap();
+        let tx = match TxWithOutputs::decode_stored(&mut body.to_vec().as_slice()).unwrap() {
+            TxWithOutputs::Transfer(tx) => tx,
+            _ => panic!("expected a transfer"),
+        };
+        assert_eq!(tx.id(), transfer_id);
+        assert_eq!(tx, Tx::decode_legacy(&mut transfer.as_slice()).unwrap());
+        let body = storage.db.get(COL_BODIES, &unbond_id).unwrap().unwrap();
+        let tx = UnbondTx::decode_stored(&mut body.to_vec().as_slice()).unwrap();
+        assert_eq!(tx.id(), unbond_id);
+        let version = storage
+            .db
+            .get(COL_NODE_INFO, TX_STORAGE_VERSION_KEY)
+            .unwrap();
+        assert_eq!(version.map(|v| v.to_vec()), Some(vec![TX_STORAGE_VERSION]));
+    }
+}
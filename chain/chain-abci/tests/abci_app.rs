@@ -1,8 +1,14 @@
 use abci::Application;
 use abci::*;
 use bit_vec::BitVec;
+use blake2::{Blake2s, Digest};
+use chain_abci::app::pruning::get_committed_account_roots;
+use chain_abci::app::rollback::rollback_blocks;
+use chain_abci::app::snapshot::{restore_snapshot, write_snapshot, SnapshotHeader};
 use chain_abci::app::*;
 use chain_abci::enclave_bridge::mock::MockClient;
+use chain_abci::enclave_bridge::software::SoftwareEnclaveClient;
+use chain_abci::enclave_bridge::EnclaveProxy;
 use chain_abci::storage::account::AccountStorage;
 use chain_abci::storage::account::AccountWrapper;
 use chain_abci::storage::tx::StarlingFixedKey;
@@ -10,16 +16,22 @@ use chain_abci::storage::*;
 use chain_core::common::{MerkleTree, Proof, H256, HASH_SIZE_256};
 use chain_core::compute_app_hash;
 use chain_core::init::address::RedeemAddress;
-use chain_core::init::coin::Coin;
+use chain_core::init::coin::{sum_coins, Coin};
 use chain_core::init::config::AccountType;
 use chain_core::init::config::InitConfig;
 use chain_core::init::config::InitNetworkParameters;
 use chain_core::init::config::{InitialValidator, ValidatorKeyType};
+use chain_core::init::config::{RewardsParameters, SlashRatio, SlashingParameters};
 use chain_core::state::account::{
-    to_stake_key, DepositBondTx, StakedState, StakedStateAddress, StakedStateOpAttributes,
-    StakedStateOpWitness, UnbondTx, WithdrawUnbondedTx,
+    to_stake_key, DelegateTx, DepositBondTx, NodeJoinRequestTx, StakedState, StakedStateAddress,
+    StakedStateOpAttributes, StakedStateOpWitness, UnbondTx, UndelegateTx, WithdrawUnbondedTx,
 };
-use chain_core::state::RewardsPoolState;
+use chain_core::state::governance::{
+    NetworkParameterChange, NetworkParameters, ParamChangeProposalTx, ParamChangeVoteTx,
+};
+use chain_core::state::proof::AccountProof;
+use chain_core::state::tendermint::{TendermintValidatorPubKey, TendermintVotePower};
+use chain_core::state::{CouncilNode, RewardsPoolState};
 use chain_core::tx::fee::{LinearFee, Milli};
 use chain_core::tx::witness::tree::RawPubkey;
 use chain_core::tx::witness::EcdsaSignature;
@@ -30,7 +42,7 @@ use chain_core::tx::{
     data::{
         access::{TxAccess, TxAccessPolicy},
         address::ExtendedAddr,
-        attribute::TxAttributes,
+        attribute::{TxAttributes, LEGACY_TX_VERSION},
         input::{TxoIndex, TxoPointer},
         output::TxOut,
         txid_hash, Tx, TxId,
@@ -38,16 +50,19 @@ use chain_core::tx::{
     witness::{TxInWitness, TxWitness},
     TxAux,
 };
-use chain_tx_filter::BlockFilter;
+use chain_tx_filter::{BlockFilter, FilterFormat, TxFilters, GCS_FILTER_KEY};
 use chain_tx_validation::TxWithOutputs;
 use hex::decode;
-use kvdb::KeyValueDB;
+use kvdb::{DBTransaction, DBValue, KeyValueDB};
 use kvdb_memorydb::create;
 use parity_scale_codec::{Decode, Encode};
 use secp256k1::schnorrsig::schnorr_sign;
 use secp256k1::{key::PublicKey, key::SecretKey, Message, Secp256k1, Signing};
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
+use std::io::{self, Cursor};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 fn get_enclave_bridge_mock() -> MockClient {
@@ -145,11 +160,36 @@ fn get_dummy_app_state(app_hash: H256) -> ChainNodeState {
         last_apphash: app_hash,
         block_time: 0,
         rewards_pool: RewardsPoolState::new(1.into(), 0),
-        fee_policy: LinearFee::new(Milli::new(1, 1), Milli::new(1, 1)),
+        network_params: NetworkParameters {
+            fee_policy: LinearFee::new(Milli::new(1, 1), Milli::new(1, 1)),
+            unbonding_period: 1,
+            required_council_node_stake: Coin::unit(),
+        },
         last_account_root_hash: [0u8; 32],
         council_nodes: vec![],
-        required_council_node_stake: Coin::unit(),
-        unbonding_period: 1,
+        slashing_config: get_slashing_config(),
+        validator_liveness: BTreeMap::new(),
+        rewards_config: get_rewards_config(),
+        rewards_weights: BTreeMap::new(),
+        delegations: BTreeMap::new(),
+        param_change_proposals: BTreeMap::new(),
+    }
+}
+
+fn get_slashing_config() -> SlashingParameters {
+    SlashingParameters {
+        jail_duration: 10,
+        byzantine_slash_ratio: SlashRatio::new(Milli::new(0, 200)).unwrap(),
+        liveness_slash_ratio: SlashRatio::new(Milli::new(0, 10)).unwrap(),
+        block_signing_window: 5,
+        missed_block_threshold: 2,
+    }
+}
+
+fn get_rewards_config() -> RewardsParameters {
+    RewardsParameters {
+        epoch_length: 2,
+        reward_rate: Milli::new(0, 500),
     }
 }
 
@@ -180,8 +220,23 @@ fn previously_stored_hash_should_match() {
 }
 
 fn init_chain_for(address: RedeemAddress) -> ChainNodeApp<MockClient> {
+    init_chain_with(address, get_enclave_bridge_mock())
+}
+
+fn init_chain_with<T: EnclaveProxy>(address: RedeemAddress, proxy: T) -> ChainNodeApp<T> {
+    init_chain_with_utxos(address, proxy, Vec::new())
+}
+
+fn init_chain_with_utxos<T: EnclaveProxy>(
+    address: RedeemAddress,
+    proxy: T,
+    initial_utxos: Vec<TxOut>,
+) -> ChainNodeApp<T> {
     let db = create_db();
-    let total = (Coin::max() - Coin::unit()).unwrap();
+    let utxos_total = sum_coins(initial_utxos.iter().map(|x| x.value)).unwrap();
+    let total = (Coin::max() - Coin::one())
+        .and_then(|x| x - utxos_total)
+        .unwrap();
     let validator_addr = "0x0e7c045110b8dbf29765047380898919c5cb56f4"
         .parse::<RedeemAddress>()
         .unwrap();
@@ -190,7 +245,7 @@ fn init_chain_for(address: RedeemAddress) -> ChainNodeApp<MockClient> {
         (address, (total, AccountType::ExternallyOwnedAccount)),
         (
             validator_addr,
-            (Coin::unit(), AccountType::ExternallyOwnedAccount),
+            (Coin::one(), AccountType::ExternallyOwnedAccount),
         ),
         (
             RedeemAddress::default(),
@@ -202,8 +257,10 @@ fn init_chain_for(address: RedeemAddress) -> ChainNodeApp<MockClient> {
     .collect();
     let params = InitNetworkParameters {
         initial_fee_policy: LinearFee::new(Milli::new(1, 1), Milli::new(1, 1)),
-        required_council_node_stake: Coin::unit(),
+        required_council_node_stake: Coin::one(),
         unbonding_period: 1,
+        slashing_config: get_slashing_config(),
+        rewards_config: get_rewards_config(),
     };
     let c = InitConfig::new(
         distribution,
@@ -216,11 +273,13 @@ fn init_chain_for(address: RedeemAddress) -> ChainNodeApp<MockClient> {
             consensus_pubkey_type: ValidatorKeyType::Ed25519,
             consensus_pubkey_b64: "MDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDA=".to_string(),
         }],
+        BTreeMap::new(),
+        initial_utxos,
     );
     let t = ::protobuf::well_known_types::Timestamp::new();
     let result = c.validate_config_get_genesis(t.get_seconds());
-    if let Ok((accounts, rp, _nodes)) = result {
-        let tx_tree = MerkleTree::empty();
+    if let Ok((accounts, rp, nodes)) = result {
+        let tx_tree = c.get_genesis_tx_tree();
         let mut account_tree =
             AccountStorage::new(Storage::new_db(Arc::new(create(1))), 20).expect("account db");
 
@@ -232,11 +291,25 @@ fn init_chain_for(address: RedeemAddress) -> ChainNodeApp<MockClient> {
             .insert(None, &mut keys, &mut wrapped)
             .expect("initial insert");
 
-        let genesis_app_hash = compute_app_hash(&tx_tree, &new_account_root, &rp);
+        let genesis_state = ChainNodeState::genesis(
+            [0u8; 32],
+            t.get_seconds(),
+            new_account_root,
+            rp,
+            c.network_params.clone(),
+            nodes,
+        );
+        let genesis_app_hash = compute_app_hash(
+            &tx_tree,
+            &new_account_root,
+            &genesis_state.rewards_pool,
+            &genesis_state.network_params,
+            &genesis_state.node_state_hash(),
+        );
 
         let example_hash = hex::encode_upper(genesis_app_hash);
         let mut app = ChainNodeApp::new_with_storage(
-            get_enclave_bridge_mock(),
+            proxy,
             &example_hash,
             TEST_CHAIN_ID,
             Storage::new_db(db.clone()),
@@ -306,6 +379,8 @@ fn init_chain_panics_with_different_app_hash() {
         initial_fee_policy: LinearFee::new(Milli::new(1, 1), Milli::new(1, 1)),
         required_council_node_stake: Coin::unit(),
         unbonding_period: 1,
+        slashing_config: get_slashing_config(),
+        rewards_config: get_rewards_config(),
     };
     let c = InitConfig::new(
         distribution,
@@ -314,6 +389,8 @@ fn init_chain_panics_with_different_app_hash() {
         RedeemAddress::default(),
         params,
         vec![],
+        BTreeMap::new(),
+        Vec::new(),
     );
 
     let example_hash = "F5E8DFBF717082D6E9508E1A5A5C9B8EAC04A39F69C40262CB733C920DA10963";
@@ -413,6 +490,44 @@ fn check_tx_should_accept_valid_tx() {
     assert_eq!(0, cresp.code);
 }
 
+#[test]
+fn check_tx_should_reject_legacy_tx() {
+    let secp = Secp256k1::new();
+    let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+    let addr = RedeemAddress::from(&PublicKey::from_secret_key(&secp, &secret_key));
+    let mut app = init_chain_for(addr);
+
+    // transactions in the legacy layout are only decoded from the storage
+    let mut attributes = StakedStateOpAttributes::new(0);
+    attributes.version = LEGACY_TX_VERSION;
+    let tx = UnbondTx::new(Coin::unit(), 0, attributes);
+    let witness = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &tx.id(), &secret_key));
+    let mut creq = RequestCheckTx::default();
+    creq.set_tx(TxAux::UnbondStakeTx(tx, witness).encode());
+    let cresp = app.check_tx(&creq);
+    assert_ne!(0, cresp.code);
+    assert!(cresp.log.contains("failed to deserialize tx"));
+}
+
+#[test]
+fn check_tx_should_reject_tx_conflicting_with_pending_tx_until_commit() {
+    let (mut app, txaux, _) = prepare_app_valid_tx();
+    let mut creq = RequestCheckTx::default();
+    creq.set_tx(txaux.encode());
+    assert_eq!(0, app.check_tx(&creq).code);
+    // the nonce was used by the pending transaction
+    assert_ne!(0, app.check_tx(&creq).code);
+
+    // the transaction wasn't included in the block, so it's valid again after the commit
+    begin_block(&mut app);
+    let mut breq = RequestEndBlock::default();
+    breq.set_height(1);
+    app.end_block(&breq);
+    app.commit(&RequestCommit::default());
+    assert!(app.mempool_state.is_empty());
+    assert_eq!(0, app.check_tx(&creq).code);
+}
+
 #[test]
 #[should_panic]
 fn two_beginblocks_should_panic() {
@@ -426,7 +541,7 @@ fn two_beginblocks_should_panic() {
     app.begin_block(&bbreq);
 }
 
-fn begin_block(app: &mut ChainNodeApp<MockClient>) {
+fn begin_block<T: EnclaveProxy>(app: &mut ChainNodeApp<T>) {
     let mut bbreq = RequestBeginBlock::default();
     let mut header = Header::default();
     header.set_time(::protobuf::well_known_types::Timestamp::new());
@@ -489,6 +604,68 @@ fn deliver_valid_tx() -> (
     }
 }
 
+#[test]
+fn software_enclave_should_process_encrypted_tx() {
+    let secp = Secp256k1::new();
+    let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+    let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+    let addr = RedeemAddress::from(&public_key);
+    let mut app = init_chain_with(addr, SoftwareEnclaveClient::new(0));
+
+    let tx = WithdrawUnbondedTx::new(
+        0,
+        vec![
+            TxOut::new_with_timelock(ExtendedAddr::OrTree([0; 32]), Coin::one(), 0),
+            TxOut::new_with_timelock(ExtendedAddr::OrTree([1; 32]), Coin::unit(), 0),
+        ],
+        TxAttributes::new_with_access(
+            0,
+            vec![TxAccessPolicy::new(public_key.clone(), TxAccess::AllData)],
+        ),
+    );
+    let witness = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &tx.id(), &secret_key));
+    let plain = PlainTxAux::WithdrawUnbondedStakeTx(tx.clone());
+    let txaux_with = |payload| TxAux::WithdrawUnbondedStakeTx {
+        txid: tx.id(),
+        no_of_outputs: tx.outputs.len() as TxoIndex,
+        witness: witness.clone(),
+        payload,
+    };
+    let payload = app.tx_validator.encrypt_payload(&tx.id(), &plain, 0);
+    assert_ne!(plain.encode(), payload.txpayload);
+
+    begin_block(&mut app);
+    let mut dreq = RequestDeliverTx::default();
+    dreq.set_tx(
+        txaux_with(TxObfuscated {
+            key_from: 0,
+            nonce: [0; 12],
+            txpayload: plain.encode(),
+        })
+        .encode(),
+    );
+    assert_ne!(0, app.deliver_tx(&dreq).code);
+    dreq.set_tx(txaux_with(payload).encode());
+    assert_eq!(0, app.deliver_tx(&dreq).code);
+
+    let mut endreq = RequestEndBlock::default();
+    endreq.set_height(1);
+    let cresp = app.end_block(&endreq);
+    let filter = BlockFilter::try_from(cresp.events[0].attributes[0].value.as_slice())
+        .expect("there should be a block filter");
+    assert!(filter.check_view_key(&public_key));
+
+    app.commit(&RequestCommit::default());
+    assert_eq!(
+        Some(TxWithOutputs::StakeWithdraw(tx.clone()).encode()),
+        app.storage
+            .db
+            .get(COL_BODIES, &tx.id()[..])
+            .unwrap()
+            .map(|body| body.to_vec())
+    );
+}
+
 #[test]
 fn deliver_tx_should_add_valid_tx() {
     let (app, tx, _, cresp) = deliver_valid_tx();
@@ -563,6 +740,25 @@ fn commit_without_beginblocks_should_panic() {
     let _cresp = app.commit(&creq);
 }
 
+#[test]
+fn block_filter_should_be_emitted_in_the_configured_format() {
+    let (app, tx, _, _) = deliver_valid_tx();
+    let format = FilterFormat::Gcs { fp_rate_bits: 20 };
+    let mut app = app.with_block_filter_format(format);
+
+    let mut endreq = RequestEndBlock::default();
+    endreq.set_height(10);
+    let cresp = app.end_block(&endreq);
+    let attribute = &cresp.events[0].attributes[0];
+    assert_eq!(GCS_FILTER_KEY, attribute.key.as_slice());
+    let filter = BlockFilter::from_tendermint_kv(&attribute.key, &attribute.value)
+        .expect("there should be a block filter");
+    assert_eq!(format, filter.format());
+    assert!(filter.check_view_key(&tx.attributes.allowed_view[0].view_key));
+    // the next block's filter is in the same format
+    assert_eq!(format, app.filter.format());
+}
+
 #[test]
 fn valid_commit_should_persist() {
     let (mut app, tx, _, _) = deliver_valid_tx();
@@ -572,10 +768,16 @@ fn valid_commit_should_persist() {
     endreq.set_height(10);
     let cresp = app.end_block(&endreq);
     assert_eq!(1, cresp.events.len());
-    assert_eq!(1, cresp.events[0].attributes.len());
+    assert_eq!(2, cresp.events[0].attributes.len());
     assert_eq!(1, app.delivered_txs.len());
     let filter = BlockFilter::try_from(cresp.events[0].attributes[0].value.as_slice())
         .expect("there should be a block filter");
+    let tx_filters = TxFilters::from_tendermint_value(&cresp.events[0].attributes[1].value)
+        .expect("there should be transaction filters");
+    assert_eq!(
+        vec![tx.id()],
+        tx_filters.matching_transactions(&tx.attributes.allowed_view[0].view_key)
+    );
 
     assert!(filter.check_view_key(&tx.attributes.allowed_view[0].view_key));
     let sample = PublicKey::from_slice(&[
@@ -690,6 +892,75 @@ fn query_should_return_an_account() {
     assert!(account.is_ok());
 }
 
+fn query_peer<T: EnclaveProxy>(app: &ChainNodeApp<T>, path: &str) -> u32 {
+    let mut qreq = RequestQuery::new();
+    qreq.path = path.into();
+    app.query_handler(&qreq).code
+}
+
+#[test]
+fn peer_filter_should_refuse_peers_and_be_reloaded() {
+    let addr = "fe7c045110b8dbf29765047380898919c5cb56f9";
+    let app = init_chain_for(addr.parse().unwrap());
+    let id_query = "/p2p/filter/id/0123456789abcdef0123456789abcdef01234567";
+    // all peers are allowed without the filter
+    assert_eq!(0, query_peer(&app, "/p2p/filter/addr/192.168.0.1:26656"));
+
+    let path = std::env::temp_dir().join(format!("peer-filter-{}.json", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"{"allowed_addrs": ["10.0.0.0/8"], "denied_addrs": ["10.0.0.13"]}"#,
+    )
+    .unwrap();
+    let app = app.with_peer_filter(peer_filter::PeerFilterFile::load(&path).unwrap());
+    assert_eq!(0, query_peer(&app, "/p2p/filter/addr/10.0.0.1:26656"));
+    assert_ne!(0, query_peer(&app, "/p2p/filter/addr/10.0.0.13:26656"));
+    assert_ne!(0, query_peer(&app, "p2p/filter/addr/192.168.0.1:26656"));
+    assert_eq!(0, query_peer(&app, id_query));
+
+    std::fs::write(
+        &path,
+        r#"{"denied_ids": ["0123456789ABCDEF0123456789ABCDEF01234567"]}"#,
+    )
+    .unwrap();
+    assert_eq!(0, query_peer(&app, "/p2p/filter/addr/192.168.0.1:26656"));
+    assert_ne!(0, query_peer(&app, id_query));
+    // invalid rules are ignored
+    std::fs::write(&path, r#"{"allowed_addrs": ["not an address"]}"#).unwrap();
+    assert_ne!(0, query_peer(&app, id_query));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn query_should_return_an_account_with_inclusion_proof() {
+    let secp = Secp256k1::new();
+    let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+    let addr = RedeemAddress::from(&PublicKey::from_secret_key(&secp, &secret_key));
+    let (mut app, _, _, _) = deliver_valid_tx();
+    let mut endreq = RequestEndBlock::default();
+    endreq.set_height(10);
+    app.end_block(&endreq);
+    app.commit(&RequestCommit::default());
+    let state = app.last_state.clone().unwrap();
+
+    let mut qreq = RequestQuery::new();
+    qreq.data = addr.to_vec();
+    qreq.path = "account".into();
+    qreq.prove = true;
+    let qresp = app.query(&qreq);
+    assert_eq!(0, qresp.code);
+    assert_eq!(state.last_block_height, qresp.height);
+    let account = StakedState::decode(&mut qresp.value.as_slice()).unwrap();
+    assert_eq!(get_account(&addr, &app), account);
+    let ops = qresp.get_proof().get_ops();
+    assert_eq!(1, ops.len());
+    assert_eq!("account", ops[0].get_field_type());
+    assert_eq!(&account.key()[..], ops[0].get_key());
+    let proof = AccountProof::decode(&mut ops[0].get_data()).unwrap();
+    assert!(proof.verify(&account, &state.last_apphash));
+    assert!(!proof.verify(&account, &app.genesis_app_hash));
+}
+
 #[test]
 fn query_should_return_proof_for_committed_tx() {
     let (mut app, tx, witness, _) = deliver_valid_tx();
@@ -775,135 +1046,1447 @@ fn get_tx_meta(txid: &TxId, app: &ChainNodeApp<MockClient>) -> BitVec {
 }
 
 #[test]
-fn all_valid_tx_types_should_commit() {
+fn genesis_utxos_should_be_spendable() {
     let secp = Secp256k1::new();
     let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
     let public_key = PublicKey::from_secret_key(&secp, &secret_key);
     let addr = RedeemAddress::from(&public_key);
-    let mut app = init_chain_for(addr);
-
     let merkle_tree = MerkleTree::new(vec![RawPubkey::from(public_key.serialize())]);
-
     let eaddr = ExtendedAddr::OrTree(merkle_tree.root_hash());
-    let tx0 = WithdrawUnbondedTx::new(
-        0,
-        vec![
-            TxOut::new_with_timelock(eaddr.clone(), Coin::one(), 0),
-            TxOut::new_with_timelock(eaddr.clone(), Coin::one(), 0),
-        ],
-        TxAttributes::new_with_access(
-            0,
-            vec![TxAccessPolicy::new(public_key.clone(), TxAccess::AllData)],
-        ),
+    let mut app = init_chain_with_utxos(
+        addr,
+        get_enclave_bridge_mock(),
+        vec![TxOut::new(eaddr.clone(), Coin::one())],
     );
-    let txid = &tx0.id();
-    let witness0 = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &txid, &secret_key));
-    let withdrawtx = TxAux::WithdrawUnbondedStakeTx {
-        txid: tx0.id(),
-        no_of_outputs: tx0.outputs.len() as TxoIndex,
-        witness: witness0,
-        payload: TxObfuscated {
-            key_from: 0,
-            nonce: [0u8; 12],
-            txpayload: PlainTxAux::WithdrawUnbondedStakeTx(tx0).encode(),
-        },
-    };
-    {
-        let account = get_account(&addr, &app);
-        // TODO: more precise amount assertions
-        assert!(account.unbonded > Coin::zero());
-        assert_eq!(account.nonce, 0);
-    }
-    block_commit(&mut app, withdrawtx, 1);
-    {
-        let account = get_account(&addr, &app);
-        assert_eq!(account.unbonded, Coin::zero());
-        assert_eq!(account.nonce, 1);
-        let spend_utxos = get_tx_meta(&txid, &app);
-        assert!(!spend_utxos.any());
-    }
-    let halfcoin = Coin::from(5000_0000u32);
-    let utxo1 = TxoPointer::new(*txid, 0);
-    let mut tx1 = Tx::new();
-    tx1.add_input(utxo1);
-    tx1.add_output(TxOut::new(eaddr.clone(), halfcoin));
-    let txid1 = tx1.id();
-    let witness1 = vec![TxInWitness::TreeSig(
-        schnorr_sign(&secp, &Message::from_slice(&txid1).unwrap(), &secret_key).0,
+    let genesis_tx = Tx::new_with(
+        vec![],
+        vec![TxOut::new(eaddr.clone(), Coin::one())],
+        TxAttributes::new(0),
+    );
+    let genesis_txid = genesis_tx.id();
+    assert_eq!(
+        app.storage
+            .db
+            .get(COL_BODIES, &genesis_txid[..])
+            .unwrap()
+            .unwrap()
+            .to_vec(),
+        TxWithOutputs::Transfer(genesis_tx).encode()
+    );
+    assert!(!get_tx_meta(&genesis_txid, &app).any());
+
+    let mut tx = Tx::new();
+    tx.add_input(TxoPointer::new(genesis_txid, 0));
+    tx.add_output(TxOut::new(eaddr.clone(), Coin::from(5000_0000u32)));
+    let txid = tx.id();
+    let witness = vec![TxInWitness::TreeSig(
+        schnorr_sign(&secp, &Message::from_slice(&txid).unwrap(), &secret_key).0,
         merkle_tree
             .generate_proof(RawPubkey::from(public_key.serialize()))
             .unwrap(),
     )]
     .into();
-    let plain_txaux = PlainTxAux::TransferTx(tx1.clone(), witness1);
     let transfertx = TxAux::TransferTx {
-        txid: tx1.id(),
-        inputs: tx1.inputs.clone(),
-        no_of_outputs: tx1.outputs.len() as TxoIndex,
+        txid,
+        inputs: tx.inputs.clone(),
+        no_of_outputs: tx.outputs.len() as TxoIndex,
         payload: TxObfuscated {
             key_from: 0,
             nonce: [0u8; 12],
-            txpayload: plain_txaux.encode(),
+            txpayload: PlainTxAux::TransferTx(tx, witness).encode(),
         },
     };
-    {
-        let spent_utxos = get_tx_meta(&txid, &app);
-        assert!(!spent_utxos.any());
-    }
-    block_commit(&mut app, transfertx, 2);
-    {
-        let spent_utxos0 = get_tx_meta(&txid, &app);
-        assert!(spent_utxos0[0] && !spent_utxos0[1]);
-        let spent_utxos1 = get_tx_meta(&txid1, &app);
-        assert!(!spent_utxos1.any());
-    }
-    let utxo2 = TxoPointer::new(*txid, 1);
-    let tx2 = DepositBondTx::new(vec![utxo2], addr.into(), StakedStateOpAttributes::new(0));
-    let witness2 = vec![TxInWitness::TreeSig(
-        schnorr_sign(&secp, &Message::from_slice(&tx2.id()).unwrap(), &secret_key).0,
+    block_commit(&mut app, transfertx, 1);
+    assert!(get_tx_meta(&genesis_txid, &app)[0]);
+    assert!(!get_tx_meta(&txid, &app).any());
+}
+
+/// app with a genesis UTXO and a block spending it and updating a staked state
+/// (returns the app, the block transactions, the genesis transaction ID and the staking address)
+fn prepare_app_with_block_txs() -> (ChainNodeApp<MockClient>, Vec<TxAux>, TxId, RedeemAddress) {
+    let secp = Secp256k1::new();
+    let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+    let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+    let addr = RedeemAddress::from(&public_key);
+    let merkle_tree = MerkleTree::new(vec![RawPubkey::from(public_key.serialize())]);
+    let eaddr = ExtendedAddr::OrTree(merkle_tree.root_hash());
+    let app = init_chain_with_utxos(
+        addr,
+        get_enclave_bridge_mock(),
+        vec![TxOut::new(eaddr.clone(), Coin::one())],
+    );
+    let genesis_txid = Tx::new_with(
+        vec![],
+        vec![TxOut::new(eaddr.clone(), Coin::one())],
+        TxAttributes::new(0),
+    )
+    .id();
+
+    let mut tx = Tx::new();
+    tx.add_input(TxoPointer::new(genesis_txid, 0));
+    tx.add_output(TxOut::new(eaddr.clone(), Coin::from(5000_0000u32)));
+    let txid = tx.id();
+    let witness = vec![TxInWitness::TreeSig(
+        schnorr_sign(&secp, &Message::from_slice(&txid).unwrap(), &secret_key).0,
         merkle_tree
             .generate_proof(RawPubkey::from(public_key.serialize()))
             .unwrap(),
     )]
     .into();
-    let depositx = TxAux::DepositStakeTx {
-        tx: tx2,
+    let transfertx = TxAux::TransferTx {
+        txid,
+        inputs: tx.inputs.clone(),
+        no_of_outputs: tx.outputs.len() as TxoIndex,
         payload: TxObfuscated {
             key_from: 0,
             nonce: [0u8; 12],
-            txpayload: PlainTxAux::DepositStakeTx(witness2).encode(),
+            txpayload: PlainTxAux::TransferTx(tx, witness).encode(),
         },
     };
-    {
-        let spent_utxos0 = get_tx_meta(&txid, &app);
-        assert!(spent_utxos0[0] && !spent_utxos0[1]);
-        let account = get_account(&addr, &app);
-        assert_eq!(account.bonded, Coin::zero());
-        assert_eq!(account.nonce, 1);
+
+    let tx = WithdrawUnbondedTx::new(
+        0,
+        vec![TxOut::new_with_timelock(eaddr, Coin::one(), 0)],
+        TxAttributes::new_with_access(0, vec![TxAccessPolicy::new(public_key, TxAccess::AllData)]),
+    );
+    let witness = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &tx.id(), &secret_key));
+    let withdrawtx = TxAux::WithdrawUnbondedStakeTx {
+        txid: tx.id(),
+        no_of_outputs: tx.outputs.len() as TxoIndex,
+        witness,
+        payload: TxObfuscated {
+            key_from: 0,
+            nonce: [0u8; 12],
+            txpayload: PlainTxAux::WithdrawUnbondedStakeTx(tx).encode(),
+        },
+    };
+    (app, vec![transfertx, withdrawtx], genesis_txid, addr)
+}
+
+/// the phase of the block execution after which the app stops (before the block is committed)
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StopAfter {
+    BeginBlock,
+    DeliverTx,
+    EndBlock,
+}
+
+/// executes the first block and returns the committed app hash (or None if it was stopped before the commit)
+fn execute_first_block(
+    app: &mut ChainNodeApp<MockClient>,
+    txs: &[TxAux],
+    stop: Option<StopAfter>,
+) -> Option<Vec<u8>> {
+    begin_block(app);
+    if stop == Some(StopAfter::BeginBlock) {
+        return None;
     }
-    block_commit(&mut app, depositx, 3);
-    {
-        let spent_utxos0 = get_tx_meta(&txid, &app);
-        assert!(spent_utxos0[0] && spent_utxos0[1]);
-        let account = get_account(&addr, &app);
-        // TODO: more precise amount assertions
-        assert!(account.bonded > Coin::zero());
-        assert_eq!(account.nonce, 2);
+    for tx in txs.iter() {
+        let mut dreq = RequestDeliverTx::default();
+        dreq.set_tx(tx.encode());
+        let dresp = app.deliver_tx(&dreq);
+        assert_eq!(0, dresp.code, "{}", dresp.log);
     }
-
-    let tx3 = UnbondTx::new(halfcoin, 2, StakedStateOpAttributes::new(0));
-    let witness3 = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &tx3.id(), &secret_key));
-    let unbondtx = TxAux::UnbondStakeTx(tx3, witness3);
-    {
-        let account = get_account(&addr, &app);
-        assert_eq!(account.unbonded, Coin::zero());
-        assert_eq!(account.nonce, 2);
+    if stop == Some(StopAfter::DeliverTx) {
+        return None;
     }
-    block_commit(&mut app, unbondtx, 4);
-    {
-        let account = get_account(&addr, &app);
-        // TODO: more precise amount assertions
-        assert!(account.unbonded > Coin::zero());
-        assert_eq!(account.nonce, 3);
+    let mut breq = RequestEndBlock::default();
+    breq.set_height(1);
+    app.end_block(&breq);
+    if stop == Some(StopAfter::EndBlock) {
+        return None;
+    }
+    Some(app.commit(&RequestCommit::default()).data)
+}
+
+/// simulates restarting the app (e.g. after a crash): only what was written to the storage is kept
+/// (the in-memory DBs write the buffered changes immediately, so e.g. the uncommitted account trie nodes are kept)
+fn restart(app: ChainNodeApp<MockClient>) -> ChainNodeApp<MockClient> {
+    let genesis_app_hash = hex::encode_upper(app.genesis_app_hash);
+    let accounts =
+        AccountStorage::new(Storage::new_db(app.accounts.db().db.clone()), 20).expect("account db");
+    ChainNodeApp::new_with_storage(
+        app.tx_validator,
+        &genesis_app_hash,
+        TEST_CHAIN_ID,
+        app.storage,
+        accounts,
+    )
+}
+
+#[test]
+fn deliver_tx_should_reject_inputs_spent_in_the_same_block() {
+    let (mut app, txs, genesis_txid, _) = prepare_app_with_block_txs();
+    begin_block(&mut app);
+    let mut dreq = RequestDeliverTx::default();
+    dreq.set_tx(txs[0].encode());
+    assert_eq!(0, app.deliver_tx(&dreq).code);
+    assert_ne!(0, app.deliver_tx(&dreq).code);
+    // nothing is written before the commit
+    assert!(!get_tx_meta(&genesis_txid, &app).any());
+}
+
+#[test]
+fn block_should_be_replayed_after_the_app_stopped_before_commit() {
+    let (mut app, txs, _, _) = prepare_app_with_block_txs();
+    let app_hash = execute_first_block(&mut app, &txs, None).expect("committed block");
+    let mut app = restart(app);
+    let info = app.info(&RequestInfo::default());
+    assert_eq!(1, info.last_block_height);
+    assert_eq!(app_hash, info.last_block_app_hash);
+
+    for stop in [
+        StopAfter::BeginBlock,
+        StopAfter::DeliverTx,
+        StopAfter::EndBlock,
+    ]
+    .iter()
+    {
+        let (mut app, txs, genesis_txid, addr) = prepare_app_with_block_txs();
+        assert!(execute_first_block(&mut app, &txs, Some(*stop)).is_none());
+        let mut app = restart(app);
+        let info = app.info(&RequestInfo::default());
+        assert_eq!(0, info.last_block_height, "{:?}", stop);
+        assert_eq!(&app.genesis_app_hash[..], &info.last_block_app_hash[..]);
+        assert!(!get_tx_meta(&genesis_txid, &app).any());
+        assert_eq!(0, get_account(&addr, &app).nonce);
+
+        // Tendermint replays the block in the handshake
+        let replayed_app_hash = execute_first_block(&mut app, &txs, None);
+        assert_eq!(Some(app_hash.clone()), replayed_app_hash, "{:?}", stop);
+        assert!(get_tx_meta(&genesis_txid, &app)[0]);
+        assert_eq!(1, get_account(&addr, &app).nonce);
+    }
+}
+
+fn commit_empty_block(app: &mut ChainNodeApp<MockClient>, height: i64) -> Vec<u8> {
+    begin_block_at(app, height, vec![], vec![]);
+    let mut breq = RequestEndBlock::default();
+    breq.set_height(height);
+    app.end_block(&breq);
+    app.commit(&RequestCommit::default()).data
+}
+
+#[test]
+fn committed_blocks_should_be_rolled_back_and_replayed() {
+    let (app, txs, genesis_txid, addr) = prepare_app_with_block_txs();
+    let mut app = app.with_undo_depth(2);
+    let genesis_state = app.last_state.clone().expect("genesis state");
+    let app_hash = execute_first_block(&mut app, &txs, None).expect("committed block");
+    let first_state = app.last_state.clone().expect("committed state");
+    let second_app_hash = commit_empty_block(&mut app, 2);
+    let account_db = Storage::new_db(app.accounts.db().db.clone());
+
+    assert!(rollback_blocks(&app.storage, &account_db, 3).is_err());
+    assert_eq!(
+        first_state,
+        rollback_blocks(&app.storage, &account_db, 1).expect("rolled back block")
+    );
+    assert_eq!(
+        genesis_state,
+        rollback_blocks(&app.storage, &account_db, 1).expect("rolled back block")
+    );
+    assert!(rollback_blocks(&app.storage, &account_db, 1).is_err());
+    assert!(app
+        .storage
+        .db
+        .get(COL_BODIES, &txs[0].tx_id()[..])
+        .unwrap()
+        .is_none());
+
+    let mut app = restart(app);
+    let info = app.info(&RequestInfo::default());
+    assert_eq!(0, info.last_block_height);
+    assert_eq!(&app.genesis_app_hash[..], &info.last_block_app_hash[..]);
+    assert!(!get_tx_meta(&genesis_txid, &app).any());
+    assert_eq!(0, get_account(&addr, &app).nonce);
+
+    assert_eq!(Some(app_hash), execute_first_block(&mut app, &txs, None));
+    assert!(get_tx_meta(&genesis_txid, &app)[0]);
+    assert_eq!(1, get_account(&addr, &app).nonce);
+    assert_eq!(second_app_hash, commit_empty_block(&mut app, 2));
+}
+
+#[test]
+fn rollback_should_be_limited_to_the_undo_depth() {
+    let (app, txs, _, _) = prepare_app_with_block_txs();
+    let mut app = app.with_undo_depth(1);
+    execute_first_block(&mut app, &txs, None).expect("committed block");
+    commit_empty_block(&mut app, 2);
+    let account_db = Storage::new_db(app.accounts.db().db.clone());
+    // the entry of the first block was removed when the second one was committed
+    assert!(rollback_blocks(&app.storage, &account_db, 2).is_err());
+    let state = rollback_blocks(&app.storage, &account_db, 1).expect("rolled back block");
+    assert_eq!(1, state.last_block_height);
+}
+
+fn query_tx(app: &mut ChainNodeApp<MockClient>, path: &str, txid: &TxId) -> ResponseQuery {
+    let mut qreq = RequestQuery::new();
+    qreq.data = txid.to_vec();
+    qreq.path = path.into();
+    qreq.prove = true;
+    app.query(&qreq)
+}
+
+/// checks the transaction proof in the response and returns the transaction tree root it was verified against
+fn verify_tx_proof(qresp: &ResponseQuery, txid: &TxId) -> H256 {
+    let proof = qresp.proof.as_ref().expect("proof");
+    assert_eq!(1, proof.ops.len());
+    let mut root = [0u8; 32];
+    root.copy_from_slice(proof.ops[0].key.as_slice());
+    let tx_proof = <Proof<H256>>::decode(&mut proof.ops[0].data.as_slice()).unwrap();
+    assert!(tx_proof.verify(&root));
+    assert_eq!(txid, tx_proof.value());
+    root
+}
+
+#[test]
+fn inclusion_and_output_status_should_be_queried_with_proofs() {
+    let (mut app, txs, genesis_txid, _) = prepare_app_with_block_txs();
+    let app_hash = execute_first_block(&mut app, &txs, None).expect("committed block");
+    let state = app.last_state.clone().expect("committed state");
+    let ids: Vec<TxId> = txs.iter().map(TxAux::tx_id).collect();
+
+    let qresp = query_tx(&mut app, "inclusion", &genesis_txid);
+    assert_eq!(0, qresp.code, "{}", qresp.log);
+    assert_eq!((0i64, 0u32).encode(), qresp.value);
+    assert_eq!(0, qresp.height);
+    assert_eq!(
+        MerkleTree::new(vec![genesis_txid]).root_hash(),
+        verify_tx_proof(&qresp, &genesis_txid)
+    );
+
+    let qresp = query_tx(&mut app, "inclusion", &ids[1]);
+    assert_eq!(0, qresp.code, "{}", qresp.log);
+    assert_eq!((1i64, 1u32).encode(), qresp.value);
+    assert_eq!(1, qresp.height);
+    let tree = MerkleTree::new(ids.clone());
+    assert_eq!(tree.root_hash(), verify_tx_proof(&qresp, &ids[1]));
+    let committed_app_hash = compute_app_hash(
+        &tree,
+        &state.last_account_root_hash,
+        &state.rewards_pool,
+        &state.network_params,
+        &state.node_state_hash(),
+    );
+    assert_eq!(app_hash, committed_app_hash.to_vec());
+
+    let qresp = query_tx(&mut app, "output_status", &genesis_txid);
+    assert_eq!(0, qresp.code, "{}", qresp.log);
+    assert_eq!(vec![true].encode(), qresp.value);
+    assert_eq!("output 0: spent", qresp.info);
+    verify_tx_proof(&qresp, &genesis_txid);
+    let qresp = query_tx(&mut app, "output_status", &ids[0]);
+    assert_eq!(vec![false].encode(), qresp.value);
+    assert_eq!(1, qresp.height);
+    verify_tx_proof(&qresp, &ids[0]);
+
+    for path in ["inclusion", "output_status"].iter() {
+        assert_eq!(1, query_tx(&mut app, path, &[0xff; 32]).code);
+    }
+}
+
+/// node storage whose writes fail while `fail_writes` is set (e.g. as if the app stopped in the middle of the commit)
+struct FailingWritesDB {
+    db: Arc<dyn KeyValueDB>,
+    fail_writes: AtomicBool,
+}
+
+impl KeyValueDB for FailingWritesDB {
+    fn get(&self, col: Option<u32>, key: &[u8]) -> io::Result<Option<DBValue>> {
+        self.db.get(col, key)
+    }
+
+    fn get_by_prefix(&self, col: Option<u32>, prefix: &[u8]) -> Option<Box<[u8]>> {
+        self.db.get_by_prefix(col, prefix)
+    }
+
+    fn write_buffered(&self, transaction: DBTransaction) {
+        self.db.write_buffered(transaction)
+    }
+
+    fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        if self.fail_writes.load(Ordering::SeqCst) {
+            Err(io::Error::new(io::ErrorKind::Other, "write failed"))
+        } else {
+            self.db.write(transaction)
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.db.flush()
+    }
+
+    fn iter<'a>(
+        &'a self,
+        col: Option<u32>,
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.db.iter(col)
+    }
+
+    fn iter_from_prefix<'a>(
+        &'a self,
+        col: Option<u32>,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.db.iter_from_prefix(col, prefix)
+    }
+
+    fn restore(&self, new_db: &str) -> io::Result<()> {
+        self.db.restore(new_db)
+    }
+}
+
+#[test]
+fn block_should_be_replayed_after_the_app_stopped_during_commit() {
+    let (mut app, txs, _, _) = prepare_app_with_block_txs();
+    let app_hash = execute_first_block(&mut app, &txs, None).expect("committed block");
+
+    let (mut app, txs, genesis_txid, addr) = prepare_app_with_block_txs();
+    let db = Arc::new(FailingWritesDB {
+        db: app.storage.db.clone(),
+        fail_writes: AtomicBool::new(false),
+    });
+    app.storage = Storage::new_db(db.clone());
+    assert!(execute_first_block(&mut app, &txs, Some(StopAfter::EndBlock)).is_none());
+    let block_account_root = app.uncommitted_account_root_hash;
+    // the account trie nodes of the block are persisted, but the app state isn't
+    db.fail_writes.store(true, Ordering::SeqCst);
+    let committed = panic::catch_unwind(AssertUnwindSafe(|| app.commit(&RequestCommit::default())));
+    assert!(committed.is_err());
+    db.fail_writes.store(false, Ordering::SeqCst);
+
+    let mut app = restart(app);
+    let account_key = to_stake_key(&StakedStateAddress::from(addr));
+    assert!(app
+        .accounts
+        .get_one(&block_account_root, &account_key)
+        .expect("account lookup problem")
+        .is_some());
+    let info = app.info(&RequestInfo::default());
+    assert_eq!(0, info.last_block_height);
+    assert_eq!(&app.genesis_app_hash[..], &info.last_block_app_hash[..]);
+    assert!(!get_tx_meta(&genesis_txid, &app).any());
+    assert_eq!(0, get_account(&addr, &app).nonce);
+
+    // the replayed block inserts the same trie nodes again
+    let replayed_app_hash = execute_first_block(&mut app, &txs, None);
+    assert_eq!(Some(app_hash), replayed_app_hash);
+    assert!(get_tx_meta(&genesis_txid, &app)[0]);
+    assert_eq!(1, get_account(&addr, &app).nonce);
+}
+
+#[test]
+fn all_valid_tx_types_should_commit() {
+    let secp = Secp256k1::new();
+    let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+    let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+    let addr = RedeemAddress::from(&public_key);
+    let mut app = init_chain_for(addr);
+
+    let merkle_tree = MerkleTree::new(vec![RawPubkey::from(public_key.serialize())]);
+
+    let eaddr = ExtendedAddr::OrTree(merkle_tree.root_hash());
+    let tx0 = WithdrawUnbondedTx::new(
+        0,
+        vec![
+            TxOut::new_with_timelock(eaddr.clone(), Coin::one(), 0),
+            TxOut::new_with_timelock(eaddr.clone(), Coin::one(), 0),
+        ],
+        TxAttributes::new_with_access(
+            0,
+            vec![TxAccessPolicy::new(public_key.clone(), TxAccess::AllData)],
+        ),
+    );
+    let txid = &tx0.id();
+    let witness0 = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &txid, &secret_key));
+    let withdrawtx = TxAux::WithdrawUnbondedStakeTx {
+        txid: tx0.id(),
+        no_of_outputs: tx0.outputs.len() as TxoIndex,
+        witness: witness0,
+        payload: TxObfuscated {
+            key_from: 0,
+            nonce: [0u8; 12],
+            txpayload: PlainTxAux::WithdrawUnbondedStakeTx(tx0).encode(),
+        },
+    };
+    {
+        let account = get_account(&addr, &app);
+        // TODO: more precise amount assertions
+        assert!(account.unbonded() > Coin::zero());
+        assert_eq!(account.nonce, 0);
+    }
+    block_commit(&mut app, withdrawtx, 1);
+    {
+        let account = get_account(&addr, &app);
+        assert_eq!(account.unbonded(), Coin::zero());
+        assert_eq!(account.nonce, 1);
+        let spend_utxos = get_tx_meta(&txid, &app);
+        assert!(!spend_utxos.any());
+    }
+    let halfcoin = Coin::from(5000_0000u32);
+    let utxo1 = TxoPointer::new(*txid, 0);
+    let mut tx1 = Tx::new();
+    tx1.add_input(utxo1);
+    tx1.add_output(TxOut::new(eaddr.clone(), halfcoin));
+    let txid1 = tx1.id();
+    let witness1 = vec![TxInWitness::TreeSig(
+        schnorr_sign(&secp, &Message::from_slice(&txid1).unwrap(), &secret_key).0,
+        merkle_tree
+            .generate_proof(RawPubkey::from(public_key.serialize()))
+            .unwrap(),
+    )]
+    .into();
+    let plain_txaux = PlainTxAux::TransferTx(tx1.clone(), witness1);
+    let transfertx = TxAux::TransferTx {
+        txid: tx1.id(),
+        inputs: tx1.inputs.clone(),
+        no_of_outputs: tx1.outputs.len() as TxoIndex,
+        payload: TxObfuscated {
+            key_from: 0,
+            nonce: [0u8; 12],
+            txpayload: plain_txaux.encode(),
+        },
+    };
+    {
+        let spent_utxos = get_tx_meta(&txid, &app);
+        assert!(!spent_utxos.any());
+    }
+    block_commit(&mut app, transfertx, 2);
+    {
+        let spent_utxos0 = get_tx_meta(&txid, &app);
+        assert!(spent_utxos0[0] && !spent_utxos0[1]);
+        let spent_utxos1 = get_tx_meta(&txid1, &app);
+        assert!(!spent_utxos1.any());
+    }
+    let utxo2 = TxoPointer::new(*txid, 1);
+    let tx2 = DepositBondTx::new(vec![utxo2], addr.into(), StakedStateOpAttributes::new(0));
+    let witness2 = vec![TxInWitness::TreeSig(
+        schnorr_sign(&secp, &Message::from_slice(&tx2.id()).unwrap(), &secret_key).0,
+        merkle_tree
+            .generate_proof(RawPubkey::from(public_key.serialize()))
+            .unwrap(),
+    )]
+    .into();
+    let depositx = TxAux::DepositStakeTx {
+        tx: tx2,
+        payload: TxObfuscated {
+            key_from: 0,
+            nonce: [0u8; 12],
+            txpayload: PlainTxAux::DepositStakeTx(witness2).encode(),
+        },
+    };
+    {
+        let spent_utxos0 = get_tx_meta(&txid, &app);
+        assert!(spent_utxos0[0] && !spent_utxos0[1]);
+        let account = get_account(&addr, &app);
+        assert_eq!(account.bonded, Coin::zero());
+        assert_eq!(account.nonce, 1);
+    }
+    block_commit(&mut app, depositx, 3);
+    {
+        let spent_utxos0 = get_tx_meta(&txid, &app);
+        assert!(spent_utxos0[0] && spent_utxos0[1]);
+        let account = get_account(&addr, &app);
+        // TODO: more precise amount assertions
+        assert!(account.bonded > Coin::zero());
+        assert_eq!(account.nonce, 2);
+    }
+
+    let tx3 = UnbondTx::new(halfcoin, 2, StakedStateOpAttributes::new(0));
+    let witness3 = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &tx3.id(), &secret_key));
+    let unbondtx = TxAux::UnbondStakeTx(tx3, witness3);
+    {
+        let account = get_account(&addr, &app);
+        assert_eq!(account.unbonded(), Coin::zero());
+        assert_eq!(account.nonce, 2);
+    }
+    block_commit(&mut app, unbondtx, 4);
+    {
+        let account = get_account(&addr, &app);
+        // TODO: more precise amount assertions
+        assert!(account.unbonded() > Coin::zero());
+        assert_eq!(account.nonce, 3);
+    }
+}
+
+#[test]
+fn node_join_tx_should_add_validator() {
+    let secp = Secp256k1::new();
+    let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+    let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+    let addr = RedeemAddress::from(&public_key);
+    let mut app = init_chain_for(addr);
+
+    let merkle_tree = MerkleTree::new(vec![RawPubkey::from(public_key.serialize())]);
+    let eaddr = ExtendedAddr::OrTree(merkle_tree.root_hash());
+    let tx0 = WithdrawUnbondedTx::new(
+        0,
+        vec![TxOut::new_with_timelock(
+            eaddr.clone(),
+            (Coin::one() + Coin::one()).unwrap(),
+            0,
+        )],
+        TxAttributes::new(0),
+    );
+    let txid = tx0.id();
+    let witness0 = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &txid, &secret_key));
+    let withdrawtx = TxAux::WithdrawUnbondedStakeTx {
+        txid,
+        no_of_outputs: tx0.outputs.len() as TxoIndex,
+        witness: witness0,
+        payload: TxObfuscated {
+            key_from: 0,
+            nonce: [0u8; 12],
+            txpayload: PlainTxAux::WithdrawUnbondedStakeTx(tx0).encode(),
+        },
+    };
+    block_commit(&mut app, withdrawtx, 1);
+    let tx1 = DepositBondTx::new(
+        vec![TxoPointer::new(txid, 0)],
+        addr.into(),
+        StakedStateOpAttributes::new(0),
+    );
+    let witness1 = vec![TxInWitness::TreeSig(
+        schnorr_sign(&secp, &Message::from_slice(&tx1.id()).unwrap(), &secret_key).0,
+        merkle_tree
+            .generate_proof(RawPubkey::from(public_key.serialize()))
+            .unwrap(),
+    )]
+    .into();
+    let depositx = TxAux::DepositStakeTx {
+        tx: tx1,
+        payload: TxObfuscated {
+            key_from: 0,
+            nonce: [0u8; 12],
+            txpayload: PlainTxAux::DepositStakeTx(witness1).encode(),
+        },
+    };
+    block_commit(&mut app, depositx, 2);
+
+    let join = |consensus_pubkey| {
+        let tx = NodeJoinRequestTx::new(
+            2,
+            addr.into(),
+            consensus_pubkey,
+            StakedStateOpAttributes::new(0),
+        );
+        let witness = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &tx.id(), &secret_key));
+        TxAux::NodeJoinTx(tx, witness)
+    };
+    // the genesis validator's consensus key
+    let mut creq = RequestCheckTx::default();
+    creq.set_tx(join(TendermintValidatorPubKey::Ed25519([0x30; 32])).encode());
+    let cresp = app.check_tx(&creq);
+    assert_ne!(0, cresp.code);
+    assert!(cresp.log.contains("council node"));
+
+    let consensus_pubkey = TendermintValidatorPubKey::Ed25519([0xee; 32]);
+    let jointx = join(consensus_pubkey.clone());
+    begin_block(&mut app);
+    let mut dreq = RequestDeliverTx::default();
+    dreq.set_tx(jointx.encode());
+    let dresp = app.deliver_tx(&dreq);
+    assert_eq!(0, dresp.code);
+    let mut breq = RequestEndBlock::default();
+    breq.set_height(3);
+    let eresp = app.end_block(&breq);
+    app.commit(&RequestCommit::default());
+
+    let account = get_account(&addr, &app);
+    assert_eq!(account.nonce, 3);
+    let (keytype, key) = consensus_pubkey.to_validator_update();
+    assert_eq!(1, eresp.validator_updates.len());
+    let update = &eresp.validator_updates[0];
+    assert_eq!(update.get_pub_key().get_field_type(), keytype);
+    assert_eq!(update.get_pub_key().get_data(), &key[..]);
+    assert_eq!(
+        update.power,
+        i64::from(TendermintVotePower::from(account.bonded))
+    );
+    let council_nodes = &app.last_state.as_ref().unwrap().council_nodes;
+    assert_eq!(2, council_nodes.len());
+    assert_eq!(
+        council_nodes[1],
+        CouncilNode::new(addr.into(), consensus_pubkey)
+    );
+
+    // the same staking address can't join twice
+    let mut creq = RequestCheckTx::default();
+    let tx = NodeJoinRequestTx::new(
+        3,
+        addr.into(),
+        TendermintValidatorPubKey::Ed25519([0xef; 32]),
+        StakedStateOpAttributes::new(0),
+    );
+    let witness = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &tx.id(), &secret_key));
+    creq.set_tx(TxAux::NodeJoinTx(tx, witness).encode());
+    assert_ne!(0, app.check_tx(&creq).code);
+}
+
+fn deliver_in_block(
+    app: &mut ChainNodeApp<MockClient>,
+    tx: TxAux,
+    height: i64,
+) -> ResponseEndBlock {
+    begin_block(app);
+    let mut dreq = RequestDeliverTx::default();
+    dreq.set_tx(tx.encode());
+    let dresp = app.deliver_tx(&dreq);
+    assert_eq!(0, dresp.code, "{}", dresp.log);
+    let mut breq = RequestEndBlock::default();
+    breq.set_height(height);
+    let eresp = app.end_block(&breq);
+    app.commit(&RequestCommit::default());
+    eresp
+}
+
+#[test]
+fn delegation_should_change_validator_voting_power() {
+    let secp = Secp256k1::new();
+    let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+    let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+    let addr = RedeemAddress::from(&public_key);
+    let mut app = init_chain_for(addr);
+    let validator_addr = "0x0e7c045110b8dbf29765047380898919c5cb56f4"
+        .parse::<RedeemAddress>()
+        .unwrap();
+
+    let merkle_tree = MerkleTree::new(vec![RawPubkey::from(public_key.serialize())]);
+    let eaddr = ExtendedAddr::OrTree(merkle_tree.root_hash());
+    let tx0 = WithdrawUnbondedTx::new(
+        0,
+        vec![TxOut::new_with_timelock(
+            eaddr.clone(),
+            (Coin::one() + Coin::one()).unwrap(),
+            0,
+        )],
+        TxAttributes::new(0),
+    );
+    let txid = tx0.id();
+    let witness0 = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &txid, &secret_key));
+    let withdrawtx = TxAux::WithdrawUnbondedStakeTx {
+        txid,
+        no_of_outputs: tx0.outputs.len() as TxoIndex,
+        witness: witness0,
+        payload: TxObfuscated {
+            key_from: 0,
+            nonce: [0u8; 12],
+            txpayload: PlainTxAux::WithdrawUnbondedStakeTx(tx0).encode(),
+        },
+    };
+    deliver_in_block(&mut app, withdrawtx, 1);
+    let tx1 = DepositBondTx::new(
+        vec![TxoPointer::new(txid, 0)],
+        addr.into(),
+        StakedStateOpAttributes::new(0),
+    );
+    let witness1 = vec![TxInWitness::TreeSig(
+        schnorr_sign(&secp, &Message::from_slice(&tx1.id()).unwrap(), &secret_key).0,
+        merkle_tree
+            .generate_proof(RawPubkey::from(public_key.serialize()))
+            .unwrap(),
+    )]
+    .into();
+    let depositx = TxAux::DepositStakeTx {
+        tx: tx1,
+        payload: TxObfuscated {
+            key_from: 0,
+            nonce: [0u8; 12],
+            txpayload: PlainTxAux::DepositStakeTx(witness1).encode(),
+        },
+    };
+    deliver_in_block(&mut app, depositx, 2);
+
+    // delegating to a staking address that isn't a council node
+    let tx = DelegateTx::new(Coin::one(), 2, addr.into(), StakedStateOpAttributes::new(0));
+    let witness = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &tx.id(), &secret_key));
+    let mut creq = RequestCheckTx::default();
+    creq.set_tx(TxAux::DelegateStakeTx(tx, witness).encode());
+    let cresp = app.check_tx(&creq);
+    assert_ne!(0, cresp.code);
+    assert!(cresp.log.contains("council node"));
+
+    let tx = DelegateTx::new(
+        Coin::one(),
+        2,
+        validator_addr.into(),
+        StakedStateOpAttributes::new(0),
+    );
+    let witness = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &tx.id(), &secret_key));
+    let eresp = deliver_in_block(&mut app, TxAux::DelegateStakeTx(tx, witness), 3);
+    assert_eq!(1, eresp.validator_updates.len());
+    assert_eq!(
+        eresp.validator_updates[0].power,
+        i64::from(TendermintVotePower::from(
+            (Coin::one() + Coin::one()).unwrap()
+        ))
+    );
+    let state = app.last_state.as_ref().unwrap();
+    assert_eq!(state.delegated_stake(&validator_addr.into()), Coin::one());
+    let account = get_account(&addr, &app);
+    assert_eq!(account.delegated(), Coin::one());
+    assert_eq!(account.nonce, 3);
+
+    let tx = UndelegateTx::new(
+        Coin::one(),
+        3,
+        validator_addr.into(),
+        StakedStateOpAttributes::new(0),
+    );
+    let witness = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &tx.id(), &secret_key));
+    let eresp = deliver_in_block(&mut app, TxAux::UndelegateStakeTx(tx, witness), 4);
+    assert_eq!(1, eresp.validator_updates.len());
+    assert_eq!(
+        eresp.validator_updates[0].power,
+        i64::from(TendermintVotePower::from(Coin::one()))
+    );
+    let state = app.last_state.as_ref().unwrap();
+    assert!(state.delegations.is_empty());
+    let account = get_account(&addr, &app);
+    assert_eq!(account.delegation, None);
+    assert_eq!(account.unbonding.len(), 1);
+}
+
+#[test]
+fn approved_param_change_should_be_activated() {
+    let secp = Secp256k1::new();
+    let secret_key = SecretKey::from_slice(&[0xce; 32]).expect("32 bytes, within curve order");
+    let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+    let addr = RedeemAddress::from(&public_key);
+    let mut app = init_chain_for(addr);
+
+    // bonds enough to have more than 2/3 of the voting power after joining the genesis validator
+    let merkle_tree = MerkleTree::new(vec![RawPubkey::from(public_key.serialize())]);
+    let eaddr = ExtendedAddr::OrTree(merkle_tree.root_hash());
+    let tx0 = WithdrawUnbondedTx::new(
+        0,
+        vec![TxOut::new_with_timelock(
+            eaddr.clone(),
+            Coin::new(10 * u64::from(Coin::one())).unwrap(),
+            0,
+        )],
+        TxAttributes::new(0),
+    );
+    let txid = tx0.id();
+    let witness0 = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &txid, &secret_key));
+    let withdrawtx = TxAux::WithdrawUnbondedStakeTx {
+        txid,
+        no_of_outputs: tx0.outputs.len() as TxoIndex,
+        witness: witness0,
+        payload: TxObfuscated {
+            key_from: 0,
+            nonce: [0u8; 12],
+            txpayload: PlainTxAux::WithdrawUnbondedStakeTx(tx0).encode(),
+        },
+    };
+    deliver_in_block(&mut app, withdrawtx, 1);
+    let tx1 = DepositBondTx::new(
+        vec![TxoPointer::new(txid, 0)],
+        addr.into(),
+        StakedStateOpAttributes::new(0),
+    );
+    let witness1 = vec![TxInWitness::TreeSig(
+        schnorr_sign(&secp, &Message::from_slice(&tx1.id()).unwrap(), &secret_key).0,
+        merkle_tree
+            .generate_proof(RawPubkey::from(public_key.serialize()))
+            .unwrap(),
+    )]
+    .into();
+    let depositx = TxAux::DepositStakeTx {
+        tx: tx1,
+        payload: TxObfuscated {
+            key_from: 0,
+            nonce: [0u8; 12],
+            txpayload: PlainTxAux::DepositStakeTx(witness1).encode(),
+        },
+    };
+    deliver_in_block(&mut app, depositx, 2);
+    let tx = NodeJoinRequestTx::new(
+        2,
+        addr.into(),
+        TendermintValidatorPubKey::Ed25519([0xee; 32]),
+        StakedStateOpAttributes::new(0),
+    );
+    let witness = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &tx.id(), &secret_key));
+    deliver_in_block(&mut app, TxAux::NodeJoinTx(tx, witness), 3);
+
+    let new_fee_policy = LinearFee::new(Milli::new(2, 0), Milli::new(1, 1));
+    let propose = |activation_height| {
+        let tx = ParamChangeProposalTx::new(
+            3,
+            addr.into(),
+            NetworkParameterChange::FeePolicy(new_fee_policy),
+            activation_height,
+            StakedStateOpAttributes::new(0),
+        );
+        let witness = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &tx.id(), &secret_key));
+        TxAux::ParamChangeProposalTx(tx, witness)
+    };
+    // it would be activated in the block it's included in
+    let mut creq = RequestCheckTx::default();
+    creq.set_tx(propose(4).encode());
+    let cresp = app.check_tx(&creq);
+    assert_ne!(0, cresp.code);
+    assert!(cresp.log.contains("future block height"));
+    // the fee of the largest transactions would overflow
+    let overflowing_fee_policy =
+        LinearFee::new(Milli::integral(0), Milli::integral(u64::max_value() / 1000));
+    let tx = ParamChangeProposalTx::new(
+        3,
+        addr.into(),
+        NetworkParameterChange::FeePolicy(overflowing_fee_policy),
+        5,
+        StakedStateOpAttributes::new(0),
+    );
+    let witness = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &tx.id(), &secret_key));
+    let mut creq = RequestCheckTx::default();
+    creq.set_tx(TxAux::ParamChangeProposalTx(tx, witness).encode());
+    let cresp = app.check_tx(&creq);
+    assert_ne!(0, cresp.code);
+    assert!(cresp.log.contains("invalid network parameter"));
+
+    let proposal = propose(5);
+    let proposal_id = proposal.tx_id();
+    deliver_in_block(&mut app, proposal, 4);
+    let state = app.last_state.as_ref().unwrap();
+    assert_eq!(
+        state.param_change_proposals[&proposal_id].votes,
+        vec![StakedStateAddress::from(addr)]
+    );
+
+    // the proposer's vote is already counted
+    let tx = ParamChangeVoteTx::new(4, addr.into(), proposal_id, StakedStateOpAttributes::new(0));
+    let witness = StakedStateOpWitness::new(get_ecdsa_witness(&secp, &tx.id(), &secret_key));
+    let mut creq = RequestCheckTx::default();
+    creq.set_tx(TxAux::ParamChangeVoteTx(tx, witness).encode());
+    let cresp = app.check_tx(&creq);
+    assert_ne!(0, cresp.code);
+    assert!(cresp.log.contains("already voted"));
+
+    let last_apphash = app.last_state.as_ref().unwrap().last_apphash;
+    begin_block(&mut app);
+    let mut breq = RequestEndBlock::default();
+    breq.set_height(5);
+    app.end_block(&breq);
+    let cresp = app.commit(&RequestCommit::default());
+    let state = app.last_state.as_ref().unwrap();
+    assert_eq!(state.network_params.fee_policy, new_fee_policy);
+    assert!(state.param_change_proposals.is_empty());
+    // the active network parameters are committed in the app hash
+    assert_ne!(last_apphash, state.last_apphash);
+    assert_eq!(cresp.data, state.last_apphash.to_vec());
+}
+
+fn get_genesis_validator() -> Validator {
+    let mut validator = Validator::default();
+    validator.set_address(
+        TendermintValidatorPubKey::Ed25519([0x30; 32])
+            .validator_address()
+            .to_vec(),
+    );
+    validator.set_power(1);
+    validator
+}
+
+fn begin_block_at<T: EnclaveProxy>(
+    app: &mut ChainNodeApp<T>,
+    height: i64,
+    votes: Vec<VoteInfo>,
+    evidence: Vec<Evidence>,
+) {
+    let mut bbreq = RequestBeginBlock::default();
+    let mut header = Header::default();
+    header.set_height(height);
+    header.set_time(::protobuf::well_known_types::Timestamp::new());
+    bbreq.set_header(header);
+    let mut last_commit_info = LastCommitInfo::default();
+    last_commit_info.set_votes(votes.into());
+    bbreq.set_last_commit_info(last_commit_info);
+    bbreq.set_byzantine_validators(evidence.into());
+    app.begin_block(&bbreq);
+}
+
+#[test]
+fn byzantine_evidence_should_slash_and_jail_validator() {
+    let mut app = init_chain_for(
+        "0xfe7c045110b8dbf29765047380898919c5cb56f9"
+            .parse()
+            .unwrap(),
+    );
+    let validator_addr = "0x0e7c045110b8dbf29765047380898919c5cb56f4"
+        .parse::<RedeemAddress>()
+        .unwrap();
+    let rewards_pool = app.last_state.as_ref().unwrap().rewards_pool.remaining;
+
+    let mut evidence = Evidence::default();
+    evidence.set_validator(get_genesis_validator());
+    begin_block_at(&mut app, 1, vec![], vec![evidence]);
+    let mut breq = RequestEndBlock::default();
+    breq.set_height(1);
+    let eresp = app.end_block(&breq);
+    let cresp = app.commit(&RequestCommit::default());
+
+    assert_eq!(1, eresp.validator_updates.len());
+    assert_eq!(0, eresp.validator_updates[0].power);
+    let account = get_account(&validator_addr, &app);
+    assert_eq!(account.jailed_until, Some(10));
+    let slashed = get_slashing_config()
+        .byzantine_slash_ratio
+        .slash(Coin::one());
+    assert_eq!(account.bonded, (Coin::one() - slashed).unwrap());
+    let state = app.last_state.as_ref().unwrap();
+    assert_eq!(
+        state.rewards_pool.remaining,
+        (rewards_pool + slashed).unwrap()
+    );
+    assert_ne!(app.genesis_app_hash, state.last_apphash);
+    assert_eq!(cresp.data, state.last_apphash.to_vec());
+}
+
+#[test]
+fn missed_blocks_should_jail_validator() {
+    let mut app = init_chain_for(
+        "0xfe7c045110b8dbf29765047380898919c5cb56f9"
+            .parse()
+            .unwrap(),
+    );
+    let validator_addr = "0x0e7c045110b8dbf29765047380898919c5cb56f4"
+        .parse::<RedeemAddress>()
+        .unwrap();
+    // the signing window is 5 blocks and the validator can miss at most 2 of them
+    for height in 1..=4 {
+        let mut vote = VoteInfo::default();
+        vote.set_validator(get_genesis_validator());
+        vote.set_signed_last_block(false);
+        let votes = if height > 1 { vec![vote] } else { vec![] };
+        begin_block_at(&mut app, height, votes, vec![]);
+        let mut breq = RequestEndBlock::default();
+        breq.set_height(height);
+        let eresp = app.end_block(&breq);
+        app.commit(&RequestCommit::default());
+        let account = get_account(&validator_addr, &app);
+        assert_eq!(account.is_jailed(), height == 4);
+        assert_eq!(
+            eresp.validator_updates.len(),
+            if height == 4 { 1 } else { 0 }
+        );
+    }
+    let account = get_account(&validator_addr, &app);
+    let slashed = get_slashing_config()
+        .liveness_slash_ratio
+        .slash(Coin::one());
+    assert_eq!(account.bonded, (Coin::one() - slashed).unwrap());
+}
+
+#[test]
+fn epoch_end_should_distribute_rewards_to_validators() {
+    let mut app = init_chain_for(
+        "0xfe7c045110b8dbf29765047380898919c5cb56f9"
+            .parse()
+            .unwrap(),
+    );
+    let validator_addr = "0x0e7c045110b8dbf29765047380898919c5cb56f4"
+        .parse::<RedeemAddress>()
+        .unwrap();
+    let rewards_pool = Coin::new(1000).unwrap();
+    app.last_state.as_mut().unwrap().rewards_pool.remaining = rewards_pool;
+    // the epoch is 2 blocks and the validator signed the first one
+    for height in 1..=2 {
+        let mut vote = VoteInfo::default();
+        vote.set_validator(get_genesis_validator());
+        vote.set_signed_last_block(true);
+        let votes = if height > 1 { vec![vote] } else { vec![] };
+        begin_block_at(&mut app, height, votes, vec![]);
+        let mut breq = RequestEndBlock::default();
+        breq.set_height(height);
+        app.end_block(&breq);
+        app.commit(&RequestCommit::default());
+    }
+    let rewards = get_rewards_config().epoch_rewards(rewards_pool);
+    assert_eq!(rewards, Coin::new(500).unwrap());
+    let account = get_account(&validator_addr, &app);
+    assert_eq!(account.bonded, (Coin::one() + rewards).unwrap());
+    let state = app.last_state.as_ref().unwrap();
+    assert_eq!(
+        state.rewards_pool.remaining,
+        (rewards_pool - rewards).unwrap()
+    );
+    assert_eq!(state.rewards_pool.distributed, rewards);
+    assert!(state.rewards_weights.is_empty());
+    assert_ne!(app.genesis_app_hash, state.last_apphash);
+}
+
+#[test]
+fn snapshot_should_restore_the_committed_state() {
+    let secp = Secp256k1::new();
+    let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+    let addr = RedeemAddress::from(&PublicKey::from_secret_key(&secp, &secret_key));
+    let (mut app, tx, _, _) = deliver_valid_tx();
+    let mut endreq = RequestEndBlock::default();
+    endreq.set_height(10);
+    app.end_block(&endreq);
+    app.commit(&RequestCommit::default());
+
+    let mut snapshot = Vec::new();
+    let state = write_snapshot(&mut snapshot, &app.storage, &app.accounts).expect("snapshot");
+    assert_eq!(app.last_state, Some(state.clone()));
+
+    let storage = Storage::new_db(create_db());
+    let mut accounts = create_account_db();
+    let genesis_app_hash = app.genesis_app_hash;
+    let untrusted = restore_snapshot(
+        Cursor::new(&snapshot),
+        &storage,
+        &mut accounts,
+        TEST_CHAIN_ID,
+        &genesis_app_hash,
+        &[0u8; 32],
+    );
+    assert!(untrusted.is_err());
+    let mut corrupted = snapshot.clone();
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 1;
+    let corrupted = restore_snapshot(
+        Cursor::new(&corrupted),
+        &storage,
+        &mut accounts,
+        TEST_CHAIN_ID,
+        &genesis_app_hash,
+        &state.last_apphash,
+    );
+    assert!(corrupted.is_err());
+    // nothing is written before the snapshot is verified
+    assert!(storage.db.iter(COL_TX_META).next().is_none());
+    assert!(storage.db.iter(COL_BODIES).next().is_none());
+
+    let restored = restore_snapshot(
+        Cursor::new(&snapshot),
+        &storage,
+        &mut accounts,
+        TEST_CHAIN_ID,
+        &genesis_app_hash,
+        &state.last_apphash,
+    )
+    .expect("restore");
+    assert_eq!(state, restored);
+    let mut restored_app = ChainNodeApp::new_with_storage(
+        get_enclave_bridge_mock(),
+        &hex::encode_upper(genesis_app_hash),
+        TEST_CHAIN_ID,
+        storage,
+        accounts,
+    );
+    assert_eq!(Some(state), restored_app.last_state);
+    assert_eq!(get_account(&addr, &app), get_account(&addr, &restored_app));
+    assert_eq!(
+        get_tx_meta(&tx.id(), &app),
+        get_tx_meta(&tx.id(), &restored_app)
+    );
+    assert!(restored_app
+        .storage
+        .db
+        .get(COL_BODIES, &tx.id()[..])
+        .unwrap()
+        .is_some());
+    // snapshots are only restored to empty storages
+    let restored_again = restore_snapshot(
+        Cursor::new(&snapshot),
+        &restored_app.storage,
+        &mut restored_app.accounts,
+        TEST_CHAIN_ID,
+        &genesis_app_hash,
+        &restored_app.last_state.as_ref().unwrap().last_apphash,
+    );
+    assert!(restored_again.is_err());
+}
+
+/// replaces the app state in the snapshot header (with a matching checksum, as a dishonest node could)
+fn replace_snapshot_state(snapshot: &[u8], update: impl FnOnce(&mut ChainNodeState)) -> Vec<u8> {
+    let mut length = [0u8; 4];
+    length.copy_from_slice(&snapshot[..4]);
+    let header_end = 4 + u32::from_le_bytes(length) as usize;
+    let mut header = SnapshotHeader::decode(&mut &snapshot[4..header_end]).unwrap();
+    update(&mut header.state);
+    let encoded = header.encode();
+    let mut replaced = (encoded.len() as u32).to_le_bytes().to_vec();
+    replaced.extend_from_slice(&encoded);
+    // the final record: length, variant, the number of entries and the checksum of the preceding records
+    let end = snapshot.len() - (4 + 1 + 8 + 32);
+    replaced.extend_from_slice(&snapshot[header_end..end]);
+    let checksum = Blake2s::digest(&replaced);
+    replaced.extend_from_slice(&snapshot[end..end + 4 + 1 + 8]);
+    replaced.extend_from_slice(&checksum);
+    replaced
+}
+
+#[test]
+fn snapshot_with_tampered_app_state_should_be_rejected() {
+    let (mut app, _, _, _) = deliver_valid_tx();
+    let mut endreq = RequestEndBlock::default();
+    endreq.set_height(10);
+    app.end_block(&endreq);
+    app.commit(&RequestCommit::default());
+    let mut snapshot = Vec::new();
+    let state = write_snapshot(&mut snapshot, &app.storage, &app.accounts).expect("snapshot");
+    assert_eq!(snapshot, replace_snapshot_state(&snapshot, |_| {}));
+
+    let validator_addr = "0x0e7c045110b8dbf29765047380898919c5cb56f4"
+        .parse::<RedeemAddress>()
+        .unwrap();
+    let tampered_states: Vec<Box<dyn Fn(&mut ChainNodeState)>> = vec![
+        Box::new(|state: &mut ChainNodeState| state.council_nodes.clear()),
+        Box::new(|state: &mut ChainNodeState| {
+            state.rewards_weights.insert(validator_addr.into(), 1);
+        }),
+        Box::new(|state: &mut ChainNodeState| {
+            state.set_delegation(validator_addr.into(), validator_addr.into(), Coin::one())
+        }),
+    ];
+    for tamper in tampered_states.iter() {
+        let tampered = replace_snapshot_state(&snapshot, tamper);
+        let storage = Storage::new_db(create_db());
+        let restored = restore_snapshot(
+            Cursor::new(&tampered),
+            &storage,
+            &mut create_account_db(),
+            TEST_CHAIN_ID,
+            &app.genesis_app_hash,
+            &state.last_apphash,
+        );
+        assert!(restored.is_err());
+        assert!(storage.db.iter(COL_TX_META).next().is_none());
+    }
+}
+
+/// updates the account in the current block (as a transaction would) and returns the new account trie root
+fn increment_nonce<T: EnclaveProxy>(
+    app: &mut ChainNodeApp<T>,
+    account_address: &RedeemAddress,
+) -> StarlingFixedKey {
+    let account_key = to_stake_key(&StakedStateAddress::from(*account_address));
+    let mut account = app
+        .accounts
+        .get_one(&app.uncommitted_account_root_hash, &account_key)
+        .expect("account lookup problem")
+        .expect("account")
+        .0;
+    account.nonce += 1;
+    let (next_root, _) = update_account(
+        account,
+        &app.uncommitted_account_root_hash,
+        &mut app.accounts,
+    );
+    app.set_uncommitted_account_root(next_root);
+    next_root
+}
+
+#[test]
+fn intermediate_account_roots_should_be_pruned_after_commit() {
+    let addr = "0xfe7c045110b8dbf29765047380898919c5cb56f9"
+        .parse::<RedeemAddress>()
+        .unwrap();
+    let account_key = to_stake_key(&StakedStateAddress::from(addr));
+    let mut app = init_chain_for(addr);
+    let genesis_root = app.uncommitted_account_root_hash;
+    begin_block_at(&mut app, 1, vec![], vec![]);
+    let intermediate_root = increment_nonce(&mut app, &addr);
+    let committed_root = increment_nonce(&mut app, &addr);
+    let mut endreq = RequestEndBlock::default();
+    endreq.set_height(1);
+    app.end_block(&endreq);
+    app.commit(&RequestCommit::default());
+
+    let state = app.last_state.as_ref().unwrap();
+    assert_eq!(committed_root, state.last_account_root_hash);
+    assert!(app.uncommitted_account_roots.is_empty());
+    assert_eq!(2, get_account(&addr, &app).nonce);
+    assert!(app
+        .accounts
+        .get_one(&intermediate_root, &account_key)
+        .unwrap_or(None)
+        .is_none());
+    // the previously committed root is kept
+    let genesis_account = app
+        .accounts
+        .get_one(&genesis_root, &account_key)
+        .expect("account lookup problem")
+        .expect("account");
+    assert_eq!(0, genesis_account.0.nonce);
+    // the committed roots are only recorded if they are retained for a number of blocks
+    assert!(get_committed_account_roots(&app.storage)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn committed_account_roots_should_be_pruned_out_of_retention_window() {
+    let addr = "0xfe7c045110b8dbf29765047380898919c5cb56f9"
+        .parse::<RedeemAddress>()
+        .unwrap();
+    let account_key = to_stake_key(&StakedStateAddress::from(addr));
+    let mut app = init_chain_for(addr);
+    app.account_root_retention = Some(1);
+    let mut roots = vec![app.uncommitted_account_root_hash];
+    for height in 1..=3 {
+        begin_block_at(&mut app, height, vec![], vec![]);
+        increment_nonce(&mut app, &addr);
+        let mut endreq = RequestEndBlock::default();
+        endreq.set_height(height);
+        app.end_block(&endreq);
+        app.commit(&RequestCommit::default());
+        roots.push(app.last_state.as_ref().unwrap().last_account_root_hash);
+    }
+    // the states committed at the genesis and at height 1 are out of the window
+    for root in roots[..2].iter() {
+        assert!(app
+            .accounts
+            .get_one(root, &account_key)
+            .unwrap_or(None)
+            .is_none());
+    }
+    for (nonce, root) in roots.iter().enumerate().skip(2) {
+        let account = app
+            .accounts
+            .get_one(root, &account_key)
+            .expect("account lookup problem")
+            .expect("account");
+        assert_eq!(nonce as u64, account.0.nonce);
+    }
+    assert_eq!(
+        vec![(2, roots[2]), (3, roots[3])],
+        get_committed_account_roots(&app.storage).unwrap()
+    );
+}
+
+#[test]
+fn expired_account_roots_should_be_kept_while_snapshot_is_written() {
+    let addr = "0xfe7c045110b8dbf29765047380898919c5cb56f9"
+        .parse::<RedeemAddress>()
+        .unwrap();
+    let account_key = to_stake_key(&StakedStateAddress::from(addr));
+    let mut app = init_chain_for(addr);
+    app.account_root_retention = Some(1);
+    app.snapshot_in_progress.store(true, Ordering::SeqCst);
+    let mut roots = vec![app.uncommitted_account_root_hash];
+    for height in 1..=4 {
+        if height == 4 {
+            app.snapshot_in_progress.store(false, Ordering::SeqCst);
+        }
+        begin_block_at(&mut app, height, vec![], vec![]);
+        increment_nonce(&mut app, &addr);
+        let mut endreq = RequestEndBlock::default();
+        endreq.set_height(height);
+        app.end_block(&endreq);
+        app.commit(&RequestCommit::default());
+        roots.push(app.last_state.as_ref().unwrap().last_account_root_hash);
+        if height == 3 {
+            // the snapshotted root may be among the expired ones
+            for root in roots.iter() {
+                assert!(app
+                    .accounts
+                    .get_one(root, &account_key)
+                    .expect("account lookup problem")
+                    .is_some());
+            }
+            assert_eq!(2, app.deferred_account_roots.len());
+        }
+    }
+    // the deferred roots are pruned once the snapshot is finished
+    assert!(app.deferred_account_roots.is_empty());
+    for root in roots[..3].iter() {
+        assert!(app
+            .accounts
+            .get_one(root, &account_key)
+            .unwrap_or(None)
+            .is_none());
+    }
+}
+
+fn query_account_at<T: EnclaveProxy>(
+    app: &ChainNodeApp<T>,
+    account_address: &RedeemAddress,
+    height: i64,
+) -> ResponseQuery {
+    let mut qreq = RequestQuery::new();
+    qreq.data = account_address.to_vec();
+    qreq.path = "account".into();
+    qreq.height = height;
+    qreq.prove = true;
+    app.query_handler(&qreq)
+}
+
+#[test]
+fn account_should_be_queried_at_committed_heights() {
+    let addr = "0xfe7c045110b8dbf29765047380898919c5cb56f9"
+        .parse::<RedeemAddress>()
+        .unwrap();
+    let mut app = init_chain_for(addr);
+    let mut app_hashes = vec![app.genesis_app_hash];
+    for height in 1..=3 {
+        begin_block_at(&mut app, height, vec![], vec![]);
+        increment_nonce(&mut app, &addr);
+        let mut endreq = RequestEndBlock::default();
+        endreq.set_height(height);
+        app.end_block(&endreq);
+        app.commit(&RequestCommit::default());
+        app_hashes.push(app.last_state.as_ref().unwrap().last_apphash);
+    }
+    for height in 1..=3 {
+        let qresp = query_account_at(&app, &addr, height);
+        assert_eq!(0, qresp.code);
+        assert_eq!(height, qresp.height);
+        let account = StakedState::decode(&mut qresp.value.as_slice()).unwrap();
+        assert_eq!(height as u64, account.nonce);
+        let proof = AccountProof::decode(&mut qresp.get_proof().get_ops()[0].get_data()).unwrap();
+        assert!(proof.verify(&account, &app_hashes[height as usize]));
+    }
+    // the latest (0) and not yet committed heights are answered from the last committed state
+    assert_eq!(3, query_account_at(&app, &addr, 0).height);
+    assert_eq!(3, query_account_at(&app, &addr, 10).height);
+
+    let mut qreq = RequestQuery::new();
+    qreq.path = "rewards_pool".into();
+    qreq.height = 2;
+    let qresp = app.query_handler(&qreq);
+    assert_eq!(2, qresp.height);
+    let rewards_pool = RewardsPoolState::decode(&mut qresp.value.as_slice()).unwrap();
+    assert_eq!(2, rewards_pool.last_block_height);
+    qreq.path = "council_nodes".into();
+    let council_nodes = Vec::<CouncilNode>::decode(&mut app.query_handler(&qreq).value.as_slice());
+    assert_eq!(
+        app.last_state.as_ref().unwrap().council_nodes,
+        council_nodes.unwrap()
+    );
+}
+
+#[test]
+fn pruned_states_should_not_be_queried() {
+    let addr = "0xfe7c045110b8dbf29765047380898919c5cb56f9"
+        .parse::<RedeemAddress>()
+        .unwrap();
+    let mut app = init_chain_for(addr);
+    app.account_root_retention = Some(1);
+    for height in 1..=3 {
+        begin_block_at(&mut app, height, vec![], vec![]);
+        increment_nonce(&mut app, &addr);
+        let mut endreq = RequestEndBlock::default();
+        endreq.set_height(height);
+        app.end_block(&endreq);
+        app.commit(&RequestCommit::default());
+    }
+    assert_eq!(3, query_account_at(&app, &addr, 1).code);
+    for height in 0..=1 {
+        assert!(app
+            .storage
+            .db
+            .get(COL_NODE_INFO, &app_state_key(height))
+            .unwrap()
+            .is_none());
+    }
+    for height in 2..=3 {
+        let qresp = query_account_at(&app, &addr, height);
+        assert_eq!(0, qresp.code);
+        assert_eq!(height, qresp.height);
     }
 }
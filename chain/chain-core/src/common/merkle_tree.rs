@@ -3,6 +3,8 @@ use std::vec::IntoIter;
 
 use blake2::Blake2s;
 use parity_scale_codec::{Decode, Encode};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 use super::{hash256, H256, H512, HASH_SIZE_256};
 
@@ -113,6 +115,7 @@ impl<T> Tree<T> {
 
 /// Sibling's hash
 #[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Sibling {
     Left(H256),
     Right(H256),
@@ -120,6 +123,7 @@ pub enum Sibling {
 
 /// Merkle path for inclusion proof
 #[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Path {
     node_hash: H256,
     sibling: Option<Sibling>,
@@ -135,6 +139,14 @@ impl Path {
         }
     }
 
+    /// Returns the hash of the leaf node the path starts from
+    fn leaf_hash(&self) -> H256 {
+        match self.sub_path {
+            None => self.node_hash,
+            Some(ref sub_path) => sub_path.leaf_hash(),
+        }
+    }
+
     /// Calculates hash of a path
     fn calculate_hash(&self) -> Option<H256> {
         match self.sub_path {
@@ -163,6 +175,7 @@ impl Path {
 // TODO: Consider implementing `Encode`/`Decode` traits using BitVec + Vec of hashes for efficiency
 /// Inclusion proof of a value
 #[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Proof<T> {
     root_hash: H256,
     path: Path,
@@ -170,13 +183,16 @@ pub struct Proof<T> {
 }
 
 impl<T> Proof<T> {
-    /// Verifies inclusion proof in given merkle tree
+    /// Verifies inclusion proof in given merkle tree (the path needs to start from the value's leaf)
     #[inline]
     pub fn verify(&self, root_hash: &H256) -> bool
     where
         T: AsRef<[u8]>,
     {
-        root_hash == &self.root_hash && root_hash == &self.path.node_hash && self.path.verify()
+        root_hash == &self.root_hash
+            && root_hash == &self.path.node_hash
+            && self.path.leaf_hash() == hash(&self.value, NodeType::Leaf)
+            && self.path.verify()
     }
 
     /// Returns a borrow of value contained in this proof
@@ -795,4 +811,15 @@ mod tests {
             .unwrap()
             .verify(&new_tree.root_hash()));
     }
+
+    #[test]
+    fn check_proof_with_wrong_value() {
+        let values = vec!["one", "two", "three", "four"];
+        let tree = MerkleTree::new(values);
+
+        let mut proof = tree.generate_proof("one").unwrap();
+        proof.value = "five";
+
+        assert!(!proof.verify(&tree.root_hash()));
+    }
 }
@@ -27,6 +27,8 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use tiny_keccak::Keccak;
 
 use crate::common::{H256, HASH_SIZE_256};
+#[cfg(feature = "bech32")]
+use crate::init::network::{get_network, Network};
 
 #[derive(Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -43,10 +45,19 @@ impl ::std::error::Error for CroAddressError {}
 // CRO: mainnet transfer
 // TCRO: testnet transfer
 // DCRO: devnet/regnet transfer
+// (`to_cro` / `from_cro` use the process-wide network, see `network::init_chain_id`)
 #[cfg(feature = "bech32")]
 pub trait CroAddress<T> {
-    fn to_cro(&self) -> Result<String, CroAddressError>;
-    fn from_cro(encoded: &str) -> Result<T, CroAddressError>;
+    fn to_cro_for(&self, network: Network) -> Result<String, CroAddressError>;
+    fn from_cro_for(encoded: &str, network: Network) -> Result<T, CroAddressError>;
+
+    fn to_cro(&self) -> Result<String, CroAddressError> {
+        self.to_cro_for(get_network())
+    }
+
+    fn from_cro(encoded: &str) -> Result<T, CroAddressError> {
+        Self::from_cro_for(encoded, get_network())
+    }
 }
 
 /// Keccak-256 crypto hash length in bytes
@@ -175,18 +186,18 @@ impl RedeemAddress {
 
 #[cfg(feature = "bech32")]
 impl CroAddress<RedeemAddress> for RedeemAddress {
-    fn to_cro(&self) -> Result<String, CroAddressError> {
+    fn to_cro_for(&self, network: Network) -> Result<String, CroAddressError> {
         let checked_data: Vec<u5> = self.0.to_vec().to_base32();
-        let encoded = bech32::encode(super::network::get_bech32_human_part(), checked_data)
+        let encoded = bech32::encode(network.bech32_human_part(), checked_data)
             .expect("bech32 crms encoding");
         Ok(encoded.to_string())
     }
 
-    fn from_cro(encoded: &str) -> Result<Self, CroAddressError> {
+    fn from_cro_for(encoded: &str, network: Network) -> Result<Self, CroAddressError> {
         let (human_part, u5_bytes) =
             bech32::decode(encoded).map_err(|e| CroAddressError::Bech32Error(e.to_string()))?;
 
-        if human_part != super::network::get_bech32_human_part() {
+        if human_part != network.bech32_human_part() {
             return Err(CroAddressError::InvalidNetwork);
         }
 
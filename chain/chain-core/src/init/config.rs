@@ -3,21 +3,30 @@ use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::prelude::v1::{String, Vec};
 
-use crate::common::Timespec;
+use crate::common::{MerkleTree, Timespec, H256};
 use crate::init::address::RedeemAddress;
 use crate::init::coin::{sum_coins, Coin, CoinError};
 use crate::init::MAX_COIN;
-use crate::state::account::{StakedState, StakedStateAddress};
+use crate::state::account::{StakedState, StakedStateAddress, Vesting};
 use crate::state::tendermint::{TendermintValidatorPubKey, TendermintVotePower};
 use crate::state::CouncilNode;
 use crate::state::RewardsPoolState;
-use crate::tx::fee::LinearFee;
+use crate::tx::data::attribute::TxAttributes;
+use crate::tx::data::output::TxOut;
+use crate::tx::data::Tx;
+use crate::tx::fee::{LinearFee, Milli};
+use crate::tx::TransactionId;
+use parity_scale_codec::{Decode, Encode};
 use std::collections::{BTreeMap, HashSet};
 
+/// Maximum number of transfer outputs created at genesis
+/// (they are all in one transaction, so they are limited in the same way as normal transaction outputs)
+pub const MAX_INITIAL_UTXOS: usize = 64;
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct InitNetworkParameters {
-    // Initial fee setting
+    // Initial fee setting (it and the two parameters below can be changed later by council node proposals)
     // -- TODO: perhaps change to be against T: FeeAlgorithm
     // TBD here, the intention would be to "freeze" the genesis config, so not sure generic field is worth it
     pub initial_fee_policy: LinearFee,
@@ -25,6 +34,68 @@ pub struct InitNetworkParameters {
     pub required_council_node_stake: Coin,
     // stake unbonding time (in seconds)
     pub unbonding_period: u32,
+    // punishments of council nodes for byzantine / liveness faults
+    pub slashing_config: SlashingParameters,
+    // distribution of the rewards pool to council nodes
+    pub rewards_config: RewardsParameters,
+}
+
+/// fraction of the bonded amount to be slashed (in thousandths, so at most 1.000)
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+#[cfg_attr(feature = "serde", serde(transparent))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SlashRatio(Milli);
+
+impl SlashRatio {
+    /// returns None if the ratio is greater than 1.000
+    pub fn new(ratio: Milli) -> Option<Self> {
+        if ratio <= Milli::integral(1) {
+            Some(SlashRatio(ratio))
+        } else {
+            None
+        }
+    }
+
+    /// the slashed part of the given amount (rounded down)
+    pub fn slash(self, amount: Coin) -> Coin {
+        let slashed = u128::from(u64::from(amount)) * u128::from(self.0.as_millis()) / 1000;
+        Coin::new(slashed as u64).unwrap_or(amount)
+    }
+}
+
+/// Network parameters related to punishing council nodes
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SlashingParameters {
+    // how long (in seconds) a jailed account has to wait before it can be unjailed
+    pub jail_duration: u32,
+    // fraction of the bonded amount slashed for byzantine faults (e.g. double signing)
+    pub byzantine_slash_ratio: SlashRatio,
+    // fraction of the bonded amount slashed for liveness faults
+    pub liveness_slash_ratio: SlashRatio,
+    // number of recent blocks in which the signatures are tracked
+    pub block_signing_window: u16,
+    // the council node is punished if it missed more blocks in the window
+    pub missed_block_threshold: u16,
+}
+
+/// Network parameters related to distributing the rewards pool to council nodes
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RewardsParameters {
+    // number of blocks in an epoch (the rewards are distributed at the end of each epoch)
+    pub epoch_length: u32,
+    // fraction of the remaining rewards pool distributed in each epoch (at most 1.000)
+    pub reward_rate: Milli,
+}
+
+impl RewardsParameters {
+    /// the part of the remaining rewards pool to be distributed in an epoch (rounded down)
+    pub fn epoch_rewards(&self, remaining: Coin) -> Coin {
+        let rewards =
+            u128::from(u64::from(remaining)) * u128::from(self.reward_rate.as_millis()) / 1000;
+        Coin::new(rewards as u64).unwrap_or(remaining)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -53,6 +124,17 @@ pub struct InitialValidator {
     pub consensus_pubkey_b64: String,
 }
 
+/// Lockup of a distribution entry's stake (the time periods are in seconds from the genesis time):
+/// nothing is released before the cliff, the rest is released linearly until the end of the vesting period
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VestingSchedule {
+    // time from genesis when the first part of the stake is released (at most the vesting period)
+    pub cliff_period: u32,
+    // time from genesis when all of the stake is released
+    pub vesting_period: u32,
+}
+
 /// Initial configuration ("app_state" in genesis.json of Tendermint config)
 /// TODO: reward/treasury config, extra validator config...
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -70,6 +152,13 @@ pub struct InitConfig {
     pub network_params: InitNetworkParameters,
     // initial validators
     pub council_nodes: Vec<InitialValidator>,
+    // vesting schedules of externally owned accounts in the distribution
+    // (their stake starts bonded and the locked part can't be unbonded)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub vesting: BTreeMap<RedeemAddress, VestingSchedule>,
+    // transfer outputs created at genesis (they count towards the total supply together with the distribution)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub initial_utxos: Vec<TxOut>,
 }
 
 pub enum DistributionError {
@@ -83,6 +172,10 @@ pub enum DistributionError {
     DuplicateValidatorAccount,
     NoValidators,
     InvalidVotingPower,
+    InvalidSlashingParameters,
+    InvalidRewardsParameters,
+    InvalidVestingSchedule(RedeemAddress),
+    InvalidInitialUtxos,
 }
 
 impl fmt::Display for DistributionError {
@@ -119,6 +212,18 @@ impl fmt::Display for DistributionError {
             DistributionError::InvalidVotingPower => {
                 write!(f, "Invalid voting power")
             },
+            DistributionError::InvalidSlashingParameters => {
+                write!(f, "Invalid slashing parameters (slash ratios need to be at most 1.000 and the missed block threshold below the signing window)")
+            },
+            DistributionError::InvalidRewardsParameters => {
+                write!(f, "Invalid rewards parameters (the epoch length needs to be non-zero and the reward rate at most 1.000)")
+            },
+            DistributionError::InvalidVestingSchedule(a) => {
+                write!(f, "Invalid vesting schedule of address ({}) (it needs to be an externally owned account, the vesting period needs to be non-zero and the cliff at most the vesting period)", a)
+            },
+            DistributionError::InvalidInitialUtxos => {
+                write!(f, "Invalid initial UTXOs (there can be at most {} and they need to have non-zero amounts)", MAX_INITIAL_UTXOS)
+            },
         }
     }
 }
@@ -132,6 +237,8 @@ impl InitConfig {
         long_term_incentive: RedeemAddress,
         network_params: InitNetworkParameters,
         council_nodes: Vec<InitialValidator>,
+        vesting: BTreeMap<RedeemAddress, VestingSchedule>,
+        initial_utxos: Vec<TxOut>,
     ) -> Self {
         InitConfig {
             distribution: owners,
@@ -140,9 +247,30 @@ impl InitConfig {
             long_term_incentive,
             network_params,
             council_nodes,
+            vesting,
+            initial_utxos,
         }
     }
 
+    /// returns the synthetic genesis transaction (without inputs) that creates the initial UTXOs (if there are any);
+    /// it isn't tied to a particular network, as it can't be replayed
+    pub fn get_genesis_tx(&self) -> Option<Tx> {
+        if self.initial_utxos.is_empty() {
+            None
+        } else {
+            Some(Tx::new_with(
+                Vec::new(),
+                self.initial_utxos.clone(),
+                TxAttributes::new(0),
+            ))
+        }
+    }
+
+    /// returns the merkle tree of transactions included at genesis (its root is a part of the genesis app hash)
+    pub fn get_genesis_tx_tree(&self) -> MerkleTree<H256> {
+        MerkleTree::new(self.get_genesis_tx().iter().map(Tx::id).collect())
+    }
+
     fn check_address(&self, address: &RedeemAddress) -> Result<(), DistributionError> {
         if self.distribution.contains_key(address) {
             Ok(())
@@ -182,6 +310,25 @@ impl InitConfig {
         }
     }
 
+    fn check_vesting_schedules(&self) -> Result<(), DistributionError> {
+        for (address, schedule) in self.vesting.iter() {
+            let is_account = match self.distribution.get(address) {
+                Some((_, t)) => {
+                    *t == AccountType::ExternallyOwnedAccount
+                        && !self.is_rewards_pool_address(address)
+                }
+                None => return Err(DistributionError::AddressNotInDistribution(*address)),
+            };
+            if !is_account
+                || schedule.vesting_period == 0
+                || schedule.cliff_period > schedule.vesting_period
+            {
+                return Err(DistributionError::InvalidVestingSchedule(*address));
+            }
+        }
+        Ok(())
+    }
+
     fn is_rewards_pool_address(&self, address: &RedeemAddress) -> bool {
         *address == self.launch_incentive_from
             || *address == self.launch_incentive_to
@@ -201,12 +348,20 @@ impl InitConfig {
             if self.is_rewards_pool_address(address) || *address_type == AccountType::Contract {
                 rewards_pool_amount += u64::from(*amount);
             } else {
-                accounts.push(StakedState::new_init(
+                let schedule = self.vesting.get(address);
+                let mut account = StakedState::new_init(
                     *amount,
                     genesis_time,
                     StakedStateAddress::BasicRedeem(*address),
-                    validator_addresses.contains(address),
-                ));
+                    validator_addresses.contains(address) || schedule.is_some(),
+                );
+                account.vesting = schedule.map(|schedule| Vesting {
+                    amount: *amount,
+                    start: genesis_time,
+                    cliff: genesis_time + Timespec::from(schedule.cliff_period),
+                    end: genesis_time + Timespec::from(schedule.vesting_period),
+                });
+                accounts.push(account);
             }
         }
         (
@@ -221,6 +376,8 @@ impl InitConfig {
     /// checks if the config is valid:
     /// - required addresses are present in the distribution
     /// - initial validator configuration is correct
+    /// - vesting schedules are correct
+    /// - initial UTXOs are correct
     /// - the total amount doesn't go over the maximum supply
     /// - ...
     /// if valid, it'll return the genesis "state"
@@ -234,6 +391,23 @@ impl InitConfig {
         if self.council_nodes.is_empty() {
             return Err(DistributionError::NoValidators);
         }
+        let slashing = &self.network_params.slashing_config;
+        if SlashRatio::new(slashing.byzantine_slash_ratio.0).is_none()
+            || SlashRatio::new(slashing.liveness_slash_ratio.0).is_none()
+            || slashing.missed_block_threshold >= slashing.block_signing_window
+        {
+            return Err(DistributionError::InvalidSlashingParameters);
+        }
+        let rewards = &self.network_params.rewards_config;
+        if rewards.epoch_length == 0 || rewards.reward_rate > Milli::integral(1) {
+            return Err(DistributionError::InvalidRewardsParameters);
+        }
+        self.check_vesting_schedules()?;
+        if self.initial_utxos.len() > MAX_INITIAL_UTXOS
+            || self.initial_utxos.iter().any(|x| x.value == Coin::zero())
+        {
+            return Err(DistributionError::InvalidInitialUtxos);
+        }
         let mut validators = Vec::with_capacity(self.council_nodes.len());
         let mut validator_addresses = HashSet::new();
         let mut validator_pubkeys = HashSet::new();
@@ -277,7 +451,12 @@ impl InitConfig {
             }
         };
 
-        let sumr = sum_coins(self.distribution.iter().map(|(_, (amount, _))| *amount));
+        let sumr = sum_coins(
+            self.distribution
+                .iter()
+                .map(|(_, (amount, _))| *amount)
+                .chain(self.initial_utxos.iter().map(|x| x.value)),
+        );
         match sumr {
             Ok(sum) => {
                 if sum != Coin::max() {
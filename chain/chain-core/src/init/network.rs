@@ -1,6 +1,6 @@
+use std::prelude::v1::String;
 use std::sync::Once;
-static INIT_NETWORK: Once = Once::new();
-static INIT_NETWORK_ID: Once = Once::new();
+static INIT_CHAIN_PARAMS: Once = Once::new();
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Network {
@@ -9,70 +9,129 @@ pub enum Network {
     Devnet,
 }
 
+impl Network {
+    /// the human readable part of Bech32 addresses on this network
+    pub fn bech32_human_part(self) -> &'static str {
+        match self {
+            Network::Mainnet => "cro",
+            Network::Testnet => "tcro",
+            Network::Devnet => "dcro",
+        }
+    }
+
+    /// the network of Bech32 addresses with the given human readable part
+    pub fn from_bech32_human_part(human_part: &str) -> Option<Self> {
+        match human_part {
+            "cro" => Some(Network::Mainnet),
+            "tcro" => Some(Network::Testnet),
+            "dcro" => Some(Network::Devnet),
+            _ => None,
+        }
+    }
+}
+
 /// Public testnet Chain ID (expected in Tendermint's genesis.json)
 pub const TESTNET_CHAIN_ID: &str = "testnet-thaler-crypto-com-chain-42";
 /// Mainnet Chain ID (expected in Tendermint's genesis.json)
 pub const MAINNET_CHAIN_ID: &str = "mainnet-crypto-com-chain-2A";
 
-/// One-time initialization of the chosen network
-/// (as address textual format / serialization + HD-wallet path depend on the network type)
-pub fn init_chain_id(chain_id_src: &str) {
-    let chain_id = chain_id_src.to_string();
-    assert!(chain_id.len() >= 6);
-    let length = chain_id.len();
-    let hexstring = &chain_id[(length - 2)..];
-    let hexvalue = hex::decode(hexstring).expect("last two characters should be hex digits");
-    assert!(1 == hexvalue.len());
-    init_network_id(hexvalue[0]);
-
-    match chain_id_src {
-        MAINNET_CHAIN_ID => init_network(Network::Mainnet),
-        TESTNET_CHAIN_ID => init_network(Network::Testnet),
-        _ => init_network(Network::Devnet),
+/// The network type and identifier a node or a client operates on
+/// (address textual format / serialization + HD-wallet path depend on the network type,
+/// the network identifier is included in transaction metadata)
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ChainParams {
+    network: Network,
+    network_id: u8,
+}
+
+impl ChainParams {
+    pub fn new(network: Network, network_id: u8) -> Self {
+        ChainParams {
+            network,
+            network_id,
+        }
+    }
+
+    /// Parses the Tendermint chain ID: the network identifier is given in its last two (hex) characters
+    pub fn from_chain_id(chain_id: &str) -> Result<Self, String> {
+        let length = chain_id.len();
+        if length < 6 || !chain_id.is_char_boundary(length - 2) {
+            return Err(format!("invalid chain id: {}", chain_id));
+        }
+        let hexvalue = hex::decode(&chain_id[(length - 2)..])
+            .map_err(|_| format!("last two characters should be hex digits: {}", chain_id))?;
+        let network = match chain_id {
+            MAINNET_CHAIN_ID => Network::Mainnet,
+            TESTNET_CHAIN_ID => Network::Testnet,
+            _ => Network::Devnet,
+        };
+        Ok(ChainParams::new(network, hexvalue[0]))
+    }
+
+    /// Returns the network type
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Returns the network identifier (a single byte included in transaction metadata)
+    pub fn network_id(&self) -> u8 {
+        self.network_id
+    }
+
+    /// Returns the human readable part of Bech32 addresses on the network
+    pub fn bech32_human_part(&self) -> &'static str {
+        self.network.bech32_human_part()
     }
 }
 
-fn init_network(network: Network) {
-    unsafe {
-        INIT_NETWORK.call_once(|| {
-            chosen_network::NETWORK = network;
-        });
+impl Default for ChainParams {
+    fn default() -> Self {
+        ChainParams::new(Network::Devnet, 0)
     }
 }
 
-fn init_network_id(id: u8) {
+/// One-time initialization of the process-wide network
+/// (a convenience for processes operating on a single network;
+/// code that may serve several networks should pass `ChainParams` explicitly)
+pub fn init_chain_id(chain_id_src: &str) {
+    init_chain_params(ChainParams::from_chain_id(chain_id_src).expect("valid chain id"));
+}
+
+/// One-time initialization of the process-wide network with the given parameters
+pub fn init_chain_params(params: ChainParams) {
     unsafe {
-        INIT_NETWORK_ID.call_once(|| {
-            chosen_network::NETWORK_ID = id;
+        INIT_CHAIN_PARAMS.call_once(|| {
+            chosen_network::CHAIN_PARAMS = params;
         });
     }
 }
 
+/// Returns the process-wide network parameters (devnet with identifier 0 if not initialized)
+pub fn get_chain_params() -> ChainParams {
+    unsafe { chosen_network::CHAIN_PARAMS }
+}
+
 /// Returns the identifier of the chosen network (a single byte included in transaction metadata)
 pub fn get_network_id() -> u8 {
-    unsafe { chosen_network::NETWORK_ID }
+    get_chain_params().network_id()
 }
 
 /// Returns the chosen network type
 pub fn get_network() -> Network {
-    unsafe { chosen_network::NETWORK }
+    get_chain_params().network()
 }
 
 /// Given the chosen network, it returns the human readable part of Bech32 address
 pub fn get_bech32_human_part() -> &'static str {
-    unsafe {
-        match chosen_network::NETWORK {
-            Network::Mainnet => "cro",
-            Network::Testnet => "tcro",
-            Network::Devnet => "dcro",
-        }
-    }
+    get_chain_params().bech32_human_part()
 }
 
 mod chosen_network {
     use super::*;
-    pub static mut NETWORK: Network = Network::Devnet;
-    pub static mut NETWORK_ID: u8 = 0;
+    pub static mut CHAIN_PARAMS: ChainParams = ChainParams {
+        network: Network::Devnet,
+        network_id: 0,
+    };
 }
 
 #[cfg(test)]
@@ -85,4 +144,16 @@ mod test {
         assert_eq!(Network::Devnet, get_network());
         assert_eq!("dcro", get_bech32_human_part());
     }
+
+    #[test]
+    fn chain_params_should_be_parsed_from_chain_id() {
+        let mainnet = ChainParams::from_chain_id(MAINNET_CHAIN_ID).unwrap();
+        assert_eq!(mainnet, ChainParams::new(Network::Mainnet, 0x2a));
+        assert_eq!("cro", mainnet.bech32_human_part());
+        let testnet = ChainParams::from_chain_id(TESTNET_CHAIN_ID).unwrap();
+        assert_eq!(testnet, ChainParams::new(Network::Testnet, 0x42));
+        assert_eq!("tcro", testnet.bech32_human_part());
+        assert!(ChainParams::from_chain_id("dev-chain-y3m1e6-XY").is_err());
+        assert!(ChainParams::from_chain_id("AB").is_err());
+    }
 }
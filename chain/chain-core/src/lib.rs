@@ -8,8 +8,6 @@
 #[macro_use]
 extern crate sgx_tstd as std;
 
-use std::prelude::v1::Vec;
-
 /// Miscellaneous definitions and generic merkle tree
 pub mod common;
 /// Types mainly related to InitChain command in ABCI
@@ -19,28 +17,33 @@ pub mod state;
 /// Transaction structure types and serialization/deserialization
 pub mod tx;
 
-use blake2::Blake2s;
-use common::{hash256, MerkleTree, Timespec, H256};
+use common::{MerkleTree, Timespec, H256};
 use parity_scale_codec::{Decode, Encode};
+use state::governance::NetworkParameters;
+use state::proof::AppHashParts;
+use state::tendermint::BlockHeight;
 use state::RewardsPoolState;
 use tx::fee::Fee;
 
 /// computes the "global" application hash (used by Tendermint to check consistency + block replaying)
-/// currently: app_hash = blake2s(root of valid TX merkle tree || blake2s(scale bytes(rewards pool state)))
-/// TODO: it should include the fee policy / network parameters etc. once that becomes changeable
+/// currently: app_hash = blake2s(root of valid TX merkle tree || root of account trie
+/// || blake2s(scale bytes(rewards pool state)) || blake2s(scale bytes(network parameters))
+/// || hash of the other consensus state of the node, see `AppHashParts`)
 /// MUST/TODO: include node whitelists
 pub fn compute_app_hash(
     valid_tx_id_tree: &MerkleTree<H256>,
     account_state_root: &H256,
     reward_pool: &RewardsPoolState,
+    network_params: &NetworkParameters,
+    node_state_hash: &H256,
 ) -> H256 {
-    let valid_tx_part = valid_tx_id_tree.root_hash();
-    let rewards_pool_part = reward_pool.hash();
-    let mut bs = Vec::new();
-    bs.extend(&valid_tx_part);
-    bs.extend(&account_state_root[..]);
-    bs.extend(&rewards_pool_part);
-    hash256::<Blake2s>(&bs)
+    AppHashParts::new(
+        valid_tx_id_tree,
+        reward_pool,
+        network_params,
+        node_state_hash,
+    )
+    .app_hash(account_state_root)
 }
 
 /// External information needed for TX validation
@@ -52,6 +55,8 @@ pub struct ChainInfo {
     pub chain_hex_id: u8,
     /// time in the previous committed block
     pub previous_block_time: Timespec,
+    /// height of the previous committed block (payloads can't be encrypted with the keys of later blocks)
+    pub last_block_height: BlockHeight,
     /// how much time is required to wait until stake state's unbonded amount can be withdrawn
     pub unbonding_period: u32,
 }
@@ -1,10 +1,18 @@
-use crate::common::{hash256, Timespec, HASH_SIZE_256};
+use crate::common::{hash256, Timespec, H256, HASH_SIZE_256};
 use crate::init::address::RedeemAddress;
 use crate::init::coin::Coin;
 use crate::init::coin::{sum_coins, CoinError};
-use crate::tx::data::attribute::TxAttributes;
+use crate::init::config::SlashRatio;
+use crate::state::tendermint::TendermintValidatorPubKey;
+#[cfg(feature = "serde")]
+use crate::tx::data::attribute::default_tx_version;
+use crate::tx::data::attribute::{
+    decode_tx_version, decode_tx_version_or_legacy, versioned_txid, TxAttributes, TxVersion,
+    LEGACY_TX_VERSION, OUTPUTS_COMMITMENT_TX_VERSION, TX_VERSION,
+};
 use crate::tx::data::input::TxoPointer;
 use crate::tx::data::output::TxOut;
+use crate::tx::data::{outputs_committed_txid, outputs_root, txid_hash, TxId};
 use crate::tx::witness::{tree::RawSignature, EcdsaSignature};
 use crate::tx::TransactionId;
 use blake2::Blake2s;
@@ -24,8 +32,8 @@ use std::fmt;
 /// Each input is 34 bytes
 ///
 /// Assuming maximum inputs allowed are 64,
-/// So, maximum deposit transaction size (34 * 64) + 21 (address) + 1 (attributes) = 2198 bytes
-const MAX_DEPOSIT_TX_SIZE: usize = 2200; // 2200 bytes
+/// So, maximum deposit transaction size (34 * 64) + 2 (number of inputs) + 21 (address) + 2 (attributes) = 2201 bytes
+const MAX_DEPOSIT_TX_SIZE: usize = 2210; // 2210 bytes
 
 /// reference counter in the sparse patricia merkle tree/trie
 pub type Count = u64;
@@ -33,6 +41,10 @@ pub type Count = u64;
 /// StakedState update counter
 pub type Nonce = u64;
 
+/// Maximum number of pending unbonding entries in a StakedState
+/// (further unbonding is rejected until some of them are withdrawn)
+pub const MAX_UNBONDING_ENTRIES: usize = 16;
+
 /// StakedState address type
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
 pub enum StakedStateAddress {
@@ -109,16 +121,72 @@ impl FromStr for StakedStateAddress {
     }
 }
 
+/// an unbonded amount that can be withdrawn once its release time passes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct UnbondingEntry {
+    pub amount: Coin,
+    pub release_time: Timespec,
+}
+
+impl UnbondingEntry {
+    /// whether the entry can be withdrawn at the given (block) time
+    pub fn is_matured(&self, time: Timespec) -> bool {
+        self.release_time <= time
+    }
+}
+
+/// bonded stake delegated to a council node (it's counted in the council node's voting power,
+/// but it stays owned by the delegating StakedState)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Delegation {
+    pub council_node: StakedStateAddress,
+    pub amount: Coin,
+}
+
+/// genesis stake that is released linearly between `start` and `end`: nothing is released before `cliff`
+/// (the locked amount can't be unbonded or undelegated, but it can still be delegated)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Vesting {
+    pub amount: Coin,
+    pub start: Timespec,
+    pub cliff: Timespec,
+    pub end: Timespec,
+}
+
+impl Vesting {
+    /// the amount that's still locked at the given (block) time
+    pub fn locked(&self, time: Timespec) -> Coin {
+        if time < self.cliff {
+            self.amount
+        } else if time >= self.end {
+            Coin::zero()
+        } else {
+            let remaining = (self.end - time) as u128;
+            let period = (self.end - self.start) as u128;
+            Coin::new((u128::from(u64::from(self.amount)) * remaining / period) as u64)
+                .expect("locked amount is below the vesting amount")
+        }
+    }
+}
+
 /// represents the StakedState (account involved in staking)
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct StakedState {
     pub nonce: Nonce,
     pub bonded: Coin,
-    pub unbonded: Coin,
-    pub unbonded_from: Timespec,
+    /// unbonded amounts waiting for withdrawal (in the order they were unbonded, at most `MAX_UNBONDING_ENTRIES`)
+    pub unbonding: Vec<UnbondingEntry>,
     pub address: StakedStateAddress,
-    // TODO: slashing + jailing
+    /// set when the account was punished for council node faults (until when it's jailed)
+    pub jailed_until: Option<Timespec>,
+    /// bonded stake delegated to a council node (at most one council node at a time)
+    pub delegation: Option<Delegation>,
+    /// the genesis vesting schedule (if the account was created with one)
+    pub vesting: Option<Vesting>,
 }
 
 /// the tree used in StakedState storage db has a hardcoded 32-byte keys,
@@ -136,8 +204,7 @@ impl Default for StakedState {
         StakedState::new(
             0,
             Coin::zero(),
-            Coin::zero(),
-            0,
+            Vec::new(),
             StakedStateAddress::BasicRedeem(RedeemAddress::default()),
         )
     }
@@ -148,16 +215,17 @@ impl StakedState {
     pub fn new(
         nonce: Nonce,
         bonded: Coin,
-        unbonded: Coin,
-        unbonded_from: Timespec,
+        unbonding: Vec<UnbondingEntry>,
         address: StakedStateAddress,
     ) -> Self {
         StakedState {
             nonce,
             bonded,
-            unbonded,
-            unbonded_from,
+            unbonding,
             address,
+            jailed_until: None,
+            delegation: None,
+            vesting: None,
         }
     }
 
@@ -172,17 +240,24 @@ impl StakedState {
             StakedState {
                 nonce: 0,
                 bonded: amount,
-                unbonded: Coin::zero(),
-                unbonded_from: genesis_time,
+                unbonding: Vec::new(),
                 address,
+                jailed_until: None,
+                delegation: None,
+                vesting: None,
             }
         } else {
             StakedState {
                 nonce: 0,
                 bonded: Coin::zero(),
-                unbonded: amount,
-                unbonded_from: genesis_time,
+                unbonding: vec![UnbondingEntry {
+                    amount,
+                    release_time: genesis_time,
+                }],
                 address,
+                jailed_until: None,
+                delegation: None,
+                vesting: None,
             }
         }
     }
@@ -193,20 +268,152 @@ impl StakedState {
         self.bonded = (self.bonded + amount).expect("should not be over the max supply");
     }
 
-    /// in-place update after unbonding a bonded stake
-    pub fn unbond(&mut self, amount: Coin, fee: Coin, unbonded_from: Timespec) {
+    /// total unbonded amount (matured or not)
+    pub fn unbonded(&self) -> Coin {
+        sum_coins(self.unbonding.iter().map(|entry| entry.amount))
+            .expect("should not be over the max supply")
+    }
+
+    /// unbonded amount that can be withdrawn at the given (block) time
+    pub fn matured_unbonded(&self, time: Timespec) -> Coin {
+        sum_coins(
+            self.unbonding
+                .iter()
+                .filter(|entry| entry.is_matured(time))
+                .map(|entry| entry.amount),
+        )
+        .expect("should not be over the max supply")
+    }
+
+    /// the latest release time of the entries that can be withdrawn at the given (block) time
+    /// (withdrawal outputs are time-locked to it)
+    pub fn matured_release_time(&self, time: Timespec) -> Option<Timespec> {
+        self.unbonding
+            .iter()
+            .filter(|entry| entry.is_matured(time))
+            .map(|entry| entry.release_time)
+            .max()
+    }
+
+    /// whether another unbonding entry can be added
+    pub fn can_unbond(&self) -> bool {
+        self.unbonding.len() < MAX_UNBONDING_ENTRIES
+    }
+
+    /// in-place update after unbonding a bonded stake (the amount can be withdrawn after `release_time`)
+    pub fn unbond(&mut self, amount: Coin, fee: Coin, release_time: Timespec) {
+        self.nonce += 1;
+        self.bonded = (self.bonded - amount)
+            .and_then(|x| x - fee)
+            .expect("should not go below zero");
+        self.unbonding.push(UnbondingEntry {
+            amount,
+            release_time,
+        });
+    }
+
+    /// in-place update after moving the unbonded stake back to bonded: the amount is taken from
+    /// the most recent unbonding entries first (the fee is paid from the resulting bonded amount)
+    pub fn rebond(&mut self, amount: Coin, fee: Coin) {
+        self.nonce += 1;
+        let mut remaining = amount;
+        while remaining > Coin::zero() {
+            let entry = self
+                .unbonding
+                .last_mut()
+                .expect("should not rebond more than unbonded");
+            if entry.amount <= remaining {
+                remaining = (remaining - entry.amount).expect("should not go below zero");
+                self.unbonding.pop();
+            } else {
+                entry.amount = (entry.amount - remaining).expect("should not go below zero");
+                remaining = Coin::zero();
+            }
+        }
+        self.bonded = (self.bonded + amount)
+            .and_then(|x| x - fee)
+            .expect("should not go below zero or over the max supply");
+    }
+
+    /// amount delegated to a council node (zero if there is no delegation)
+    pub fn delegated(&self) -> Coin {
+        self.delegation
+            .map_or(Coin::zero(), |delegation| delegation.amount)
+    }
+
+    /// in-place update after delegating some of the bonded stake (the fee is paid from the bonded amount)
+    pub fn delegate(&mut self, council_node: StakedStateAddress, amount: Coin, fee: Coin) {
         self.nonce += 1;
-        self.unbonded_from = unbonded_from;
         self.bonded = (self.bonded - amount)
             .and_then(|x| x - fee)
             .expect("should not go below zero");
-        self.unbonded = (self.unbonded + amount).expect("should not be over the max supply");
+        let delegated = (self.delegated() + amount).expect("should not be over the max supply");
+        self.delegation = Some(Delegation {
+            council_node,
+            amount: delegated,
+        });
+    }
+
+    /// in-place update after undelegating some of the delegated stake: the amount - fee
+    /// is moved to a new unbonding entry released at `release_time`
+    pub fn undelegate(&mut self, amount: Coin, fee: Coin, release_time: Timespec) {
+        self.nonce += 1;
+        let delegation = self
+            .delegation
+            .as_mut()
+            .expect("should not undelegate without a delegation");
+        delegation.amount = (delegation.amount - amount).expect("should not go below zero");
+        if delegation.amount == Coin::zero() {
+            self.delegation = None;
+        }
+        self.unbonding.push(UnbondingEntry {
+            amount: (amount - fee).expect("should not go below zero"),
+            release_time,
+        });
+    }
+
+    /// in-place update after withdrawing the unbonded stake matured at the given (block) time
+    pub fn withdraw(&mut self, time: Timespec) {
+        self.nonce += 1;
+        self.unbonding.retain(|entry| !entry.is_matured(time));
     }
 
-    /// in-place update after withdrawing unbonded stake
-    pub fn withdraw(&mut self) {
+    /// in-place update after an operation whose fee is paid from the bonded stake (e.g. joining council nodes)
+    pub fn pay_bonded_fee(&mut self, fee: Coin) {
         self.nonce += 1;
-        self.unbonded = Coin::zero();
+        self.bonded = (self.bonded - fee).expect("should not go below zero");
+    }
+
+    /// in-place update after receiving council node rewards (the nonce isn't changed, as it's not an account operation)
+    pub fn add_reward(&mut self, amount: Coin) {
+        self.bonded = (self.bonded + amount).expect("should not be over the max supply");
+    }
+
+    /// the stake still locked by the vesting schedule at the given (block) time
+    /// (it has to stay in the bonded or delegated amounts)
+    pub fn locked(&self, time: Timespec) -> Coin {
+        self.vesting
+            .map_or(Coin::zero(), |vesting| vesting.locked(time))
+    }
+
+    /// whether the account was jailed (and not unjailed yet)
+    pub fn is_jailed(&self) -> bool {
+        self.jailed_until.is_some()
+    }
+
+    /// in-place update after punishing a council node fault: slashes the bonded amount
+    /// (the slashed amount is returned) and jails the account
+    pub fn slash_and_jail(&mut self, ratio: SlashRatio, jailed_until: Timespec) -> Coin {
+        let slashed = ratio.slash(self.bonded);
+        self.bonded = (self.bonded - slashed).expect("should not go below zero");
+        self.jailed_until = Some(jailed_until);
+        slashed
+    }
+
+    /// in-place update after unjailing (the fee is paid from the bonded amount)
+    pub fn unjail(&mut self, fee: Coin) {
+        self.pay_bonded_fee(fee);
+        self.jailed_until = None;
     }
 
     /// the tree used in StakedState storage db has a hardcoded 32-byte keys,
@@ -218,31 +425,114 @@ impl StakedState {
 }
 
 /// attributes in StakedState-related transactions
-#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+/// (versioned in the same way as `TxAttributes`)
+#[derive(Debug, PartialEq, Eq, Clone, Encode)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct StakedStateOpAttributes {
+    #[cfg_attr(feature = "serde", serde(default = "default_tx_version"))]
+    pub version: TxVersion,
     pub chain_hex_id: u8,
     // TODO: Other attributes?
 }
 
+impl Decode for StakedStateOpAttributes {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let version = decode_tx_version(input)?;
+        StakedStateOpAttributes::decode_fields(version, input)
+    }
+}
+
 impl StakedStateOpAttributes {
     pub fn new(chain_hex_id: u8) -> Self {
-        StakedStateOpAttributes { chain_hex_id }
+        StakedStateOpAttributes {
+            version: TX_VERSION,
+            chain_hex_id,
+        }
+    }
+
+    /// decodes the fields following the (already decoded) version tag
+    fn decode_fields<I: Input>(version: TxVersion, input: &mut I) -> Result<Self, Error> {
+        // version 1 fields (the same as in the legacy layout)
+        let chain_hex_id = u8::decode(input)?;
+        Ok(StakedStateOpAttributes {
+            version,
+            chain_hex_id,
+        })
+    }
+
+    /// decodes the attributes in the legacy layout (see `LEGACY_TX_VERSION`)
+    fn decode_legacy<I: Input>(input: &mut I) -> Result<Self, Error> {
+        StakedStateOpAttributes::decode_fields(LEGACY_TX_VERSION, input)
+    }
+
+    /// encodes the attributes in the legacy layout (see `LEGACY_TX_VERSION`)
+    fn encode_legacy_to<W: Output>(&self, dest: &mut W) {
+        self.chain_hex_id.encode_to(dest);
     }
 }
 
 /// takes UTXOs inputs, deposits them in the specified StakedState's bonded amount - fee
 /// (updates StakedState's bonded + nonce)
-#[derive(Debug, PartialEq, Eq, Clone, Encode)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DepositBondTx {
+    pub attributes: StakedStateOpAttributes,
     pub inputs: Vec<TxoPointer>,
     pub to_staked_account: StakedStateAddress,
-    pub attributes: StakedStateOpAttributes,
+}
+
+impl Encode for DepositBondTx {
+    fn encode_to<W: Output>(&self, dest: &mut W) {
+        if self.attributes.version == LEGACY_TX_VERSION {
+            dest.push_byte(LEGACY_TX_VERSION);
+            self.inputs.encode_to(dest);
+            self.to_staked_account.encode_to(dest);
+            self.attributes.encode_legacy_to(dest);
+        } else {
+            self.attributes.encode_to(dest);
+            self.inputs.encode_to(dest);
+            self.to_staked_account.encode_to(dest);
+        }
+    }
 }
 
 impl Decode for DepositBondTx {
     fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let version = decode_tx_version(input)?;
+        DepositBondTx::decode_fields(version, input)
+    }
+}
+
+impl TransactionId for DepositBondTx {
+    fn id(&self) -> TxId {
+        versioned_txid(&self.encode())
+    }
+}
+
+impl DepositBondTx {
+    pub fn new(
+        inputs: Vec<TxoPointer>,
+        to_staked_account: StakedStateAddress,
+        attributes: StakedStateOpAttributes,
+    ) -> Self {
+        DepositBondTx {
+            attributes,
+            inputs,
+            to_staked_account,
+        }
+    }
+
+    /// decodes a stored TX: unlike `decode`, it also accepts the legacy transactions
+    /// (tagged with `LEGACY_TX_VERSION`), which can't be submitted anymore
+    pub fn decode_stored<I: Input>(input: &mut I) -> Result<Self, Error> {
+        match decode_tx_version_or_legacy(input)? {
+            LEGACY_TX_VERSION => DepositBondTx::decode_legacy(input),
+            version => DepositBondTx::decode_fields(version, input),
+        }
+    }
+
+    /// decodes the fields following the (already decoded) version tag
+    fn decode_fields<I: Input>(version: TxVersion, input: &mut I) -> Result<Self, Error> {
         let size = input
             .remaining_len()?
             .ok_or_else(|| "Unable to calculate size of input")?;
@@ -251,31 +541,37 @@ impl Decode for DepositBondTx {
             return Err("Input too large".into());
         }
 
+        let attributes = StakedStateOpAttributes::decode_fields(version, input)?;
         let inputs = <Vec<TxoPointer>>::decode(input)?;
         let to_staked_account = StakedStateAddress::decode(input)?;
-        let attributes = StakedStateOpAttributes::decode(input)?;
 
         Ok(DepositBondTx {
+            attributes,
             inputs,
             to_staked_account,
-            attributes,
         })
     }
-}
 
-impl TransactionId for DepositBondTx {}
+    /// decodes a TX in the legacy layout without the version tag, as stored before the format was versioned
+    /// (see `LEGACY_TX_VERSION`)
+    pub fn decode_legacy<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let size = input
+            .remaining_len()?
+            .ok_or_else(|| "Unable to calculate size of input")?;
 
-impl DepositBondTx {
-    pub fn new(
-        inputs: Vec<TxoPointer>,
-        to_staked_account: StakedStateAddress,
-        attributes: StakedStateOpAttributes,
-    ) -> Self {
-        DepositBondTx {
+        if size > MAX_DEPOSIT_TX_SIZE {
+            return Err("Input too large".into());
+        }
+
+        let inputs = <Vec<TxoPointer>>::decode(input)?;
+        let to_staked_account = StakedStateAddress::decode(input)?;
+        let attributes = StakedStateOpAttributes::decode_legacy(input)?;
+
+        Ok(DepositBondTx {
+            attributes,
             inputs,
             to_staked_account,
-            attributes,
-        }
+        })
     }
 }
 
@@ -289,26 +585,86 @@ impl fmt::Display for DepositBondTx {
     }
 }
 
-/// updates the StakedState (TODO: implicit from the witness?) by moving some of the bonded amount - fee into unbonded,
-/// and setting the unbonded_from to last_block_time+min_unbonding_time (network parameter)
-#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+/// updates the StakedState (TODO: implicit from the witness?) by moving some of the bonded amount - fee into
+/// a new unbonding entry released at last_block_time+min_unbonding_time (network parameter)
+#[derive(Debug, PartialEq, Eq, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct UnbondTx {
+    pub attributes: StakedStateOpAttributes,
     pub value: Coin,
     pub nonce: Nonce,
-    pub attributes: StakedStateOpAttributes,
 }
 
-impl TransactionId for UnbondTx {}
+impl Encode for UnbondTx {
+    fn encode_to<W: Output>(&self, dest: &mut W) {
+        if self.attributes.version == LEGACY_TX_VERSION {
+            dest.push_byte(LEGACY_TX_VERSION);
+            self.value.encode_to(dest);
+            self.nonce.encode_to(dest);
+            self.attributes.encode_legacy_to(dest);
+        } else {
+            self.attributes.encode_to(dest);
+            self.value.encode_to(dest);
+            self.nonce.encode_to(dest);
+        }
+    }
+}
+
+impl Decode for UnbondTx {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let version = decode_tx_version(input)?;
+        UnbondTx::decode_fields(version, input)
+    }
+}
+
+impl TransactionId for UnbondTx {
+    fn id(&self) -> TxId {
+        versioned_txid(&self.encode())
+    }
+}
 
 impl UnbondTx {
     pub fn new(value: Coin, nonce: Nonce, attributes: StakedStateOpAttributes) -> Self {
         UnbondTx {
+            attributes,
             value,
             nonce,
-            attributes,
         }
     }
+
+    /// decodes a stored TX: unlike `decode`, it also accepts the legacy transactions
+    /// (tagged with `LEGACY_TX_VERSION`), which can't be submitted anymore
+    pub fn decode_stored<I: Input>(input: &mut I) -> Result<Self, Error> {
+        match decode_tx_version_or_legacy(input)? {
+            LEGACY_TX_VERSION => UnbondTx::decode_legacy(input),
+            version => UnbondTx::decode_fields(version, input),
+        }
+    }
+
+    /// decodes the fields following the (already decoded) version tag
+    fn decode_fields<I: Input>(version: TxVersion, input: &mut I) -> Result<Self, Error> {
+        let attributes = StakedStateOpAttributes::decode_fields(version, input)?;
+        let value = Coin::decode(input)?;
+        let nonce = Nonce::decode(input)?;
+        Ok(UnbondTx {
+            attributes,
+            value,
+            nonce,
+        })
+    }
+
+    /// decodes a TX in the legacy layout without the version tag, as stored before the format was versioned
+    /// (see `LEGACY_TX_VERSION`)
+    pub fn decode_legacy<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let value = Coin::decode(input)?;
+        let nonce = Nonce::decode(input)?;
+        let attributes = StakedStateOpAttributes::decode_legacy(input)?;
+        Ok(UnbondTx {
+            attributes,
+            value,
+            nonce,
+        })
+    }
 }
 
 impl fmt::Display for UnbondTx {
@@ -318,26 +674,199 @@ impl fmt::Display for UnbondTx {
     }
 }
 
-/// takes the StakedState (TODO: implicit from the witness?) and creates UTXOs
-/// (update's StakedState's unbonded + nonce)
+/// updates the StakedState (TODO: implicit from the witness?) by moving some of the unbonded amount
+/// (not withdrawn yet) back to bonded - fee (the most recent unbonding entries are taken first)
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RebondTx {
+    pub attributes: StakedStateOpAttributes,
+    pub value: Coin,
+    pub nonce: Nonce,
+}
+
+impl TransactionId for RebondTx {}
+
+impl RebondTx {
+    pub fn new(value: Coin, nonce: Nonce, attributes: StakedStateOpAttributes) -> Self {
+        RebondTx {
+            attributes,
+            value,
+            nonce,
+        }
+    }
+}
+
+impl fmt::Display for RebondTx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "rebonded: {} (nonce: {})", self.value, self.nonce)?;
+        write!(f, "")
+    }
+}
+
+/// updates the StakedState (TODO: implicit from the witness?) by delegating some of the bonded amount - fee
+/// to a council node (its voting power and rewards then include the delegated amount)
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DelegateTx {
+    pub attributes: StakedStateOpAttributes,
+    pub value: Coin,
+    pub nonce: Nonce,
+    pub council_node: StakedStateAddress,
+}
+
+impl TransactionId for DelegateTx {}
+
+impl DelegateTx {
+    pub fn new(
+        value: Coin,
+        nonce: Nonce,
+        council_node: StakedStateAddress,
+        attributes: StakedStateOpAttributes,
+    ) -> Self {
+        DelegateTx {
+            attributes,
+            value,
+            nonce,
+            council_node,
+        }
+    }
+}
+
+impl fmt::Display for DelegateTx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "delegated: {} -> council node: {} (nonce: {})",
+            self.value, self.council_node, self.nonce
+        )?;
+        write!(f, "")
+    }
+}
+
+/// updates the StakedState (TODO: implicit from the witness?) by moving some of the delegated amount - fee
+/// into a new unbonding entry released at last_block_time+min_unbonding_time (network parameter)
 #[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UndelegateTx {
+    pub attributes: StakedStateOpAttributes,
+    pub value: Coin,
+    pub nonce: Nonce,
+    pub council_node: StakedStateAddress,
+}
+
+impl TransactionId for UndelegateTx {}
+
+impl UndelegateTx {
+    pub fn new(
+        value: Coin,
+        nonce: Nonce,
+        council_node: StakedStateAddress,
+        attributes: StakedStateOpAttributes,
+    ) -> Self {
+        UndelegateTx {
+            attributes,
+            value,
+            nonce,
+            council_node,
+        }
+    }
+}
+
+impl fmt::Display for UndelegateTx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "undelegated: {} <- council node: {} (nonce: {})",
+            self.value, self.council_node, self.nonce
+        )?;
+        write!(f, "")
+    }
+}
+
+/// takes the StakedState (TODO: implicit from the witness?) and creates UTXOs out of the matured unbonding entries
+/// (update's StakedState's unbonding entries + nonce)
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct WithdrawUnbondedTx {
+    pub attributes: TxAttributes,
     pub nonce: Nonce,
     pub outputs: Vec<TxOut>,
-    pub attributes: TxAttributes,
 }
 
-impl TransactionId for WithdrawUnbondedTx {}
+impl Encode for WithdrawUnbondedTx {
+    fn encode_to<W: Output>(&self, dest: &mut W) {
+        if self.attributes.version == LEGACY_TX_VERSION {
+            dest.push_byte(LEGACY_TX_VERSION);
+            self.nonce.encode_to(dest);
+            self.outputs.encode_to(dest);
+            self.attributes.encode_legacy_to(dest);
+        } else {
+            self.attributes.encode_to(dest);
+            self.nonce.encode_to(dest);
+            self.outputs.encode_to(dest);
+        }
+    }
+}
+
+impl Decode for WithdrawUnbondedTx {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let version = decode_tx_version(input)?;
+        WithdrawUnbondedTx::decode_fields(version, input)
+    }
+}
+
+impl TransactionId for WithdrawUnbondedTx {
+    fn id(&self) -> TxId {
+        if self.attributes.version >= OUTPUTS_COMMITMENT_TX_VERSION {
+            outputs_committed_txid(&self.body_hash(), &outputs_root(&self.outputs))
+        } else {
+            versioned_txid(&self.encode())
+        }
+    }
+}
 
 impl WithdrawUnbondedTx {
     pub fn new(nonce: Nonce, outputs: Vec<TxOut>, attributes: TxAttributes) -> Self {
         WithdrawUnbondedTx {
+            attributes,
             nonce,
             outputs,
-            attributes,
         }
     }
+
+    /// decodes a stored TX: unlike `decode`, it also accepts the legacy transactions
+    /// (tagged with `LEGACY_TX_VERSION`), which can't be submitted anymore
+    pub fn decode_stored<I: Input>(input: &mut I) -> Result<Self, Error> {
+        match decode_tx_version_or_legacy(input)? {
+            LEGACY_TX_VERSION => WithdrawUnbondedTx::decode_legacy(input),
+            version => WithdrawUnbondedTx::decode_fields(version, input),
+        }
+    }
+
+    /// decodes the fields following the (already decoded) version tag
+    fn decode_fields<I: Input>(version: TxVersion, input: &mut I) -> Result<Self, Error> {
+        let attributes = TxAttributes::decode_fields(version, input)?;
+        let nonce = Nonce::decode(input)?;
+        let outputs = <Vec<TxOut>>::decode(input)?;
+        Ok(WithdrawUnbondedTx {
+            attributes,
+            nonce,
+            outputs,
+        })
+    }
+
+    /// decodes a TX in the legacy layout without the version tag, as stored before the format was versioned
+    /// (see `LEGACY_TX_VERSION`)
+    pub fn decode_legacy<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let nonce = Nonce::decode(input)?;
+        let outputs = <Vec<TxOut>>::decode(input)?;
+        let attributes = TxAttributes::decode_legacy(input)?;
+        Ok(WithdrawUnbondedTx {
+            attributes,
+            nonce,
+            outputs,
+        })
+    }
 }
 
 impl WithdrawUnbondedTx {
@@ -345,6 +874,13 @@ impl WithdrawUnbondedTx {
     pub fn get_output_total(&self) -> Result<Coin, CoinError> {
         sum_coins(self.outputs.iter().map(|x| x.value))
     }
+
+    /// hash of the encoding without the outputs (see `OUTPUTS_COMMITMENT_TX_VERSION`)
+    pub fn body_hash(&self) -> H256 {
+        let mut body = self.attributes.encode();
+        self.nonce.encode_to(&mut body);
+        txid_hash(&body)
+    }
 }
 
 impl fmt::Display for WithdrawUnbondedTx {
@@ -357,6 +893,79 @@ impl fmt::Display for WithdrawUnbondedTx {
     }
 }
 
+/// requests the StakedState to be registered as a council node (validator) with the given consensus key
+/// (updates StakedState's bonded - fee + nonce; the bonded amount needs to be at least `required_council_node_stake`)
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NodeJoinRequestTx {
+    pub attributes: StakedStateOpAttributes,
+    pub nonce: Nonce,
+    pub address: StakedStateAddress,
+    pub consensus_pubkey: TendermintValidatorPubKey,
+}
+
+impl TransactionId for NodeJoinRequestTx {}
+
+impl NodeJoinRequestTx {
+    pub fn new(
+        nonce: Nonce,
+        address: StakedStateAddress,
+        consensus_pubkey: TendermintValidatorPubKey,
+        attributes: StakedStateOpAttributes,
+    ) -> Self {
+        NodeJoinRequestTx {
+            attributes,
+            nonce,
+            address,
+            consensus_pubkey,
+        }
+    }
+}
+
+impl fmt::Display for NodeJoinRequestTx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} (bonded) -> council node: {:?} (nonce: {})",
+            self.address, self.consensus_pubkey, self.nonce
+        )?;
+        write!(f, "")
+    }
+}
+
+/// releases the (previously punished) StakedState from the jail after the jail period
+/// (updates StakedState's bonded - fee + nonce)
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UnjailTx {
+    pub attributes: StakedStateOpAttributes,
+    pub nonce: Nonce,
+    pub address: StakedStateAddress,
+}
+
+impl TransactionId for UnjailTx {}
+
+impl UnjailTx {
+    pub fn new(
+        nonce: Nonce,
+        address: StakedStateAddress,
+        attributes: StakedStateOpAttributes,
+    ) -> Self {
+        UnjailTx {
+            attributes,
+            nonce,
+            address,
+        }
+    }
+}
+
+impl fmt::Display for UnjailTx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "unjailed: {} (nonce: {})", self.address, self.nonce)?;
+        write!(f, "")
+    }
+}
+
 /// A witness for StakedState operations
 #[derive(Debug, PartialEq, Eq, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
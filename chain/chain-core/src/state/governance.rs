@@ -0,0 +1,177 @@
+use crate::common::{hash256, H256};
+use crate::init::coin::Coin;
+use crate::init::config::InitNetworkParameters;
+use crate::state::account::{Nonce, StakedStateAddress, StakedStateOpAttributes};
+use crate::state::tendermint::BlockHeight;
+use crate::tx::data::TxId;
+use crate::tx::fee::{FeeAlgorithm, LinearFee};
+use crate::tx::{TransactionId, TX_AUX_SIZE};
+use blake2::Blake2s;
+use parity_scale_codec::{Decode, Encode};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Maximum unbonding period (in seconds) a council node proposal can set (one year)
+pub const MAX_UNBONDING_PERIOD: u32 = 365 * 24 * 60 * 60;
+
+/// Network parameters that can be changed by council node proposals after genesis
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NetworkParameters {
+    /// fee policy to apply -- TODO: change to be against T: FeeAlgorithm
+    pub fee_policy: LinearFee,
+    /// time when unbonded stake can be withdrawn
+    pub unbonding_period: u32,
+    /// (minimal?) amount required to be bonded in validator-associated accounts
+    pub required_council_node_stake: Coin,
+}
+
+impl NetworkParameters {
+    /// retrieves the hash of the current parameters (currently blake2s(scale_code_bytes(network_parameters)))
+    pub fn hash(&self) -> H256 {
+        hash256::<Blake2s>(&self.encode())
+    }
+
+    /// updates the changed parameter (the change is expected to be valid, see `NetworkParameterChange::is_valid`)
+    pub fn apply(&mut self, change: &NetworkParameterChange) {
+        match change {
+            NetworkParameterChange::FeePolicy(fee_policy) => self.fee_policy = *fee_policy,
+            NetworkParameterChange::UnbondingPeriod(period) => self.unbonding_period = *period,
+            NetworkParameterChange::RequiredCouncilNodeStake(stake) => {
+                self.required_council_node_stake = *stake
+            }
+        }
+    }
+}
+
+impl From<&InitNetworkParameters> for NetworkParameters {
+    fn from(params: &InitNetworkParameters) -> Self {
+        NetworkParameters {
+            fee_policy: params.initial_fee_policy,
+            unbonding_period: params.unbonding_period,
+            required_council_node_stake: params.required_council_node_stake,
+        }
+    }
+}
+
+/// A new value of one of the network parameters
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum NetworkParameterChange {
+    FeePolicy(LinearFee),
+    UnbondingPeriod(u32),
+    RequiredCouncilNodeStake(Coin),
+}
+
+impl NetworkParameterChange {
+    /// checks the new value: the fee of the largest transactions needs to be computable with the fee policy,
+    /// the unbonding period can be at most `MAX_UNBONDING_PERIOD` and the stake at most the maximum supply
+    pub fn is_valid(&self) -> bool {
+        match self {
+            NetworkParameterChange::FeePolicy(fee_policy) => {
+                fee_policy.calculate_fee(TX_AUX_SIZE).is_ok()
+            }
+            NetworkParameterChange::UnbondingPeriod(period) => *period <= MAX_UNBONDING_PERIOD,
+            NetworkParameterChange::RequiredCouncilNodeStake(stake) => *stake <= Coin::max(),
+        }
+    }
+}
+
+impl fmt::Display for NetworkParameterChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkParameterChange::FeePolicy(fee_policy) => write!(
+                f,
+                "fee policy: {:?} + {:?} * size",
+                fee_policy.constant, fee_policy.coefficient
+            ),
+            NetworkParameterChange::UnbondingPeriod(period) => {
+                write!(f, "unbonding period: {} seconds", period)
+            }
+            NetworkParameterChange::RequiredCouncilNodeStake(stake) => {
+                write!(f, "required council node stake: {}", stake)
+            }
+        }
+    }
+}
+
+/// takes the fee from the council node's bonded amount, proposes a network parameter change
+/// that's activated at the given block height if enough council nodes vote for it
+/// (updates StakedState's bonded - fee + nonce; the proposer's vote is included)
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ParamChangeProposalTx {
+    pub attributes: StakedStateOpAttributes,
+    pub nonce: Nonce,
+    pub address: StakedStateAddress,
+    pub change: NetworkParameterChange,
+    pub activation_height: BlockHeight,
+}
+
+impl TransactionId for ParamChangeProposalTx {}
+
+impl ParamChangeProposalTx {
+    pub fn new(
+        nonce: Nonce,
+        address: StakedStateAddress,
+        change: NetworkParameterChange,
+        activation_height: BlockHeight,
+        attributes: StakedStateOpAttributes,
+    ) -> Self {
+        ParamChangeProposalTx {
+            attributes,
+            nonce,
+            address,
+            change,
+            activation_height,
+        }
+    }
+}
+
+impl fmt::Display for ParamChangeProposalTx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "proposed by: {} (nonce: {})", self.address, self.nonce)?;
+        writeln!(f, "change: {}", self.change)?;
+        writeln!(f, "activation height: {}", self.activation_height)?;
+        write!(f, "")
+    }
+}
+
+/// takes the fee from the council node's bonded amount, votes for a pending network parameter change proposal
+/// (identified by the proposal's transaction ID)
+/// (updates StakedState's bonded - fee + nonce)
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ParamChangeVoteTx {
+    pub attributes: StakedStateOpAttributes,
+    pub nonce: Nonce,
+    pub address: StakedStateAddress,
+    pub proposal_id: TxId,
+}
+
+impl TransactionId for ParamChangeVoteTx {}
+
+impl ParamChangeVoteTx {
+    pub fn new(
+        nonce: Nonce,
+        address: StakedStateAddress,
+        proposal_id: TxId,
+        attributes: StakedStateOpAttributes,
+    ) -> Self {
+        ParamChangeVoteTx {
+            attributes,
+            nonce,
+            address,
+            proposal_id,
+        }
+    }
+}
+
+impl fmt::Display for ParamChangeVoteTx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "voted by: {} (nonce: {})", self.address, self.nonce)?;
+        writeln!(f, "proposal: {}", hex::encode(&self.proposal_id[..]))?;
+        write!(f, "")
+    }
+}
@@ -1,5 +1,9 @@
 /// data types related to account operations
 pub mod account;
+/// data types related to network parameter change proposals
+pub mod governance;
+/// inclusion proofs of the account states in the application hash
+pub mod proof;
 /// data types related to working with Tendermint
 pub mod tendermint;
 
@@ -19,6 +23,8 @@ pub struct RewardsPoolState {
     pub remaining: Coin,
     /// last block height that updated it (i64 from Tendermint protobuf)
     pub last_block_height: BlockHeight,
+    /// total amount distributed to council nodes so far
+    pub distributed: Coin,
 }
 
 impl RewardsPoolState {
@@ -31,6 +37,7 @@ impl RewardsPoolState {
         RewardsPoolState {
             remaining,
             last_block_height,
+            distributed: Coin::zero(),
         }
     }
 }
@@ -0,0 +1,99 @@
+use crate::common::{hash256, MerkleTree, H256};
+use crate::state::account::StakedState;
+use crate::state::governance::NetworkParameters;
+use crate::state::RewardsPoolState;
+use blake2::Blake2s;
+use parity_scale_codec::{Decode, Encode};
+use std::prelude::v1::Vec;
+
+/// the application hash components other than the account trie root
+/// (so that an account state's inclusion can be checked against the application hash)
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct AppHashParts {
+    /// root of the valid TX merkle tree
+    pub valid_tx_root: H256,
+    /// blake2s(scale bytes(rewards pool state))
+    pub rewards_pool_hash: H256,
+    /// blake2s(scale bytes(network parameters))
+    pub network_params_hash: H256,
+    /// blake2s of the other consensus state of the node (council nodes, punishments, rewards distribution,
+    /// delegations and parameter change proposals -- see `ChainNodeState::node_state_hash` in chain-abci)
+    pub node_state_hash: H256,
+}
+
+impl AppHashParts {
+    pub fn new(
+        valid_tx_id_tree: &MerkleTree<H256>,
+        reward_pool: &RewardsPoolState,
+        network_params: &NetworkParameters,
+        node_state_hash: &H256,
+    ) -> Self {
+        AppHashParts {
+            valid_tx_root: valid_tx_id_tree.root_hash(),
+            rewards_pool_hash: reward_pool.hash(),
+            network_params_hash: network_params.hash(),
+            node_state_hash: *node_state_hash,
+        }
+    }
+
+    /// computes the application hash with the given account trie root (see `compute_app_hash`)
+    pub fn app_hash(&self, account_state_root: &H256) -> H256 {
+        let mut bs = Vec::new();
+        bs.extend(&self.valid_tx_root);
+        bs.extend(&account_state_root[..]);
+        bs.extend(&self.rewards_pool_hash);
+        bs.extend(&self.network_params_hash);
+        bs.extend(&self.node_state_hash);
+        hash256::<Blake2s>(&bs)
+    }
+}
+
+/// the side of a branch in the account trie
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+pub enum TrieSide {
+    /// the subtree of the keys with the branch's split bit unset
+    Zero,
+    /// the subtree of the keys with the branch's split bit set
+    One,
+}
+
+/// Proof of a staked state's inclusion in the account trie whose root is committed in the application hash
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct AccountProof {
+    /// the sibling subtrees on the path from the account's leaf up to the trie root
+    /// (the side of the branch they are on and their locations, i.e. node hashes)
+    pub siblings: Vec<(TrieSide, H256)>,
+    /// the other application hash components
+    pub app_hash_parts: AppHashParts,
+}
+
+/// the location (hash) of an account trie node: blake2s(tag || parts)
+/// (the tags are "d" for data, "l" for leaf and "b" for branch nodes)
+fn trie_node_hash(tag: &[u8], parts: &[&[u8]]) -> H256 {
+    let mut bs = Vec::from(tag);
+    for part in parts {
+        bs.extend_from_slice(part);
+    }
+    hash256::<Blake2s>(&bs)
+}
+
+impl AccountProof {
+    /// computes the account trie root from the staked state and the sibling subtrees
+    pub fn account_root(&self, account: &StakedState) -> H256 {
+        let key = account.key();
+        let data = trie_node_hash(b"d", &[&key[..], &account.encode()[..]]);
+        let mut location = trie_node_hash(b"l", &[&key[..], &data[..]]);
+        for (side, sibling) in self.siblings.iter() {
+            location = match side {
+                TrieSide::Zero => trie_node_hash(b"b", &[&sibling[..], &location[..]]),
+                TrieSide::One => trie_node_hash(b"b", &[&location[..], &sibling[..]]),
+            };
+        }
+        location
+    }
+
+    /// true if the staked state is in the account trie committed in the application hash
+    pub fn verify(&self, account: &StakedState, app_hash: &H256) -> bool {
+        self.app_hash_parts.app_hash(&self.account_root(account)) == *app_hash
+    }
+}
@@ -1,6 +1,8 @@
+use crate::common::hash256;
 use parity_scale_codec::{Decode, Encode};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use std::fmt;
 use std::prelude::v1::{String, ToString, Vec};
 
@@ -22,6 +24,9 @@ pub enum TendermintValidatorPubKey {
     // TODO: some other schemes when they are added in TM?
 }
 
+/// Tendermint validator address (as referred to in commit votes or evidence)
+pub type TendermintValidatorAddress = [u8; 20];
+
 impl TendermintValidatorPubKey {
     pub fn to_validator_update(&self) -> (String, Vec<u8>) {
         match self {
@@ -32,6 +37,17 @@ impl TendermintValidatorPubKey {
             }
         }
     }
+
+    /// "ed25519: address is the first 20-bytes of the SHA256 of the raw 32-byte public key"
+    pub fn validator_address(&self) -> TendermintValidatorAddress {
+        match self {
+            TendermintValidatorPubKey::Ed25519(key) => {
+                let mut address = [0u8; 20];
+                address.copy_from_slice(&hash256::<Sha256>(key)[..20]);
+                address
+            }
+        }
+    }
 }
 
 /// "Note that the maximum total power of the validator set is bounded by MaxTotalVotingPower = MaxInt64 / 8.
@@ -12,53 +12,82 @@ use crate::init::address::{CroAddress, CroAddressError};
 use bech32::{self, u5, FromBase32, ToBase32};
 
 #[cfg(feature = "bech32")]
-use crate::init::network::get_bech32_human_part;
+use crate::init::network::Network;
 
 /// TODO: opaque types?
 type TreeRoot = H256;
 
-/// Currently, only Ethereum-style redeem address + MAST of Or operations (records the root).
+/// Currently, only Ethereum-style redeem address + MAST of Or operations (records the root)
+/// or a hash-time-locked contract (records the hash of `HashTimeLock`).
 /// TODO: HD-addresses?
 /// TODO: custom Encode/Decode when data structures are finalized (for backwards/forwards compatibility, encoders/decoders should be able to work with old formats)
 #[derive(Debug, PartialEq, Eq, Clone, Encode, Decode, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ExtendedAddr {
     OrTree(TreeRoot),
+    HashTimeLock(H256),
 }
 
+/// the textual (bech32) form of a hash-time-locked address is prefixed with this byte
+/// (tree addresses are just the 32-byte root)
+#[cfg(feature = "bech32")]
+const HASH_TIME_LOCK_ADDRESS_TAG: u8 = 1;
+
 #[cfg(feature = "bech32")]
 impl ExtendedAddr {
-    fn get_string(&self, hash: TreeRoot) -> String {
-        let checked_data: Vec<u5> = hash.to_vec().to_base32();
+    fn get_string(&self, data: &[u8], network: Network) -> String {
+        let checked_data: Vec<u5> = data.to_base32();
 
-        bech32::encode(get_bech32_human_part(), checked_data)
+        bech32::encode(network.bech32_human_part(), checked_data)
             .expect("bech32 should be successful in ExtendedAddr get_string")
     }
 }
 
 #[cfg(feature = "bech32")]
 impl CroAddress<ExtendedAddr> for ExtendedAddr {
-    fn to_cro(&self) -> Result<String, CroAddressError> {
+    fn to_cro_for(&self, network: Network) -> Result<String, CroAddressError> {
         match self {
             ExtendedAddr::OrTree(hash) => {
-                let encoded = self.get_string(*hash);
+                let encoded = self.get_string(&hash[..], network);
                 Ok(encoded.to_string())
             }
+            ExtendedAddr::HashTimeLock(hash) => {
+                let mut data = vec![HASH_TIME_LOCK_ADDRESS_TAG];
+                data.extend_from_slice(&hash[..]);
+                Ok(self.get_string(&data, network))
+            }
         }
     }
 
-    fn from_cro(encoded: &str) -> Result<Self, CroAddressError> {
+    fn from_cro_for(encoded: &str, network: Network) -> Result<Self, CroAddressError> {
         bech32::decode(encoded)
             .map_err(|e| CroAddressError::Bech32Error(e.to_string()))
-            .and_then(|a| Vec::from_base32(&a.1).map_err(|_e| CroAddressError::ConvertError))
-            .and_then(|src| {
-                let mut a: TreeRoot = [0 as u8; 32];
-                a.copy_from_slice(&src.as_slice());
-                Ok(ExtendedAddr::OrTree(a))
+            .and_then(|(human_part, data)| {
+                if human_part == network.bech32_human_part() {
+                    Ok(data)
+                } else {
+                    Err(CroAddressError::InvalidNetwork)
+                }
+            })
+            .and_then(|data| Vec::from_base32(&data).map_err(|_e| CroAddressError::ConvertError))
+            .and_then(|src: Vec<u8>| {
+                let mut a: H256 = [0 as u8; 32];
+                match src.len() {
+                    32 => {
+                        a.copy_from_slice(&src.as_slice());
+                        Ok(ExtendedAddr::OrTree(a))
+                    }
+                    33 if src[0] == HASH_TIME_LOCK_ADDRESS_TAG => {
+                        a.copy_from_slice(&src[1..]);
+                        Ok(ExtendedAddr::HashTimeLock(a))
+                    }
+                    _ => Err(CroAddressError::ConvertError),
+                }
             })
     }
 }
 
+/// formats the address for the process-wide network (a convenience, see `CroAddress::to_cro_for`)
 #[cfg(feature = "bech32")]
 impl fmt::Display for ExtendedAddr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -71,16 +100,23 @@ impl fmt::Display for ExtendedAddr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ExtendedAddr::OrTree(hash) => write!(f, "0x{}", hex::encode(hash)),
+            ExtendedAddr::HashTimeLock(hash) => write!(f, "htlc:0x{}", hex::encode(hash)),
         }
     }
 }
 
+/// parses the address on the network given by its human readable part
+/// (use `CroAddress::from_cro_for` to only accept addresses on a given network)
 #[cfg(feature = "bech32")]
 impl FromStr for ExtendedAddr {
     type Err = CroAddressError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        ExtendedAddr::from_cro(s).map_err(|_e| CroAddressError::ConvertError)
+        let (human_part, _) =
+            bech32::decode(s).map_err(|e| CroAddressError::Bech32Error(e.to_string()))?;
+        let network =
+            Network::from_bech32_human_part(&human_part).ok_or(CroAddressError::InvalidNetwork)?;
+        ExtendedAddr::from_cro_for(s, network)
     }
 }
 
@@ -119,4 +155,37 @@ mod test {
         .unwrap();
         assert_eq!(a, b);
     }
+
+    #[test]
+    fn hash_time_lock_address_should_roundtrip() {
+        let a = ExtendedAddr::HashTimeLock([0xab; 32]);
+        let b = a.to_cro().unwrap();
+        assert_eq!(ExtendedAddr::from_cro(&b).unwrap(), a);
+        assert_ne!(
+            ExtendedAddr::from_cro(&b).unwrap(),
+            ExtendedAddr::OrTree([0xab; 32])
+        );
+    }
+
+    #[test]
+    fn address_should_be_encoded_for_the_given_network() {
+        let a = ExtendedAddr::OrTree([0xab; 32]);
+        let testnet = a.to_cro_for(Network::Testnet).unwrap();
+        let mainnet = a.to_cro_for(Network::Mainnet).unwrap();
+        assert!(testnet.starts_with("tcro1"));
+        assert!(mainnet.starts_with("cro1"));
+        assert_eq!(
+            ExtendedAddr::from_cro_for(&testnet, Network::Testnet).unwrap(),
+            a
+        );
+        assert_eq!(
+            ExtendedAddr::from_cro_for(&mainnet, Network::Mainnet).unwrap(),
+            a
+        );
+        assert!(ExtendedAddr::from_cro_for(&testnet, Network::Mainnet).is_err());
+        assert!(ExtendedAddr::from_cro_for(&mainnet, Network::Devnet).is_err());
+        // the textual format is parsed on the network it's for
+        assert_eq!(ExtendedAddr::from_str(&testnet).unwrap(), a);
+        assert_eq!(ExtendedAddr::from_str(&mainnet).unwrap(), a);
+    }
 }
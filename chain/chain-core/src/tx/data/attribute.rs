@@ -1,4 +1,4 @@
-use parity_scale_codec::{Decode, Encode};
+use parity_scale_codec::{Decode, Encode, Error, Input, Output};
 #[cfg(feature = "serde")]
 use serde::de;
 #[cfg(feature = "serde")]
@@ -8,11 +8,77 @@ use std::fmt;
 use std::prelude::v1::Vec;
 
 use crate::tx::data::access::TxAccessPolicy;
+use crate::tx::data::{txid_hash, TxId};
+
+/// Transaction format version
+pub type TxVersion = u8;
+
+/// Format version of transactions created by this release.
+///
+/// Attributes are encoded as the first field of every transaction type
+/// and the version is the first field of attributes, so the version is always
+/// the leading byte of an encoded transaction. Decoders dispatch on it:
+/// fields introduced by a newer format should only be read when the decoded version
+/// has them, and older versions should keep decoding (with defaults for new fields),
+/// so that stored transactions and clients don't need to be migrated.
+pub const TX_VERSION: TxVersion = 2;
+
+/// Version from which the IDs of the transactions with outputs (`Tx` and `WithdrawUnbondedTx`)
+/// commit to their outputs separately: the ID is the hash of the body hash (of the encoding without the outputs)
+/// and the root of the Merkle tree of the outputs (see `outputs_root`), so that outputs can be disclosed
+/// with inclusion proofs against the transaction ID (see `RedactedTx`).
+/// The IDs of the older versions are the hash of the whole encoding.
+pub const OUTPUTS_COMMITMENT_TX_VERSION: TxVersion = 2;
+
+/// Version of transactions created before the format was versioned.
+///
+/// Their fields are in the original order (attributes last, without the version).
+/// The transaction types that already existed then (`Tx`, `WithdrawUnbondedTx`, `DepositBondTx` and `UnbondTx`)
+/// encode them as this version tag followed by the legacy encoding, but their transaction ID
+/// is still the hash of the legacy encoding, so that the stored transactions keep their IDs (and witnesses).
+/// Legacy transactions can't be submitted anymore: the plain decoders reject this version,
+/// the tagged encoding is only read by the `decode_stored` decoders (used for the stored transactions)
+/// and the untagged legacy encoding (as it was stored before) only by the explicit `decode_legacy` decoders.
+pub const LEGACY_TX_VERSION: TxVersion = 0;
+
+/// decodes and checks the version tag of transaction attributes
+pub(crate) fn decode_tx_version<I: Input>(input: &mut I) -> Result<TxVersion, Error> {
+    match decode_tx_version_or_legacy(input)? {
+        LEGACY_TX_VERSION => Err("Unsupported transaction version".into()),
+        version => Ok(version),
+    }
+}
+
+/// decodes and checks the version tag of the transaction types that have a legacy layout (see `LEGACY_TX_VERSION`)
+pub(crate) fn decode_tx_version_or_legacy<I: Input>(input: &mut I) -> Result<TxVersion, Error> {
+    let version = TxVersion::decode(input)?;
+    if version > TX_VERSION {
+        Err("Unsupported transaction version".into())
+    } else {
+        Ok(version)
+    }
+}
+
+/// computes the ID of an encoded transaction of the types that have a legacy layout
+/// (legacy transactions are identified by the hash of their untagged encoding)
+pub(crate) fn versioned_txid(encoded: &[u8]) -> TxId {
+    match encoded.split_first() {
+        Some((&LEGACY_TX_VERSION, legacy)) => txid_hash(legacy),
+        _ => txid_hash(encoded),
+    }
+}
+
+#[cfg(feature = "serde")]
+pub(crate) fn default_tx_version() -> TxVersion {
+    TX_VERSION
+}
 
 /// Tx extra metadata, e.g. network ID
-#[derive(Debug, Default, PartialEq, Eq, Clone, Encode, Decode)]
+#[derive(Debug, PartialEq, Eq, Clone, Encode)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TxAttributes {
+    #[cfg_attr(feature = "serde", serde(default = "default_tx_version"))]
+    pub version: TxVersion,
     #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_chain_hex_id"))]
     #[cfg_attr(
         feature = "serde",
@@ -20,7 +86,19 @@ pub struct TxAttributes {
     )]
     pub chain_hex_id: u8,
     pub allowed_view: Vec<TxAccessPolicy>,
-    // TODO: other attributes, e.g. versioning info
+}
+
+impl Decode for TxAttributes {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let version = decode_tx_version(input)?;
+        TxAttributes::decode_fields(version, input)
+    }
+}
+
+impl Default for TxAttributes {
+    fn default() -> Self {
+        TxAttributes::new(0)
+    }
 }
 
 #[cfg(feature = "serde")]
@@ -74,6 +152,7 @@ impl TxAttributes {
     /// creates tx attributes
     pub fn new(chain_hex_id: u8) -> Self {
         TxAttributes {
+            version: TX_VERSION,
             chain_hex_id,
             allowed_view: Vec::new(),
         }
@@ -82,8 +161,35 @@ impl TxAttributes {
     /// creates tx attributes with access policy
     pub fn new_with_access(chain_hex_id: u8, allowed_view: Vec<TxAccessPolicy>) -> Self {
         TxAttributes {
+            version: TX_VERSION,
             chain_hex_id,
             allowed_view,
         }
     }
+
+    /// decodes the fields following the (already decoded) version tag
+    pub(crate) fn decode_fields<I: Input>(
+        version: TxVersion,
+        input: &mut I,
+    ) -> Result<Self, Error> {
+        // version 1 fields (the same as in the legacy layout)
+        let chain_hex_id = u8::decode(input)?;
+        let allowed_view = <Vec<TxAccessPolicy>>::decode(input)?;
+        Ok(TxAttributes {
+            version,
+            chain_hex_id,
+            allowed_view,
+        })
+    }
+
+    /// decodes the attributes in the legacy layout (see `LEGACY_TX_VERSION`)
+    pub(crate) fn decode_legacy<I: Input>(input: &mut I) -> Result<Self, Error> {
+        TxAttributes::decode_fields(LEGACY_TX_VERSION, input)
+    }
+
+    /// encodes the attributes in the legacy layout (see `LEGACY_TX_VERSION`)
+    pub(crate) fn encode_legacy_to<W: Output>(&self, dest: &mut W) {
+        self.chain_hex_id.encode_to(dest);
+        self.allowed_view.encode_to(dest);
+    }
 }
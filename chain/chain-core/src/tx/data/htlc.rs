@@ -0,0 +1,95 @@
+use blake2::Blake2s;
+use parity_scale_codec::{Decode, Encode};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt;
+
+use crate::common::{hash256, Timespec, H256};
+use crate::tx::data::address::ExtendedAddr;
+
+/// The secret that unlocks a hash lock
+pub type HashLockPreimage = H256;
+
+/// Computes the hash lock of a secret.
+/// SHA-256 is used, so that the same secret can lock funds on other chains (atomic swaps).
+pub fn hash_lock(preimage: &HashLockPreimage) -> H256 {
+    hash256::<Sha256>(preimage)
+}
+
+/// Hash-time-locked contract:
+/// before `timeout`, the funds can be claimed by `claim_root` with the preimage of `hash`;
+/// from `timeout` on, they can be refunded to `refund_root`.
+///
+/// Outputs only commit to the contract (see `address`), the full contract is revealed in the input witness.
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HashTimeLock {
+    /// SHA-256 hash of the secret preimage
+    pub hash: H256,
+    /// root of the address (MAST) that can claim the funds with the preimage
+    pub claim_root: H256,
+    /// root of the address (MAST) that can take the funds back after the timeout
+    pub refund_root: H256,
+    /// seconds since UNIX epoch -- compared against the previous block time
+    pub timeout: Timespec,
+}
+
+impl HashTimeLock {
+    /// creates a new contract
+    pub fn new(hash: H256, claim_root: H256, refund_root: H256, timeout: Timespec) -> Self {
+        HashTimeLock {
+            hash,
+            claim_root,
+            refund_root,
+            timeout,
+        }
+    }
+
+    /// the address to send the locked funds to: blake2s of the SCALE-encoded contract
+    pub fn address(&self) -> ExtendedAddr {
+        ExtendedAddr::HashTimeLock(hash256::<Blake2s>(&self.encode()))
+    }
+
+    /// checks if the preimage unlocks the hash lock
+    pub fn check_preimage(&self, preimage: &HashLockPreimage) -> bool {
+        hash_lock(preimage) == self.hash
+    }
+
+    /// checks if the claim period is over (and the funds can be refunded)
+    pub fn is_expired(&self, block_time: Timespec) -> bool {
+        block_time >= self.timeout
+    }
+}
+
+impl fmt::Display for HashTimeLock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "hash: {}", hex::encode(&self.hash))?;
+        writeln!(f, "claim root: {}", hex::encode(&self.claim_root))?;
+        writeln!(f, "refund root: {}", hex::encode(&self.refund_root))?;
+        write!(f, "timeout: {}", self.timeout)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn preimage_should_unlock_hash_lock() {
+        let preimage = [0x11; 32];
+        let htlc = HashTimeLock::new(hash_lock(&preimage), [0x01; 32], [0x02; 32], 100);
+        assert!(htlc.check_preimage(&preimage));
+        assert!(!htlc.check_preimage(&[0x12; 32]));
+    }
+
+    #[test]
+    fn address_should_commit_to_contract() {
+        let htlc = HashTimeLock::new([0x00; 32], [0x01; 32], [0x02; 32], 100);
+        let mut other = htlc.clone();
+        other.timeout = 101;
+        assert_ne!(htlc.address(), other.address());
+        assert!(!htlc.is_expired(99));
+        assert!(htlc.is_expired(100));
+    }
+}
@@ -6,6 +6,8 @@ pub mod access;
 pub mod address;
 /// Miscellaneous TX attributes, such as network ID
 pub mod attribute;
+/// Hash-time-locked contracts (for atomic swaps / payment channels)
+pub mod htlc;
 /// Transaction inputs (pointers to previous transaction outputs)
 pub mod input;
 /// Transaction outputs (amount to an address)
@@ -14,21 +16,28 @@ pub mod output;
 use std::fmt;
 
 use blake2::Blake2s;
-use parity_scale_codec::{Decode, Encode, Error, Input};
+use parity_scale_codec::{Decode, Encode, Error, Input, Output};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use crate::common::{hash256, H256};
+use crate::common::{hash256, MerkleTree, H256};
 use crate::init::coin::{sum_coins, Coin, CoinError};
-use crate::tx::data::{attribute::TxAttributes, input::TxoPointer, output::TxOut};
+use crate::tx::data::attribute::{
+    decode_tx_version, decode_tx_version_or_legacy, versioned_txid, TxAttributes, TxVersion,
+    LEGACY_TX_VERSION, OUTPUTS_COMMITMENT_TX_VERSION,
+};
+use crate::tx::data::{
+    input::{TxoIndex, TxoPointer},
+    output::TxOut,
+};
 use crate::tx::TransactionId;
 
 /// Each input is 34 bytes
 /// Each output is 33 (address) + 8 (amount) + 9 (timelock) = 50 bytes
-/// Assuming maximum allowed view keys are 64. Attributes are 1 + (64 * 42) = 2688 bytes
+/// Assuming maximum allowed view keys are 64. Attributes are 1 (version) + 1 + (64 * 42) = 2690 bytes
 ///
 /// Assuming maximum inputs and outputs allowed are 64 each,
-/// So, maximum transaction size (34 * 64) + (50 * 64) + 2688 = 8064
+/// So, maximum transaction size (34 * 64) + (50 * 64) + 2690 = 8066
 const MAX_TX_SIZE: usize = 8100; // 8100 bytes
 
 /// Calculates hash of the input data -- if SCALE-serialized TX is passed in, it's equivalent to TxId.
@@ -41,38 +50,64 @@ pub fn txid_hash(buf: &[u8]) -> H256 {
 pub const TXID_HASH_ID: &[u8; 7] = b"blake2s";
 
 /// Transaction ID -- currently, blake2s hash of SCALE-serialized TX data
+/// (for the transactions with outputs, see `OUTPUTS_COMMITMENT_TX_VERSION`)
 pub type TxId = H256;
 
+/// Leaf of the Merkle tree of transaction outputs: the output index followed by the encoded output
+pub fn output_leaf(index: TxoIndex, output: &TxOut) -> Vec<u8> {
+    (index, output).encode()
+}
+
+/// Root of the Merkle tree of transaction outputs (see `OUTPUTS_COMMITMENT_TX_VERSION`)
+pub fn outputs_root(outputs: &[TxOut]) -> H256 {
+    MerkleTree::new(
+        outputs
+            .iter()
+            .enumerate()
+            .map(|(index, output)| output_leaf(index as TxoIndex, output))
+            .collect(),
+    )
+    .root_hash()
+}
+
+/// ID of a transaction that commits to its outputs (see `OUTPUTS_COMMITMENT_TX_VERSION`)
+pub fn outputs_committed_txid(body_hash: &H256, outputs_root: &H256) -> TxId {
+    let mut buf = body_hash.to_vec();
+    buf.extend_from_slice(outputs_root);
+    txid_hash(&buf)
+}
+
 /// A Transaction containing tx inputs and tx outputs.
+/// Attributes are encoded first, so that the format version (see `TX_VERSION`) is the leading byte
+/// (legacy transactions are encoded as `LEGACY_TX_VERSION` followed by their legacy encoding).
 /// TODO: max input/output size?
-/// TODO: custom Encode/Decode when data structures are finalized (for backwards/forwards compatibility, encoders/decoders should be able to work with old formats)
-#[derive(Debug, Default, PartialEq, Eq, Clone, Encode)]
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Tx {
+    pub attributes: TxAttributes,
     pub inputs: Vec<TxoPointer>,
     pub outputs: Vec<TxOut>,
-    pub attributes: TxAttributes,
 }
 
-impl Decode for Tx {
-    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
-        let size = input
-            .remaining_len()?
-            .ok_or_else(|| "Unable to calculate size of input")?;
-
-        if size > MAX_TX_SIZE {
-            return Err("Input too large".into());
+impl Encode for Tx {
+    fn encode_to<W: Output>(&self, dest: &mut W) {
+        if self.attributes.version == LEGACY_TX_VERSION {
+            dest.push_byte(LEGACY_TX_VERSION);
+            self.inputs.encode_to(dest);
+            self.outputs.encode_to(dest);
+            self.attributes.encode_legacy_to(dest);
+        } else {
+            self.attributes.encode_to(dest);
+            self.inputs.encode_to(dest);
+            self.outputs.encode_to(dest);
         }
+    }
+}
 
-        let inputs = <Vec<TxoPointer>>::decode(input)?;
-        let outputs = <Vec<TxOut>>::decode(input)?;
-        let attributes = TxAttributes::decode(input)?;
-
-        Ok(Tx {
-            inputs,
-            outputs,
-            attributes,
-        })
+impl Decode for Tx {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let version = decode_tx_version(input)?;
+        Tx::decode_fields(version, input)
     }
 }
 
@@ -88,7 +123,15 @@ impl fmt::Display for Tx {
     }
 }
 
-impl TransactionId for Tx {}
+impl TransactionId for Tx {
+    fn id(&self) -> TxId {
+        if self.attributes.version >= OUTPUTS_COMMITMENT_TX_VERSION {
+            outputs_committed_txid(&self.body_hash(), &outputs_root(&self.outputs))
+        } else {
+            versioned_txid(&self.encode())
+        }
+    }
+}
 
 impl Tx {
     /// creates an empty TX
@@ -99,9 +142,9 @@ impl Tx {
     /// creates a TX initialized with a provided vectors of inputs and outputs
     pub fn new_with(ins: Vec<TxoPointer>, outs: Vec<TxOut>, attr: TxAttributes) -> Self {
         Tx {
+            attributes: attr,
             inputs: ins,
             outputs: outs,
-            attributes: attr,
         }
     }
 
@@ -119,4 +162,63 @@ impl Tx {
     pub fn get_output_total(&self) -> Result<Coin, CoinError> {
         sum_coins(self.outputs.iter().map(|x| x.value))
     }
+
+    /// hash of the encoding without the outputs (see `OUTPUTS_COMMITMENT_TX_VERSION`)
+    pub fn body_hash(&self) -> H256 {
+        let mut body = self.attributes.encode();
+        self.inputs.encode_to(&mut body);
+        txid_hash(&body)
+    }
+
+    /// decodes a stored TX: unlike `decode`, it also accepts the legacy transactions
+    /// (tagged with `LEGACY_TX_VERSION`), which can't be submitted anymore
+    pub fn decode_stored<I: Input>(input: &mut I) -> Result<Self, Error> {
+        match decode_tx_version_or_legacy(input)? {
+            LEGACY_TX_VERSION => Tx::decode_legacy(input),
+            version => Tx::decode_fields(version, input),
+        }
+    }
+
+    /// decodes the fields following the (already decoded) version tag
+    fn decode_fields<I: Input>(version: TxVersion, input: &mut I) -> Result<Self, Error> {
+        let size = input
+            .remaining_len()?
+            .ok_or_else(|| "Unable to calculate size of input")?;
+
+        if size > MAX_TX_SIZE {
+            return Err("Input too large".into());
+        }
+
+        let attributes = TxAttributes::decode_fields(version, input)?;
+        let inputs = <Vec<TxoPointer>>::decode(input)?;
+        let outputs = <Vec<TxOut>>::decode(input)?;
+
+        Ok(Tx {
+            attributes,
+            inputs,
+            outputs,
+        })
+    }
+
+    /// decodes a TX in the legacy layout without the version tag, as stored before the format was versioned
+    /// (see `LEGACY_TX_VERSION`)
+    pub fn decode_legacy<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let size = input
+            .remaining_len()?
+            .ok_or_else(|| "Unable to calculate size of input")?;
+
+        if size > MAX_TX_SIZE {
+            return Err("Input too large".into());
+        }
+
+        let inputs = <Vec<TxoPointer>>::decode(input)?;
+        let outputs = <Vec<TxOut>>::decode(input)?;
+        let attributes = TxAttributes::decode_legacy(input)?;
+
+        Ok(Tx {
+            attributes,
+            inputs,
+            outputs,
+        })
+    }
 }
@@ -155,10 +155,16 @@ impl LinearFee {
         }
     }
 
+    /// the fee for the given transaction size (rounded up);
+    /// it's computed without overflows, so an error is returned if it's above the maximum supply
     pub fn estimate(&self, sz: usize) -> Result<Fee, CoinError> {
-        let msz = Milli::integral(sz as u64);
-        let fee = self.constant + self.coefficient * msz;
-        let coin = Coin::new(fee.to_integral())?;
+        let millis = u128::from(self.constant.as_millis())
+            + u128::from(self.coefficient.as_millis()) * sz as u128;
+        let fee = (millis + 999) / 1000;
+        if fee > u128::from(u64::max_value()) {
+            return Err(CoinError::OutOfBound(u64::max_value()));
+        }
+        let coin = Coin::new(fee as u64)?;
         Ok(Fee(coin))
     }
 }
@@ -214,4 +220,16 @@ mod test {
         test_milli_mul_eq(241, 900001_900);
         test_milli_mul_eq(241, 400);
     }
+
+    #[test]
+    fn check_fee_estimate() {
+        let fee_policy = LinearFee::new(Milli::new(1, 1), Milli::new(0, 500));
+        assert_eq!(
+            fee_policy.estimate(3).unwrap().to_coin(),
+            Coin::new(3).unwrap()
+        );
+        let overflowing =
+            LinearFee::new(Milli::integral(1), Milli::integral(u64::max_value() / 1000));
+        assert!(overflowing.estimate(1024).is_err());
+    }
 }
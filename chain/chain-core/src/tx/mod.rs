@@ -9,17 +9,33 @@ pub mod witness;
 
 use std::fmt;
 
-use parity_scale_codec::{Decode, Encode, Error, Input};
+use parity_scale_codec::{Decode, Encode, Error, Input, Output};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-use self::data::Tx;
+use self::data::attribute::{
+    decode_tx_version, TxAttributes, OUTPUTS_COMMITMENT_TX_VERSION, TX_VERSION,
+};
+use self::data::{output_leaf, outputs_committed_txid, Tx};
 use self::witness::TxWitness;
-use crate::state::account::{DepositBondTx, StakedStateOpWitness, UnbondTx, WithdrawUnbondedTx};
+use crate::common::{MerkleTree, Proof, H256, HASH_SIZE_256};
+use crate::state::account::{
+    DelegateTx, DepositBondTx, NodeJoinRequestTx, RebondTx, StakedStateOpWitness, UnbondTx,
+    UndelegateTx, UnjailTx, WithdrawUnbondedTx,
+};
+use crate::state::governance::{ParamChangeProposalTx, ParamChangeVoteTx};
 use crate::state::tendermint::BlockHeight;
 use crate::tx::data::{txid_hash, TxId};
 use data::input::{TxoIndex, TxoPointer};
 use data::output::TxOut;
 
-const TX_AUX_SIZE: usize = 1024 * 60; // 60 KB
+/// Maximum size of an encoded transaction (`TxAux`)
+pub const TX_AUX_SIZE: usize = 1024 * 60; // 60 KB
+
+/// Format of the transactions sealed by the enclaves, stored in their additional (authenticated) data
+/// after the transaction ID: it's only absent in the transactions sealed before the format was versioned,
+/// which are in the legacy layout (see `LEGACY_TX_VERSION`)
+const SEALED_TX_FORMAT: u8 = 1;
 
 /// wrapper around transactions with outputs
 #[derive(Encode, Decode, Clone)]
@@ -39,13 +55,153 @@ impl TxWithOutputs {
         }
     }
 
-    /// returns the particular transaction type's id (currently blake2s_hash(SCALE-encoded tx))
+    /// returns the particular transaction type's id (see `OUTPUTS_COMMITMENT_TX_VERSION`)
     pub fn id(&self) -> TxId {
         match self {
             TxWithOutputs::Transfer(tx) => tx.id(),
             TxWithOutputs::StakeWithdraw(tx) => tx.id(),
         }
     }
+
+    /// returns the particular transaction type's attributes (incl. the view access policies)
+    pub fn attributes(&self) -> &TxAttributes {
+        match self {
+            TxWithOutputs::Transfer(tx) => &tx.attributes,
+            TxWithOutputs::StakeWithdraw(tx) => &tx.attributes,
+        }
+    }
+
+    /// returns the particular transaction type's hash of the encoding without the outputs
+    /// (see `OUTPUTS_COMMITMENT_TX_VERSION`)
+    pub fn body_hash(&self) -> H256 {
+        match self {
+            TxWithOutputs::Transfer(tx) => tx.body_hash(),
+            TxWithOutputs::StakeWithdraw(tx) => tx.body_hash(),
+        }
+    }
+
+    /// decodes a stored transaction: unlike `decode`, it also accepts the legacy transactions
+    /// (tagged with `LEGACY_TX_VERSION`), e.g. in the transaction bodies migrated from the legacy layout
+    pub fn decode_stored<I: Input>(input: &mut I) -> Result<Self, Error> {
+        match input.read_byte()? {
+            0 => Ok(TxWithOutputs::Transfer(Tx::decode_stored(input)?)),
+            1 => Ok(TxWithOutputs::StakeWithdraw(
+                WithdrawUnbondedTx::decode_stored(input)?,
+            )),
+            _ => Err("No such variant in enum TxWithOutputs".into()),
+        }
+    }
+
+    /// decodes a transaction in the legacy layout without the version tag, as stored before the format was versioned
+    /// (see `LEGACY_TX_VERSION`)
+    pub fn decode_legacy<I: Input>(input: &mut I) -> Result<Self, Error> {
+        match input.read_byte()? {
+            0 => Ok(TxWithOutputs::Transfer(Tx::decode_legacy(input)?)),
+            1 => Ok(TxWithOutputs::StakeWithdraw(
+                WithdrawUnbondedTx::decode_legacy(input)?,
+            )),
+            _ => Err("No such variant in enum TxWithOutputs".into()),
+        }
+    }
+
+    /// the additional (authenticated) data the transaction is sealed with by the enclaves:
+    /// its ID followed by the format of the sealed encoding
+    pub fn sealed_additional_data(&self) -> Vec<u8> {
+        let mut data = self.id().to_vec();
+        data.push(SEALED_TX_FORMAT);
+        data
+    }
+
+    /// decodes a transaction unsealed by the enclaves (in the format given by its additional data)
+    /// and checks it against the transaction ID in the additional data
+    pub fn decode_unsealed(additional_data: &[u8], mut data: &[u8]) -> Option<Self> {
+        if additional_data.len() < HASH_SIZE_256 {
+            return None;
+        }
+        let (txid, format) = additional_data.split_at(HASH_SIZE_256);
+        let tx = match format {
+            [] => TxWithOutputs::decode_legacy(&mut data),
+            [SEALED_TX_FORMAT] => TxWithOutputs::decode(&mut data),
+            _ => return None,
+        }
+        .ok()?;
+        if data.is_empty() && tx.id()[..] == txid[..] {
+            Some(tx)
+        } else {
+            None
+        }
+    }
+}
+
+/// Part of a transaction disclosed to a view key that was only granted access to some outputs (`TxAccess::Output`):
+/// inputs, attributes and other outputs are withheld.
+/// The id of the full transaction is included, so that its inclusion can be checked against the chain,
+/// and the disclosed outputs are linked to it with inclusion proofs in the outputs root the id commits to
+/// (so only the transactions from `OUTPUTS_COMMITMENT_TX_VERSION` can be redacted).
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RedactedTx {
+    /// id of the full transaction
+    pub txid: TxId,
+    /// hash of the full transaction's encoding without the outputs
+    pub body_hash: H256,
+    /// disclosed outputs with their indices in the full transaction
+    pub outputs: Vec<(TxoIndex, TxOut)>,
+    /// inclusion proofs of the disclosed outputs' leaves (see `output_leaf`) in the outputs root
+    pub proofs: Vec<Proof<Vec<u8>>>,
+}
+
+impl RedactedTx {
+    /// redacts the transaction to the outputs at the given indices (out of range indices are skipped).
+    /// Returns None if there is no output to disclose or the transaction doesn't commit to its outputs
+    /// (its version is before `OUTPUTS_COMMITMENT_TX_VERSION`).
+    pub fn new(tx: &TxWithOutputs, indices: &[TxoIndex]) -> Option<Self> {
+        if tx.attributes().version < OUTPUTS_COMMITMENT_TX_VERSION {
+            return None;
+        }
+        let leaves: Vec<Vec<u8>> = tx
+            .outputs()
+            .iter()
+            .enumerate()
+            .map(|(index, output)| output_leaf(index as TxoIndex, output))
+            .collect();
+        let tree = MerkleTree::new(leaves.clone());
+        let mut outputs = Vec::new();
+        let mut proofs = Vec::new();
+        for index in indices.iter() {
+            if let Some(leaf) = leaves.get(*index as usize) {
+                outputs.push((*index, tx.outputs()[*index as usize].clone()));
+                proofs.push(tree.generate_proof(leaf.clone())?);
+            }
+        }
+        if outputs.is_empty() {
+            None
+        } else {
+            Some(RedactedTx {
+                txid: tx.id(),
+                body_hash: tx.body_hash(),
+                outputs,
+                proofs,
+            })
+        }
+    }
+
+    /// checks that the disclosed outputs (at their indices) are committed to by the transaction id
+    pub fn verify(&self) -> bool {
+        let outputs_root = match self.proofs.first() {
+            Some(proof) => proof.root_hash(),
+            None => return false,
+        };
+        self.outputs.len() == self.proofs.len()
+            && outputs_committed_txid(&self.body_hash, &outputs_root) == self.txid
+            && self
+                .outputs
+                .iter()
+                .zip(self.proofs.iter())
+                .all(|((index, output), proof)| {
+                    proof.verify(&outputs_root) && *proof.value() == output_leaf(*index, output)
+                })
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
@@ -84,8 +240,11 @@ pub struct TxObfuscated {
     pub txpayload: Vec<u8>,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Encode)]
-/// TODO: custom Encode/Decode when data structures are finalized (for backwards/forwards compatibility, encoders/decoders should be able to work with old formats)
+#[derive(Debug, PartialEq, Eq, Clone)]
+/// Transaction as submitted to the chain.
+/// It's encoded with the format version (see `TX_VERSION`) as the leading byte,
+/// followed by the variant tag and fields
+/// (the ones submitted before the format was versioned can be read with `TxAux::decode_legacy`).
 pub enum TxAux {
     /// normal value transfer Tx with the vector of witnesses
     TransferTx {
@@ -108,6 +267,97 @@ pub enum TxAux {
         witness: StakedStateOpWitness,
         payload: TxObfuscated,
     },
+    /// Tx that registers a staked state as a council node (witness for account)
+    NodeJoinTx(NodeJoinRequestTx, StakedStateOpWitness),
+    /// Tx that releases a jailed staked state after the jail period (witness for account)
+    UnjailTx(UnjailTx, StakedStateOpWitness),
+    /// Tx that modifies account state -- moves some unbonded stake back into bonded (witness for account)
+    RebondStakeTx(RebondTx, StakedStateOpWitness),
+    /// Tx that modifies account state -- delegates some bonded stake to a council node (witness for account)
+    DelegateStakeTx(DelegateTx, StakedStateOpWitness),
+    /// Tx that modifies account state -- moves some delegated stake into unbonded (witness for account)
+    UndelegateStakeTx(UndelegateTx, StakedStateOpWitness),
+    /// Tx that proposes a network parameter change (witness for council node's account)
+    ParamChangeProposalTx(ParamChangeProposalTx, StakedStateOpWitness),
+    /// Tx that votes for a pending network parameter change (witness for council node's account)
+    ParamChangeVoteTx(ParamChangeVoteTx, StakedStateOpWitness),
+}
+
+impl Encode for TxAux {
+    fn encode_to<W: Output>(&self, dest: &mut W) {
+        dest.push_byte(TX_VERSION);
+        match self {
+            TxAux::TransferTx {
+                txid,
+                inputs,
+                no_of_outputs,
+                payload,
+            } => {
+                dest.push_byte(0);
+                txid.encode_to(dest);
+                inputs.encode_to(dest);
+                no_of_outputs.encode_to(dest);
+                payload.encode_to(dest);
+            }
+            TxAux::DepositStakeTx { tx, payload } => {
+                dest.push_byte(1);
+                tx.encode_to(dest);
+                payload.encode_to(dest);
+            }
+            TxAux::UnbondStakeTx(tx, witness) => {
+                dest.push_byte(2);
+                tx.encode_to(dest);
+                witness.encode_to(dest);
+            }
+            TxAux::WithdrawUnbondedStakeTx {
+                txid,
+                no_of_outputs,
+                witness,
+                payload,
+            } => {
+                dest.push_byte(3);
+                txid.encode_to(dest);
+                no_of_outputs.encode_to(dest);
+                witness.encode_to(dest);
+                payload.encode_to(dest);
+            }
+            TxAux::NodeJoinTx(tx, witness) => {
+                dest.push_byte(4);
+                tx.encode_to(dest);
+                witness.encode_to(dest);
+            }
+            TxAux::UnjailTx(tx, witness) => {
+                dest.push_byte(5);
+                tx.encode_to(dest);
+                witness.encode_to(dest);
+            }
+            TxAux::RebondStakeTx(tx, witness) => {
+                dest.push_byte(6);
+                tx.encode_to(dest);
+                witness.encode_to(dest);
+            }
+            TxAux::DelegateStakeTx(tx, witness) => {
+                dest.push_byte(7);
+                tx.encode_to(dest);
+                witness.encode_to(dest);
+            }
+            TxAux::UndelegateStakeTx(tx, witness) => {
+                dest.push_byte(8);
+                tx.encode_to(dest);
+                witness.encode_to(dest);
+            }
+            TxAux::ParamChangeProposalTx(tx, witness) => {
+                dest.push_byte(9);
+                tx.encode_to(dest);
+                witness.encode_to(dest);
+            }
+            TxAux::ParamChangeVoteTx(tx, witness) => {
+                dest.push_byte(10);
+                tx.encode_to(dest);
+                witness.encode_to(dest);
+            }
+        }
+    }
 }
 
 impl Decode for TxAux {
@@ -120,6 +370,8 @@ impl Decode for TxAux {
             return Err("Input too large".into());
         }
 
+        // all variants have had the same layout since version 1
+        decode_tx_version(input)?;
         match input.read_byte()? {
             0 => Ok(TxAux::TransferTx {
                 txid: TxId::decode(input)?,
@@ -141,6 +393,34 @@ impl Decode for TxAux {
                 witness: StakedStateOpWitness::decode(input)?,
                 payload: TxObfuscated::decode(input)?,
             }),
+            4 => Ok(TxAux::NodeJoinTx(
+                NodeJoinRequestTx::decode(input)?,
+                StakedStateOpWitness::decode(input)?,
+            )),
+            5 => Ok(TxAux::UnjailTx(
+                UnjailTx::decode(input)?,
+                StakedStateOpWitness::decode(input)?,
+            )),
+            6 => Ok(TxAux::RebondStakeTx(
+                RebondTx::decode(input)?,
+                StakedStateOpWitness::decode(input)?,
+            )),
+            7 => Ok(TxAux::DelegateStakeTx(
+                DelegateTx::decode(input)?,
+                StakedStateOpWitness::decode(input)?,
+            )),
+            8 => Ok(TxAux::UndelegateStakeTx(
+                UndelegateTx::decode(input)?,
+                StakedStateOpWitness::decode(input)?,
+            )),
+            9 => Ok(TxAux::ParamChangeProposalTx(
+                ParamChangeProposalTx::decode(input)?,
+                StakedStateOpWitness::decode(input)?,
+            )),
+            10 => Ok(TxAux::ParamChangeVoteTx(
+                ParamChangeVoteTx::decode(input)?,
+                StakedStateOpWitness::decode(input)?,
+            )),
             _ => Err("No such variant in enum TxAux".into()),
         }
     }
@@ -154,6 +434,43 @@ pub trait TransactionId: Encode {
 }
 
 impl TxAux {
+    /// decodes a transaction in the legacy layout without the version tag, as submitted before the format was versioned
+    /// (e.g. in the blocks before the upgrade; see `LEGACY_TX_VERSION`)
+    pub fn decode_legacy<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let size = input
+            .remaining_len()?
+            .ok_or_else(|| "Unable to calculate size of input")?;
+
+        if size > TX_AUX_SIZE {
+            return Err("Input too large".into());
+        }
+
+        // only the first four variants existed
+        match input.read_byte()? {
+            0 => Ok(TxAux::TransferTx {
+                txid: TxId::decode(input)?,
+                inputs: <Vec<TxoPointer>>::decode(input)?,
+                no_of_outputs: TxoIndex::decode(input)?,
+                payload: TxObfuscated::decode(input)?,
+            }),
+            1 => Ok(TxAux::DepositStakeTx {
+                tx: DepositBondTx::decode_legacy(input)?,
+                payload: TxObfuscated::decode(input)?,
+            }),
+            2 => Ok(TxAux::UnbondStakeTx(
+                UnbondTx::decode_legacy(input)?,
+                StakedStateOpWitness::decode(input)?,
+            )),
+            3 => Ok(TxAux::WithdrawUnbondedStakeTx {
+                txid: TxId::decode(input)?,
+                no_of_outputs: TxoIndex::decode(input)?,
+                witness: StakedStateOpWitness::decode(input)?,
+                payload: TxObfuscated::decode(input)?,
+            }),
+            _ => Err("No such variant in enum TxAux".into()),
+        }
+    }
+
     /// retrieves a TX ID (currently blake2s(scale_codec_bytes(tx)))
     pub fn tx_id(&self) -> TxId {
         match self {
@@ -161,6 +478,13 @@ impl TxAux {
             TxAux::DepositStakeTx { tx, .. } => tx.id(),
             TxAux::UnbondStakeTx(tx, _) => tx.id(),
             TxAux::WithdrawUnbondedStakeTx { txid, .. } => *txid,
+            TxAux::NodeJoinTx(tx, _) => tx.id(),
+            TxAux::UnjailTx(tx, _) => tx.id(),
+            TxAux::RebondStakeTx(tx, _) => tx.id(),
+            TxAux::DelegateStakeTx(tx, _) => tx.id(),
+            TxAux::UndelegateStakeTx(tx, _) => tx.id(),
+            TxAux::ParamChangeProposalTx(tx, _) => tx.id(),
+            TxAux::ParamChangeVoteTx(tx, _) => tx.id(),
         }
     }
 }
@@ -191,6 +515,13 @@ impl fmt::Display for TxAux {
                 )?;
                 writeln!(f, "witness: {:?}\n", witness)
             }
+            TxAux::NodeJoinTx(tx, witness) => display_tx_witness(f, tx, witness),
+            TxAux::UnjailTx(tx, witness) => display_tx_witness(f, tx, witness),
+            TxAux::RebondStakeTx(tx, witness) => display_tx_witness(f, tx, witness),
+            TxAux::DelegateStakeTx(tx, witness) => display_tx_witness(f, tx, witness),
+            TxAux::UndelegateStakeTx(tx, witness) => display_tx_witness(f, tx, witness),
+            TxAux::ParamChangeProposalTx(tx, witness) => display_tx_witness(f, tx, witness),
+            TxAux::ParamChangeVoteTx(tx, witness) => display_tx_witness(f, tx, witness),
         }
     }
 }
@@ -199,11 +530,17 @@ impl fmt::Display for TxAux {
 pub mod tests {
     use super::*;
     use crate::common::MerkleTree;
+    use crate::init::address::RedeemAddress;
     use crate::init::coin::Coin;
+    use crate::state::account::{StakedStateAddress, StakedStateOpAttributes};
+    use crate::state::governance::NetworkParameterChange;
+    use crate::state::tendermint::TendermintValidatorPubKey;
     use crate::tx::data::access::{TxAccess, TxAccessPolicy};
     use crate::tx::data::address::ExtendedAddr;
+    use crate::tx::data::attribute::{TxAttributes, LEGACY_TX_VERSION};
     use crate::tx::data::input::TxoPointer;
     use crate::tx::data::output::TxOut;
+    use crate::tx::data::outputs_root;
     use crate::tx::witness::tree::RawPubkey;
     use crate::tx::witness::TxInWitness;
     use parity_scale_codec::{Decode, Encode};
@@ -244,4 +581,361 @@ pub mod tests {
         let decoded = PlainTxAux::decode(&mut data).expect("decode tx aux");
         assert_eq!(txa, decoded);
     }
+
+    /// bytes as produced by the encoders before the format was versioned (attributes last, without the version)
+    /// -- these must keep decoding to the same tx ids and round-trip through the current encoding
+    #[test]
+    fn legacy_encoding_round_trips() {
+        // one input, no outputs, chain id, no view keys
+        let mut tx_legacy = vec![0x04];
+        tx_legacy.extend_from_slice(&[0x01; 32]);
+        tx_legacy.extend_from_slice(&[0x01, 0x00, 0x00, 0xab, 0x00]);
+        let tx = Tx::decode_legacy(&mut tx_legacy.as_slice()).expect("decode legacy tx");
+        assert_eq!(tx.attributes.version, LEGACY_TX_VERSION);
+        assert_eq!(tx.attributes.chain_hex_id, 0xab);
+        assert_eq!(tx.inputs, vec![TxoPointer::new([0x01; 32].into(), 1)]);
+        assert!(tx.outputs.is_empty());
+        assert_eq!(tx.id(), txid_hash(&tx_legacy));
+        let encoded = tx.encode();
+        assert_eq!(encoded[0], LEGACY_TX_VERSION);
+        assert_eq!(&encoded[1..], &tx_legacy[..]);
+        // legacy transactions can only be decoded from the storage
+        assert!(Tx::decode(&mut encoded.as_slice()).is_err());
+        let decoded = Tx::decode_stored(&mut encoded.as_slice()).expect("decode tagged legacy tx");
+        assert_eq!(decoded, tx);
+        assert_eq!(decoded.id(), txid_hash(&tx_legacy));
+        // the same transaction in the current format is a different one
+        let current = Tx::new_with(tx.inputs.clone(), vec![], TxAttributes::new(0xab));
+        assert_ne!(current.id(), tx.id());
+
+        // transfer variant, legacy tx
+        let mut stored_legacy = vec![0u8];
+        stored_legacy.extend_from_slice(&tx_legacy);
+        let stored = TxWithOutputs::decode_legacy(&mut stored_legacy.as_slice())
+            .expect("decode legacy tx with outputs");
+        assert_eq!(stored.id(), tx.id());
+        // sealed before the format was versioned (only the tx id in the additional data)
+        let unsealed = TxWithOutputs::decode_unsealed(&tx.id(), &stored_legacy)
+            .expect("decode legacy sealed tx");
+        assert_eq!(unsealed.id(), tx.id());
+        let resealed =
+            TxWithOutputs::decode_unsealed(&stored.sealed_additional_data(), &stored.encode())
+                .expect("decode sealed tx");
+        assert_eq!(resealed.id(), tx.id());
+        let restored = TxWithOutputs::decode_stored(&mut stored.encode().as_slice())
+            .expect("decode stored tx");
+        assert_eq!(restored.id(), tx.id());
+        assert!(TxWithOutputs::decode(&mut stored.encode().as_slice()).is_err());
+        assert!(TxWithOutputs::decode_unsealed(&[0u8; 32], &stored_legacy).is_none());
+
+        // value, nonce, chain id
+        let mut unbond_legacy = Vec::new();
+        unbond_legacy.extend_from_slice(&1u64.to_le_bytes());
+        unbond_legacy.extend_from_slice(&2u64.to_le_bytes());
+        unbond_legacy.push(0xab);
+        let unbond = UnbondTx::decode_legacy(&mut unbond_legacy.as_slice())
+            .expect("decode legacy unbond tx");
+        assert_eq!(unbond.attributes.version, LEGACY_TX_VERSION);
+        assert_eq!(unbond.value, Coin::unit());
+        assert_eq!(unbond.nonce, 2);
+        assert_eq!(unbond.id(), txid_hash(&unbond_legacy));
+
+        // one input, address, chain id
+        let mut deposit_legacy = vec![0x04];
+        deposit_legacy.extend_from_slice(&[0x02; 32]);
+        deposit_legacy.extend_from_slice(&[0x00, 0x00, 0x00]);
+        deposit_legacy.extend_from_slice(&[0xdd; 20]);
+        deposit_legacy.push(0xab);
+        let deposit = DepositBondTx::decode_legacy(&mut deposit_legacy.as_slice())
+            .expect("decode legacy deposit tx");
+        assert_eq!(
+            deposit.to_staked_account,
+            StakedStateAddress::BasicRedeem(RedeemAddress::from([0xdd; 20]))
+        );
+        assert_eq!(deposit.id(), txid_hash(&deposit_legacy));
+        let encoded = deposit.encode();
+        assert_eq!(
+            DepositBondTx::decode_stored(&mut encoded.as_slice())
+                .expect("decode tagged legacy deposit"),
+            deposit
+        );
+        assert!(DepositBondTx::decode(&mut encoded.as_slice()).is_err());
+
+        // nonce, one output, chain id, no view keys
+        let output = TxOut::new(ExtendedAddr::OrTree([0xbb; 32].into()), Coin::unit());
+        let mut withdraw_legacy = 3u64.to_le_bytes().to_vec();
+        withdraw_legacy.extend_from_slice(&vec![output.clone()].encode());
+        withdraw_legacy.extend_from_slice(&[0xab, 0x00]);
+        let withdraw = WithdrawUnbondedTx::decode_legacy(&mut withdraw_legacy.as_slice())
+            .expect("decode legacy withdraw tx");
+        assert_eq!(withdraw.outputs, vec![output]);
+        assert_eq!(withdraw.id(), txid_hash(&withdraw_legacy));
+        let encoded = withdraw.encode();
+        assert_eq!(
+            WithdrawUnbondedTx::decode_stored(&mut encoded.as_slice())
+                .expect("decode tagged legacy withdraw tx"),
+            withdraw
+        );
+        assert!(WithdrawUnbondedTx::decode(&mut encoded.as_slice()).is_err());
+
+        // variant tag, legacy unbond tx, witness
+        let txaux = unbond_tx_aux();
+        let witness = match &txaux {
+            TxAux::UnbondStakeTx(_, witness) => witness.clone(),
+            _ => unreachable!(),
+        };
+        let mut txaux_legacy = vec![2u8];
+        txaux_legacy.extend_from_slice(&unbond_legacy);
+        txaux_legacy.extend_from_slice(&witness.encode());
+        let decoded =
+            TxAux::decode_legacy(&mut txaux_legacy.as_slice()).expect("decode legacy tx aux");
+        assert_eq!(decoded, TxAux::UnbondStakeTx(unbond.clone(), witness));
+        assert_eq!(decoded.tx_id(), txid_hash(&unbond_legacy));
+        // legacy transactions can't be submitted anymore
+        let encoded = decoded.encode();
+        assert_eq!(encoded[0], TX_VERSION);
+        assert!(TxAux::decode(&mut encoded.as_slice()).is_err());
+        let stored = unbond.encode();
+        assert_eq!(
+            UnbondTx::decode_stored(&mut stored.as_slice()).expect("decode tagged legacy unbond"),
+            unbond
+        );
+        assert!(UnbondTx::decode(&mut stored.as_slice()).is_err());
+    }
+
+    fn unbond_tx_aux() -> TxAux {
+        let secp = Secp256k1::new();
+        let sk = SecretKey::from_slice(&[0xcc; 32][..]).expect("secret key");
+        let tx = UnbondTx::new(Coin::unit(), 2, StakedStateOpAttributes::new(0xab));
+        let msg = Message::from_slice(&tx.id()).expect("msg");
+        let witness = StakedStateOpWitness::new(secp.sign_recoverable(&msg, &sk));
+        TxAux::UnbondStakeTx(tx, witness)
+    }
+
+    fn versioned_tx_auxes() -> Vec<TxAux> {
+        let mut tx = Tx::new();
+        tx.add_input(TxoPointer::new([0x01; 32].into(), 1));
+        tx.add_output(TxOut::new(
+            ExtendedAddr::OrTree([0xbb; 32].into()),
+            Coin::unit(),
+        ));
+        let payload = TxObfuscated {
+            key_from: 0,
+            nonce: [0u8; 12],
+            txpayload: vec![0xcc; 10],
+        };
+        let deposit = DepositBondTx::new(
+            vec![TxoPointer::new([0x02; 32].into(), 0)],
+            StakedStateAddress::BasicRedeem(RedeemAddress::from([0xdd; 20])),
+            StakedStateOpAttributes::new(0xab),
+        );
+        let unbond = unbond_tx_aux();
+        let witness = match &unbond {
+            TxAux::UnbondStakeTx(_, witness) => witness.clone(),
+            _ => unreachable!(),
+        };
+        let withdraw = WithdrawUnbondedTx::new(1, tx.outputs.clone(), TxAttributes::new(0xab));
+        let node_join = NodeJoinRequestTx::new(
+            3,
+            StakedStateAddress::BasicRedeem(RedeemAddress::from([0xdd; 20])),
+            TendermintValidatorPubKey::Ed25519([0xee; 32]),
+            StakedStateOpAttributes::new(0xab),
+        );
+        vec![
+            TxAux::TransferTx {
+                txid: tx.id(),
+                inputs: tx.inputs.clone(),
+                no_of_outputs: tx.outputs.len() as TxoIndex,
+                payload: payload.clone(),
+            },
+            TxAux::DepositStakeTx {
+                tx: deposit,
+                payload: payload.clone(),
+            },
+            unbond,
+            TxAux::WithdrawUnbondedStakeTx {
+                txid: withdraw.id(),
+                no_of_outputs: withdraw.outputs.len() as TxoIndex,
+                witness: witness.clone(),
+                payload,
+            },
+            TxAux::NodeJoinTx(node_join, witness.clone()),
+            TxAux::UnjailTx(
+                UnjailTx::new(
+                    4,
+                    StakedStateAddress::BasicRedeem(RedeemAddress::from([0xdd; 20])),
+                    StakedStateOpAttributes::new(0xab),
+                ),
+                witness.clone(),
+            ),
+            TxAux::RebondStakeTx(
+                RebondTx::new(Coin::unit(), 5, StakedStateOpAttributes::new(0xab)),
+                witness.clone(),
+            ),
+            TxAux::DelegateStakeTx(
+                DelegateTx::new(
+                    Coin::unit(),
+                    6,
+                    StakedStateAddress::BasicRedeem(RedeemAddress::from([0xdd; 20])),
+                    StakedStateOpAttributes::new(0xab),
+                ),
+                witness.clone(),
+            ),
+            TxAux::UndelegateStakeTx(
+                UndelegateTx::new(
+                    Coin::unit(),
+                    7,
+                    StakedStateAddress::BasicRedeem(RedeemAddress::from([0xdd; 20])),
+                    StakedStateOpAttributes::new(0xab),
+                ),
+                witness.clone(),
+            ),
+            TxAux::ParamChangeProposalTx(
+                ParamChangeProposalTx::new(
+                    8,
+                    StakedStateAddress::BasicRedeem(RedeemAddress::from([0xdd; 20])),
+                    NetworkParameterChange::UnbondingPeriod(60),
+                    100,
+                    StakedStateOpAttributes::new(0xab),
+                ),
+                witness.clone(),
+            ),
+            TxAux::ParamChangeVoteTx(
+                ParamChangeVoteTx::new(
+                    9,
+                    StakedStateAddress::BasicRedeem(RedeemAddress::from([0xdd; 20])),
+                    [0x0d; 32],
+                    StakedStateOpAttributes::new(0xab),
+                ),
+                witness,
+            ),
+        ]
+    }
+
+    #[test]
+    fn encoding_starts_with_version() {
+        for txaux in versioned_tx_auxes() {
+            let encoded = txaux.encode();
+            assert_eq!(encoded[0], TX_VERSION);
+            let decoded = TxAux::decode(&mut encoded.as_slice()).expect("decode tx aux");
+            assert_eq!(txaux, decoded);
+        }
+        let attributes = TxAttributes::new(0xab);
+        assert_eq!(
+            Tx::new_with(vec![], vec![], attributes.clone()).encode()[0],
+            TX_VERSION
+        );
+        assert_eq!(
+            WithdrawUnbondedTx::new(0, vec![], attributes).encode()[0],
+            TX_VERSION
+        );
+        let attributes = StakedStateOpAttributes::new(0xab);
+        assert_eq!(
+            UnbondTx::new(Coin::unit(), 0, attributes.clone()).encode()[0],
+            TX_VERSION
+        );
+        let address = StakedStateAddress::BasicRedeem(RedeemAddress::from([0xdd; 20]));
+        assert_eq!(
+            DepositBondTx::new(vec![], address, attributes).encode()[0],
+            TX_VERSION
+        );
+    }
+
+    #[test]
+    fn unknown_versions_are_rejected() {
+        for txaux in versioned_tx_auxes() {
+            for version in [0, TX_VERSION + 1].iter() {
+                let mut encoded = txaux.encode();
+                encoded[0] = *version;
+                assert!(TxAux::decode(&mut encoded.as_slice()).is_err());
+            }
+        }
+        let mut tx = Tx::new().encode();
+        tx[0] = TX_VERSION + 1;
+        assert!(Tx::decode(&mut tx.as_slice()).is_err());
+        let mut unbond =
+            UnbondTx::new(Coin::unit(), 0, StakedStateOpAttributes::new(0xab)).encode();
+        unbond[0] = TX_VERSION + 1;
+        assert!(UnbondTx::decode(&mut unbond.as_slice()).is_err());
+    }
+
+    /// bytes as produced by version 1 encoders -- these must keep decoding
+    /// (to the same tx id) after the transaction formats are extended
+    #[test]
+    fn version_1_encoding_decodes() {
+        // version, chain id, no view keys, one input, no outputs
+        let mut tx_v1 = vec![1u8, 0xab, 0x00, 0x04];
+        tx_v1.extend_from_slice(&[0x01; 32]);
+        tx_v1.extend_from_slice(&[0x01, 0x00, 0x00]);
+        let tx = Tx::decode(&mut tx_v1.as_slice()).expect("decode v1 tx");
+        assert_eq!(tx.attributes.version, 1);
+        assert_eq!(tx.attributes.chain_hex_id, 0xab);
+        assert_eq!(tx.inputs, vec![TxoPointer::new([0x01; 32].into(), 1)]);
+        assert!(tx.outputs.is_empty());
+        assert_eq!(tx.id(), txid_hash(&tx_v1));
+
+        // version, chain id, value, nonce
+        let mut unbond_v1 = vec![1u8, 0xab];
+        unbond_v1.extend_from_slice(&1u64.to_le_bytes());
+        unbond_v1.extend_from_slice(&2u64.to_le_bytes());
+        let unbond = UnbondTx::decode(&mut unbond_v1.as_slice()).expect("decode v1 unbond tx");
+        let mut attributes = StakedStateOpAttributes::new(0xab);
+        attributes.version = 1;
+        assert_eq!(unbond, UnbondTx::new(Coin::unit(), 2, attributes));
+        assert_eq!(unbond.id(), txid_hash(&unbond_v1));
+
+        // version, variant tag, unbond tx, witness
+        let witness = match unbond_tx_aux() {
+            TxAux::UnbondStakeTx(_, witness) => witness,
+            _ => unreachable!(),
+        };
+        let mut txaux_v1 = vec![1u8, 2];
+        txaux_v1.extend_from_slice(&unbond_v1);
+        txaux_v1.extend_from_slice(&witness.encode());
+        let decoded = TxAux::decode(&mut txaux_v1.as_slice()).expect("decode v1 tx aux");
+        assert_eq!(TxAux::UnbondStakeTx(unbond, witness), decoded);
+    }
+
+    #[test]
+    fn outputs_are_committed_to_by_txid() {
+        let outputs: Vec<TxOut> = (0..3)
+            .map(|i| TxOut::new(ExtendedAddr::OrTree([i; 32]), Coin::unit()))
+            .collect();
+        let tx = Tx::new_with(
+            vec![TxoPointer::new([0x01; 32].into(), 1)],
+            outputs.clone(),
+            TxAttributes::new(0xab),
+        );
+        assert_eq!(
+            tx.id(),
+            outputs_committed_txid(&tx.body_hash(), &outputs_root(&outputs))
+        );
+        let mut changed = tx.clone();
+        changed.outputs[2].value = Coin::zero();
+        assert_ne!(tx.id(), changed.id());
+        let mut changed = tx.clone();
+        changed.inputs[0].index = 2;
+        assert_ne!(tx.id(), changed.id());
+
+        let withdraw = WithdrawUnbondedTx::new(3, outputs.clone(), TxAttributes::new(0xab));
+        assert_eq!(
+            withdraw.id(),
+            outputs_committed_txid(&withdraw.body_hash(), &outputs_root(&outputs))
+        );
+
+        let redacted =
+            RedactedTx::new(&TxWithOutputs::Transfer(tx.clone()), &[0, 2, 5]).expect("redacted tx");
+        assert_eq!(redacted.txid, tx.id());
+        assert_eq!(
+            redacted.outputs,
+            vec![(0, outputs[0].clone()), (2, outputs[2].clone())]
+        );
+        assert!(redacted.verify());
+        let mut swapped = redacted.clone();
+        swapped.proofs.swap(0, 1);
+        assert!(!swapped.verify());
+        let mut other_body = redacted;
+        other_body.body_hash = [0x02; 32];
+        assert!(!other_body.verify());
+    }
 }
@@ -7,7 +7,8 @@ use std::prelude::v1::Vec;
 // TODO: switch to normal signatures + explicit public key
 use secp256k1::{self, recovery::RecoverableSignature, schnorrsig::SchnorrSignature};
 
-use crate::common::Proof;
+use crate::common::{Proof, H256};
+use crate::tx::data::htlc::{HashLockPreimage, HashTimeLock};
 use crate::tx::witness::tree::{RawPubkey, RawSignature};
 
 pub type EcdsaSignature = RecoverableSignature;
@@ -20,6 +21,9 @@ pub type EcdsaSignature = RecoverableSignature;
 /// 32 + 33 + 660 = 725 bytes. So, each witness will be around 64 + 725 = 789 bytes == 800 bytes
 ///
 /// Assuming maximum 64 witnesses are allowed, maximum witness size will be 800 * 64 = 51200
+///
+/// Hash-time-locked witnesses additionally contain the contract (104 bytes) and the preimage (32 bytes),
+/// but their claim/refund addresses are expected to be small trees.
 const MAX_WITNESS_SIZE: usize = 51200; // 800 bytes for each of 64 witnesses = 51200 bytes
 
 /// A transaction witness is a vector of input witnesses
@@ -81,6 +85,17 @@ impl ::std::ops::DerefMut for TxWitness {
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum TxInWitness {
     TreeSig(SchnorrSignature, Proof<RawPubkey>),
+    /// spends a hash-time-locked output before the timeout:
+    /// signature + proof for the claim root, the contract and the hash lock preimage
+    HashTimeLockClaim(
+        SchnorrSignature,
+        Proof<RawPubkey>,
+        HashTimeLock,
+        HashLockPreimage,
+    ),
+    /// spends a hash-time-locked output after the timeout:
+    /// signature + proof for the refund root and the contract
+    HashTimeLockRefund(SchnorrSignature, Proof<RawPubkey>, HashTimeLock),
 }
 
 impl fmt::Display for TxInWitness {
@@ -97,12 +112,31 @@ impl Encode for TxInWitness {
                 schnorrsig.serialize_default().encode_to(dest);
                 proof.encode_to(dest);
             }
+            TxInWitness::HashTimeLockClaim(ref schnorrsig, ref proof, ref htlc, ref preimage) => {
+                dest.push_byte(1);
+                schnorrsig.serialize_default().encode_to(dest);
+                proof.encode_to(dest);
+                htlc.encode_to(dest);
+                preimage.encode_to(dest);
+            }
+            TxInWitness::HashTimeLockRefund(ref schnorrsig, ref proof, ref htlc) => {
+                dest.push_byte(2);
+                schnorrsig.serialize_default().encode_to(dest);
+                proof.encode_to(dest);
+                htlc.encode_to(dest);
+            }
         }
     }
 
     fn size_hint(&self) -> usize {
         match self {
             TxInWitness::TreeSig(_, ref proof) => 65 + proof.size_hint(),
+            TxInWitness::HashTimeLockClaim(_, ref proof, ref htlc, _) => {
+                65 + proof.size_hint() + htlc.size_hint() + 32
+            }
+            TxInWitness::HashTimeLockRefund(_, ref proof, ref htlc) => {
+                65 + proof.size_hint() + htlc.size_hint()
+            }
         }
     }
 }
@@ -112,13 +146,31 @@ impl Decode for TxInWitness {
         let tag = input.read_byte()?;
         match tag {
             0 => {
-                let raw_sig = RawSignature::decode(input)?;
-                let schnorrsig = SchnorrSignature::from_default(&raw_sig)
-                    .map_err(|_| Error::from("Unable to parse schnorr signature"))?;
-                let proof = Proof::decode(input)?;
+                let (schnorrsig, proof) = decode_tree_sig(input)?;
                 Ok(TxInWitness::TreeSig(schnorrsig, proof))
             }
+            1 => {
+                let (schnorrsig, proof) = decode_tree_sig(input)?;
+                let htlc = HashTimeLock::decode(input)?;
+                let preimage = H256::decode(input)?;
+                Ok(TxInWitness::HashTimeLockClaim(
+                    schnorrsig, proof, htlc, preimage,
+                ))
+            }
+            2 => {
+                let (schnorrsig, proof) = decode_tree_sig(input)?;
+                let htlc = HashTimeLock::decode(input)?;
+                Ok(TxInWitness::HashTimeLockRefund(schnorrsig, proof, htlc))
+            }
             _ => Err(Error::from("Invalid tag")),
         }
     }
 }
+
+fn decode_tree_sig<I: Input>(input: &mut I) -> Result<(SchnorrSignature, Proof<RawPubkey>), Error> {
+    let raw_sig = RawSignature::decode(input)?;
+    let schnorrsig = SchnorrSignature::from_default(&raw_sig)
+        .map_err(|_| Error::from("Unable to parse schnorr signature"))?;
+    let proof = Proof::decode(input)?;
+    Ok((schnorrsig, proof))
+}
@@ -1,8 +1,12 @@
 use chain_core::init::address::RedeemAddress;
 use chain_core::init::coin::Coin;
 use chain_core::init::config::{
-    AccountType, InitConfig, InitNetworkParameters, InitialValidator, ValidatorKeyType,
+    AccountType, InitConfig, InitNetworkParameters, InitialValidator, RewardsParameters,
+    SlashRatio, SlashingParameters, ValidatorKeyType, VestingSchedule, MAX_INITIAL_UTXOS,
 };
+use chain_core::state::account::StakedStateAddress;
+use chain_core::tx::data::address::ExtendedAddr;
+use chain_core::tx::data::output::TxOut;
 use chain_core::tx::fee::{LinearFee, Milli};
 use serde::Deserialize;
 use std::collections::BTreeMap;
@@ -20,8 +24,7 @@ pub struct ERC20Holder {
     balance: String,
 }
 
-#[test]
-fn test_verify_test_example_snapshot() {
+fn get_example_config(vesting: BTreeMap<RedeemAddress, VestingSchedule>) -> InitConfig {
     let distribution_txt = include_str!("distribution.json");
     let distribution: Distribution = serde_json::from_str(&distribution_txt).unwrap();
     let mut dist: BTreeMap<RedeemAddress, (Coin, AccountType)> = BTreeMap::new();
@@ -43,6 +46,17 @@ fn test_verify_test_example_snapshot() {
         initial_fee_policy: fee_policy,
         required_council_node_stake: Coin::new(50_000_000_0000_0000).unwrap(),
         unbonding_period: 86400,
+        slashing_config: SlashingParameters {
+            jail_duration: 86400,
+            byzantine_slash_ratio: SlashRatio::new(Milli::new(0, 200)).unwrap(),
+            liveness_slash_ratio: SlashRatio::new(Milli::new(0, 10)).unwrap(),
+            block_signing_window: 100,
+            missed_block_threshold: 50,
+        },
+        rewards_config: RewardsParameters {
+            epoch_length: 86400,
+            reward_rate: Milli::new(0, 100),
+        },
     };
     let launch_incentive_from = "0x35f517cab9a37bc31091c2f155d965af84e0bc85"
         .parse::<RedeemAddress>()
@@ -60,14 +74,128 @@ fn test_verify_test_example_snapshot() {
         consensus_pubkey_type: ValidatorKeyType::Ed25519,
         consensus_pubkey_b64: "EIosObgfONUsnWCBGRpFlRFq5lSxjGIChRlVrVWVkcE=".to_string(),
     };
-    let config = InitConfig::new(
+    InitConfig::new(
         dist,
         launch_incentive_from,
         launch_incentive_to,
         long_term_incentive,
         params,
         vec![example_validator],
-    );
+        vesting,
+        Vec::new(),
+    )
+}
+
+#[test]
+fn test_verify_test_example_snapshot() {
+    let config = get_example_config(BTreeMap::new());
     let result = config.validate_config_get_genesis(0);
     assert!(result.is_ok());
 }
+
+#[test]
+fn test_vesting_schedule_locks_genesis_stake() {
+    let address = "0xe17ee7b3c676701c66b395a35f0df4c2276a344e"
+        .parse::<RedeemAddress>()
+        .unwrap();
+    let mut vesting = BTreeMap::new();
+    vesting.insert(
+        address,
+        VestingSchedule {
+            cliff_period: 100,
+            vesting_period: 1000,
+        },
+    );
+    let config = get_example_config(vesting);
+    let genesis_time = 10;
+    let (accounts, _, _) = config
+        .validate_config_get_genesis(genesis_time)
+        .unwrap_or_else(|_| panic!("valid config"));
+    let account = accounts
+        .iter()
+        .find(|account| account.address == StakedStateAddress::from(address))
+        .expect("vested account");
+    let amount = Coin::new(103_157_774_129_240).unwrap();
+    assert_eq!(account.bonded, amount);
+    assert!(account.unbonding.is_empty());
+    assert_eq!(account.locked(genesis_time + 99), amount);
+    assert_eq!(
+        account.locked(genesis_time + 500),
+        Coin::new(51_578_887_064_620).unwrap()
+    );
+    assert_eq!(account.locked(genesis_time + 1000), Coin::zero());
+}
+
+#[test]
+fn test_invalid_vesting_schedule() {
+    let contract = "0x35f517cab9a37bc31091c2f155d965af84e0bc85"
+        .parse::<RedeemAddress>()
+        .unwrap();
+    let account = "0xe17ee7b3c676701c66b395a35f0df4c2276a344e"
+        .parse::<RedeemAddress>()
+        .unwrap();
+    let invalid = vec![
+        (
+            contract,
+            VestingSchedule {
+                cliff_period: 0,
+                vesting_period: 1000,
+            },
+        ),
+        (
+            account,
+            VestingSchedule {
+                cliff_period: 0,
+                vesting_period: 0,
+            },
+        ),
+        (
+            account,
+            VestingSchedule {
+                cliff_period: 1001,
+                vesting_period: 1000,
+            },
+        ),
+    ];
+    for (address, schedule) in invalid {
+        let mut vesting = BTreeMap::new();
+        vesting.insert(address, schedule);
+        let config = get_example_config(vesting);
+        assert!(config.validate_config_get_genesis(0).is_err());
+    }
+}
+
+#[test]
+fn test_initial_utxos_count_towards_total_supply() {
+    let address = "0x34b07f4974db599691bec2d3634f2f57c2086c01"
+        .parse::<RedeemAddress>()
+        .unwrap();
+    let utxo_address = ExtendedAddr::OrTree([0x11; 32]);
+    let mut config = get_example_config(BTreeMap::new());
+    // the UTXOs would go over the total supply
+    config.initial_utxos = vec![TxOut::new(utxo_address.clone(), Coin::unit())];
+    assert!(config.validate_config_get_genesis(0).is_err());
+    // the distribution entry's amount is moved to the UTXOs
+    let (amount, _) = config.distribution.remove(&address).expect("EOA");
+    let half = Coin::new(u64::from(amount) / 2).unwrap();
+    config.initial_utxos = vec![
+        TxOut::new(utxo_address.clone(), half),
+        TxOut::new_with_timelock(utxo_address.clone(), (amount - half).unwrap(), 100),
+    ];
+    assert!(config.validate_config_get_genesis(0).is_ok());
+    let genesis_tx = config.get_genesis_tx().expect("genesis tx");
+    assert!(genesis_tx.inputs.is_empty());
+    assert_eq!(genesis_tx.outputs, config.initial_utxos);
+    // zero-value or too many UTXOs are invalid
+    config.initial_utxos = vec![
+        TxOut::new(utxo_address.clone(), Coin::zero()),
+        TxOut::new(utxo_address.clone(), amount),
+    ];
+    assert!(config.validate_config_get_genesis(0).is_err());
+    config.initial_utxos = vec![TxOut::new(utxo_address.clone(), Coin::unit()); MAX_INITIAL_UTXOS];
+    config.initial_utxos.push(TxOut::new(
+        utxo_address,
+        (amount - Coin::new(MAX_INITIAL_UTXOS as u64).unwrap()).unwrap(),
+    ));
+    assert!(config.validate_config_get_genesis(0).is_err());
+}
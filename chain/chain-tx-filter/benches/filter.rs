@@ -0,0 +1,71 @@
+use chain_core::init::address::RedeemAddress;
+use chain_core::state::account::StakedStateAddress;
+use chain_tx_filter::{BlockFilter, FilterFormat};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+/// number of addresses (not added to the filters) to estimate the false positive rates with
+const QUERIES: u32 = 100_000;
+
+fn address(i: u32) -> StakedStateAddress {
+    let mut bytes = [0u8; 20];
+    bytes[..4].copy_from_slice(&i.to_le_bytes());
+    StakedStateAddress::BasicRedeem(RedeemAddress::from(bytes))
+}
+
+/// the encoded filter with the given number of addresses
+fn make_filter(format: FilterFormat, items: u32) -> (Vec<u8>, Vec<u8>) {
+    let mut filter = BlockFilter::new(format);
+    for i in 0..items {
+        filter.add_staked_state_address(&address(i));
+    }
+    filter.get_tendermint_kv().expect("filter with items")
+}
+
+/// prints the sizes and the false positive rates of the filters
+/// (they are not measured by criterion)
+fn report_size_and_fp_rate(formats: &[(&str, FilterFormat)], block_sizes: &[u32]) {
+    for items in block_sizes {
+        for (name, format) in formats {
+            let (key, value) = make_filter(*format, *items);
+            let filter = BlockFilter::from_tendermint_kv(&key, &value).expect("valid filter");
+            let false_positives = (*items..*items + QUERIES)
+                .filter(|i| filter.check_staked_state_address(&address(*i)))
+                .count();
+            println!(
+                "{} with {} items: {} bytes, false positive rate {:.5}",
+                name,
+                items,
+                value.len(),
+                false_positives as f64 / f64::from(QUERIES)
+            );
+        }
+    }
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let formats = [
+        ("ethbloom", FilterFormat::EthBloom),
+        ("gcs p=10", FilterFormat::Gcs { fp_rate_bits: 10 }),
+        ("gcs p=20", FilterFormat::Gcs { fp_rate_bits: 20 }),
+    ];
+    let block_sizes = [10, 100, 1000];
+    report_size_and_fp_rate(&formats, &block_sizes);
+
+    for (name, format) in formats.iter() {
+        let format = *format;
+        c.bench_function(&format!("{} build 1000 items", name), move |b| {
+            b.iter(|| make_filter(format, 1000))
+        });
+        let (key, value) = make_filter(format, 1000);
+        c.bench_function(&format!("{} decode and check", name), move |b| {
+            b.iter(|| {
+                BlockFilter::from_tendermint_kv(&key, &value)
+                    .expect("valid filter")
+                    .check_staked_state_address(&address(1001))
+            })
+        });
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
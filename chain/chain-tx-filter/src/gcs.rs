@@ -0,0 +1,254 @@
+//! Golomb-coded sets (GCS), similar to BIP 158 filters:
+//! the N set items are hashed to the range [0, N * 2^P), the sorted hashed values' differences
+//! are Golomb-Rice coded with the parameter P, so a membership test has a false positive rate of about 1 / 2^P
+use blake2::Blake2s;
+use chain_core::common::hash256;
+use std::collections::BTreeSet;
+
+/// the highest supported Golomb-Rice parameter (false positive rate of about 1 / 2^32)
+pub const MAX_FP_RATE_BITS: u8 = 32;
+
+/// the encoded set starts with the Golomb-Rice parameter (1 byte) and the number of items (4 bytes, little endian)
+const HEADER_SIZE: usize = 5;
+
+/// hashes the item to a 64-bit value (the first 8 bytes of its blake2s hash)
+pub fn hash_item(item: &[u8]) -> u64 {
+    let hash = hash256::<Blake2s>(item);
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash[..8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// maps the 64-bit hash uniformly to [0, range)
+fn reduce(hash: u64, range: u64) -> u64 {
+    ((u128::from(hash) * u128::from(range)) >> 64) as u64
+}
+
+/// Immutable probabilistic set of (hashed) items
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GolombCodedSet {
+    fp_rate_bits: u8,
+    /// the sorted hashed items (reduced to [0, N * 2^P))
+    values: Vec<u64>,
+}
+
+impl GolombCodedSet {
+    /// creates a set from the item hashes (see `hash_item`)
+    ///
+    /// # Panics
+    ///
+    /// if `fp_rate_bits` is zero or above `MAX_FP_RATE_BITS`
+    pub fn new(hashes: &BTreeSet<u64>, fp_rate_bits: u8) -> Self {
+        assert!(
+            fp_rate_bits > 0 && fp_rate_bits <= MAX_FP_RATE_BITS,
+            "invalid Golomb-Rice parameter"
+        );
+        let range = (hashes.len() as u64) << fp_rate_bits;
+        // the reduction preserves the (sorted) order of the hashes
+        let values = hashes.iter().map(|hash| reduce(*hash, range)).collect();
+        GolombCodedSet {
+            fp_rate_bits,
+            values,
+        }
+    }
+
+    /// the false positive rate of membership tests is about 1 / 2^fp_rate_bits
+    pub fn fp_rate_bits(&self) -> u8 {
+        self.fp_rate_bits
+    }
+
+    /// the number of items in the set
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// true if there are no items in the set
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// tests if the item with the given hash (see `hash_item`) is in the set
+    /// true = maybe present
+    /// false = not present
+    pub fn contains(&self, hash: u64) -> bool {
+        if self.values.is_empty() {
+            return false;
+        }
+        let range = (self.values.len() as u64) << self.fp_rate_bits;
+        self.values.binary_search(&reduce(hash, range)).is_ok()
+    }
+
+    /// encodes the set: header (Golomb-Rice parameter + number of items) followed by the Golomb-Rice coded differences
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = BitWriter::default();
+        writer.bytes.push(self.fp_rate_bits);
+        writer
+            .bytes
+            .extend_from_slice(&(self.values.len() as u32).to_le_bytes());
+        let mut last = 0;
+        for value in self.values.iter() {
+            let delta = value - last;
+            let quotient = delta >> self.fp_rate_bits;
+            for _ in 0..quotient {
+                writer.write_bit(true);
+            }
+            writer.write_bit(false);
+            writer.write_bits(delta, self.fp_rate_bits);
+            last = *value;
+        }
+        writer.bytes
+    }
+
+    /// decodes the set encoded with `to_bytes`
+    pub fn from_bytes(data: &[u8]) -> Result<Self, &'static str> {
+        if data.len() < HEADER_SIZE {
+            return Err("Invalid length, Golomb-coded set header is expected to be 5 bytes");
+        }
+        let fp_rate_bits = data[0];
+        if fp_rate_bits == 0 || fp_rate_bits > MAX_FP_RATE_BITS {
+            return Err("Invalid Golomb-Rice parameter");
+        }
+        let mut len_bytes = [0u8; 4];
+        len_bytes.copy_from_slice(&data[1..HEADER_SIZE]);
+        let len = u32::from_le_bytes(len_bytes) as usize;
+        let mut reader = BitReader::new(&data[HEADER_SIZE..]);
+        // each value takes at least P + 1 bits
+        if len > reader.remaining() / (usize::from(fp_rate_bits) + 1) {
+            return Err("Invalid length, fewer Golomb-coded values than expected");
+        }
+        let mut values = Vec::with_capacity(len);
+        let mut last: u64 = 0;
+        for _ in 0..len {
+            let mut quotient: u64 = 0;
+            while reader.read_bit().ok_or("Truncated Golomb-coded value")? {
+                quotient += 1;
+            }
+            let remainder = reader
+                .read_bits(fp_rate_bits)
+                .ok_or("Truncated Golomb-coded value")?;
+            let delta = quotient
+                .checked_shl(u32::from(fp_rate_bits))
+                .filter(|shifted| shifted >> fp_rate_bits == quotient)
+                .ok_or("Golomb-coded value out of range")?
+                | remainder;
+            last = last
+                .checked_add(delta)
+                .ok_or("Golomb-coded value out of range")?;
+            values.push(last);
+        }
+        Ok(GolombCodedSet {
+            fp_rate_bits,
+            values,
+        })
+    }
+}
+
+/// writes bits (most significant first) to bytes
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    /// the number of bits used in the last byte (0 = a new byte is needed)
+    used: u8,
+}
+
+impl BitWriter {
+    fn write_bit(&mut self, bit: bool) {
+        if self.used == 0 {
+            self.bytes.push(0);
+        }
+        if bit {
+            let last = self.bytes.len() - 1;
+            self.bytes[last] |= 0x80 >> self.used;
+        }
+        self.used = (self.used + 1) % 8;
+    }
+
+    /// writes the `count` lowest bits of the value
+    fn write_bits(&mut self, value: u64, count: u8) {
+        for i in (0..count).rev() {
+            self.write_bit((value >> i) & 1 == 1);
+        }
+    }
+}
+
+/// reads bits (most significant first) from bytes
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        BitReader { bytes, position: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() * 8 - self.position
+    }
+
+    fn read_bit(&mut self) -> Option<bool> {
+        let byte = self.bytes.get(self.position / 8)?;
+        let bit = byte & (0x80 >> (self.position % 8)) != 0;
+        self.position += 1;
+        Some(bit)
+    }
+
+    fn read_bits(&mut self, count: u8) -> Option<u64> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = (value << 1) | u64::from(self.read_bit()?);
+        }
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hashes(range: std::ops::Range<u32>) -> BTreeSet<u64> {
+        range.map(|i| hash_item(&i.to_le_bytes())).collect()
+    }
+
+    #[test]
+    fn set_should_contain_its_items_after_roundtrip() {
+        let items = hashes(0..500);
+        let set = GolombCodedSet::new(&items, 16);
+        let decoded = GolombCodedSet::from_bytes(&set.to_bytes()).expect("decode");
+        assert_eq!(set, decoded);
+        assert_eq!(500, decoded.len());
+        for hash in items.iter() {
+            assert!(decoded.contains(*hash));
+        }
+    }
+
+    #[test]
+    fn false_positive_rate_should_follow_the_parameter() {
+        let set = GolombCodedSet::new(&hashes(0..1000), 10);
+        let false_positives = hashes(1000..101_000)
+            .into_iter()
+            .filter(|hash| set.contains(*hash))
+            .count();
+        // expected around 100_000 / 2^10 ~= 98
+        assert!(false_positives > 30 && false_positives < 300);
+    }
+
+    #[test]
+    fn empty_set_should_contain_nothing() {
+        let set = GolombCodedSet::new(&BTreeSet::new(), 20);
+        assert_eq!(HEADER_SIZE, set.to_bytes().len());
+        let decoded = GolombCodedSet::from_bytes(&set.to_bytes()).expect("decode");
+        assert!(decoded.is_empty());
+        assert!(!decoded.contains(hash_item(b"item")));
+    }
+
+    #[test]
+    fn invalid_encoding_should_be_rejected() {
+        let mut encoded = GolombCodedSet::new(&hashes(0..10), 8).to_bytes();
+        assert!(GolombCodedSet::from_bytes(&encoded[..3]).is_err());
+        encoded.truncate(encoded.len() - 2);
+        assert!(GolombCodedSet::from_bytes(&encoded).is_err());
+        assert!(GolombCodedSet::from_bytes(&[0, 0, 0, 0, 0]).is_err());
+        assert!(GolombCodedSet::from_bytes(&[8, 0xff, 0xff, 0xff, 0xff, 0]).is_err());
+    }
+}
@@ -1,39 +1,159 @@
+pub mod gcs;
+pub mod tx_filters;
+
+pub use crate::tx_filters::{TxFilters, TX_FILTERS_KEY};
+
+use crate::gcs::{hash_item, GolombCodedSet};
 use chain_core::state::account::StakedStateAddress;
 use ethbloom::{Bloom, Input};
 use parity_scale_codec::Encode;
 use secp256k1::key::PublicKey;
+use std::collections::BTreeSet;
 use std::convert::TryFrom;
 
-/// Probabilistic fixed-size filter wrapper
-#[derive(Default, Debug)]
+/// the `block_filter` event attribute key of the fixed-size bloom filter (the original format)
+pub const ETHBLOOM_FILTER_KEY: &[u8] = b"ethbloom";
+/// the `block_filter` event attribute key of the Golomb-coded set filter (version 1 of its encoding)
+pub const GCS_FILTER_KEY: &[u8] = b"gcs-v1";
+
+/// Format of the block filter emitted in tendermint events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterFormat {
+    /// fixed-size (256-byte) ethbloom
+    EthBloom,
+    /// Golomb-coded set with the false positive rate of about 1 / 2^fp_rate_bits
+    Gcs { fp_rate_bits: u8 },
+}
+
+impl Default for FilterFormat {
+    fn default() -> Self {
+        FilterFormat::EthBloom
+    }
+}
+
+#[derive(Debug)]
+enum FilterData {
+    EthBloom(Bloom),
+    /// hashes of the items added to a Golomb-coded set filter (the set is built when it's emitted)
+    GcsItems {
+        fp_rate_bits: u8,
+        hashes: BTreeSet<u64>,
+    },
+    /// Golomb-coded set decoded from tendermint events
+    Gcs(GolombCodedSet),
+}
+
+/// Probabilistic filter wrapper (an ethbloom or a Golomb-coded set)
+#[derive(Debug)]
 pub struct BlockFilter {
-    // may be replaced with GCS, e.g. https://github.com/dac-gmbh/golomb-set
-    bloom: Bloom,
+    data: FilterData,
     modified: bool,
 }
 
+impl Default for BlockFilter {
+    fn default() -> Self {
+        BlockFilter::new(FilterFormat::default())
+    }
+}
+
 impl BlockFilter {
+    /// creates an empty filter that's emitted in the given format
+    pub fn new(format: FilterFormat) -> Self {
+        let data = match format {
+            FilterFormat::EthBloom => FilterData::EthBloom(Bloom::default()),
+            FilterFormat::Gcs { fp_rate_bits } => FilterData::GcsItems {
+                fp_rate_bits,
+                hashes: BTreeSet::new(),
+            },
+        };
+        BlockFilter {
+            data,
+            modified: false,
+        }
+    }
+
+    /// the format of the filter
+    pub fn format(&self) -> FilterFormat {
+        match &self.data {
+            FilterData::EthBloom(_) => FilterFormat::EthBloom,
+            FilterData::GcsItems { fp_rate_bits, .. } => FilterFormat::Gcs {
+                fp_rate_bits: *fp_rate_bits,
+            },
+            FilterData::Gcs(set) => FilterFormat::Gcs {
+                fp_rate_bits: set.fp_rate_bits(),
+            },
+        }
+    }
+
+    fn add(&mut self, item: &[u8]) {
+        self.modified = true;
+        match &mut self.data {
+            FilterData::EthBloom(bloom) => bloom.accrue(Input::Raw(item)),
+            FilterData::GcsItems { hashes, .. } => {
+                hashes.insert(hash_item(item));
+            }
+            FilterData::Gcs(_) => panic!("Golomb-coded set decoded from events can't be extended"),
+        }
+    }
+
+    fn check(&self, item: &[u8]) -> bool {
+        match &self.data {
+            FilterData::EthBloom(bloom) => bloom.contains_input(Input::Raw(item)),
+            FilterData::GcsItems { hashes, .. } => hashes.contains(&hash_item(item)),
+            FilterData::Gcs(set) => set.contains(hash_item(item)),
+        }
+    }
+
     /// adds a view key to the filter
+    ///
+    /// # Panics
+    ///
+    /// if the filter is a Golomb-coded set decoded from tendermint events
     pub fn add_view_key(&mut self, view_key: &PublicKey) {
-        self.modified = true;
-        self.bloom.accrue(Input::Raw(&view_key.serialize()[..]));
+        self.add(&view_key.serialize()[..]);
     }
 
     /// adds a staked state address to the filter
+    ///
+    /// # Panics
+    ///
+    /// if the filter is a Golomb-coded set decoded from tendermint events
     pub fn add_staked_state_address(&mut self, address: &StakedStateAddress) {
-        self.modified = true;
-        self.bloom.accrue(Input::Raw(&address.encode()));
+        self.add(&address.encode());
     }
 
-    /// gets a Key-Value payload for tendermint events (if any view keys were added)
+    /// gets a Key-Value payload for tendermint events (if any view keys were added);
+    /// the key identifies the format (and its version) of the value
     pub fn get_tendermint_kv(&self) -> Option<(Vec<u8>, Vec<u8>)> {
-        if self.modified {
-            Some((
-                Vec::from(&b"ethbloom"[..]),
-                Vec::from(&self.bloom.data()[..]),
-            ))
+        if !self.modified {
+            return None;
+        }
+        match &self.data {
+            FilterData::EthBloom(bloom) => {
+                Some((Vec::from(ETHBLOOM_FILTER_KEY), Vec::from(&bloom.data()[..])))
+            }
+            FilterData::GcsItems {
+                fp_rate_bits,
+                hashes,
+            } => Some((
+                Vec::from(GCS_FILTER_KEY),
+                GolombCodedSet::new(hashes, *fp_rate_bits).to_bytes(),
+            )),
+            FilterData::Gcs(set) => Some((Vec::from(GCS_FILTER_KEY), set.to_bytes())),
+        }
+    }
+
+    /// decodes the filter from the Key-Value payload of tendermint events (see `get_tendermint_kv`)
+    pub fn from_tendermint_kv(key: &[u8], value: &[u8]) -> Result<Self, &'static str> {
+        if key == ETHBLOOM_FILTER_KEY {
+            BlockFilter::try_from(value)
+        } else if key == GCS_FILTER_KEY {
+            Ok(BlockFilter {
+                data: FilterData::Gcs(GolombCodedSet::from_bytes(value)?),
+                modified: false,
+            })
         } else {
-            None
+            Err("Unknown block filter format")
         }
     }
 
@@ -41,14 +161,14 @@ impl BlockFilter {
     /// true = maybe present
     /// false = not present
     pub fn check_view_key(&self, view_key: &PublicKey) -> bool {
-        self.bloom.contains_input(Input::Raw(&view_key.serialize()))
+        self.check(&view_key.serialize())
     }
 
     /// tests if a staked state address is in the filter
     /// true = maybe present
     /// false = not present
     pub fn check_staked_state_address(&self, address: &StakedStateAddress) -> bool {
-        self.bloom.contains_input(Input::Raw(&address.encode()))
+        self.check(&address.encode())
     }
 
     /// check if view keys were added since its creation
@@ -57,6 +177,7 @@ impl BlockFilter {
     }
 }
 
+/// decodes the ethbloom filter
 impl TryFrom<&[u8]> for BlockFilter {
     type Error = &'static str;
 
@@ -68,9 +189,41 @@ impl TryFrom<&[u8]> for BlockFilter {
             bloom_array.copy_from_slice(&value);
             let bloom = Bloom::from(&bloom_array);
             Ok(BlockFilter {
-                bloom,
+                data: FilterData::EthBloom(bloom),
                 modified: false,
             })
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chain_core::init::address::RedeemAddress;
+
+    fn address(i: u8) -> StakedStateAddress {
+        StakedStateAddress::BasicRedeem(RedeemAddress::from([i; 20]))
+    }
+
+    #[test]
+    fn filter_should_be_decoded_from_its_tendermint_kv() {
+        let formats = [
+            FilterFormat::EthBloom,
+            FilterFormat::Gcs { fp_rate_bits: 20 },
+        ];
+        for format in formats.iter() {
+            let mut filter = BlockFilter::new(*format);
+            assert!(filter.get_tendermint_kv().is_none());
+            filter.add_staked_state_address(&address(1));
+            filter.add_staked_state_address(&address(2));
+            assert!(filter.check_staked_state_address(&address(1)));
+            let (key, value) = filter.get_tendermint_kv().expect("modified filter");
+            let decoded = BlockFilter::from_tendermint_kv(&key, &value).expect("decode");
+            assert_eq!(*format, decoded.format());
+            assert!(decoded.check_staked_state_address(&address(1)));
+            assert!(decoded.check_staked_state_address(&address(2)));
+            assert!(!decoded.check_staked_state_address(&address(3)));
+        }
+        assert!(BlockFilter::from_tendermint_kv(b"gcs-v2", &[]).is_err());
+    }
+}
@@ -0,0 +1,107 @@
+//! Per-transaction view key filters: emitted alongside the block filter,
+//! so that clients only request the (obfuscated) transactions that may be addressed to their view keys
+use crate::gcs::{hash_item, GolombCodedSet};
+use chain_core::tx::data::TxId;
+use parity_scale_codec::{Decode, Encode};
+use secp256k1::key::PublicKey;
+use std::collections::BTreeSet;
+
+/// the `block_filter` event attribute key of the per-transaction filters (version 1 of their encoding)
+pub const TX_FILTERS_KEY: &[u8] = b"tx-gcs-v1";
+
+/// the false positive rate of a single transaction's filter is about 1 / 2^16
+pub const TX_FILTER_FP_RATE_BITS: u8 = 16;
+
+/// Golomb-coded sets of the view keys allowed to see each transaction in a block
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TxFilters {
+    filters: Vec<(TxId, GolombCodedSet)>,
+}
+
+impl TxFilters {
+    /// adds a filter of the transaction's view keys
+    pub fn add_transaction<'a, I>(&mut self, txid: TxId, view_keys: I)
+    where
+        I: IntoIterator<Item = &'a PublicKey>,
+    {
+        let hashes: BTreeSet<u64> = view_keys
+            .into_iter()
+            .map(|view_key| hash_item(&view_key.serialize()[..]))
+            .collect();
+        self.filters
+            .push((txid, GolombCodedSet::new(&hashes, TX_FILTER_FP_RATE_BITS)));
+    }
+
+    /// the number of transactions with filters
+    pub fn len(&self) -> usize {
+        self.filters.len()
+    }
+
+    /// true if there are no transaction filters
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// returns the IDs of transactions whose filters match the view key
+    /// (the view key may be allowed to see them; the other transactions are not addressed to it)
+    pub fn matching_transactions(&self, view_key: &PublicKey) -> Vec<TxId> {
+        let hash = hash_item(&view_key.serialize()[..]);
+        self.filters
+            .iter()
+            .filter(|(_, filter)| filter.contains(hash))
+            .map(|(txid, _)| *txid)
+            .collect()
+    }
+
+    /// gets a Key-Value payload for tendermint events
+    /// (the value is SCALE-encoded list of transaction IDs and their encoded Golomb-coded sets)
+    pub fn get_tendermint_kv(&self) -> (Vec<u8>, Vec<u8>) {
+        let encoded: Vec<(TxId, Vec<u8>)> = self
+            .filters
+            .iter()
+            .map(|(txid, filter)| (*txid, filter.to_bytes()))
+            .collect();
+        (Vec::from(TX_FILTERS_KEY), encoded.encode())
+    }
+
+    /// decodes the filters from the value of the Key-Value payload of tendermint events (see `get_tendermint_kv`)
+    pub fn from_tendermint_value(value: &[u8]) -> Result<Self, &'static str> {
+        let mut data = value;
+        let encoded: Vec<(TxId, Vec<u8>)> =
+            Decode::decode(&mut data).map_err(|_| "Invalid transaction filters encoding")?;
+        let filters = encoded
+            .into_iter()
+            .map(|(txid, filter)| Ok((txid, GolombCodedSet::from_bytes(&filter)?)))
+            .collect::<Result<Vec<_>, &'static str>>()?;
+        Ok(TxFilters { filters })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use secp256k1::{Secp256k1, SecretKey};
+
+    fn view_key(i: u8) -> PublicKey {
+        let secp = Secp256k1::new();
+        PublicKey::from_secret_key(&secp, &SecretKey::from_slice(&[i; 32]).unwrap())
+    }
+
+    #[test]
+    fn only_transactions_for_the_view_key_should_match() {
+        let mut filters = TxFilters::default();
+        filters.add_transaction([1; 32], &[view_key(1), view_key(2)]);
+        filters.add_transaction([2; 32], &[view_key(2)]);
+        filters.add_transaction([3; 32], &[]);
+        let (key, value) = filters.get_tendermint_kv();
+        assert_eq!(TX_FILTERS_KEY, key.as_slice());
+        let decoded = TxFilters::from_tendermint_value(&value).expect("decode");
+        assert_eq!(filters, decoded);
+        assert_eq!(vec![[1; 32]], decoded.matching_transactions(&view_key(1)));
+        assert_eq!(
+            vec![[1; 32], [2; 32]],
+            decoded.matching_transactions(&view_key(2))
+        );
+        assert!(decoded.matching_transactions(&view_key(3)).is_empty());
+    }
+}
@@ -17,13 +17,17 @@ extern crate sgx_tstd as std;
 use std::prelude::v1::Vec;
 
 use chain_core::init::coin::Coin;
-use chain_core::state::account::{DepositBondTx, StakedState, UnbondTx, WithdrawUnbondedTx};
+use chain_core::state::account::{
+    DelegateTx, DepositBondTx, NodeJoinRequestTx, RebondTx, StakedState, UnbondTx, UndelegateTx,
+    UnjailTx, WithdrawUnbondedTx,
+};
+use chain_core::state::governance::{ParamChangeProposalTx, ParamChangeVoteTx};
 use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
 use chain_core::tx::data::Tx;
 use chain_core::tx::data::TxId;
 use chain_core::tx::fee::Fee;
-use chain_core::tx::witness::TxWitness;
+use chain_core::tx::witness::{TxInWitness, TxWitness};
 use chain_core::tx::TransactionId;
 pub use chain_core::tx::TxWithOutputs;
 pub use chain_core::ChainInfo;
@@ -77,6 +81,40 @@ pub enum Error {
     AccountWithdrawOutputNotLocked,
     /// incorrect nonce supplied in staked state operation
     AccountIncorrectNonce,
+    /// hash-time-locked output claimed after its timeout
+    HashTimeLockExpired,
+    /// hash-time-locked output refunded before its timeout
+    HashTimeLockNotExpired,
+    /// staked state address in transaction doesn't match the one that signed it
+    MismatchAccountAddress,
+    /// bonded amount (after fee) is below the required council node stake
+    NotEnoughStake,
+    /// staking address or consensus key is already used by a council node
+    DuplicateCouncilNode,
+    /// staked state is jailed (or its jail period hasn't passed yet)
+    AccountJailed,
+    /// unjailing a staked state that isn't jailed
+    AccountNotJailed,
+    /// staked state already has the maximum number of unbonding entries
+    TooManyUnbondingEntries,
+    /// rebonding more than the unbonded amount in the staked state
+    NotEnoughUnbonded,
+    /// delegating to a staking address that isn't a council node
+    NotCouncilNode,
+    /// staked state already delegates to a different council node
+    AlreadyDelegated,
+    /// undelegating more than the amount delegated to the council node
+    NotEnoughDelegated,
+    /// network parameter change proposed to be activated at a past or the current block height
+    InvalidActivationHeight,
+    /// voting for a network parameter change proposal that doesn't exist (or was already activated / rejected)
+    ProposalNotFound,
+    /// council node already voted for the network parameter change proposal
+    AlreadyVoted,
+    /// unbonding or undelegating stake that is still locked by the vesting schedule
+    VestingLocked,
+    /// network parameter change to an invalid value
+    InvalidNetworkParameter,
 }
 
 /// FIXME: this will go away with simplified intra-enclave FFI calls
@@ -102,6 +140,23 @@ impl From<i32> for Error {
                 Error::AccountWithdrawOutputNotLocked
             }
             x if x == Error::AccountIncorrectNonce as i32 => Error::AccountIncorrectNonce,
+            x if x == Error::HashTimeLockExpired as i32 => Error::HashTimeLockExpired,
+            x if x == Error::HashTimeLockNotExpired as i32 => Error::HashTimeLockNotExpired,
+            x if x == Error::MismatchAccountAddress as i32 => Error::MismatchAccountAddress,
+            x if x == Error::NotEnoughStake as i32 => Error::NotEnoughStake,
+            x if x == Error::DuplicateCouncilNode as i32 => Error::DuplicateCouncilNode,
+            x if x == Error::AccountJailed as i32 => Error::AccountJailed,
+            x if x == Error::AccountNotJailed as i32 => Error::AccountNotJailed,
+            x if x == Error::TooManyUnbondingEntries as i32 => Error::TooManyUnbondingEntries,
+            x if x == Error::NotEnoughUnbonded as i32 => Error::NotEnoughUnbonded,
+            x if x == Error::NotCouncilNode as i32 => Error::NotCouncilNode,
+            x if x == Error::AlreadyDelegated as i32 => Error::AlreadyDelegated,
+            x if x == Error::NotEnoughDelegated as i32 => Error::NotEnoughDelegated,
+            x if x == Error::InvalidActivationHeight as i32 => Error::InvalidActivationHeight,
+            x if x == Error::ProposalNotFound as i32 => Error::ProposalNotFound,
+            x if x == Error::AlreadyVoted as i32 => Error::AlreadyVoted,
+            x if x == Error::VestingLocked as i32 => Error::VestingLocked,
+            x if x == Error::InvalidNetworkParameter as i32 => Error::InvalidNetworkParameter,
             _ => Error::EnclaveRejected,
         }
     }
@@ -142,9 +197,58 @@ impl fmt::Display for Error {
             AccountNotUnbonded => write!(f, "account not unbonded for withdrawal"),
             AccountWithdrawOutputNotLocked => write!(
                 f,
-                "account withdrawal outputs not time-locked to unbonded_from"
+                "account withdrawal outputs not time-locked to the release time of the withdrawn unbonded stake"
             ),
             AccountIncorrectNonce => write!(f, "incorrect transaction count for account operation"),
+            HashTimeLockExpired => {
+                write!(f, "hash-time-locked output can't be claimed after timeout")
+            }
+            HashTimeLockNotExpired => write!(
+                f,
+                "hash-time-locked output can't be refunded before timeout"
+            ),
+            MismatchAccountAddress => write!(
+                f,
+                "staked state address doesn't match the transaction signer"
+            ),
+            NotEnoughStake => write!(f, "bonded amount is below the required council node stake"),
+            DuplicateCouncilNode => write!(
+                f,
+                "staking address or consensus key already used by a council node"
+            ),
+            AccountJailed => write!(f, "account is jailed"),
+            AccountNotJailed => write!(f, "account is not jailed"),
+            TooManyUnbondingEntries => write!(
+                f,
+                "account has too many unbonding entries (withdraw the matured ones first)"
+            ),
+            NotEnoughUnbonded => write!(f, "rebonded amount is above the account's unbonded amount"),
+            NotCouncilNode => write!(f, "staking address is not a council node"),
+            AlreadyDelegated => write!(
+                f,
+                "account already delegates to a different council node (undelegate first)"
+            ),
+            NotEnoughDelegated => write!(
+                f,
+                "undelegated amount is above the amount delegated to the council node"
+            ),
+            InvalidActivationHeight => write!(
+                f,
+                "network parameter change must be activated at a future block height"
+            ),
+            ProposalNotFound => write!(f, "network parameter change proposal not found"),
+            AlreadyVoted => write!(
+                f,
+                "council node already voted for the network parameter change proposal"
+            ),
+            VestingLocked => write!(
+                f,
+                "the amount is still locked by the account's vesting schedule"
+            ),
+            InvalidNetworkParameter => write!(
+                f,
+                "invalid network parameter value (the fee policy overflows, or the unbonding period or the stake is too large)"
+            ),
         }
     }
 }
@@ -211,6 +315,7 @@ fn check_inputs(
                 return Err(Error::OutputInTimelock);
             }
         }
+        check_hash_time_lock(&in_witness, extra_info)?;
         let wv = verify_tx_address(&in_witness, main_txid, &txout.address);
         if let Err(_e) = wv {
             return Err(Error::EcdsaCrypto); // FIXME: Err(Error::EcdsaCrypto(e));
@@ -225,6 +330,23 @@ fn check_inputs(
     Ok(incoins)
 }
 
+/// claims of hash-time-locked outputs are only valid before the timeout, refunds only after it
+fn check_hash_time_lock(witness: &TxInWitness, extra_info: &ChainInfo) -> Result<(), Error> {
+    match witness {
+        TxInWitness::HashTimeLockClaim(_, _, htlc, _)
+            if htlc.is_expired(extra_info.previous_block_time) =>
+        {
+            Err(Error::HashTimeLockExpired)
+        }
+        TxInWitness::HashTimeLockRefund(_, _, htlc)
+            if !htlc.is_expired(extra_info.previous_block_time) =>
+        {
+            Err(Error::HashTimeLockNotExpired)
+        }
+        _ => Ok(()),
+    }
+}
+
 fn check_outputs_basic(outputs: &[TxOut]) -> Result<(), Error> {
     // check that there are outputs
     if outputs.is_empty() {
@@ -243,6 +365,24 @@ fn check_outputs_basic(outputs: &[TxOut]) -> Result<(), Error> {
     Ok(())
 }
 
+/// checks that the stake locked by the vesting schedule stays in the bonded or delegated amounts
+/// after the given amount is moved out of them
+fn check_vesting_lock(
+    account: &StakedState,
+    released: Coin,
+    extra_info: &ChainInfo,
+) -> Result<(), Error> {
+    let locked = account.locked(extra_info.previous_block_time);
+    if locked == Coin::zero() {
+        return Ok(());
+    }
+    let remaining = (account.bonded + account.delegated()).and_then(|staked| staked - released);
+    match remaining {
+        Ok(remaining) if remaining >= locked => Ok(()),
+        _ => Err(Error::VestingLocked),
+    }
+}
+
 fn check_input_output_sums(
     incoins: Coin,
     outcoins: Coin,
@@ -319,8 +459,10 @@ pub fn verify_bonded_deposit(
     transaction_inputs: Vec<TxWithOutputs>,
     maccount: Option<StakedState>,
 ) -> Result<(Fee, Option<StakedState>), Error> {
+    if let Some(true) = maccount.as_ref().map(StakedState::is_jailed) {
+        return Err(Error::AccountJailed);
+    }
     let incoins = verify_bonded_deposit_core(maintx, witness, extra_info, transaction_inputs)?;
-    // TODO: checking account not jailed etc.?
     let deposit_amount = (incoins - extra_info.min_fee_computed.to_coin()).expect("init");
     let account = match maccount {
         Some(mut a) => {
@@ -354,7 +496,14 @@ pub fn verify_unbonding(
     if maintx.value == Coin::zero() {
         return Err(Error::ZeroCoin);
     }
+    // the number of unbonding entries is bounded
+    if !account.can_unbond() {
+        return Err(Error::TooManyUnbondingEntries);
+    }
     check_input_output_sums(account.bonded, maintx.value, &extra_info)?;
+    let released = (maintx.value + extra_info.min_fee_computed.to_coin())
+        .map_err(|_coin_err| Error::InvalidSum)?;
+    check_vesting_lock(&account, released, &extra_info)?;
     account.unbond(
         maintx.value,
         extra_info.min_fee_computed.to_coin(),
@@ -364,6 +513,117 @@ pub fn verify_unbonding(
     Ok((extra_info.min_fee_computed, Some(account)))
 }
 
+/// checks moving some amount from unbonded (not withdrawn yet) back to bonded in staked states;
+/// the fee is paid from the resulting bonded amount
+/// NOTE: witness is assumed to be checked in chain-abci
+pub fn verify_rebonding(
+    maintx: &RebondTx,
+    extra_info: ChainInfo,
+    mut account: StakedState,
+) -> Result<(Fee, Option<StakedState>), Error> {
+    check_attributes(maintx.attributes.chain_hex_id, &extra_info)?;
+
+    // checks that account transaction count matches to the one in transaction
+    if maintx.nonce != account.nonce {
+        return Err(Error::AccountIncorrectNonce);
+    }
+    // check that a non-zero amount is being rebonded
+    if maintx.value == Coin::zero() {
+        return Err(Error::ZeroCoin);
+    }
+    if account.is_jailed() {
+        return Err(Error::AccountJailed);
+    }
+    if maintx.value > account.unbonded() {
+        return Err(Error::NotEnoughUnbonded);
+    }
+    let bonded = match account.bonded + maintx.value {
+        Ok(bonded) => bonded,
+        Err(_coin_err) => return Err(Error::InvalidSum),
+    };
+    check_input_output_sums(bonded, Coin::zero(), &extra_info)?;
+    account.rebond(maintx.value, extra_info.min_fee_computed.to_coin());
+    Ok((extra_info.min_fee_computed, Some(account)))
+}
+
+/// checks delegating some amount from bonded to a council node in staked states
+/// NOTE: witness is assumed to be checked in chain-abci;
+/// checks against the council node state (whether the target is a council node) are done there as well
+pub fn verify_delegation(
+    maintx: &DelegateTx,
+    extra_info: ChainInfo,
+    mut account: StakedState,
+) -> Result<(Fee, Option<StakedState>), Error> {
+    check_attributes(maintx.attributes.chain_hex_id, &extra_info)?;
+
+    // checks that account transaction count matches to the one in transaction
+    if maintx.nonce != account.nonce {
+        return Err(Error::AccountIncorrectNonce);
+    }
+    // check that a non-zero amount is being delegated
+    if maintx.value == Coin::zero() {
+        return Err(Error::ZeroCoin);
+    }
+    if account.is_jailed() {
+        return Err(Error::AccountJailed);
+    }
+    if let Some(delegation) = account.delegation {
+        if delegation.council_node != maintx.council_node {
+            return Err(Error::AlreadyDelegated);
+        }
+    }
+    check_input_output_sums(account.bonded, maintx.value, &extra_info)?;
+    account.delegate(
+        maintx.council_node,
+        maintx.value,
+        extra_info.min_fee_computed.to_coin(),
+    );
+    // only pay the minimal fee from the bonded amount if correct; the rest remains in bonded
+    Ok((extra_info.min_fee_computed, Some(account)))
+}
+
+/// checks moving some amount from delegated to unbonded in staked states
+/// (the fee is paid from the undelegated amount, as the bonded amount may be all delegated)
+/// NOTE: witness is assumed to be checked in chain-abci
+pub fn verify_undelegation(
+    maintx: &UndelegateTx,
+    extra_info: ChainInfo,
+    mut account: StakedState,
+) -> Result<(Fee, Option<StakedState>), Error> {
+    check_attributes(maintx.attributes.chain_hex_id, &extra_info)?;
+
+    // checks that account transaction count matches to the one in transaction
+    if maintx.nonce != account.nonce {
+        return Err(Error::AccountIncorrectNonce);
+    }
+    // check that a non-zero amount is being undelegated
+    if maintx.value == Coin::zero() {
+        return Err(Error::ZeroCoin);
+    }
+    match account.delegation {
+        Some(delegation)
+            if delegation.council_node == maintx.council_node
+                && delegation.amount >= maintx.value => {}
+        _ => return Err(Error::NotEnoughDelegated),
+    }
+    // the number of unbonding entries is bounded
+    if !account.can_unbond() {
+        return Err(Error::TooManyUnbondingEntries);
+    }
+    check_input_output_sums(maintx.value, Coin::zero(), &extra_info)?;
+    // what remains after the fee becomes the unbonding entry, so it shouldn't be empty
+    if maintx.value <= extra_info.min_fee_computed.to_coin() {
+        return Err(Error::InputOutputDoNotMatch);
+    }
+    check_vesting_lock(&account, maintx.value, &extra_info)?;
+    account.undelegate(
+        maintx.value,
+        extra_info.min_fee_computed.to_coin(),
+        extra_info.previous_block_time + i64::from(extra_info.unbonding_period),
+    );
+    Ok((extra_info.min_fee_computed, Some(account)))
+}
+
 /// checks wihdrawing from a staked state -- TODO: this will be moved to an enclave
 /// NOTE: witness is assumed to be checked in chain-abci
 pub fn verify_unbonded_withdraw_core(
@@ -377,19 +637,22 @@ pub fn verify_unbonded_withdraw_core(
     if maintx.nonce != account.nonce {
         return Err(Error::AccountIncorrectNonce);
     }
-    // checks that account can withdraw to outputs
-    if account.unbonded_from > extra_info.previous_block_time {
-        return Err(Error::AccountNotUnbonded);
-    }
+    // checks that account can withdraw to outputs (only the matured unbonding entries are withdrawn)
+    let release_time = match account.matured_release_time(extra_info.previous_block_time) {
+        Some(release_time) => release_time,
+        None => return Err(Error::AccountNotUnbonded),
+    };
     // checks that there is something to wihdraw
-    if account.unbonded == Coin::zero() {
+    // (the stake locked by a vesting schedule can't be unbonded, so all matured entries can be withdrawn)
+    let unbonded = account.matured_unbonded(extra_info.previous_block_time);
+    if unbonded == Coin::zero() {
         return Err(Error::ZeroCoin);
     }
-    // checks that outputs are locked to the unbonded time
+    // checks that outputs are locked to the release time of the withdrawn entries
     if !maintx
         .outputs
         .iter()
-        .all(|x| x.valid_from == Some(account.unbonded_from))
+        .all(|x| x.valid_from == Some(release_time))
     {
         return Err(Error::AccountWithdrawOutputNotLocked);
     }
@@ -397,7 +660,7 @@ pub fn verify_unbonded_withdraw_core(
     if let Err(_coin_err) = outcoins {
         return Err(Error::InvalidSum); // FIXME: Err(Error::InvalidSum(coin_err));
     }
-    check_input_output_sums(account.unbonded, outcoins.unwrap(), &extra_info)
+    check_input_output_sums(unbonded, outcoins.unwrap(), &extra_info)
 }
 
 /// checks wihdrawing from a staked state
@@ -409,6 +672,108 @@ pub fn verify_unbonded_withdraw(
     mut account: StakedState,
 ) -> Result<(Fee, Option<StakedState>), Error> {
     let fee = verify_unbonded_withdraw_core(maintx, extra_info, &account)?;
-    account.withdraw();
+    account.withdraw(extra_info.previous_block_time);
     Ok((fee, Some(account)))
 }
+
+/// checks a request to join the council nodes; the fee is paid from the bonded amount
+/// NOTE: witness is assumed to be checked in chain-abci;
+/// checks against the council node state (required stake, duplicates) are done there as well
+pub fn verify_node_join(
+    maintx: &NodeJoinRequestTx,
+    extra_info: ChainInfo,
+    mut account: StakedState,
+) -> Result<(Fee, Option<StakedState>), Error> {
+    check_attributes(maintx.attributes.chain_hex_id, &extra_info)?;
+    if maintx.address != account.address {
+        return Err(Error::MismatchAccountAddress);
+    }
+    // checks that account transaction count matches to the one in transaction
+    if maintx.nonce != account.nonce {
+        return Err(Error::AccountIncorrectNonce);
+    }
+    if account.is_jailed() {
+        return Err(Error::AccountJailed);
+    }
+    check_input_output_sums(account.bonded, Coin::zero(), &extra_info)?;
+    account.pay_bonded_fee(extra_info.min_fee_computed.to_coin());
+    Ok((extra_info.min_fee_computed, Some(account)))
+}
+
+/// checks releasing a jailed staked state after its jail period; the fee is paid from the bonded amount
+/// NOTE: witness is assumed to be checked in chain-abci
+pub fn verify_unjailing(
+    maintx: &UnjailTx,
+    extra_info: ChainInfo,
+    mut account: StakedState,
+) -> Result<(Fee, Option<StakedState>), Error> {
+    check_attributes(maintx.attributes.chain_hex_id, &extra_info)?;
+    if maintx.address != account.address {
+        return Err(Error::MismatchAccountAddress);
+    }
+    // checks that account transaction count matches to the one in transaction
+    if maintx.nonce != account.nonce {
+        return Err(Error::AccountIncorrectNonce);
+    }
+    match account.jailed_until {
+        None => return Err(Error::AccountNotJailed),
+        Some(jailed_until) if jailed_until > extra_info.previous_block_time => {
+            return Err(Error::AccountJailed);
+        }
+        _ => {}
+    }
+    check_input_output_sums(account.bonded, Coin::zero(), &extra_info)?;
+    account.unjail(extra_info.min_fee_computed.to_coin());
+    Ok((extra_info.min_fee_computed, Some(account)))
+}
+
+/// checks a network parameter change proposal; the fee is paid from the bonded amount
+/// NOTE: witness is assumed to be checked in chain-abci;
+/// checks against the council node and proposal state (council node membership, activation height) are done there as well
+pub fn verify_param_change_proposal(
+    maintx: &ParamChangeProposalTx,
+    extra_info: ChainInfo,
+    mut account: StakedState,
+) -> Result<(Fee, Option<StakedState>), Error> {
+    check_attributes(maintx.attributes.chain_hex_id, &extra_info)?;
+    if maintx.address != account.address {
+        return Err(Error::MismatchAccountAddress);
+    }
+    // checks that account transaction count matches to the one in transaction
+    if maintx.nonce != account.nonce {
+        return Err(Error::AccountIncorrectNonce);
+    }
+    if account.is_jailed() {
+        return Err(Error::AccountJailed);
+    }
+    if !maintx.change.is_valid() {
+        return Err(Error::InvalidNetworkParameter);
+    }
+    check_input_output_sums(account.bonded, Coin::zero(), &extra_info)?;
+    account.pay_bonded_fee(extra_info.min_fee_computed.to_coin());
+    Ok((extra_info.min_fee_computed, Some(account)))
+}
+
+/// checks a vote for a network parameter change proposal; the fee is paid from the bonded amount
+/// NOTE: witness is assumed to be checked in chain-abci;
+/// checks against the council node and proposal state (council node membership, pending proposal, duplicate votes) are done there as well
+pub fn verify_param_change_vote(
+    maintx: &ParamChangeVoteTx,
+    extra_info: ChainInfo,
+    mut account: StakedState,
+) -> Result<(Fee, Option<StakedState>), Error> {
+    check_attributes(maintx.attributes.chain_hex_id, &extra_info)?;
+    if maintx.address != account.address {
+        return Err(Error::MismatchAccountAddress);
+    }
+    // checks that account transaction count matches to the one in transaction
+    if maintx.nonce != account.nonce {
+        return Err(Error::AccountIncorrectNonce);
+    }
+    if account.is_jailed() {
+        return Err(Error::AccountJailed);
+    }
+    check_input_output_sums(account.bonded, Coin::zero(), &extra_info)?;
+    account.pay_bonded_fee(extra_info.min_fee_computed.to_coin());
+    Ok((extra_info.min_fee_computed, Some(account)))
+}
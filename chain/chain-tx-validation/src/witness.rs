@@ -1,12 +1,18 @@
+use chain_core::common::{Proof, H256};
 use chain_core::init::address::RedeemAddress;
 use chain_core::state::account::{StakedStateAddress, StakedStateOpWitness};
 use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::TxId;
+use chain_core::tx::witness::tree::RawPubkey;
 use chain_core::tx::witness::TxInWitness;
-use secp256k1::{schnorrsig::schnorr_verify, Message, PublicKey, Secp256k1};
+use secp256k1::{
+    schnorrsig::{schnorr_verify, SchnorrSignature},
+    Message, PublicKey, Secp256k1, Verification,
+};
 
 /// verify a given extended address is associated to the witness
 /// and the signature against the given transation `Tx`
+/// NOTE: the timeout of hash-time-locked outputs is checked separately (it needs the block time)
 /// TODO: capture possible errors in enum?
 ///
 pub fn verify_tx_address(
@@ -19,17 +25,47 @@ pub fn verify_tx_address(
 
     match (witness, address) {
         (TxInWitness::TreeSig(sig, proof), ExtendedAddr::OrTree(root_hash)) => {
-            if !proof.verify(root_hash) {
+            verify_tree_sig(&secp, &message, sig, proof, root_hash)
+        }
+        (
+            TxInWitness::HashTimeLockClaim(sig, proof, htlc, preimage),
+            ExtendedAddr::HashTimeLock(_),
+        ) => {
+            if htlc.address() != *address {
+                Err(secp256k1::Error::InvalidPublicKey)
+            } else if !htlc.check_preimage(preimage) {
+                Err(secp256k1::Error::InvalidMessage)
+            } else {
+                verify_tree_sig(&secp, &message, sig, proof, &htlc.claim_root)
+            }
+        }
+        (TxInWitness::HashTimeLockRefund(sig, proof, htlc), ExtendedAddr::HashTimeLock(_)) => {
+            if htlc.address() != *address {
                 Err(secp256k1::Error::InvalidPublicKey)
             } else {
-                schnorr_verify(
-                    &secp,
-                    &message,
-                    &sig,
-                    &PublicKey::from_slice(proof.value().as_bytes())?,
-                )
+                verify_tree_sig(&secp, &message, sig, proof, &htlc.refund_root)
             }
         }
+        _ => Err(secp256k1::Error::InvalidPublicKey),
+    }
+}
+
+fn verify_tree_sig<C: Verification>(
+    secp: &Secp256k1<C>,
+    message: &Message,
+    sig: &SchnorrSignature,
+    proof: &Proof<RawPubkey>,
+    root_hash: &H256,
+) -> Result<(), secp256k1::Error> {
+    if !proof.verify(root_hash) {
+        Err(secp256k1::Error::InvalidPublicKey)
+    } else {
+        schnorr_verify(
+            secp,
+            message,
+            sig,
+            &PublicKey::from_slice(proof.value().as_bytes())?,
+        )
     }
 }
 
@@ -55,10 +91,12 @@ pub fn verify_tx_recover_address(
 pub mod tests {
     use super::*;
 
+    use parity_scale_codec::Decode;
     use secp256k1::schnorrsig::schnorr_sign;
     use secp256k1::SecretKey;
 
     use chain_core::common::MerkleTree;
+    use chain_core::tx::data::htlc::{hash_lock, HashTimeLock};
     use chain_core::tx::data::Tx;
     use chain_core::tx::witness::tree::RawPubkey;
     use chain_core::tx::TransactionId;
@@ -126,6 +164,61 @@ pub mod tests {
         assert!(verify_tx_address(&witness, &transation.id(), &address).is_ok())
     }
 
+    /// a proof as encoded by the releases before the leaf hash was checked against the proven value
+    /// (of the second public key in the tree of the public keys of 0xcd.. and 0xde..):
+    /// the witnesses of the existing addresses need to keep verifying
+    #[test]
+    fn check_stored_1_of_2_proof_verify() {
+        let transation = Tx::new();
+
+        let secp = Secp256k1::new();
+
+        let secret_key = SecretKey::from_slice(&[0xde; 32]).expect("Unable to create secret key");
+        let public_key =
+            RawPubkey::from(PublicKey::from_secret_key(&secp, &secret_key).serialize());
+
+        let root_hash: H256 = [
+            0xf7, 0x03, 0x46, 0x28, 0xc0, 0xd0, 0xd6, 0x9c, 0x10, 0x7b, 0xa5, 0xe3, 0x4b, 0x3b,
+            0x21, 0x25, 0xe1, 0xea, 0x1d, 0x2b, 0xf3, 0x62, 0x1c, 0x6a, 0x7d, 0x74, 0x81, 0x91,
+            0xfc, 0x36, 0xa9, 0x5e,
+        ];
+        let leaf_hash: H256 = [
+            0xf8, 0x2d, 0x42, 0xeb, 0x5d, 0x86, 0x26, 0x69, 0xef, 0xbe, 0x41, 0xcd, 0x32, 0xe7,
+            0xfb, 0x1b, 0xf9, 0xc3, 0x44, 0xd7, 0xc2, 0x72, 0xd8, 0x97, 0xdd, 0x46, 0x3b, 0x45,
+            0xda, 0xce, 0x10, 0x48,
+        ];
+        let sibling_hash: H256 = [
+            0x1e, 0x27, 0xa8, 0xc4, 0x5f, 0x19, 0xe5, 0x20, 0x24, 0x5e, 0x63, 0x83, 0x2a, 0x81,
+            0x82, 0x1c, 0xdc, 0x9b, 0x5a, 0x5c, 0x79, 0x96, 0x27, 0x80, 0x3e, 0x5a, 0x5e, 0x49,
+            0xa7, 0x60, 0xc0, 0x47,
+        ];
+
+        // root hash, path (node hash, no sibling, sub-path (leaf hash, left sibling, no sub-path)), value
+        let mut encoded = root_hash.to_vec();
+        encoded.extend_from_slice(&root_hash);
+        encoded.extend_from_slice(&[0, 1]);
+        encoded.extend_from_slice(&leaf_hash);
+        encoded.extend_from_slice(&[1, 0]);
+        encoded.extend_from_slice(&sibling_hash);
+        encoded.push(0);
+        encoded.extend_from_slice(public_key.as_ref());
+        let proof = Proof::<RawPubkey>::decode(&mut encoded.as_slice()).expect("stored proof");
+        assert_eq!(&public_key, proof.value());
+
+        let address = ExtendedAddr::OrTree(root_hash);
+        let witness = TxInWitness::TreeSig(
+            schnorr_sign(
+                &secp,
+                &Message::from_slice(&transation.id()).unwrap(),
+                &secret_key,
+            )
+            .0,
+            proof,
+        );
+
+        assert!(verify_tx_address(&witness, &transation.id(), &address).is_ok())
+    }
+
     #[test]
     fn check_1_of_2_incorrect_proof() {
         let transation = Tx::new();
@@ -183,4 +276,67 @@ pub mod tests {
 
         assert_eq!(address, recovered_address);
     }
+
+    fn htlc_witness_parts(
+        transaction: &Tx,
+        secret_key: &SecretKey,
+    ) -> (SchnorrSignature, Proof<RawPubkey>, H256) {
+        let secp = Secp256k1::new();
+        let public_key = PublicKey::from_secret_key(&secp, secret_key);
+        let merkle_tree = MerkleTree::new(vec![RawPubkey::from(public_key.serialize())]);
+        let signature = schnorr_sign(
+            &secp,
+            &Message::from_slice(&transaction.id()).unwrap(),
+            secret_key,
+        )
+        .0;
+        let proof = merkle_tree
+            .generate_proof(RawPubkey::from(public_key.serialize()))
+            .unwrap();
+        (signature, proof, merkle_tree.root_hash())
+    }
+
+    #[test]
+    fn check_hash_time_lock_claim_and_refund_verify() {
+        let transaction = Tx::new();
+        let claim_key = SecretKey::from_slice(&[0xcd; 32]).expect("Unable to create secret key");
+        let refund_key = SecretKey::from_slice(&[0xde; 32]).expect("Unable to create secret key");
+        let (claim_sig, claim_proof, claim_root) = htlc_witness_parts(&transaction, &claim_key);
+        let (refund_sig, refund_proof, refund_root) = htlc_witness_parts(&transaction, &refund_key);
+
+        let preimage = [0x42; 32];
+        let htlc = HashTimeLock::new(hash_lock(&preimage), claim_root, refund_root, 100);
+        let address = htlc.address();
+
+        let claim = TxInWitness::HashTimeLockClaim(
+            claim_sig.clone(),
+            claim_proof.clone(),
+            htlc.clone(),
+            preimage,
+        );
+        assert!(verify_tx_address(&claim, &transaction.id(), &address).is_ok());
+
+        let wrong_preimage =
+            TxInWitness::HashTimeLockClaim(claim_sig, claim_proof, htlc.clone(), [0x43; 32]);
+        assert!(verify_tx_address(&wrong_preimage, &transaction.id(), &address).is_err());
+
+        let refund =
+            TxInWitness::HashTimeLockRefund(refund_sig.clone(), refund_proof.clone(), htlc.clone());
+        assert!(verify_tx_address(&refund, &transaction.id(), &address).is_ok());
+
+        // the refund key can't claim
+        let refund_key_claim = TxInWitness::HashTimeLockClaim(
+            refund_sig.clone(),
+            refund_proof.clone(),
+            htlc.clone(),
+            preimage,
+        );
+        assert!(verify_tx_address(&refund_key_claim, &transaction.id(), &address).is_err());
+
+        // the contract must match the address
+        let mut other_htlc = htlc;
+        other_htlc.timeout = 0;
+        let other_refund = TxInWitness::HashTimeLockRefund(refund_sig, refund_proof, other_htlc);
+        assert!(verify_tx_address(&other_refund, &transaction.id(), &address).is_err());
+    }
 }
@@ -16,7 +16,7 @@ use chain_core::state::account::StakedStateAddress;
 use client_common::balance::BalanceChange;
 use client_common::storage::SledStorage;
 use client_common::tendermint::{Client, RpcClient};
-use client_common::{Error, ErrorKind, Result, Storage};
+use client_common::{Error, ErrorKind, Result, Storage, TrustedNode};
 use client_core::signer::DefaultSigner;
 use client_core::transaction_builder::DefaultTransactionBuilder;
 use client_core::wallet::{DefaultWalletClient, WalletClient};
@@ -31,7 +31,9 @@ use self::address_command::AddressCommand;
 use self::transaction_command::TransactionCommand;
 use self::wallet_command::WalletCommand;
 
-use crate::{ask_passphrase, storage_path, tendermint_url};
+use crate::{
+    ask_passphrase, legacy_tx_height, storage_path, tendermint_url, trusted_tendermint_url,
+};
 
 #[derive(Debug, StructOpt)]
 #[structopt(
@@ -170,6 +172,7 @@ impl Command {
                     tendermint_client,
                     fee_algorithm,
                     transaction_cipher,
+                    TrustedNode::new(RpcClient::new(&trusted_tendermint_url())),
                 );
                 transaction_command.execute(network_ops_client.get_wallet(), &network_ops_client)
             }
@@ -199,6 +202,7 @@ impl Command {
                     tendermint_client,
                     fee_algorithm,
                     transaction_cipher,
+                    TrustedNode::new(RpcClient::new(&trusted_tendermint_url())),
                 );
                 Self::get_staked_stake(&network_ops_client, name, address)
             }
@@ -223,7 +227,8 @@ impl Command {
                     .with_wallet(storage.clone())
                     .build()?;
                 let synchronizer =
-                    ManualSynchronizer::new(storage, tendermint_client, block_handler);
+                    ManualSynchronizer::new(storage, tendermint_client, block_handler)
+                        .with_legacy_tx_height(legacy_tx_height()?);
 
                 Self::resync(wallet_client, synchronizer, name, *batch_size, *force)
             }
@@ -248,18 +253,63 @@ impl Command {
         ]));
         table.add_row(Row::new(vec![
             Cell::from(&"Unbonded".to_string()),
-            Cell::from(&format!("{}", staked_state.unbonded)),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::from(&"Unbonded From".to_string()),
-            Cell::from(&format!(
-                "{}",
-                <DateTime<Local>>::from(DateTime::<Utc>::from_utc(
-                    NaiveDateTime::from_timestamp(staked_state.unbonded_from, 0),
-                    Utc
-                ))
-            )),
+            Cell::from(&format!("{}", staked_state.unbonded())),
         ]));
+        let now = Utc::now().timestamp();
+        for entry in staked_state.unbonding.iter() {
+            table.add_row(Row::new(vec![
+                Cell::from(&format!(
+                    "Unbonding ({})",
+                    if entry.is_matured(now) {
+                        "matured"
+                    } else {
+                        "not matured"
+                    }
+                )),
+                Cell::from(&format!(
+                    "{} (withdrawable from {})",
+                    entry.amount,
+                    <DateTime<Local>>::from(DateTime::<Utc>::from_utc(
+                        NaiveDateTime::from_timestamp(entry.release_time, 0),
+                        Utc
+                    ))
+                )),
+            ]));
+        }
+        if let Some(delegation) = staked_state.delegation {
+            table.add_row(Row::new(vec![
+                Cell::from(&"Delegated".to_string()),
+                Cell::from(&format!(
+                    "{} (to council node {})",
+                    delegation.amount, delegation.council_node
+                )),
+            ]));
+        }
+        if let Some(vesting) = staked_state.vesting {
+            table.add_row(Row::new(vec![
+                Cell::from(&"Vesting Locked".to_string()),
+                Cell::from(&format!(
+                    "{} (fully released at {})",
+                    vesting.locked(now),
+                    <DateTime<Local>>::from(DateTime::<Utc>::from_utc(
+                        NaiveDateTime::from_timestamp(vesting.end, 0),
+                        Utc
+                    ))
+                )),
+            ]));
+        }
+        if let Some(jailed_until) = staked_state.jailed_until {
+            table.add_row(Row::new(vec![
+                Cell::from(&"Jailed Until".to_string()),
+                Cell::from(&format!(
+                    "{}",
+                    <DateTime<Local>>::from(DateTime::<Utc>::from_utc(
+                        NaiveDateTime::from_timestamp(jailed_until, 0),
+                        Utc
+                    ))
+                )),
+            ]));
+        }
 
         table.printstd();
 
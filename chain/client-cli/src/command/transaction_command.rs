@@ -8,13 +8,18 @@ use structopt::StructOpt;
 use unicase::eq_ascii;
 
 use chain_core::common::{Timespec, HASH_SIZE_256};
+use chain_core::init::address::CroAddress;
 use chain_core::init::coin::Coin;
+use chain_core::init::network::Network;
 use chain_core::state::account::{StakedStateAddress, StakedStateOpAttributes};
+use chain_core::state::governance::NetworkParameterChange;
+use chain_core::state::tendermint::{BlockHeight, TendermintValidatorPubKey};
 use chain_core::tx::data::access::{TxAccess, TxAccessPolicy};
 use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::attribute::TxAttributes;
 use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
+use chain_core::tx::fee::{LinearFee, Milli};
 use chain_core::tx::TxAux;
 use client_common::{Error, ErrorKind, PublicKey, Result};
 use client_core::WalletClient;
@@ -27,7 +32,14 @@ pub enum TransactionType {
     Transfer,
     Deposit,
     Unbond,
+    Rebond,
+    Delegate,
+    Undelegate,
     Withdraw,
+    NodeJoin,
+    Unjail,
+    ParamChangeProposal,
+    ParamChangeVote,
 }
 
 impl FromStr for TransactionType {
@@ -40,8 +52,22 @@ impl FromStr for TransactionType {
             Ok(TransactionType::Deposit)
         } else if eq_ascii(s, "unbond") {
             Ok(TransactionType::Unbond)
+        } else if eq_ascii(s, "rebond") {
+            Ok(TransactionType::Rebond)
+        } else if eq_ascii(s, "delegate") {
+            Ok(TransactionType::Delegate)
+        } else if eq_ascii(s, "undelegate") {
+            Ok(TransactionType::Undelegate)
         } else if eq_ascii(s, "withdraw") {
             Ok(TransactionType::Withdraw)
+        } else if eq_ascii(s, "node-join") {
+            Ok(TransactionType::NodeJoin)
+        } else if eq_ascii(s, "unjail") {
+            Ok(TransactionType::Unjail)
+        } else if eq_ascii(s, "param-change-proposal") {
+            Ok(TransactionType::ParamChangeProposal)
+        } else if eq_ascii(s, "param-change-vote") {
+            Ok(TransactionType::ParamChangeVote)
         } else {
             Err(ErrorKind::DeserializationError.into())
         }
@@ -107,6 +133,15 @@ fn new_transaction<T: WalletClient, N: NetworkOpsClient>(
         TransactionType::Unbond => {
             new_unbond_transaction(network_ops_client, name, &passphrase, chain_id)
         }
+        TransactionType::Rebond => {
+            new_rebond_transaction(network_ops_client, name, &passphrase, chain_id)
+        }
+        TransactionType::Delegate => {
+            new_delegate_transaction(network_ops_client, name, &passphrase, chain_id)
+        }
+        TransactionType::Undelegate => {
+            new_undelegate_transaction(network_ops_client, name, &passphrase, chain_id)
+        }
         TransactionType::Withdraw => new_withdraw_transaction(
             wallet_client,
             network_ops_client,
@@ -114,6 +149,18 @@ fn new_transaction<T: WalletClient, N: NetworkOpsClient>(
             &passphrase,
             chain_id,
         ),
+        TransactionType::NodeJoin => {
+            new_node_join_transaction(network_ops_client, name, &passphrase, chain_id)
+        }
+        TransactionType::Unjail => {
+            new_unjail_transaction(network_ops_client, name, &passphrase, chain_id)
+        }
+        TransactionType::ParamChangeProposal => {
+            new_param_change_proposal_transaction(network_ops_client, name, &passphrase, chain_id)
+        }
+        TransactionType::ParamChangeVote => {
+            new_param_change_vote_transaction(network_ops_client, name, &passphrase, chain_id)
+        }
     }?;
 
     wallet_client.broadcast_transaction(&transaction)?;
@@ -129,7 +176,7 @@ fn new_withdraw_transaction<T: WalletClient, N: NetworkOpsClient>(
     chain_id: &str,
 ) -> Result<TxAux> {
     let from_address = ask_staking_address()?;
-    let to_address = ask_transfer_address()?;
+    let to_address = ask_transfer_address(wallet_client.chain_params().network())?;
     let view_keys = ask_view_keys()?;
 
     let self_view_key = wallet_client.view_key(name, passphrase)?;
@@ -180,6 +227,168 @@ fn new_unbond_transaction<N: NetworkOpsClient>(
         .create_unbond_stake_transaction(name, passphrase, &address, value, attributes)
 }
 
+fn new_rebond_transaction<N: NetworkOpsClient>(
+    network_ops_client: &N,
+    name: &str,
+    passphrase: &SecUtf8,
+    chain_id: &str,
+) -> Result<TxAux> {
+    let attributes =
+        StakedStateOpAttributes::new(decode(chain_id).context(ErrorKind::DeserializationError)?[0]);
+    let address = ask_staking_address()?;
+
+    ask("Enter amount: ");
+    let value = text()
+        .context(ErrorKind::IoError)?
+        .parse::<Coin>()
+        .context(ErrorKind::DeserializationError)?;
+
+    network_ops_client
+        .create_rebond_stake_transaction(name, passphrase, &address, value, attributes)
+}
+
+fn new_delegate_transaction<N: NetworkOpsClient>(
+    network_ops_client: &N,
+    name: &str,
+    passphrase: &SecUtf8,
+    chain_id: &str,
+) -> Result<TxAux> {
+    let attributes =
+        StakedStateOpAttributes::new(decode(chain_id).context(ErrorKind::DeserializationError)?[0]);
+    let address = ask_staking_address()?;
+    let council_node = ask_council_node_address()?;
+
+    ask("Enter amount: ");
+    let value = text()
+        .context(ErrorKind::IoError)?
+        .parse::<Coin>()
+        .context(ErrorKind::DeserializationError)?;
+
+    network_ops_client.create_delegate_stake_transaction(
+        name,
+        passphrase,
+        &address,
+        council_node,
+        value,
+        attributes,
+    )
+}
+
+fn new_undelegate_transaction<N: NetworkOpsClient>(
+    network_ops_client: &N,
+    name: &str,
+    passphrase: &SecUtf8,
+    chain_id: &str,
+) -> Result<TxAux> {
+    let attributes =
+        StakedStateOpAttributes::new(decode(chain_id).context(ErrorKind::DeserializationError)?[0]);
+    let address = ask_staking_address()?;
+    let council_node = ask_council_node_address()?;
+
+    ask("Enter amount: ");
+    let value = text()
+        .context(ErrorKind::IoError)?
+        .parse::<Coin>()
+        .context(ErrorKind::DeserializationError)?;
+
+    network_ops_client.create_undelegate_stake_transaction(
+        name,
+        passphrase,
+        &address,
+        council_node,
+        value,
+        attributes,
+    )
+}
+
+fn new_node_join_transaction<N: NetworkOpsClient>(
+    network_ops_client: &N,
+    name: &str,
+    passphrase: &SecUtf8,
+    chain_id: &str,
+) -> Result<TxAux> {
+    let attributes =
+        StakedStateOpAttributes::new(decode(chain_id).context(ErrorKind::DeserializationError)?[0]);
+    let address = ask_staking_address()?;
+    let consensus_pubkey = ask_consensus_pubkey()?;
+
+    network_ops_client.create_node_join_transaction(
+        name,
+        passphrase,
+        &address,
+        consensus_pubkey,
+        attributes,
+    )
+}
+
+fn new_unjail_transaction<N: NetworkOpsClient>(
+    network_ops_client: &N,
+    name: &str,
+    passphrase: &SecUtf8,
+    chain_id: &str,
+) -> Result<TxAux> {
+    let attributes =
+        StakedStateOpAttributes::new(decode(chain_id).context(ErrorKind::DeserializationError)?[0]);
+    let address = ask_staking_address()?;
+
+    network_ops_client.create_unjail_transaction(name, passphrase, &address, attributes)
+}
+
+fn new_param_change_proposal_transaction<N: NetworkOpsClient>(
+    network_ops_client: &N,
+    name: &str,
+    passphrase: &SecUtf8,
+    chain_id: &str,
+) -> Result<TxAux> {
+    let attributes =
+        StakedStateOpAttributes::new(decode(chain_id).context(ErrorKind::DeserializationError)?[0]);
+    let address = ask_staking_address()?;
+    let change = ask_network_parameter_change()?;
+
+    ask("Enter activation block height: ");
+    let activation_height = text()
+        .context(ErrorKind::IoError)?
+        .parse::<BlockHeight>()
+        .context(ErrorKind::DeserializationError)?;
+
+    network_ops_client.create_param_change_proposal_transaction(
+        name,
+        passphrase,
+        &address,
+        change,
+        activation_height,
+        attributes,
+    )
+}
+
+fn new_param_change_vote_transaction<N: NetworkOpsClient>(
+    network_ops_client: &N,
+    name: &str,
+    passphrase: &SecUtf8,
+    chain_id: &str,
+) -> Result<TxAux> {
+    let attributes =
+        StakedStateOpAttributes::new(decode(chain_id).context(ErrorKind::DeserializationError)?[0]);
+    let address = ask_staking_address()?;
+
+    ask("Enter proposal transaction ID: ");
+    let proposal_id_decoded =
+        decode(&text().context(ErrorKind::IoError)?).context(ErrorKind::DeserializationError)?;
+    if proposal_id_decoded.len() != HASH_SIZE_256 {
+        return Err(ErrorKind::DeserializationError.into());
+    }
+    let mut proposal_id: [u8; HASH_SIZE_256] = [0; HASH_SIZE_256];
+    proposal_id.copy_from_slice(&proposal_id_decoded);
+
+    network_ops_client.create_param_change_vote_transaction(
+        name,
+        passphrase,
+        &address,
+        proposal_id,
+        attributes,
+    )
+}
+
 fn new_deposit_transaction<N: NetworkOpsClient>(
     network_ops_client: &N,
     name: &str,
@@ -201,7 +410,7 @@ fn new_transfer_transaction<T: WalletClient>(
     passphrase: &SecUtf8,
     chain_id: &str,
 ) -> Result<TxAux> {
-    let outputs = ask_outputs()?;
+    let outputs = ask_outputs(wallet_client.chain_params().network())?;
     let view_keys = ask_view_keys()?;
 
     let self_view_key = wallet_client.view_key(name, passphrase)?;
@@ -248,7 +457,7 @@ fn ask_view_keys() -> Result<Vec<PublicKey>> {
     }
 }
 
-fn ask_outputs() -> Result<Vec<TxOut>> {
+fn ask_outputs(network: Network) -> Result<Vec<TxOut>> {
     let mut outputs = Vec::new();
 
     let mut flag = true;
@@ -257,8 +466,7 @@ fn ask_outputs() -> Result<Vec<TxOut>> {
         ask("Enter output address: ");
         let address_encoded = text().context(ErrorKind::IoError)?;
 
-        let address = address_encoded
-            .parse::<ExtendedAddr>()
+        let address = ExtendedAddr::from_cro_for(&address_encoded, network)
             .context(ErrorKind::DeserializationError)?;
 
         ask("Enter amount: ");
@@ -341,11 +549,72 @@ fn ask_staking_address() -> Result<StakedStateAddress> {
     Ok(address)
 }
 
-fn ask_transfer_address() -> Result<ExtendedAddr> {
-    ask("Enter transfer address: ");
+fn ask_council_node_address() -> Result<StakedStateAddress> {
+    ask("Enter council node staking address: ");
     let address = text()
         .context(ErrorKind::IoError)?
-        .parse::<ExtendedAddr>()
+        .parse::<StakedStateAddress>()
+        .context(ErrorKind::DeserializationError)?;
+
+    Ok(address)
+}
+
+fn ask_network_parameter_change() -> Result<NetworkParameterChange> {
+    ask("Enter network parameter to change (fee-policy/unbonding-period/required-council-node-stake): ");
+    let parameter = text().context(ErrorKind::IoError)?;
+
+    if eq_ascii(&parameter, "fee-policy") {
+        ask("Enter base fee: ");
+        let constant = text()
+            .context(ErrorKind::IoError)?
+            .parse::<Milli>()
+            .context(ErrorKind::DeserializationError)?;
+        ask("Enter per byte fee: ");
+        let coefficient = text()
+            .context(ErrorKind::IoError)?
+            .parse::<Milli>()
+            .context(ErrorKind::DeserializationError)?;
+        Ok(NetworkParameterChange::FeePolicy(LinearFee::new(
+            constant,
+            coefficient,
+        )))
+    } else if eq_ascii(&parameter, "unbonding-period") {
+        ask("Enter unbonding period (in seconds): ");
+        let period = text()
+            .context(ErrorKind::IoError)?
+            .parse::<u32>()
+            .context(ErrorKind::DeserializationError)?;
+        Ok(NetworkParameterChange::UnbondingPeriod(period))
+    } else if eq_ascii(&parameter, "required-council-node-stake") {
+        ask("Enter required council node stake: ");
+        let stake = text()
+            .context(ErrorKind::IoError)?
+            .parse::<Coin>()
+            .context(ErrorKind::DeserializationError)?;
+        Ok(NetworkParameterChange::RequiredCouncilNodeStake(stake))
+    } else {
+        Err(ErrorKind::DeserializationError.into())
+    }
+}
+
+fn ask_consensus_pubkey() -> Result<TendermintValidatorPubKey> {
+    ask("Enter base64-encoded Ed25519 consensus public key (as in Tendermint's priv_validator_key.json): ");
+    let decoded = base64::decode(&text().context(ErrorKind::IoError)?)
+        .context(ErrorKind::DeserializationError)?;
+
+    if decoded.len() != 32 {
+        return Err(ErrorKind::DeserializationError.into());
+    }
+
+    let mut pubkey = [0u8; 32];
+    pubkey.copy_from_slice(&decoded);
+
+    Ok(TendermintValidatorPubKey::Ed25519(pubkey))
+}
+
+fn ask_transfer_address(network: Network) -> Result<ExtendedAddr> {
+    ask("Enter transfer address: ");
+    let address = ExtendedAddr::from_cro_for(&text().context(ErrorKind::IoError)?, network)
         .context(ErrorKind::DeserializationError)?;
 
     Ok(address)
@@ -7,6 +7,7 @@ use quest::{ask, error, password};
 use secstr::SecUtf8;
 use structopt::StructOpt;
 
+use chain_core::init::network::{init_chain_params, ChainParams};
 use client_common::{ErrorKind, Result};
 
 use crate::command::Command;
@@ -29,6 +30,12 @@ fn main() {
 }
 
 fn execute() -> Result<()> {
+    // addresses are read and displayed in the textual format of the network (devnet by default)
+    if let Ok(chain_id) = std::env::var("CRYPTO_CHAIN_ID") {
+        let chain_params =
+            ChainParams::from_chain_id(&chain_id).map_err(|_| ErrorKind::InvalidInput)?;
+        init_chain_params(chain_params);
+    }
     let command = Command::from_args();
     command.execute()
 }
@@ -47,6 +54,23 @@ pub(crate) fn tendermint_url() -> String {
     }
 }
 
+/// Tendermint node the app hashes are taken from to verify the queried staked states
+/// (it should be a node the user trusts; defaults to the queried node)
+pub(crate) fn trusted_tendermint_url() -> String {
+    match std::env::var("CRYPTO_CLIENT_TRUSTED_TENDERMINT") {
+        Ok(url) => url,
+        Err(_) => tendermint_url(),
+    }
+}
+
+/// Last block height with transactions in the legacy format (committed before the transaction format was versioned)
+pub(crate) fn legacy_tx_height() -> Result<u64> {
+    match std::env::var("CRYPTO_LEGACY_TX_HEIGHT") {
+        Ok(height) => Ok(height.parse().context(ErrorKind::InvalidInput)?),
+        Err(_) => Ok(0),
+    }
+}
+
 pub(crate) fn ask_passphrase(message: Option<&str>) -> Result<SecUtf8> {
     match message {
         None => ask("Enter passphrase: "),
@@ -1,7 +1,7 @@
 use chrono::{DateTime, Utc};
 
 use chain_core::tx::data::TxId;
-use chain_tx_filter::BlockFilter;
+use chain_tx_filter::{BlockFilter, TxFilters};
 
 use crate::Transaction;
 
@@ -18,6 +18,8 @@ pub struct BlockHeader {
     pub transaction_ids: Vec<TxId>,
     /// Bloom filter for view keys and staking addresses
     pub block_filter: BlockFilter,
+    /// Per-transaction view key filters (`None` if the node didn't emit them)
+    pub tx_filters: Option<TxFilters>,
     /// List of un-encrypted transactions (only contains transactions of type `DepositStake` and `UnbondStake`)
     pub unencrypted_transactions: Vec<Transaction>,
 }
@@ -128,6 +128,9 @@ pub enum ErrorKind {
     /// Transaction validation failure
     #[fail(display = "Transaction validation failed")]
     TransactionValidationFailed,
+    /// Invalid inclusion proof
+    #[fail(display = "Invalid inclusion proof")]
+    InvalidProof,
 }
 
 impl Fail for Error {
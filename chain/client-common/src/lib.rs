@@ -1,6 +1,7 @@
 #![deny(missing_docs, unsafe_code, unstable_features)]
 //! This crate contains all the common types and utilities used by other `client-*` crates.
 mod block_header;
+mod proof;
 mod transaction;
 
 pub mod balance;
@@ -16,6 +17,8 @@ pub use error::{Error, ErrorKind, Result};
 #[doc(inline)]
 pub use key::{PrivateKey, PublicKey};
 #[doc(inline)]
+pub use proof::{verify_staked_state, StakedStateProof, TrustedAppHashes, TrustedNode};
+#[doc(inline)]
 pub use storage::{SecureStorage, Storage};
 #[doc(inline)]
 pub use transaction::{SignedTransaction, Transaction};
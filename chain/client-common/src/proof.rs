@@ -0,0 +1,193 @@
+use std::thread;
+use std::time::Duration;
+
+use failure::{format_err, ResultExt};
+use parity_scale_codec::Decode;
+
+use chain_core::common::{H256, HASH_SIZE_256};
+use chain_core::state::account::StakedState;
+use chain_core::state::proof::AccountProof;
+
+use crate::tendermint::types::QueryResult;
+use crate::tendermint::Client;
+use crate::{ErrorKind, Result};
+
+/// How many times the header committing the app hash of a state is polled for
+const NEXT_BLOCK_POLLS: usize = 10;
+/// Interval between polling for the header committing the app hash of a state
+const NEXT_BLOCK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Source of the app hashes the query results are verified against
+pub trait TrustedAppHashes: Send + Sync {
+    /// Returns the app hash of the state committed at `height` (in the header of the next block)
+    fn app_hash(&self, height: u64) -> Result<H256>;
+}
+
+/// Takes the app hashes from the block headers of a tendermint node the client trusts
+/// (e.g. its own full node); it shouldn't be the node the queries are sent to
+#[derive(Clone)]
+pub struct TrustedNode<C: Client> {
+    client: C,
+}
+
+impl<C: Client> TrustedNode<C> {
+    /// Creates a new instance of `TrustedNode`
+    #[inline]
+    pub fn new(client: C) -> Self {
+        Self { client }
+    }
+}
+
+impl<C: Client> TrustedAppHashes for TrustedNode<C> {
+    fn app_hash(&self, height: u64) -> Result<H256> {
+        // the next block may not be committed yet
+        for _ in 0..NEXT_BLOCK_POLLS {
+            if self.client.status()?.last_block_height()? > height {
+                let decoded = hex::decode(self.client.block(height + 1)?.app_hash())
+                    .context(ErrorKind::DeserializationError)?;
+                if decoded.len() != HASH_SIZE_256 {
+                    return Err(ErrorKind::DeserializationError.into());
+                }
+                let mut app_hash = [0u8; HASH_SIZE_256];
+                app_hash.copy_from_slice(&decoded);
+                return Ok(app_hash);
+            }
+            thread::sleep(NEXT_BLOCK_POLL_INTERVAL);
+        }
+        Err(
+            format_err!("No block committed the app hash at height {} yet", height)
+                .context(ErrorKind::RpcError)
+                .into(),
+        )
+    }
+}
+
+/// Staked state in the result of the `account` query (made with `prove` set) with its inclusion proof.
+///
+/// The staked state is only verified once the proof is checked against the app hash committed at `height`
+/// (in the header of the next block). That header needs to come from a trusted source (see `TrustedAppHashes`),
+/// not from the node that answered the query: otherwise the node can make up both the staked state and the app hash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StakedStateProof {
+    /// Queried staked state
+    pub staked_state: StakedState,
+    /// Proof of the staked state's inclusion in the account trie and the other app hash components
+    pub proof: AccountProof,
+    /// Height of the state the query was answered from
+    pub height: u64,
+}
+
+impl StakedStateProof {
+    /// Decodes the staked state and its proof in the result of the `account` query
+    pub fn from_query_result(result: &QueryResult) -> Result<Self> {
+        let bytes = result.bytes()?;
+        let staked_state =
+            StakedState::decode(&mut bytes.as_slice()).context(ErrorKind::DeserializationError)?;
+        let (key, data) = result.proof_op("account")?.ok_or(ErrorKind::InvalidProof)?;
+        if key[..] != staked_state.key()[..] {
+            return Err(ErrorKind::InvalidProof.into());
+        }
+        let proof = AccountProof::decode(&mut data.as_slice()).context(ErrorKind::InvalidProof)?;
+
+        Ok(StakedStateProof {
+            staked_state,
+            proof,
+            height: result.height()?,
+        })
+    }
+
+    /// Verifies the staked state against the trusted app hash committed at `height`
+    pub fn verify(&self, trusted_app_hash: &H256) -> Result<&StakedState> {
+        if self.proof.verify(&self.staked_state, trusted_app_hash) {
+            Ok(&self.staked_state)
+        } else {
+            Err(ErrorKind::InvalidProof.into())
+        }
+    }
+}
+
+/// Decodes the staked state in the result of the `account` query (made with `prove` set)
+/// and verifies its inclusion in the account trie committed in the trusted app hash (see `StakedStateProof`)
+pub fn verify_staked_state(result: &QueryResult, trusted_app_hash: &H256) -> Result<StakedState> {
+    let staked_state_proof = StakedStateProof::from_query_result(result)?;
+    staked_state_proof.verify(trusted_app_hash)?;
+    Ok(staked_state_proof.staked_state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use base64::encode;
+    use parity_scale_codec::Encode;
+
+    use chain_core::init::address::RedeemAddress;
+    use chain_core::init::coin::Coin;
+    use chain_core::state::proof::AppHashParts;
+
+    use crate::tendermint::types::{Proof, ProofOp, Response};
+
+    fn query_result(staked_state: &StakedState, proof: &AccountProof) -> QueryResult {
+        QueryResult {
+            response: Response {
+                value: encode(&staked_state.encode()),
+                height: "1".to_owned(),
+                proof: Some(Proof {
+                    ops: vec![ProofOp {
+                        field_type: "account".to_owned(),
+                        key: Some(encode(&staked_state.key())),
+                        data: Some(encode(&proof.encode())),
+                    }],
+                }),
+            },
+        }
+    }
+
+    #[test]
+    fn check_verify_staked_state() {
+        let staked_state = StakedState::new(
+            1,
+            Coin::unit(),
+            Vec::new(),
+            RedeemAddress::from([1; 20]).into(),
+        );
+        // the trie only has the account's leaf
+        let proof = AccountProof {
+            siblings: Vec::new(),
+            app_hash_parts: AppHashParts {
+                valid_tx_root: [0; 32],
+                rewards_pool_hash: [1; 32],
+                network_params_hash: [2; 32],
+                node_state_hash: [3; 32],
+            },
+        };
+        let app_hash = proof
+            .app_hash_parts
+            .app_hash(&proof.account_root(&staked_state));
+        let result = query_result(&staked_state, &proof);
+
+        assert_eq!(
+            1,
+            StakedStateProof::from_query_result(&result).unwrap().height
+        );
+
+        assert_eq!(
+            staked_state,
+            verify_staked_state(&result, &app_hash).unwrap()
+        );
+        assert_eq!(
+            ErrorKind::InvalidProof,
+            verify_staked_state(&result, &[3; 32]).unwrap_err().kind()
+        );
+
+        let mut other_state = staked_state.clone();
+        other_state.bonded = Coin::zero();
+        let other_result = query_result(&other_state, &proof);
+        assert_eq!(
+            ErrorKind::InvalidProof,
+            verify_staked_state(&other_result, &app_hash)
+                .unwrap_err()
+                .kind()
+        );
+    }
+}
@@ -28,6 +28,6 @@ pub trait Client: Send + Sync {
     /// Makes `broadcast_tx_sync` call to tendermint
     fn broadcast_transaction(&self, transaction: &[u8]) -> Result<BroadcastTxResult>;
 
-    /// Makes `abci_query` call to tendermint
-    fn query(&self, path: &str, data: &[u8]) -> Result<QueryResult>;
+    /// Makes `abci_query` call to tendermint (the result includes a proof if `prove` is set and the query supports it)
+    fn query(&self, path: &str, data: &[u8], prove: bool) -> Result<QueryResult>;
 }
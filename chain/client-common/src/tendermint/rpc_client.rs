@@ -148,12 +148,12 @@ impl Client for RpcClient {
             })
     }
 
-    fn query(&self, path: &str, data: &[u8]) -> Result<QueryResult> {
+    fn query(&self, path: &str, data: &[u8], prove: bool) -> Result<QueryResult> {
         let params = [
             json!(path),
             json!(hex::encode(data)),
             json!(null),
-            json!(null),
+            json!(prove),
         ];
         self.call("abci_query", &params)
     }
@@ -36,8 +36,13 @@ pub struct Header {
 impl Block {
     /// Returns un-encrypted transactions in a block (this may also contain invalid transactions)
     ///
+    /// `legacy_tx_height` is the last block height whose transactions are in the legacy layout
+    /// (committed before the transaction format was versioned; 0 if the chain started with versioned transactions).
+    ///
     /// NOTE: Un-encrypted transactions only contain deposit stake and unbond stake transactions
-    pub fn unencrypted_transactions(&self) -> Result<Vec<Transaction>> {
+    pub fn unencrypted_transactions(&self, legacy_tx_height: u64) -> Result<Vec<Transaction>> {
+        let is_legacy = self.height()? <= legacy_tx_height;
+
         match &self.block.data.txs {
             None => Ok(Vec::new()),
             Some(transactions) => transactions
@@ -45,8 +50,17 @@ impl Block {
                 .map(|raw_transaction| -> Result<TxAux> {
                     let decoded =
                         decode(&raw_transaction).context(ErrorKind::DeserializationError)?;
-                    let tx_aux = TxAux::decode(&mut decoded.as_slice())
-                        .context(ErrorKind::DeserializationError)?;
+                    let mut data = decoded.as_slice();
+                    let tx_aux = if is_legacy {
+                        TxAux::decode_legacy(&mut data)
+                    } else {
+                        TxAux::decode(&mut data)
+                    }
+                    .context(ErrorKind::DeserializationError)?;
+
+                    if !data.is_empty() {
+                        return Err(ErrorKind::DeserializationError.into());
+                    }
 
                     Ok(tx_aux)
                 })
@@ -158,7 +172,7 @@ mod tests {
             },
         };
 
-        let unencrypted_transactions = block.unencrypted_transactions().unwrap();
+        let unencrypted_transactions = block.unencrypted_transactions(0).unwrap();
         assert_eq!(1, unencrypted_transactions.len());
 
         match (transaction, &unencrypted_transactions[0]) {
@@ -170,6 +184,51 @@ mod tests {
         }
     }
 
+    #[test]
+    fn check_legacy_unencrypted_transactions() {
+        let witness = match unbond_transaction() {
+            TxAux::UnbondStakeTx(_, witness) => witness,
+            _ => unreachable!(),
+        };
+        // variant tag, value, nonce, chain id (attributes last, without the version), witness
+        let mut legacy = vec![2u8];
+        legacy.extend_from_slice(&100u64.to_le_bytes());
+        legacy.extend_from_slice(&0u64.to_le_bytes());
+        legacy.push(0);
+        legacy.extend_from_slice(&witness.encode());
+        let mut trailing = legacy.clone();
+        trailing.push(0);
+
+        let block = |raw_transaction: &[u8]| Block {
+            block: BlockInner {
+                header: Header {
+                    app_hash: "3891040F29C6A56A5E36B17DCA6992D8F91D1EAAB4439D008D19A9D703271D3C"
+                        .to_owned(),
+                    height: "1".to_owned(),
+                    time: DateTime::from_str("2019-04-09T09:38:41.735577Z").unwrap(),
+                },
+                data: Data {
+                    txs: Some(vec![encode(raw_transaction)]),
+                },
+            },
+        };
+
+        let unencrypted_transactions = block(&legacy).unencrypted_transactions(1).unwrap();
+        match &unencrypted_transactions[..] {
+            [Transaction::UnbondStakeTransaction(unbond_transaction)] => {
+                assert_eq!(Coin::new(100).unwrap(), unbond_transaction.value);
+                assert_eq!(0, unbond_transaction.attributes.version);
+            }
+            _ => unreachable!(),
+        }
+        // the block is after the legacy transactions
+        assert!(block(&legacy).unencrypted_transactions(0).is_err());
+        assert!(block(&trailing).unencrypted_transactions(1).is_err());
+        assert!(block(&unbond_transaction().encode())
+            .unencrypted_transactions(1)
+            .is_err());
+    }
+
     #[test]
     fn check_height() {
         let block = Block {
@@ -1,5 +1,4 @@
 #![allow(missing_docs)]
-use std::convert::TryFrom;
 
 use base64::decode;
 use failure::ResultExt;
@@ -7,7 +6,7 @@ use serde::Deserialize;
 
 use chain_core::common::TendermintEventType;
 use chain_core::tx::data::TxId;
-use chain_tx_filter::BlockFilter;
+use chain_tx_filter::{BlockFilter, TxFilters, TX_FILTERS_KEY};
 
 use crate::{Error, ErrorKind, Result};
 
@@ -80,24 +79,56 @@ impl BlockResults {
         }
     }
 
+    /// Returns the decoded key-value pairs of the block filter event in block results
+    fn block_filter_attributes(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let end_block = match &self.results.end_block {
+            None => return Ok(Vec::new()),
+            Some(ref end_block) => end_block,
+        };
+        for event in end_block.events.iter() {
+            if event.event_type == TendermintEventType::BlockFilter.to_string() {
+                return event
+                    .attributes
+                    .iter()
+                    .map(|attribute| -> Result<(Vec<u8>, Vec<u8>)> {
+                        let key =
+                            decode(&attribute.key).context(ErrorKind::DeserializationError)?;
+                        let value =
+                            decode(&attribute.value).context(ErrorKind::DeserializationError)?;
+                        Ok((key, value))
+                    })
+                    .collect();
+            }
+        }
+        Ok(Vec::new())
+    }
+
     /// Returns block filter in block results
     pub fn block_filter(&self) -> Result<BlockFilter> {
-        match &self.results.end_block {
+        // the key identifies the filter format (ethbloom or a Golomb-coded set)
+        match self
+            .block_filter_attributes()?
+            .into_iter()
+            .find(|(key, _)| key.as_slice() != TX_FILTERS_KEY)
+        {
             None => Ok(BlockFilter::default()),
-            Some(ref end_block) => {
-                for event in end_block.events.iter() {
-                    if event.event_type == TendermintEventType::BlockFilter.to_string() {
-                        let attribute = &event.attributes[0];
-                        let decoded =
-                            decode(&attribute.value).context(ErrorKind::DeserializationError)?;
-
-                        return Ok(BlockFilter::try_from(decoded.as_slice())
-                            .map_err(|_| Error::from(ErrorKind::DeserializationError))?);
-                    }
-                }
+            Some((key, value)) => Ok(BlockFilter::from_tendermint_kv(&key, &value)
+                .map_err(|_| Error::from(ErrorKind::DeserializationError))?),
+        }
+    }
 
-                Ok(BlockFilter::default())
-            }
+    /// Returns per-transaction view key filters in block results (if the node emitted them)
+    pub fn tx_filters(&self) -> Result<Option<TxFilters>> {
+        match self
+            .block_filter_attributes()?
+            .into_iter()
+            .find(|(key, _)| key.as_slice() == TX_FILTERS_KEY)
+        {
+            None => Ok(None),
+            Some((_, value)) => Ok(Some(
+                TxFilters::from_tendermint_value(&value)
+                    .map_err(|_| Error::from(ErrorKind::DeserializationError))?,
+            )),
         }
     }
 }
@@ -107,6 +138,9 @@ mod tests {
     use super::*;
 
     use base64::encode;
+    use chain_core::init::address::RedeemAddress;
+    use chain_core::state::account::StakedStateAddress;
+    use chain_tx_filter::{FilterFormat, ETHBLOOM_FILTER_KEY};
 
     #[test]
     fn check_ids() {
@@ -138,7 +172,7 @@ mod tests {
                     events: vec![Event {
                         event_type: TendermintEventType::BlockFilter.to_string(),
                         attributes: vec![Attribute {
-                            key: "ethbloom".to_owned(),
+                            key: encode(ETHBLOOM_FILTER_KEY),
                             value: encode(&[0; 256][..]),
                         }],
                     }],
@@ -148,6 +182,61 @@ mod tests {
         assert!(block_results.block_filter().is_ok());
     }
 
+    #[test]
+    fn check_gcs_block_filter() {
+        let address = StakedStateAddress::BasicRedeem(RedeemAddress::from([1; 20]));
+        let mut filter = BlockFilter::new(FilterFormat::Gcs { fp_rate_bits: 20 });
+        filter.add_staked_state_address(&address);
+        let (key, value) = filter.get_tendermint_kv().unwrap();
+        let block_results = BlockResults {
+            height: "2".to_owned(),
+            results: Results {
+                deliver_tx: None,
+                end_block: Some(EndBlock {
+                    events: vec![Event {
+                        event_type: TendermintEventType::BlockFilter.to_string(),
+                        attributes: vec![Attribute {
+                            key: encode(&key),
+                            value: encode(&value),
+                        }],
+                    }],
+                }),
+            },
+        };
+        let decoded = block_results.block_filter().unwrap();
+        assert!(decoded.check_staked_state_address(&address));
+    }
+
+    #[test]
+    fn check_tx_filters() {
+        let mut tx_filters = TxFilters::default();
+        tx_filters.add_transaction([1; 32], &[]);
+        let (key, value) = tx_filters.get_tendermint_kv();
+        let block_results = BlockResults {
+            height: "2".to_owned(),
+            results: Results {
+                deliver_tx: None,
+                end_block: Some(EndBlock {
+                    events: vec![Event {
+                        event_type: TendermintEventType::BlockFilter.to_string(),
+                        attributes: vec![
+                            Attribute {
+                                key: encode(ETHBLOOM_FILTER_KEY),
+                                value: encode(&[0; 256][..]),
+                            },
+                            Attribute {
+                                key: encode(&key),
+                                value: encode(&value),
+                            },
+                        ],
+                    }],
+                }),
+            },
+        };
+        assert!(block_results.block_filter().is_ok());
+        assert_eq!(Some(tx_filters), block_results.tx_filters().unwrap());
+    }
+
     #[test]
     fn check_wrong_id() {
         let block_results = BlockResults {
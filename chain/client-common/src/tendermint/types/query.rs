@@ -10,13 +10,60 @@ pub struct QueryResult {
     pub response: Response,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct Response {
     pub value: String,
+    #[serde(default)]
+    pub height: String,
+    #[serde(default)]
+    pub proof: Option<Proof>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Proof {
+    pub ops: Vec<ProofOp>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProofOp {
+    #[serde(rename = "type")]
+    pub field_type: String,
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub data: Option<String>,
 }
 
 impl QueryResult {
     pub fn bytes(&self) -> Result<Vec<u8>> {
         Ok(decode(&self.response.value).context(ErrorKind::DeserializationError)?)
     }
+
+    /// Returns the height of the state the query was answered from
+    pub fn height(&self) -> Result<u64> {
+        Ok(self
+            .response
+            .height
+            .parse::<u64>()
+            .context(ErrorKind::DeserializationError)?)
+    }
+
+    /// Returns the decoded key and data of the proof operation of the given type (if it's included)
+    pub fn proof_op(&self, field_type: &str) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        let op = self
+            .response
+            .proof
+            .as_ref()
+            .and_then(|proof| proof.ops.iter().find(|op| op.field_type == field_type));
+        match op {
+            None => Ok(None),
+            Some(op) => {
+                let key = decode(op.key.as_ref().map_or("", String::as_str))
+                    .context(ErrorKind::DeserializationError)?;
+                let data = decode(op.data.as_ref().map_or("", String::as_str))
+                    .context(ErrorKind::DeserializationError)?;
+                Ok(Some((key, data)))
+            }
+        }
+    }
 }
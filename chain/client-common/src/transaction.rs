@@ -1,4 +1,4 @@
-use parity_scale_codec::{Decode, Encode};
+use parity_scale_codec::{Decode, Encode, Error, Input};
 use serde::{Deserialize, Serialize};
 
 use chain_core::state::account::{
@@ -6,7 +6,7 @@ use chain_core::state::account::{
 };
 use chain_core::tx::data::{Tx, TxId};
 use chain_core::tx::witness::TxWitness;
-use chain_core::tx::TransactionId;
+use chain_core::tx::{RedactedTx, TransactionId};
 
 /// Enum containing different types of transactions
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
@@ -20,6 +20,8 @@ pub enum Transaction {
     UnbondStakeTransaction(UnbondTx),
     /// Withdraw unbounded stake transaction
     WithdrawUnbondedStakeTransaction(WithdrawUnbondedTx),
+    /// Outputs of a transaction disclosed to a view key with `TxAccess::Output` policies (e.g. auditors)
+    RedactedTransaction(RedactedTx),
 }
 
 impl TransactionId for Transaction {
@@ -29,6 +31,46 @@ impl TransactionId for Transaction {
             Transaction::DepositStakeTransaction(ref transaction) => transaction.id(),
             Transaction::UnbondStakeTransaction(ref transaction) => transaction.id(),
             Transaction::WithdrawUnbondedStakeTransaction(ref transaction) => transaction.id(),
+            Transaction::RedactedTransaction(ref transaction) => transaction.txid,
+        }
+    }
+}
+
+impl Transaction {
+    /// Decodes a stored transaction: unlike `decode`, it also accepts the legacy transactions
+    /// (tagged with `LEGACY_TX_VERSION`), which can't be submitted anymore
+    pub fn decode_stored<I: Input>(input: &mut I) -> Result<Self, Error> {
+        match input.read_byte()? {
+            0 => Ok(Transaction::TransferTransaction(Tx::decode_stored(input)?)),
+            1 => Ok(Transaction::DepositStakeTransaction(
+                DepositBondTx::decode_stored(input)?,
+            )),
+            2 => Ok(Transaction::UnbondStakeTransaction(
+                UnbondTx::decode_stored(input)?,
+            )),
+            3 => Ok(Transaction::WithdrawUnbondedStakeTransaction(
+                WithdrawUnbondedTx::decode_stored(input)?,
+            )),
+            4 => Ok(Transaction::RedactedTransaction(RedactedTx::decode(input)?)),
+            _ => Err("No such variant in enum Transaction".into()),
+        }
+    }
+
+    /// Decodes a transaction in the legacy layout, as stored before the transaction format was versioned
+    /// (only the transfer, deposit, unbond and withdraw transactions existed)
+    pub fn decode_legacy<I: Input>(input: &mut I) -> Result<Self, Error> {
+        match input.read_byte()? {
+            0 => Ok(Transaction::TransferTransaction(Tx::decode_legacy(input)?)),
+            1 => Ok(Transaction::DepositStakeTransaction(
+                DepositBondTx::decode_legacy(input)?,
+            )),
+            2 => Ok(Transaction::UnbondStakeTransaction(
+                UnbondTx::decode_legacy(input)?,
+            )),
+            3 => Ok(Transaction::WithdrawUnbondedStakeTransaction(
+                WithdrawUnbondedTx::decode_legacy(input)?,
+            )),
+            _ => Err("No such variant in enum Transaction".into()),
         }
     }
 }
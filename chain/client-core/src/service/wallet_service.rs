@@ -99,6 +99,7 @@ where
 
                 Ok(None)
             }
+            ExtendedAddr::HashTimeLock(_) => Ok(None),
         }
     }
 
@@ -9,7 +9,10 @@ use secstr::SecUtf8;
 
 use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::attribute::TxAttributes;
+use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
+use chain_core::tx::data::TxId;
+use chain_core::tx::witness::TxWitness;
 use chain_core::tx::TxAux;
 use client_common::{Result, SignedTransaction};
 
@@ -38,6 +41,19 @@ pub trait TransactionBuilder: Send + Sync {
         return_address: ExtendedAddr,
     ) -> Result<TxAux>;
 
+    /// Builds a transaction which spends all the given `inputs` to `to_address` (the fees are deducted from
+    /// the output). Input witnesses are created by `witness` from the transaction ID -- this is used for
+    /// spending outputs which can't be signed by `Signer`, e.g. hash-time-locked outputs.
+    fn build_with_witness<W>(
+        &self,
+        inputs: Vec<(TxoPointer, TxOut)>,
+        to_address: ExtendedAddr,
+        attributes: TxAttributes,
+        witness: W,
+    ) -> Result<TxAux>
+    where
+        W: Fn(&TxId) -> Result<TxWitness>;
+
     /// Obfuscates given signed transaction
     fn obfuscate(&self, signed_transaction: SignedTransaction) -> Result<TxAux>;
 }
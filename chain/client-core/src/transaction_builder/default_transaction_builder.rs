@@ -4,9 +4,11 @@ use secstr::SecUtf8;
 use chain_core::init::coin::{sum_coins, Coin};
 use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::attribute::TxAttributes;
+use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
-use chain_core::tx::data::Tx;
+use chain_core::tx::data::{Tx, TxId};
 use chain_core::tx::fee::FeeAlgorithm;
+use chain_core::tx::witness::TxWitness;
 use chain_core::tx::{TransactionId, TxAux};
 use client_common::{ErrorKind, Result, SignedTransaction};
 use client_index::TransactionObfuscation;
@@ -111,6 +113,55 @@ where
         }
     }
 
+    fn build_with_witness<W>(
+        &self,
+        inputs: Vec<(TxoPointer, TxOut)>,
+        to_address: ExtendedAddr,
+        attributes: TxAttributes,
+        witness: W,
+    ) -> Result<TxAux>
+    where
+        W: Fn(&TxId) -> Result<TxWitness>,
+    {
+        let input_value = sum_coins(inputs.iter().map(|(_, output)| output.value))
+            .context(ErrorKind::BalanceAdditionError)?;
+        let inputs = inputs
+            .into_iter()
+            .map(|(input, _)| input)
+            .collect::<Vec<TxoPointer>>();
+        let mut fees = Coin::zero();
+
+        loop {
+            let output_value = (input_value - fees).context(ErrorKind::InsufficientBalance)?;
+            if output_value == Coin::zero() {
+                return Err(ErrorKind::InsufficientBalance.into());
+            }
+
+            let transaction = Tx::new_with(
+                inputs.clone(),
+                vec![TxOut::new(to_address.clone(), output_value)],
+                attributes.clone(),
+            );
+            let transaction_witness = witness(&transaction.id())?;
+
+            let signed_transaction =
+                SignedTransaction::TransferTransaction(transaction, transaction_witness);
+            let tx_aux = self.transaction_obfuscation.encrypt(signed_transaction)?;
+
+            let new_fees = self
+                .fee_algorithm
+                .calculate_for_txaux(&tx_aux)
+                .context(ErrorKind::BalanceAdditionError)?
+                .to_coin();
+
+            if new_fees > fees {
+                fees = new_fees;
+            } else {
+                return Ok(tx_aux);
+            }
+        }
+    }
+
     #[inline]
     fn obfuscate(&self, signed_transaction: SignedTransaction) -> Result<TxAux> {
         self.transaction_obfuscation.encrypt(signed_transaction)
@@ -2,7 +2,10 @@ use secstr::SecUtf8;
 
 use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::attribute::TxAttributes;
+use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
+use chain_core::tx::data::TxId;
+use chain_core::tx::witness::TxWitness;
 use chain_core::tx::TxAux;
 use client_common::{ErrorKind, Result, SignedTransaction};
 
@@ -25,6 +28,19 @@ impl TransactionBuilder for UnauthorizedTransactionBuilder {
         Err(ErrorKind::PermissionDenied.into())
     }
 
+    fn build_with_witness<W>(
+        &self,
+        _: Vec<(TxoPointer, TxOut)>,
+        _: ExtendedAddr,
+        _: TxAttributes,
+        _: W,
+    ) -> Result<TxAux>
+    where
+        W: Fn(&TxId) -> Result<TxWitness>,
+    {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
     fn obfuscate(&self, _signed_transaction: SignedTransaction) -> Result<TxAux> {
         Err(ErrorKind::PermissionDenied.into())
     }
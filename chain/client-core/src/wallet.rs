@@ -6,12 +6,14 @@ pub use default_wallet_client::DefaultWalletClient;
 use secp256k1::schnorrsig::SchnorrSignature;
 use secstr::SecUtf8;
 
-use chain_core::common::{Proof, H256};
+use chain_core::common::{Proof, Timespec, H256};
 use chain_core::init::address::RedeemAddress;
 use chain_core::init::coin::Coin;
+use chain_core::init::network::ChainParams;
 use chain_core::state::account::StakedStateAddress;
 use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::attribute::TxAttributes;
+use chain_core::tx::data::htlc::{HashLockPreimage, HashTimeLock};
 use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
 use chain_core::tx::data::Tx;
@@ -25,6 +27,10 @@ use crate::{InputSelectionStrategy, UnspentTransactions};
 
 /// Interface for a generic wallet
 pub trait WalletClient: Send + Sync {
+    /// Returns the network the wallet operates on (its addresses' textual format
+    /// and the network identifier of the transactions created with it)
+    fn chain_params(&self) -> ChainParams;
+
     /// Retrieves names of all wallets stored
     fn wallets(&self) -> Result<Vec<String>>;
 
@@ -152,6 +158,40 @@ pub trait WalletClient: Send + Sync {
         return_address: ExtendedAddr,
     ) -> Result<TxAux>;
 
+    /// Creates a hash-time-locked contract which can be claimed by `claim_address` with the preimage of `hash`
+    /// before `timeout` and refunded to a new 1-of-1 address of this wallet afterwards. Funds are locked by sending
+    /// them to the contract's address (`HashTimeLock::address`) with `create_transaction`.
+    ///
+    /// NOTE: the counterparty's view key needs to be in the locking transaction's attributes, so that its index
+    /// can find the hash-time-locked outputs.
+    fn new_hash_time_lock(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        hash: H256,
+        claim_address: &ExtendedAddr,
+        timeout: Timespec,
+    ) -> Result<HashTimeLock>;
+
+    /// Claims all the unspent outputs locked by given contract (to its claim address in this wallet)
+    fn claim_hash_time_lock(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        contract: &HashTimeLock,
+        preimage: HashLockPreimage,
+        attributes: TxAttributes,
+    ) -> Result<TxAux>;
+
+    /// Refunds all the unspent outputs locked by given contract (to its refund address in this wallet)
+    fn refund_hash_time_lock(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        contract: &HashTimeLock,
+        attributes: TxAttributes,
+    ) -> Result<TxAux>;
+
     /// Broadcasts a transaction to Crypto.com Chain
     fn broadcast_transaction(&self, tx_aux: &TxAux) -> Result<BroadcastTxResult>;
 }
@@ -3,12 +3,14 @@ use parity_scale_codec::Encode;
 use secp256k1::schnorrsig::SchnorrSignature;
 use secstr::SecUtf8;
 
-use chain_core::common::{Proof, H256};
+use chain_core::common::{Proof, Timespec, H256};
 use chain_core::init::address::RedeemAddress;
 use chain_core::init::coin::{sum_coins, Coin};
+use chain_core::init::network::{get_chain_params, ChainParams};
 use chain_core::state::account::StakedStateAddress;
 use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::attribute::TxAttributes;
+use chain_core::tx::data::htlc::{HashLockPreimage, HashTimeLock};
 use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
 use chain_core::tx::data::Tx;
@@ -42,6 +44,7 @@ where
     multi_sig_session_service: MultiSigSessionService<S>,
     index: I,
     transaction_builder: T,
+    chain_params: ChainParams,
 }
 
 impl<S, I, T> DefaultWalletClient<S, I, T>
@@ -51,7 +54,7 @@ where
     T: TransactionBuilder,
 {
     /// Creates a new instance of `DefaultWalletClient`
-    fn new(storage: S, index: I, transaction_builder: T) -> Self {
+    fn new(storage: S, index: I, transaction_builder: T, chain_params: ChainParams) -> Self {
         Self {
             key_service: KeyService::new(storage.clone()),
             wallet_service: WalletService::new(storage.clone()),
@@ -59,10 +62,87 @@ where
             multi_sig_session_service: MultiSigSessionService::new(storage),
             index,
             transaction_builder,
+            chain_params,
         }
     }
 }
 
+impl<S, I, T> DefaultWalletClient<S, I, T>
+where
+    S: Storage,
+    I: Index,
+    T: TransactionBuilder,
+{
+    /// Spends all the unspent outputs of a hash-time-locked contract to `root_hash` (which has to be a 1-of-1
+    /// address in given wallet). The witness is a claim if `preimage` is provided, otherwise a refund.
+    fn spend_hash_time_lock(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        contract: &HashTimeLock,
+        root_hash: &H256,
+        preimage: Option<HashLockPreimage>,
+        attributes: TxAttributes,
+    ) -> Result<TxAux> {
+        let to_address = ExtendedAddr::OrTree(*root_hash);
+        self.wallet_service
+            .find_root_hash(name, passphrase, &to_address)?
+            .ok_or_else(|| Error::from(ErrorKind::AddressNotFound))?;
+
+        if self
+            .root_hash_service
+            .required_signers(root_hash, passphrase)?
+            != 1
+        {
+            return Err(ErrorKind::InvalidTransaction.into());
+        }
+
+        let public_key = self.root_hash_service.public_key(root_hash, passphrase)?;
+        let private_key = self
+            .private_key(passphrase, &public_key)?
+            .ok_or_else(|| Error::from(ErrorKind::PrivateKeyNotFound))?;
+        let proof =
+            self.root_hash_service
+                .generate_proof(root_hash, vec![public_key], passphrase)?;
+
+        let inputs = self
+            .index
+            .address_details(&contract.address())?
+            .unspent_transactions
+            .into_iter()
+            .collect::<Vec<(TxoPointer, TxOut)>>();
+
+        if inputs.is_empty() {
+            return Err(format_err!("No unspent outputs locked by the contract")
+                .context(ErrorKind::InvalidInput)
+                .into());
+        }
+
+        let no_of_inputs = inputs.len();
+
+        self.transaction_builder.build_with_witness(
+            inputs,
+            to_address,
+            attributes,
+            |transaction_id| {
+                let signature = private_key.schnorr_sign(transaction_id)?;
+                let witness = match preimage {
+                    Some(preimage) => TxInWitness::HashTimeLockClaim(
+                        signature,
+                        proof.clone(),
+                        contract.clone(),
+                        preimage,
+                    ),
+                    None => {
+                        TxInWitness::HashTimeLockRefund(signature, proof.clone(), contract.clone())
+                    }
+                };
+                Ok(TxWitness::from(vec![witness; no_of_inputs]))
+            },
+        )
+    }
+}
+
 impl DefaultWalletClient<UnauthorizedStorage, UnauthorizedIndex, UnauthorizedTransactionBuilder> {
     /// Returns builder for `DefaultWalletClient`
     pub fn builder() -> DefaultWalletClientBuilder<
@@ -80,6 +160,11 @@ where
     I: Index,
     T: TransactionBuilder,
 {
+    #[inline]
+    fn chain_params(&self) -> ChainParams {
+        self.chain_params
+    }
+
     #[inline]
     fn wallets(&self) -> Result<Vec<String>> {
         self.wallet_service.names()
@@ -216,6 +301,11 @@ where
                 self.root_hash_service
                     .generate_proof(address, public_keys, passphrase)
             }
+            ExtendedAddr::HashTimeLock(_) => Err(format_err!(
+                "Proofs can only be generated for tree addresses"
+            )
+            .context(ErrorKind::InvalidInput)
+            .into()),
         }
     }
 
@@ -299,6 +389,72 @@ where
         )
     }
 
+    fn new_hash_time_lock(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        hash: H256,
+        claim_address: &ExtendedAddr,
+        timeout: Timespec,
+    ) -> Result<HashTimeLock> {
+        let claim_root = match claim_address {
+            ExtendedAddr::OrTree(root_hash) => *root_hash,
+            ExtendedAddr::HashTimeLock(_) => {
+                return Err(format_err!("Claim address has to be a tree address")
+                    .context(ErrorKind::InvalidInput)
+                    .into())
+            }
+        };
+
+        let refund_root = match self.new_transfer_address(name, passphrase)? {
+            ExtendedAddr::OrTree(root_hash) => root_hash,
+            ExtendedAddr::HashTimeLock(_) => unreachable!("new transfer address is a tree address"),
+        };
+
+        Ok(HashTimeLock::new(hash, claim_root, refund_root, timeout))
+    }
+
+    fn claim_hash_time_lock(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        contract: &HashTimeLock,
+        preimage: HashLockPreimage,
+        attributes: TxAttributes,
+    ) -> Result<TxAux> {
+        if !contract.check_preimage(&preimage) {
+            return Err(format_err!("Preimage does not match the hash lock")
+                .context(ErrorKind::InvalidInput)
+                .into());
+        }
+
+        self.spend_hash_time_lock(
+            name,
+            passphrase,
+            contract,
+            &contract.claim_root,
+            Some(preimage),
+            attributes,
+        )
+    }
+
+    fn refund_hash_time_lock(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        contract: &HashTimeLock,
+        attributes: TxAttributes,
+    ) -> Result<TxAux> {
+        self.spend_hash_time_lock(
+            name,
+            passphrase,
+            contract,
+            &contract.refund_root,
+            None,
+            attributes,
+        )
+    }
+
     #[inline]
     fn broadcast_transaction(&self, tx_aux: &TxAux) -> Result<BroadcastTxResult> {
         self.index.broadcast_transaction(&tx_aux.encode())
@@ -458,6 +614,7 @@ where
     storage: S,
     index: I,
     transaction_builder: T,
+    chain_params: ChainParams,
     storage_set: bool,
     index_set: bool,
     transaction_builder_set: bool,
@@ -475,6 +632,7 @@ impl Default
             storage: UnauthorizedStorage,
             index: UnauthorizedIndex,
             transaction_builder: UnauthorizedTransactionBuilder,
+            chain_params: get_chain_params(),
             storage_set: false,
             index_set: false,
             transaction_builder_set: false,
@@ -497,6 +655,7 @@ where
             storage,
             index: self.index,
             transaction_builder: self.transaction_builder,
+            chain_params: self.chain_params,
             storage_set: true,
             index_set: self.index_set,
             transaction_builder_set: self.transaction_builder_set,
@@ -512,6 +671,7 @@ where
             storage: self.storage,
             index,
             transaction_builder: self.transaction_builder,
+            chain_params: self.chain_params,
             storage_set: self.storage_set,
             index_set: true,
             transaction_builder_set: self.transaction_builder_set,
@@ -527,12 +687,19 @@ where
             storage: self.storage,
             index: self.index,
             transaction_builder,
+            chain_params: self.chain_params,
             storage_set: self.storage_set,
             index_set: self.index_set,
             transaction_builder_set: true,
         }
     }
 
+    /// Sets the network the wallet operates on (the process-wide network by default)
+    pub fn with_chain_params(mut self, chain_params: ChainParams) -> Self {
+        self.chain_params = chain_params;
+        self
+    }
+
     /// Builds `DefaultWalletClient`
     pub fn build(self) -> Result<DefaultWalletClient<S, I, T>> {
         if !self.index_set && !self.transaction_builder_set || self.storage_set && self.index_set {
@@ -540,6 +707,7 @@ where
                 self.storage,
                 self.index,
                 self.transaction_builder,
+                self.chain_params,
             ))
         } else {
             Err(ErrorKind::InvalidInput.into())
@@ -556,8 +724,10 @@ mod tests {
     use std::time::SystemTime;
 
     use chrono::DateTime;
+    use parity_scale_codec::Decode;
 
     use chain_core::init::coin::CoinError;
+    use chain_core::tx::data::htlc::hash_lock;
     use chain_core::tx::data::input::{TxoIndex, TxoPointer};
     use chain_core::tx::data::{Tx, TxId};
     use chain_core::tx::fee::{Fee, FeeAlgorithm};
@@ -1391,4 +1561,100 @@ mod tests {
 
         assert!(verify_tx_address(&witness, &transaction.id(), &tree_address).is_ok())
     }
+
+    #[test]
+    fn check_hash_time_lock_flow() {
+        let storage = MemoryStorage::default();
+        let wallet = DefaultWalletClient::builder()
+            .with_wallet(storage.clone())
+            .build()
+            .unwrap();
+
+        let passphrase = &SecUtf8::from("passphrase");
+        let name = "name";
+
+        wallet.new_wallet(name, passphrase).unwrap();
+        let claim_address = wallet.new_transfer_address(name, passphrase).unwrap();
+
+        let preimage = [0x42; 32];
+        let contract = wallet
+            .new_hash_time_lock(name, passphrase, hash_lock(&preimage), &claim_address, 100)
+            .unwrap();
+
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            wallet
+                .new_hash_time_lock(name, passphrase, [0; 32], &contract.address(), 100)
+                .unwrap_err()
+                .kind()
+        );
+
+        let wallet = DefaultWalletClient::builder()
+            .with_wallet(storage.clone())
+            .with_transaction_read(MockIndex::new(
+                ExtendedAddr::OrTree([0; 32]),
+                contract.address(),
+                ExtendedAddr::OrTree([2; 32]),
+            ))
+            .with_transaction_write(DefaultTransactionBuilder::new(
+                DefaultSigner::new(storage),
+                ZeroFeeAlgorithm::default(),
+                MockTransactionCipher,
+            ))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            wallet
+                .claim_hash_time_lock(
+                    name,
+                    passphrase,
+                    &contract,
+                    [0x43; 32],
+                    TxAttributes::new(171)
+                )
+                .unwrap_err()
+                .kind()
+        );
+
+        for preimage in vec![Some(preimage), None] {
+            let transaction = match preimage {
+                Some(preimage) => wallet.claim_hash_time_lock(
+                    name,
+                    passphrase,
+                    &contract,
+                    preimage,
+                    TxAttributes::new(171),
+                ),
+                None => wallet.refund_hash_time_lock(
+                    name,
+                    passphrase,
+                    &contract,
+                    TxAttributes::new(171),
+                ),
+            }
+            .unwrap();
+
+            let (tx, witness) = match transaction {
+                TxAux::TransferTx { payload, .. } => {
+                    match SignedTransaction::decode(&mut payload.txpayload.as_slice()).unwrap() {
+                        SignedTransaction::TransferTransaction(tx, witness) => (tx, witness),
+                        _ => unreachable!(),
+                    }
+                }
+                _ => unreachable!(),
+            };
+
+            let root_hash = if preimage.is_some() {
+                contract.claim_root
+            } else {
+                contract.refund_root
+            };
+            assert_eq!(1, tx.inputs.len());
+            assert_eq!(ExtendedAddr::OrTree(root_hash), tx.outputs[0].address);
+            assert_eq!(Coin::new(30).unwrap(), tx.outputs[0].value);
+            assert!(verify_tx_address(&witness[0], &tx.id(), &contract.address()).is_ok());
+        }
+    }
 }
@@ -5,9 +5,9 @@ use chain_core::tx::data::TxId;
 use chain_core::tx::{TxAux, TxWithOutputs};
 use client_common::tendermint::Client;
 use client_common::SECP;
-use client_common::{ErrorKind, PrivateKey, Result, SignedTransaction, Transaction};
+use client_common::{Error, ErrorKind, PrivateKey, Result, SignedTransaction, Transaction};
 use enclave_protocol::{
-    DecryptionRequest, DecryptionResponse, EncryptionRequest, EncryptionResponse,
+    DecryptedTx, DecryptionRequest, DecryptionResponse, EncryptionRequest, EncryptionResponse,
 };
 
 use crate::TransactionObfuscation;
@@ -34,7 +34,7 @@ where
     fn encrypt_request(&self, encryption_request: EncryptionRequest) -> Result<TxAux> {
         let response = self
             .client
-            .query("mockencrypt", &encryption_request.encode())?
+            .query("mockencrypt", &encryption_request.encode(), false)?
             .bytes()?;
 
         let encrypted_transaction = EncryptionResponse::decode(&mut response.as_slice())
@@ -65,7 +65,7 @@ where
 
         let response = self
             .client
-            .query("mockdecrypt", &request.encode())?
+            .query("mockdecrypt", &request.encode(), false)?
             .bytes()?;
 
         let txs = DecryptionResponse::decode(&mut response.as_slice())
@@ -75,10 +75,22 @@ where
         let transactions = txs
             .into_iter()
             .map(|tx| match tx {
-                TxWithOutputs::Transfer(t) => Transaction::TransferTransaction(t),
-                TxWithOutputs::StakeWithdraw(t) => Transaction::WithdrawUnbondedStakeTransaction(t),
+                DecryptedTx::Full(TxWithOutputs::Transfer(t)) => {
+                    Ok(Transaction::TransferTransaction(t))
+                }
+                DecryptedTx::Full(TxWithOutputs::StakeWithdraw(t)) => {
+                    Ok(Transaction::WithdrawUnbondedStakeTransaction(t))
+                }
+                // the disclosed outputs need to be committed to by one of the requested transactions
+                DecryptedTx::Redacted(t) => {
+                    if transaction_ids.contains(&t.txid) && t.verify() {
+                        Ok(Transaction::RedactedTransaction(t))
+                    } else {
+                        Err(Error::from(ErrorKind::InvalidProof))
+                    }
+                }
             })
-            .collect::<Vec<Transaction>>();
+            .collect::<Result<Vec<Transaction>>>()?;
 
         Ok(transactions)
     }
@@ -159,7 +171,7 @@ mod tests {
             unreachable!()
         }
 
-        fn query(&self, path: &str, _data: &[u8]) -> Result<QueryResult> {
+        fn query(&self, path: &str, _data: &[u8], _prove: bool) -> Result<QueryResult> {
             match path {
                 "mockdecrypt" => {
                     let response = DecryptionResponse {
@@ -170,6 +182,7 @@ mod tests {
                     Ok(QueryResult {
                         response: Response {
                             value: encode(&response),
+                            ..Default::default()
                         },
                     })
                 }
@@ -182,6 +195,7 @@ mod tests {
                     Ok(QueryResult {
                         response: Response {
                             value: encode(&response),
+                            ..Default::default()
                         },
                     })
                 }
@@ -4,7 +4,7 @@ use chain_core::tx::data::TxId;
 use chain_core::tx::{TxAux, TxWithOutputs};
 use client_common::SECP;
 use client_common::{Error, ErrorKind, PrivateKey, Result, SignedTransaction, Transaction};
-use enclave_protocol::{DecryptionRequest, DecryptionResponse};
+use enclave_protocol::{DecryptedTx, DecryptionRequest, DecryptionResponse};
 use failure::ResultExt;
 use parity_scale_codec::{Decode, Encode};
 use std::io::{Read, Write};
@@ -84,12 +84,22 @@ impl TransactionObfuscation for DefaultTransactionObfuscation {
                 let transactions = txs
                     .into_iter()
                     .map(|tx| match tx {
-                        TxWithOutputs::Transfer(t) => Transaction::TransferTransaction(t),
-                        TxWithOutputs::StakeWithdraw(t) => {
-                            Transaction::WithdrawUnbondedStakeTransaction(t)
+                        DecryptedTx::Full(TxWithOutputs::Transfer(t)) => {
+                            Ok(Transaction::TransferTransaction(t))
+                        }
+                        DecryptedTx::Full(TxWithOutputs::StakeWithdraw(t)) => {
+                            Ok(Transaction::WithdrawUnbondedStakeTransaction(t))
+                        }
+                        // the disclosed outputs need to be committed to by one of the requested transactions
+                        DecryptedTx::Redacted(t) => {
+                            if transaction_ids.contains(&t.txid) && t.verify() {
+                                Ok(Transaction::RedactedTransaction(t))
+                            } else {
+                                Err(Error::from(ErrorKind::InvalidProof))
+                            }
                         }
                     })
-                    .collect::<Vec<Transaction>>();
+                    .collect::<Result<Vec<Transaction>>>()?;
 
                 Ok(transactions)
             }
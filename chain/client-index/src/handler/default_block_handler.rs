@@ -80,9 +80,23 @@ where
         }
 
         if block_header.block_filter.check_view_key(&view_key.into()) {
-            let transactions = self
-                .transaction_cipher
-                .decrypt(&block_header.transaction_ids, private_key)?;
+            // only the transactions whose filters match the view key are requested
+            // (or all transactions in the block if the node didn't emit the per-transaction filters)
+            let transaction_ids = match block_header.tx_filters {
+                Some(ref tx_filters) => tx_filters
+                    .matching_transactions(&view_key.into())
+                    .into_iter()
+                    .filter(|transaction_id| block_header.transaction_ids.contains(transaction_id))
+                    .collect(),
+                None => block_header.transaction_ids.clone(),
+            };
+
+            let transactions = if transaction_ids.is_empty() {
+                Vec::new()
+            } else {
+                self.transaction_cipher
+                    .decrypt(&transaction_ids, private_key)?
+            };
 
             for transaction in transactions {
                 self.on_transaction(
@@ -117,11 +131,14 @@ mod tests {
     use chain_core::tx::data::output::TxOut;
     use chain_core::tx::data::{Tx, TxId};
     use chain_core::tx::{TransactionId, TxAux};
-    use chain_tx_filter::BlockFilter;
+    use chain_tx_filter::{BlockFilter, TxFilters};
     use client_common::storage::MemoryStorage;
     use client_common::{SignedTransaction, Transaction};
+    use secp256k1::PublicKey as SecpPublicKey;
 
-    struct MockTransactionCipher;
+    struct MockTransactionCipher {
+        expected_transaction_ids: Vec<TxId>,
+    }
 
     impl TransactionObfuscation for MockTransactionCipher {
         fn decrypt(
@@ -129,9 +146,7 @@ mod tests {
             transaction_ids: &[TxId],
             _private_key: &PrivateKey,
         ) -> Result<Vec<Transaction>> {
-            assert_eq!(2, transaction_ids.len());
-            assert_eq!(transfer_transaction().id(), transaction_ids[0]);
-            assert_eq!(unbond_transaction().id(), transaction_ids[1]);
+            assert_eq!(self.expected_transaction_ids.as_slice(), transaction_ids);
             Ok(vec![transfer_transaction()])
         }
 
@@ -194,6 +209,7 @@ mod tests {
             block_time: DateTime::from_str("2019-04-09T09:38:41.735577Z").unwrap(),
             transaction_ids,
             block_filter,
+            tx_filters: None,
             unencrypted_transactions: vec![unbond_transaction()],
         }
     }
@@ -208,7 +224,12 @@ mod tests {
         let block_header = block_header(&view_key);
 
         let block_handler = DefaultBlockHandler::new(
-            MockTransactionCipher,
+            MockTransactionCipher {
+                expected_transaction_ids: vec![
+                    transfer_transaction().id(),
+                    unbond_transaction().id(),
+                ],
+            },
             MockTransactionHandler,
             storage.clone(),
         );
@@ -248,4 +269,46 @@ mod tests {
             global_state_service.last_block_height(&view_key).unwrap()
         );
     }
+
+    #[test]
+    fn only_transactions_matching_tx_filters_should_be_decrypted() {
+        let storage = MemoryStorage::default();
+
+        let private_key = PrivateKey::new().unwrap();
+        let view_key = PublicKey::from(&private_key);
+        let other_view_key = PublicKey::from(&PrivateKey::new().unwrap());
+
+        let mut block_header = block_header(&view_key);
+        let mut tx_filters = TxFilters::default();
+        tx_filters.add_transaction(
+            transfer_transaction().id(),
+            &[SecpPublicKey::from(&view_key)],
+        );
+        tx_filters.add_transaction(
+            unbond_transaction().id(),
+            &[SecpPublicKey::from(&other_view_key)],
+        );
+        block_header.tx_filters = Some(tx_filters);
+
+        let block_handler = DefaultBlockHandler::new(
+            MockTransactionCipher {
+                expected_transaction_ids: vec![transfer_transaction().id()],
+            },
+            MockTransactionHandler,
+            storage.clone(),
+        );
+
+        block_handler
+            .on_next(block_header, &view_key, &private_key)
+            .unwrap();
+
+        let transaction = transfer_transaction();
+        assert_eq!(
+            transaction,
+            TransactionService::new(storage)
+                .get(&transaction.id())
+                .unwrap()
+                .unwrap()
+        );
+    }
 }
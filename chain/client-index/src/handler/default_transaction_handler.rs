@@ -4,7 +4,7 @@ use chain_core::state::account::{DepositBondTx, WithdrawUnbondedTx};
 use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
 use chain_core::tx::data::{Tx, TxId};
-use chain_core::tx::TransactionId;
+use chain_core::tx::{RedactedTx, TransactionId};
 use client_common::balance::{BalanceChange, TransactionChange};
 use client_common::{Result, Storage, Transaction};
 
@@ -117,6 +117,30 @@ where
         self.address_service.apply_memento(&memento)
     }
 
+    fn on_redacted_transaction(
+        &self,
+        transaction: RedactedTx,
+        block_height: u64,
+        block_time: DateTime<Utc>,
+    ) -> Result<()> {
+        // Inputs are not disclosed, so only the outputs are tracked
+        let transaction_id = transaction.txid;
+        let mut memento = AddressMemento::new(transaction_id);
+
+        for (index, output) in transaction.outputs.into_iter() {
+            self.handle_transaction_output(
+                &mut memento,
+                transaction_id,
+                output,
+                index as usize,
+                block_height,
+                block_time,
+            );
+        }
+
+        self.address_service.apply_memento(&memento)
+    }
+
     fn handle_transaction_input(
         &self,
         memento: &mut AddressMemento,
@@ -201,6 +225,9 @@ where
             Transaction::WithdrawUnbondedStakeTransaction(transaction) => {
                 self.on_withdraw_unbonded_stake_transaction(transaction, block_height, block_time)
             }
+            Transaction::RedactedTransaction(transaction) => {
+                self.on_redacted_transaction(transaction, block_height, block_time)
+            }
         }
     }
 }
@@ -254,7 +281,7 @@ mod tests {
             unreachable!()
         }
 
-        fn query(&self, _path: &str, _data: &[u8]) -> Result<QueryResult> {
+        fn query(&self, _path: &str, _data: &[u8], _prove: bool) -> Result<QueryResult> {
             unreachable!()
         }
     }
@@ -384,4 +411,41 @@ mod tests {
                 .len()
         );
     }
+
+    #[test]
+    fn check_redacted_transaction_flow() {
+        let storage = MemoryStorage::default();
+
+        let index = DefaultIndex::new(storage.clone(), MockClient);
+        let transaction_handler = DefaultTransactionHandler::new(storage);
+
+        let transaction = Transaction::RedactedTransaction(RedactedTx {
+            txid: [1; 32],
+            body_hash: [0; 32],
+            outputs: vec![(
+                1,
+                TxOut::new(ExtendedAddr::OrTree([0; 32]), Coin::new(100).unwrap()),
+            )],
+            proofs: Vec::new(),
+        });
+
+        transaction_handler
+            .on_next(
+                transaction,
+                0,
+                DateTime::from_str("2019-04-09T09:38:41.735577Z").unwrap(),
+            )
+            .unwrap();
+
+        let address_details = index
+            .address_details(&ExtendedAddr::OrTree([0; 32]))
+            .unwrap();
+
+        assert_eq!(Coin::new(100).unwrap(), address_details.balance);
+        assert_eq!(1, address_details.transaction_history.len());
+        assert_eq!(
+            Some(&TxoPointer::new([1; 32], 1)),
+            address_details.unspent_transactions.keys().next()
+        );
+    }
 }
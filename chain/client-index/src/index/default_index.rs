@@ -115,7 +115,7 @@ mod tests {
             })
         }
 
-        fn query(&self, _path: &str, _data: &[u8]) -> Result<QueryResult> {
+        fn query(&self, _path: &str, _data: &[u8], _prove: bool) -> Result<QueryResult> {
             unreachable!()
         }
     }
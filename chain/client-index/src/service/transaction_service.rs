@@ -1,7 +1,7 @@
 use std::convert::TryInto;
 
 use failure::ResultExt;
-use parity_scale_codec::{Decode, Encode};
+use parity_scale_codec::Encode;
 
 use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
@@ -9,7 +9,9 @@ use chain_core::tx::data::TxId;
 use chain_core::tx::TransactionId;
 use client_common::{Error, ErrorKind, Result, Storage, Transaction};
 
-const KEYSPACE: &str = "index_transaction";
+const KEYSPACE: &str = "index_transaction_v1";
+/// Transactions stored before the transaction format was versioned (in the legacy layout; only read)
+const LEGACY_KEYSPACE: &str = "index_transaction";
 
 /// Exposes functionalities for managing transaction storage
 ///
@@ -34,15 +36,14 @@ where
 
     /// Retrieves transaction with given id
     pub fn get(&self, id: &TxId) -> Result<Option<Transaction>> {
-        let transaction = self
-            .storage
-            .get(KEYSPACE, id)?
-            .map(|bytes| {
-                Transaction::decode(&mut bytes.as_slice()).context(ErrorKind::DeserializationError)
-            })
-            .transpose()?;
+        if let Some(bytes) = self.storage.get(KEYSPACE, id)? {
+            return decode_all(&bytes, Transaction::decode_stored).map(Some);
+        }
 
-        Ok(transaction)
+        self.storage
+            .get(LEGACY_KEYSPACE, id)?
+            .map(|bytes| decode_all(&bytes, Transaction::decode_legacy))
+            .transpose()
     }
 
     /// Sets transaction with given id and value
@@ -65,6 +66,15 @@ where
                     Transaction::WithdrawUnbondedStakeTransaction(withdraw_transaction) => {
                         Ok(withdraw_transaction.outputs)
                     }
+                    Transaction::RedactedTransaction(redacted_transaction) => {
+                        // Only disclosed outputs are known (indexed as in the full transaction)
+                        return redacted_transaction
+                            .outputs
+                            .into_iter()
+                            .find(|(index, _)| *index == input.index)
+                            .map(|(_, output)| output)
+                            .ok_or_else(|| Error::from(ErrorKind::OutputNotFound));
+                    }
                     _ => Err(Error::from(ErrorKind::InvalidTransaction)),
                 }?;
 
@@ -81,7 +91,22 @@ where
     /// Clears all storage
     #[inline]
     pub fn clear(&self) -> Result<()> {
-        self.storage.clear(KEYSPACE)
+        self.storage.clear(KEYSPACE)?;
+        self.storage.clear(LEGACY_KEYSPACE)
+    }
+}
+
+/// Decodes a stored transaction, which must take up all the bytes
+fn decode_all<'a>(
+    mut bytes: &'a [u8],
+    decode: impl Fn(&mut &'a [u8]) -> std::result::Result<Transaction, parity_scale_codec::Error>,
+) -> Result<Transaction> {
+    let transaction = decode(&mut bytes).context(ErrorKind::DeserializationError)?;
+
+    if bytes.is_empty() {
+        Ok(transaction)
+    } else {
+        Err(ErrorKind::DeserializationError.into())
     }
 }
 
@@ -93,6 +118,7 @@ mod tests {
     use chain_core::tx::data::address::ExtendedAddr;
     use chain_core::tx::data::attribute::TxAttributes;
     use chain_core::tx::data::Tx;
+    use chain_core::tx::RedactedTx;
     use client_common::storage::MemoryStorage;
 
     fn transfer_transaction() -> Transaction {
@@ -134,4 +160,88 @@ mod tests {
         assert!(transaction_service.clear().is_ok());
         assert_eq!(None, transaction_service.get(&transaction_id).unwrap());
     }
+
+    #[test]
+    fn check_legacy_transaction() {
+        let storage = MemoryStorage::default();
+
+        // transfer variant: no inputs, one output, chain id, no view keys (attributes last, without the version)
+        let output = TxOut::new(ExtendedAddr::OrTree([0; 32]), Coin::new(100).unwrap());
+        let mut tx = vec![0x00];
+        tx.extend_from_slice(&vec![output.clone()].encode());
+        tx.extend_from_slice(&[0x00, 0x00]);
+        let transaction_id = chain_core::tx::data::txid_hash(&tx);
+        let mut legacy = vec![0x00];
+        legacy.extend_from_slice(&tx);
+        storage
+            .set(LEGACY_KEYSPACE, &transaction_id, legacy.clone())
+            .expect("store legacy transaction");
+
+        let transaction_service = TransactionService::new(storage.clone());
+        let transaction = transaction_service.get(&transaction_id).unwrap().unwrap();
+        assert_eq!(transaction_id, transaction.id());
+        assert_eq!(
+            output,
+            transaction_service
+                .get_output(&TxoPointer::new(transaction_id, 0))
+                .unwrap()
+                .unwrap()
+        );
+
+        // stored again in the current keyspace (tagged with the legacy version)
+        assert!(transaction_service.set(&transaction).is_ok());
+        assert_eq!(
+            transaction,
+            transaction_service.get(&transaction_id).unwrap().unwrap()
+        );
+        assert!(transaction_service.clear().is_ok());
+        assert_eq!(None, transaction_service.get(&transaction_id).unwrap());
+
+        legacy.push(0);
+        storage
+            .set(LEGACY_KEYSPACE, &transaction_id, legacy)
+            .expect("store legacy transaction");
+        assert_eq!(
+            ErrorKind::DeserializationError,
+            transaction_service.get(&transaction_id).unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn check_redacted_output() {
+        let storage = MemoryStorage::default();
+
+        let transaction_service = TransactionService::new(storage);
+        let transaction = Transaction::RedactedTransaction(RedactedTx {
+            txid: [1; 32],
+            body_hash: [0; 32],
+            outputs: vec![(
+                2,
+                TxOut::new(ExtendedAddr::OrTree([0; 32]), Coin::new(100).unwrap()),
+            )],
+            proofs: Vec::new(),
+        });
+
+        assert!(transaction_service.set(&transaction).is_ok());
+        assert_eq!(
+            transaction,
+            transaction_service.get(&[1; 32]).unwrap().unwrap()
+        );
+
+        assert_eq!(
+            Coin::new(100).unwrap(),
+            transaction_service
+                .get_output(&TxoPointer::new([1; 32], 2))
+                .unwrap()
+                .unwrap()
+                .value
+        );
+        assert_eq!(
+            ErrorKind::OutputNotFound,
+            transaction_service
+                .get_output(&TxoPointer::new([1; 32], 0))
+                .unwrap_err()
+                .kind()
+        );
+    }
 }
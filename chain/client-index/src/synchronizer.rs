@@ -41,6 +41,7 @@ where
     global_state_service: GlobalStateService<S>,
     client: C,
     block_handler: H,
+    legacy_tx_height: u64,
 }
 
 impl<S, C, H> ManualSynchronizer<S, C, H>
@@ -56,9 +57,18 @@ where
             global_state_service: GlobalStateService::new(storage),
             client,
             block_handler,
+            legacy_tx_height: 0,
         }
     }
 
+    /// Sets the last block height whose transactions are in the legacy layout
+    /// (committed before the transaction format was versioned)
+    #[inline]
+    pub fn with_legacy_tx_height(mut self, legacy_tx_height: u64) -> Self {
+        self.legacy_tx_height = legacy_tx_height;
+        self
+    }
+
     /// Synchronizes transaction index for given view key with Crypto.com Chain (from last known height)
     pub fn sync(
         &self,
@@ -125,7 +135,12 @@ where
                     return Ok(());
                 }
 
-                let block_header = prepare_block_header(staking_addresses, &block, &block_result)?;
+                let block_header = prepare_block_header(
+                    staking_addresses,
+                    &block,
+                    &block_result,
+                    self.legacy_tx_height,
+                )?;
                 self.block_handler
                     .on_next(block_header, view_key, private_key)?;
 
@@ -179,7 +194,12 @@ where
             let block = self.client.block(current_block_height)?;
             let block_result = self.client.block_results(current_block_height)?;
 
-            let block_header = prepare_block_header(staking_addresses, &block, &block_result)?;
+            let block_header = prepare_block_header(
+                staking_addresses,
+                &block,
+                &block_result,
+                self.legacy_tx_height,
+            )?;
             self.block_handler
                 .on_next(block_header, view_key, private_key)?;
 
@@ -212,7 +232,12 @@ where
 
             let block_result = self.client.block_results(current_block_height)?;
 
-            let block_header = prepare_block_header(staking_addresses, &block, &block_result)?;
+            let block_header = prepare_block_header(
+                staking_addresses,
+                &block,
+                &block_result,
+                self.legacy_tx_height,
+            )?;
             self.block_handler
                 .on_next(block_header, view_key, private_key)?;
 
@@ -233,10 +258,11 @@ fn check_unencrypted_transactions(
     block_filter: &BlockFilter,
     staking_addresses: &[StakedStateAddress],
     block: &Block,
+    legacy_tx_height: u64,
 ) -> Result<Vec<Transaction>> {
     for staking_address in staking_addresses {
         if block_filter.check_staked_state_address(staking_address) {
-            return block.unencrypted_transactions();
+            return block.unencrypted_transactions(legacy_tx_height);
         }
     }
 
@@ -247,6 +273,7 @@ fn prepare_block_header(
     staking_addresses: &[StakedStateAddress],
     block: &Block,
     block_result: &BlockResults,
+    legacy_tx_height: u64,
 ) -> Result<BlockHeader> {
     let app_hash = block.app_hash();
     let block_height = block.height()?;
@@ -254,9 +281,10 @@ fn prepare_block_header(
 
     let transaction_ids = block_result.transaction_ids()?;
     let block_filter = block_result.block_filter()?;
+    let tx_filters = block_result.tx_filters()?;
 
     let unencrypted_transactions =
-        check_unencrypted_transactions(&block_filter, staking_addresses, block)?;
+        check_unencrypted_transactions(&block_filter, staking_addresses, block, legacy_tx_height)?;
 
     Ok(BlockHeader {
         app_hash,
@@ -264,6 +292,7 @@ fn prepare_block_header(
         block_time,
         transaction_ids,
         block_filter,
+        tx_filters,
         unencrypted_transactions,
     })
 }
@@ -284,6 +313,7 @@ mod tests {
     use chain_core::init::coin::Coin;
     use chain_core::state::account::{StakedStateOpAttributes, StakedStateOpWitness, UnbondTx};
     use chain_core::tx::TxAux;
+    use chain_tx_filter::ETHBLOOM_FILTER_KEY;
     use client_common::storage::MemoryStorage;
     use client_common::tendermint::types::*;
     use client_common::ErrorKind;
@@ -389,7 +419,7 @@ mod tests {
                             events: vec![Event {
                                 event_type: TendermintEventType::BlockFilter.to_string(),
                                 attributes: vec![Attribute {
-                                    key: "ethbloom".to_owned(),
+                                    key: encode(ETHBLOOM_FILTER_KEY),
                                     value: encode(&[0; 256][..]),
                                 }],
                             }],
@@ -399,6 +429,7 @@ mod tests {
             } else if height == 2 {
                 let mut block_filter = BlockFilter::default();
                 block_filter.add_staked_state_address(&self.staking_address);
+                let (filter_key, filter_value) = block_filter.get_tendermint_kv().unwrap();
 
                 Ok(BlockResults {
                     height: "2".to_string(),
@@ -416,8 +447,8 @@ mod tests {
                             events: vec![Event {
                                 event_type: TendermintEventType::BlockFilter.to_string(),
                                 attributes: vec![Attribute {
-                                    key: "ethbloom".to_owned(),
-                                    value: encode(&block_filter.get_tendermint_kv().unwrap().1),
+                                    key: encode(&filter_key),
+                                    value: encode(&filter_value),
                                 }],
                             }],
                         }),
@@ -439,7 +470,7 @@ mod tests {
             unreachable!()
         }
 
-        fn query(&self, _path: &str, _data: &[u8]) -> Result<QueryResult> {
+        fn query(&self, _path: &str, _data: &[u8], _prove: bool) -> Result<QueryResult> {
             unreachable!()
         }
     }
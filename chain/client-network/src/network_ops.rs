@@ -7,12 +7,15 @@ use secstr::SecUtf8;
 
 use chain_core::init::coin::Coin;
 use chain_core::state::account::{StakedState, StakedStateAddress, StakedStateOpAttributes};
+use chain_core::state::governance::NetworkParameterChange;
+use chain_core::state::tendermint::{BlockHeight, TendermintValidatorPubKey};
 use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::attribute::TxAttributes;
 use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
+use chain_core::tx::data::TxId;
 use chain_core::tx::TxAux;
-use client_common::Result;
+use client_common::{Result, StakedStateProof};
 
 /// Interface for performing network operations on Crypto.com Chain
 pub trait NetworkOpsClient: Send + Sync {
@@ -36,6 +39,39 @@ pub trait NetworkOpsClient: Send + Sync {
         attributes: StakedStateOpAttributes,
     ) -> Result<TxAux>;
 
+    /// creates a new transaction for moving unbonded stake (not withdrawn yet) back to bonded
+    fn create_rebond_stake_transaction(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        address: &StakedStateAddress,
+        value: Coin,
+        attributes: StakedStateOpAttributes,
+    ) -> Result<TxAux>;
+
+    /// creates a new transaction for delegating bonded stake to a council node
+    fn create_delegate_stake_transaction(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        address: &StakedStateAddress,
+        council_node: StakedStateAddress,
+        value: Coin,
+        attributes: StakedStateOpAttributes,
+    ) -> Result<TxAux>;
+
+    /// creates a new transaction for undelegating stake from a council node
+    /// (the undelegated stake can be withdrawn after the unbonding period)
+    fn create_undelegate_stake_transaction(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        address: &StakedStateAddress,
+        council_node: StakedStateAddress,
+        value: Coin,
+        attributes: StakedStateOpAttributes,
+    ) -> Result<TxAux>;
+
     /// Creates a new transaction for withdrawing unbonded stake from an account
     fn create_withdraw_unbonded_stake_transaction(
         &self,
@@ -47,6 +83,7 @@ pub trait NetworkOpsClient: Send + Sync {
     ) -> Result<TxAux>;
 
     /// Creates a new transaction for withdrawing all unbonded stake from an account
+    /// (only the unbonding entries matured at the latest block time are withdrawn)
     fn create_withdraw_all_unbonded_stake_transaction(
         &self,
         name: &str,
@@ -56,11 +93,64 @@ pub trait NetworkOpsClient: Send + Sync {
         attributes: TxAttributes,
     ) -> Result<TxAux>;
 
+    /// Creates a new transaction for registering a staked state as a council node
+    /// with the given consensus (validator) public key
+    fn create_node_join_transaction(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        staking_account_address: &StakedStateAddress,
+        consensus_pubkey: TendermintValidatorPubKey,
+        attributes: StakedStateOpAttributes,
+    ) -> Result<TxAux>;
+
+    /// Creates a new transaction for releasing a jailed staked state after its jail period
+    fn create_unjail_transaction(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        address: &StakedStateAddress,
+        attributes: StakedStateOpAttributes,
+    ) -> Result<TxAux>;
+
+    /// Creates a new transaction for proposing a network parameter change (signed by a council node's staked state)
+    /// to be activated at the end of the given block height if enough council nodes vote for it
+    fn create_param_change_proposal_transaction(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        address: &StakedStateAddress,
+        change: NetworkParameterChange,
+        activation_height: BlockHeight,
+        attributes: StakedStateOpAttributes,
+    ) -> Result<TxAux>;
+
+    /// Creates a new transaction for voting for a pending network parameter change
+    /// (identified by the proposal's transaction ID)
+    fn create_param_change_vote_transaction(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        address: &StakedStateAddress,
+        proposal_id: TxId,
+        attributes: StakedStateOpAttributes,
+    ) -> Result<TxAux>;
+
     /// Returns staked stake corresponding to given address
+    /// (verified against the trusted app hash of the queried state)
     fn get_staked_state(
         &self,
         name: &str,
         passphrase: &SecUtf8,
         address: &StakedStateAddress,
     ) -> Result<StakedState>;
+
+    /// Returns staked state corresponding to given address with its inclusion proof (not verified yet):
+    /// it can be verified against the app hash in the header at `height + 1` from a trusted source
+    fn get_staked_state_proof(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        address: &StakedStateAddress,
+    ) -> Result<StakedStateProof>;
 }
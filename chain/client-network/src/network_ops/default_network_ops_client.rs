@@ -1,33 +1,42 @@
 use failure::ResultExt;
-use parity_scale_codec::Decode;
 use secstr::SecUtf8;
 
+use chain_core::common::Timespec;
 use chain_core::init::coin::Coin;
 use chain_core::state::account::{
-    DepositBondTx, StakedState, StakedStateAddress, StakedStateOpAttributes, StakedStateOpWitness,
-    UnbondTx, WithdrawUnbondedTx,
+    DelegateTx, DepositBondTx, NodeJoinRequestTx, RebondTx, StakedState, StakedStateAddress,
+    StakedStateOpAttributes, StakedStateOpWitness, UnbondTx, UndelegateTx, UnjailTx,
+    WithdrawUnbondedTx,
 };
+use chain_core::state::governance::{
+    NetworkParameterChange, ParamChangeProposalTx, ParamChangeVoteTx,
+};
+use chain_core::state::tendermint::{BlockHeight, TendermintValidatorPubKey};
 use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::attribute::TxAttributes;
 use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
+use chain_core::tx::data::TxId;
 use chain_core::tx::fee::FeeAlgorithm;
 use chain_core::tx::{TransactionId, TxAux};
 use client_common::tendermint::Client;
-use client_common::{Error, ErrorKind, Result, SignedTransaction};
+use client_common::{
+    Error, ErrorKind, Result, SignedTransaction, StakedStateProof, TrustedAppHashes,
+};
 use client_core::{Signer, UnspentTransactions, WalletClient};
 use client_index::TransactionObfuscation;
 
 use crate::NetworkOpsClient;
 
 /// Default implementation of `NetworkOpsClient`
-pub struct DefaultNetworkOpsClient<W, S, C, F, E>
+pub struct DefaultNetworkOpsClient<W, S, C, F, E, T>
 where
     W: WalletClient,
     S: Signer,
     C: Client,
     F: FeeAlgorithm,
     E: TransactionObfuscation,
+    T: TrustedAppHashes,
 {
     /// WalletClient
     wallet_client: W,
@@ -35,27 +44,31 @@ where
     client: C,
     fee_algorithm: F,
     transaction_cipher: E,
+    trusted_app_hashes: T,
 }
 
-impl<W, S, C, F, E> DefaultNetworkOpsClient<W, S, C, F, E>
+impl<W, S, C, F, E, T> DefaultNetworkOpsClient<W, S, C, F, E, T>
 where
     W: WalletClient,
     S: Signer,
     C: Client,
     F: FeeAlgorithm,
     E: TransactionObfuscation,
+    T: TrustedAppHashes,
 {
     /// use WalletClient
     pub fn get_wallet(&self) -> &W {
         &self.wallet_client
     }
     /// Creates a new instance of `DefaultNetworkOpsClient`
+    /// (the staked states queried from `client` are verified against the app hashes from `trusted_app_hashes`)
     pub fn new(
         wallet_client: W,
         signer: S,
         client: C,
         fee_algorithm: F,
         transaction_cipher: E,
+        trusted_app_hashes: T,
     ) -> Self {
         Self {
             wallet_client,
@@ -63,39 +76,58 @@ where
             client,
             fee_algorithm,
             transaction_cipher,
+            trusted_app_hashes,
         }
     }
 
-    /// Get account info
-    fn get_account(&self, staked_state_address: &[u8]) -> Result<StakedState> {
-        let bytes = self
-            .client
-            .query("account", staked_state_address)?
-            .bytes()?;
+    /// Get account info with its inclusion proof (to be verified against a trusted header, see `StakedStateProof`)
+    fn get_account(&self, staked_state_address: &[u8]) -> Result<StakedStateProof> {
+        let result = self.client.query("account", staked_state_address, true)?;
+        StakedStateProof::from_query_result(&result)
+    }
+
+    /// Get account info verified against the trusted app hash of the queried state
+    fn get_verified_account(&self, staked_state_address: &[u8]) -> Result<StakedState> {
+        let staked_state_proof = self.get_account(staked_state_address)?;
+        let trusted_app_hash = self
+            .trusted_app_hashes
+            .app_hash(staked_state_proof.height)?;
+        staked_state_proof.verify(&trusted_app_hash)?;
+        Ok(staked_state_proof.staked_state)
+    }
 
-        StakedState::decode(&mut bytes.as_slice())
-            .context(ErrorKind::DeserializationError)
-            .map_err(Into::into)
+    /// Get the time of the latest block (unbonded stake matured at this time can be withdrawn)
+    fn get_last_block_time(&self) -> Result<Timespec> {
+        let last_block_height = self.client.status()?.last_block_height()?;
+        let block = self.client.block(last_block_height)?;
+        Ok(block.block.header.time.timestamp())
     }
 
-    /// Get staked state info
-    fn get_staked_state_account(
+    /// Checks that the staked state address belongs to the wallet
+    fn check_staked_state_address(
         &self,
-        to_staked_account: &StakedStateAddress,
-    ) -> Result<StakedState> {
-        match to_staked_account {
-            StakedStateAddress::BasicRedeem(ref a) => self.get_account(&a.0),
+        name: &str,
+        passphrase: &SecUtf8,
+        address: &StakedStateAddress,
+    ) -> Result<()> {
+        match address {
+            StakedStateAddress::BasicRedeem(ref redeem_address) => {
+                self.wallet_client
+                    .find_public_key(name, passphrase, redeem_address)?;
+            }
         }
+        Ok(())
     }
 }
 
-impl<W, S, C, F, E> NetworkOpsClient for DefaultNetworkOpsClient<W, S, C, F, E>
+impl<W, S, C, F, E, T> NetworkOpsClient for DefaultNetworkOpsClient<W, S, C, F, E, T>
 where
     W: WalletClient,
     S: Signer,
     C: Client,
     F: FeeAlgorithm,
     E: TransactionObfuscation,
+    T: TrustedAppHashes,
 {
     fn create_deposit_bonded_stake_transaction(
         &self,
@@ -159,6 +191,101 @@ where
         Ok(TxAux::UnbondStakeTx(transaction, signature))
     }
 
+    fn create_rebond_stake_transaction(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        address: &StakedStateAddress,
+        value: Coin,
+        attributes: StakedStateOpAttributes,
+    ) -> Result<TxAux> {
+        let staked_state = self.get_staked_state(name, passphrase, address)?;
+        let nonce = staked_state.nonce;
+
+        let transaction = RebondTx::new(value, nonce, attributes);
+
+        let public_key = match address {
+            StakedStateAddress::BasicRedeem(ref redeem_address) => self
+                .wallet_client
+                .find_public_key(name, passphrase, redeem_address)?
+                .ok_or_else(|| Error::from(ErrorKind::AddressNotFound))?,
+        };
+        let private_key = self
+            .wallet_client
+            .private_key(passphrase, &public_key)?
+            .ok_or_else(|| Error::from(ErrorKind::PrivateKeyNotFound))?;
+
+        let signature = private_key
+            .sign(transaction.id())
+            .map(StakedStateOpWitness::new)?;
+
+        Ok(TxAux::RebondStakeTx(transaction, signature))
+    }
+
+    fn create_delegate_stake_transaction(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        address: &StakedStateAddress,
+        council_node: StakedStateAddress,
+        value: Coin,
+        attributes: StakedStateOpAttributes,
+    ) -> Result<TxAux> {
+        let staked_state = self.get_staked_state(name, passphrase, address)?;
+        let nonce = staked_state.nonce;
+
+        let transaction = DelegateTx::new(value, nonce, council_node, attributes);
+
+        let public_key = match address {
+            StakedStateAddress::BasicRedeem(ref redeem_address) => self
+                .wallet_client
+                .find_public_key(name, passphrase, redeem_address)?
+                .ok_or_else(|| Error::from(ErrorKind::AddressNotFound))?,
+        };
+        let private_key = self
+            .wallet_client
+            .private_key(passphrase, &public_key)?
+            .ok_or_else(|| Error::from(ErrorKind::PrivateKeyNotFound))?;
+
+        let signature = private_key
+            .sign(transaction.id())
+            .map(StakedStateOpWitness::new)?;
+
+        Ok(TxAux::DelegateStakeTx(transaction, signature))
+    }
+
+    fn create_undelegate_stake_transaction(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        address: &StakedStateAddress,
+        council_node: StakedStateAddress,
+        value: Coin,
+        attributes: StakedStateOpAttributes,
+    ) -> Result<TxAux> {
+        let staked_state = self.get_staked_state(name, passphrase, address)?;
+        let nonce = staked_state.nonce;
+
+        let transaction = UndelegateTx::new(value, nonce, council_node, attributes);
+
+        let public_key = match address {
+            StakedStateAddress::BasicRedeem(ref redeem_address) => self
+                .wallet_client
+                .find_public_key(name, passphrase, redeem_address)?
+                .ok_or_else(|| Error::from(ErrorKind::AddressNotFound))?,
+        };
+        let private_key = self
+            .wallet_client
+            .private_key(passphrase, &public_key)?
+            .ok_or_else(|| Error::from(ErrorKind::PrivateKeyNotFound))?;
+
+        let signature = private_key
+            .sign(transaction.id())
+            .map(StakedStateOpWitness::new)?;
+
+        Ok(TxAux::UndelegateStakeTx(transaction, signature))
+    }
+
     fn create_withdraw_unbonded_stake_transaction(
         &self,
         name: &str,
@@ -209,8 +336,14 @@ where
     ) -> Result<TxAux> {
         let staked_state = self.get_staked_state(name, passphrase, from_address)?;
 
-        let temp_output =
-            TxOut::new_with_timelock(to_address.clone(), Coin::zero(), staked_state.unbonded_from);
+        // only the unbonding entries matured at the latest block time can be withdrawn
+        let last_block_time = self.get_last_block_time()?;
+        let release_time = staked_state
+            .matured_release_time(last_block_time)
+            .ok_or_else(|| Error::from(ErrorKind::InsufficientBalance))?;
+        let unbonded = staked_state.matured_unbonded(last_block_time);
+
+        let temp_output = TxOut::new_with_timelock(to_address.clone(), Coin::zero(), release_time);
 
         let temp_transaction = self.create_withdraw_unbonded_stake_transaction(
             name,
@@ -226,8 +359,8 @@ where
             .context(ErrorKind::BalanceAdditionError)?
             .to_coin();
 
-        let amount = (staked_state.unbonded - fee).context(ErrorKind::BalanceAdditionError)?;
-        let output = TxOut::new_with_timelock(to_address, amount, staked_state.unbonded_from);
+        let amount = (unbonded - fee).context(ErrorKind::BalanceAdditionError)?;
+        let output = TxOut::new_with_timelock(to_address, amount, release_time);
 
         self.create_withdraw_unbonded_stake_transaction(
             name,
@@ -238,6 +371,138 @@ where
         )
     }
 
+    fn create_node_join_transaction(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        staking_account_address: &StakedStateAddress,
+        consensus_pubkey: TendermintValidatorPubKey,
+        attributes: StakedStateOpAttributes,
+    ) -> Result<TxAux> {
+        let staked_state = self.get_staked_state(name, passphrase, staking_account_address)?;
+
+        let transaction = NodeJoinRequestTx::new(
+            staked_state.nonce,
+            *staking_account_address,
+            consensus_pubkey,
+            attributes,
+        );
+
+        let public_key = match staking_account_address {
+            StakedStateAddress::BasicRedeem(ref redeem_address) => self
+                .wallet_client
+                .find_public_key(name, passphrase, redeem_address)?
+                .ok_or_else(|| Error::from(ErrorKind::AddressNotFound))?,
+        };
+        let private_key = self
+            .wallet_client
+            .private_key(passphrase, &public_key)?
+            .ok_or_else(|| Error::from(ErrorKind::PrivateKeyNotFound))?;
+
+        let signature = private_key
+            .sign(transaction.id())
+            .map(StakedStateOpWitness::new)?;
+
+        Ok(TxAux::NodeJoinTx(transaction, signature))
+    }
+
+    fn create_unjail_transaction(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        address: &StakedStateAddress,
+        attributes: StakedStateOpAttributes,
+    ) -> Result<TxAux> {
+        let staked_state = self.get_staked_state(name, passphrase, address)?;
+
+        let transaction = UnjailTx::new(staked_state.nonce, *address, attributes);
+
+        let public_key = match address {
+            StakedStateAddress::BasicRedeem(ref redeem_address) => self
+                .wallet_client
+                .find_public_key(name, passphrase, redeem_address)?
+                .ok_or_else(|| Error::from(ErrorKind::AddressNotFound))?,
+        };
+        let private_key = self
+            .wallet_client
+            .private_key(passphrase, &public_key)?
+            .ok_or_else(|| Error::from(ErrorKind::PrivateKeyNotFound))?;
+
+        let signature = private_key
+            .sign(transaction.id())
+            .map(StakedStateOpWitness::new)?;
+
+        Ok(TxAux::UnjailTx(transaction, signature))
+    }
+
+    fn create_param_change_proposal_transaction(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        address: &StakedStateAddress,
+        change: NetworkParameterChange,
+        activation_height: BlockHeight,
+        attributes: StakedStateOpAttributes,
+    ) -> Result<TxAux> {
+        let staked_state = self.get_staked_state(name, passphrase, address)?;
+
+        let transaction = ParamChangeProposalTx::new(
+            staked_state.nonce,
+            *address,
+            change,
+            activation_height,
+            attributes,
+        );
+
+        let public_key = match address {
+            StakedStateAddress::BasicRedeem(ref redeem_address) => self
+                .wallet_client
+                .find_public_key(name, passphrase, redeem_address)?
+                .ok_or_else(|| Error::from(ErrorKind::AddressNotFound))?,
+        };
+        let private_key = self
+            .wallet_client
+            .private_key(passphrase, &public_key)?
+            .ok_or_else(|| Error::from(ErrorKind::PrivateKeyNotFound))?;
+
+        let signature = private_key
+            .sign(transaction.id())
+            .map(StakedStateOpWitness::new)?;
+
+        Ok(TxAux::ParamChangeProposalTx(transaction, signature))
+    }
+
+    fn create_param_change_vote_transaction(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        address: &StakedStateAddress,
+        proposal_id: TxId,
+        attributes: StakedStateOpAttributes,
+    ) -> Result<TxAux> {
+        let staked_state = self.get_staked_state(name, passphrase, address)?;
+
+        let transaction =
+            ParamChangeVoteTx::new(staked_state.nonce, *address, proposal_id, attributes);
+
+        let public_key = match address {
+            StakedStateAddress::BasicRedeem(ref redeem_address) => self
+                .wallet_client
+                .find_public_key(name, passphrase, redeem_address)?
+                .ok_or_else(|| Error::from(ErrorKind::AddressNotFound))?,
+        };
+        let private_key = self
+            .wallet_client
+            .private_key(passphrase, &public_key)?
+            .ok_or_else(|| Error::from(ErrorKind::PrivateKeyNotFound))?;
+
+        let signature = private_key
+            .sign(transaction.id())
+            .map(StakedStateOpWitness::new)?;
+
+        Ok(TxAux::ParamChangeVoteTx(transaction, signature))
+    }
+
     fn get_staked_state(
         &self,
         name: &str,
@@ -245,14 +510,25 @@ where
         address: &StakedStateAddress,
     ) -> Result<StakedState> {
         // Verify if `address` belongs to current wallet
+        self.check_staked_state_address(name, passphrase, address)?;
+
         match address {
-            StakedStateAddress::BasicRedeem(ref redeem_address) => {
-                self.wallet_client
-                    .find_public_key(name, passphrase, redeem_address)?;
-            }
+            StakedStateAddress::BasicRedeem(ref a) => self.get_verified_account(&a.0),
         }
+    }
+
+    fn get_staked_state_proof(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        address: &StakedStateAddress,
+    ) -> Result<StakedStateProof> {
+        // Verify if `address` belongs to current wallet
+        self.check_staked_state_address(name, passphrase, address)?;
 
-        self.get_staked_state_account(address)
+        match address {
+            StakedStateAddress::BasicRedeem(ref a) => self.get_account(&a.0),
+        }
     }
 }
 
@@ -260,10 +536,16 @@ where
 mod tests {
     use super::*;
 
-    use parity_scale_codec::Encode;
+    use parity_scale_codec::{Decode, Encode};
 
+    use std::str::FromStr;
+
+    use chrono::DateTime;
+
+    use chain_core::common::H256;
     use chain_core::init::address::RedeemAddress;
     use chain_core::init::coin::CoinError;
+    use chain_core::state::proof::{AccountProof, AppHashParts};
     use chain_core::tx::data::input::TxoIndex;
     use chain_core::tx::data::TxId;
     use chain_core::tx::fee::Fee;
@@ -341,11 +623,29 @@ mod tests {
         }
 
         fn status(&self) -> Result<Status> {
-            unreachable!()
+            Ok(Status {
+                sync_info: SyncInfo {
+                    latest_block_height: "1".to_owned(),
+                    latest_app_hash:
+                        "3891040F29C6A56A5E36B17DCA6992D8F91D1EAAB4439D008D19A9D703271D3C"
+                            .to_owned(),
+                },
+            })
         }
 
         fn block(&self, _: u64) -> Result<Block> {
-            unreachable!()
+            Ok(Block {
+                block: BlockInner {
+                    header: Header {
+                        app_hash:
+                            "3891040F29C6A56A5E36B17DCA6992D8F91D1EAAB4439D008D19A9D703271D3C"
+                                .to_owned(),
+                        height: "1".to_owned(),
+                        time: DateTime::from_str("2019-04-01T00:00:00.000000Z").unwrap(),
+                    },
+                    data: Data { txs: None },
+                },
+            })
         }
 
         fn block_batch<'a, T: Iterator<Item = &'a u64>>(&self, _heights: T) -> Result<Vec<Block>> {
@@ -367,17 +667,67 @@ mod tests {
             unreachable!()
         }
 
-        fn query(&self, _path: &str, _data: &[u8]) -> Result<QueryResult> {
+        fn query(&self, _path: &str, _data: &[u8], _prove: bool) -> Result<QueryResult> {
+            let (proof, _) = mock_account_proof();
             Ok(QueryResult {
                 response: Response {
-                    value:
-                        "AAAAAAAAAAAAAAAAAAAAAAAAeiLByLEia/aSXAAAAAAADbIhxPV9XTi5aBOcBukTKq+E6N8="
-                            .to_string(),
+                    value: MOCK_STAKED_STATE.to_owned(),
+                    height: "0".to_owned(),
+                    proof: Some(Proof {
+                        ops: vec![ProofOp {
+                            field_type: "account".to_owned(),
+                            key: Some(base64::encode(&mock_staked_state().key())),
+                            data: Some(base64::encode(&proof.encode())),
+                        }],
+                    }),
                 },
             })
         }
     }
 
+    /// the staked state returned by the mock `account` query
+    const MOCK_STAKED_STATE: &str =
+        "AAAAAAAAAAAAAAAAAAAAAAQAAHoiwcixImv2klwAAAAAAA2yIcT1fV04uWgTnAbpEyqvhOjfAAAA";
+
+    fn mock_staked_state() -> StakedState {
+        let bytes = base64::decode(MOCK_STAKED_STATE).unwrap();
+        StakedState::decode(&mut bytes.as_slice()).unwrap()
+    }
+
+    /// trusts the app hash the mock staked state is committed in
+    struct MockTrustedAppHashes(H256);
+
+    impl Default for MockTrustedAppHashes {
+        fn default() -> Self {
+            MockTrustedAppHashes(mock_account_proof().1)
+        }
+    }
+
+    impl TrustedAppHashes for MockTrustedAppHashes {
+        fn app_hash(&self, height: u64) -> Result<H256> {
+            assert_eq!(0, height);
+            Ok(self.0)
+        }
+    }
+
+    /// the proof of the mock staked state (the only account in the trie)
+    /// and the app hash it's committed in (at height 0)
+    fn mock_account_proof() -> (AccountProof, H256) {
+        let proof = AccountProof {
+            siblings: Vec::new(),
+            app_hash_parts: AppHashParts {
+                valid_tx_root: [0; 32],
+                rewards_pool_hash: [1; 32],
+                network_params_hash: [2; 32],
+                node_state_hash: [3; 32],
+            },
+        };
+        let app_hash = proof
+            .app_hash_parts
+            .app_hash(&proof.account_root(&mock_staked_state()));
+        (proof, app_hash)
+    }
+
     #[test]
     fn check_create_deposit_bonded_stake_transaction() {
         let name = "name";
@@ -402,6 +752,7 @@ mod tests {
             tendermint_client,
             fee_algorithm,
             MockTransactionCipher,
+            MockTrustedAppHashes::default(),
         );
 
         let inputs: Vec<TxoPointer> = vec![];
@@ -446,6 +797,7 @@ mod tests {
             tendermint_client,
             fee_algorithm,
             MockTransactionCipher,
+            MockTrustedAppHashes::default(),
         );
 
         let value = Coin::new(0).unwrap();
@@ -460,6 +812,383 @@ mod tests {
             .is_ok());
     }
 
+    #[test]
+    fn check_get_staked_state_proof() {
+        let name = "name";
+        let passphrase = &SecUtf8::from("passphrase");
+
+        let storage = MemoryStorage::default();
+        let signer = DefaultSigner::new(storage.clone());
+
+        let wallet_client = DefaultWalletClient::builder()
+            .with_wallet(storage.clone())
+            .build()
+            .unwrap();
+
+        wallet_client.new_wallet(name, passphrase).unwrap();
+
+        let network_ops_client = DefaultNetworkOpsClient::new(
+            wallet_client,
+            signer,
+            MockClient::default(),
+            UnitFeeAlgorithm::default(),
+            MockTransactionCipher,
+            MockTrustedAppHashes::default(),
+        );
+
+        let address = network_ops_client
+            .get_wallet()
+            .new_staking_address(name, passphrase)
+            .unwrap();
+        let staked_state_proof = network_ops_client
+            .get_staked_state_proof(name, passphrase, &address)
+            .unwrap();
+        let (_, app_hash) = mock_account_proof();
+
+        assert_eq!(0, staked_state_proof.height);
+        assert_eq!(
+            &mock_staked_state(),
+            staked_state_proof.verify(&app_hash).unwrap()
+        );
+        assert_eq!(
+            ErrorKind::InvalidProof,
+            staked_state_proof.verify(&[0; 32]).unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn check_get_staked_state_with_untrusted_proof() {
+        let name = "name";
+        let passphrase = &SecUtf8::from("passphrase");
+
+        let storage = MemoryStorage::default();
+        let signer = DefaultSigner::new(storage.clone());
+
+        let wallet_client = DefaultWalletClient::builder()
+            .with_wallet(storage.clone())
+            .build()
+            .unwrap();
+
+        wallet_client.new_wallet(name, passphrase).unwrap();
+
+        // the queried node's proof doesn't match the trusted app hash
+        let network_ops_client = DefaultNetworkOpsClient::new(
+            wallet_client,
+            signer,
+            MockClient::default(),
+            UnitFeeAlgorithm::default(),
+            MockTransactionCipher,
+            MockTrustedAppHashes([0; 32]),
+        );
+
+        let address = network_ops_client
+            .get_wallet()
+            .new_staking_address(name, passphrase)
+            .unwrap();
+
+        assert_eq!(
+            ErrorKind::InvalidProof,
+            network_ops_client
+                .get_staked_state(name, passphrase, &address)
+                .unwrap_err()
+                .kind()
+        );
+        assert_eq!(
+            ErrorKind::InvalidProof,
+            network_ops_client
+                .create_unbond_stake_transaction(
+                    name,
+                    passphrase,
+                    &address,
+                    Coin::unit(),
+                    StakedStateOpAttributes::new(0),
+                )
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn check_create_rebond_stake_transaction() {
+        let name = "name";
+        let passphrase = &SecUtf8::from("passphrase");
+
+        let storage = MemoryStorage::default();
+        let signer = DefaultSigner::new(storage.clone());
+
+        let fee_algorithm = UnitFeeAlgorithm::default();
+
+        let wallet_client = DefaultWalletClient::builder()
+            .with_wallet(storage.clone())
+            .build()
+            .unwrap();
+
+        wallet_client.new_wallet(name, passphrase).unwrap();
+
+        let tendermint_client = MockClient::default();
+        let network_ops_client = DefaultNetworkOpsClient::new(
+            wallet_client,
+            signer,
+            tendermint_client,
+            fee_algorithm,
+            MockTransactionCipher,
+            MockTrustedAppHashes::default(),
+        );
+
+        let value = Coin::new(10).unwrap();
+        let address = network_ops_client
+            .get_wallet()
+            .new_staking_address(name, passphrase)
+            .unwrap();
+
+        let transaction = network_ops_client
+            .create_rebond_stake_transaction(
+                name,
+                passphrase,
+                &address,
+                value,
+                StakedStateOpAttributes::new(0),
+            )
+            .unwrap();
+
+        match transaction {
+            TxAux::RebondStakeTx(tx, witness) => {
+                let account_address = verify_tx_recover_address(&witness, &tx.id())
+                    .expect("Unable to verify transaction");
+
+                assert_eq!(account_address, address);
+                assert_eq!(tx.value, value);
+            }
+            _ => {
+                unreachable!("`create_rebond_stake_transaction()` created invalid transaction type")
+            }
+        }
+    }
+
+    #[test]
+    fn check_create_delegate_stake_transaction() {
+        let name = "name";
+        let passphrase = &SecUtf8::from("passphrase");
+
+        let storage = MemoryStorage::default();
+        let signer = DefaultSigner::new(storage.clone());
+
+        let fee_algorithm = UnitFeeAlgorithm::default();
+
+        let wallet_client = DefaultWalletClient::builder()
+            .with_wallet(storage.clone())
+            .build()
+            .unwrap();
+
+        wallet_client.new_wallet(name, passphrase).unwrap();
+
+        let tendermint_client = MockClient::default();
+        let network_ops_client = DefaultNetworkOpsClient::new(
+            wallet_client,
+            signer,
+            tendermint_client,
+            fee_algorithm,
+            MockTransactionCipher,
+            MockTrustedAppHashes::default(),
+        );
+
+        let value = Coin::new(10).unwrap();
+        let address = network_ops_client
+            .get_wallet()
+            .new_staking_address(name, passphrase)
+            .unwrap();
+        let council_node = StakedStateAddress::BasicRedeem(RedeemAddress::from([0x33; 20]));
+
+        let transaction = network_ops_client
+            .create_delegate_stake_transaction(
+                name,
+                passphrase,
+                &address,
+                council_node,
+                value,
+                StakedStateOpAttributes::new(0),
+            )
+            .unwrap();
+
+        match transaction {
+            TxAux::DelegateStakeTx(tx, witness) => {
+                let account_address = verify_tx_recover_address(&witness, &tx.id())
+                    .expect("Unable to verify transaction");
+
+                assert_eq!(account_address, address);
+                assert_eq!(tx.council_node, council_node);
+                assert_eq!(tx.value, value);
+            }
+            _ => unreachable!(
+                "`create_delegate_stake_transaction()` created invalid transaction type"
+            ),
+        }
+    }
+
+    #[test]
+    fn check_create_node_join_transaction() {
+        let name = "name";
+        let passphrase = &SecUtf8::from("passphrase");
+
+        let storage = MemoryStorage::default();
+        let signer = DefaultSigner::new(storage.clone());
+
+        let fee_algorithm = UnitFeeAlgorithm::default();
+
+        let wallet_client = DefaultWalletClient::builder()
+            .with_wallet(storage.clone())
+            .build()
+            .unwrap();
+
+        wallet_client.new_wallet(name, passphrase).unwrap();
+
+        let tendermint_client = MockClient::default();
+        let network_ops_client = DefaultNetworkOpsClient::new(
+            wallet_client,
+            signer,
+            tendermint_client,
+            fee_algorithm,
+            MockTransactionCipher,
+            MockTrustedAppHashes::default(),
+        );
+
+        let address = network_ops_client
+            .get_wallet()
+            .new_staking_address(name, passphrase)
+            .unwrap();
+        let consensus_pubkey = TendermintValidatorPubKey::Ed25519([0xee; 32]);
+
+        let transaction = network_ops_client
+            .create_node_join_transaction(
+                name,
+                passphrase,
+                &address,
+                consensus_pubkey.clone(),
+                StakedStateOpAttributes::new(0),
+            )
+            .unwrap();
+
+        match transaction {
+            TxAux::NodeJoinTx(tx, witness) => {
+                let account_address = verify_tx_recover_address(&witness, &tx.id())
+                    .expect("Unable to verify transaction");
+
+                assert_eq!(account_address, address);
+                assert_eq!(tx.address, address);
+                assert_eq!(tx.consensus_pubkey, consensus_pubkey);
+            }
+            _ => unreachable!("`create_node_join_transaction()` created invalid transaction type"),
+        }
+    }
+
+    #[test]
+    fn check_create_unjail_transaction() {
+        let name = "name";
+        let passphrase = &SecUtf8::from("passphrase");
+
+        let storage = MemoryStorage::default();
+        let signer = DefaultSigner::new(storage.clone());
+
+        let fee_algorithm = UnitFeeAlgorithm::default();
+
+        let wallet_client = DefaultWalletClient::builder()
+            .with_wallet(storage.clone())
+            .build()
+            .unwrap();
+
+        wallet_client.new_wallet(name, passphrase).unwrap();
+
+        let tendermint_client = MockClient::default();
+        let network_ops_client = DefaultNetworkOpsClient::new(
+            wallet_client,
+            signer,
+            tendermint_client,
+            fee_algorithm,
+            MockTransactionCipher,
+            MockTrustedAppHashes::default(),
+        );
+
+        let address = network_ops_client
+            .get_wallet()
+            .new_staking_address(name, passphrase)
+            .unwrap();
+
+        let transaction = network_ops_client
+            .create_unjail_transaction(name, passphrase, &address, StakedStateOpAttributes::new(0))
+            .unwrap();
+
+        match transaction {
+            TxAux::UnjailTx(tx, witness) => {
+                let account_address = verify_tx_recover_address(&witness, &tx.id())
+                    .expect("Unable to verify transaction");
+
+                assert_eq!(account_address, address);
+                assert_eq!(tx.address, address);
+            }
+            _ => unreachable!("`create_unjail_transaction()` created invalid transaction type"),
+        }
+    }
+
+    #[test]
+    fn check_create_param_change_proposal_transaction() {
+        let name = "name";
+        let passphrase = &SecUtf8::from("passphrase");
+
+        let storage = MemoryStorage::default();
+        let signer = DefaultSigner::new(storage.clone());
+
+        let fee_algorithm = UnitFeeAlgorithm::default();
+
+        let wallet_client = DefaultWalletClient::builder()
+            .with_wallet(storage.clone())
+            .build()
+            .unwrap();
+
+        wallet_client.new_wallet(name, passphrase).unwrap();
+
+        let tendermint_client = MockClient::default();
+        let network_ops_client = DefaultNetworkOpsClient::new(
+            wallet_client,
+            signer,
+            tendermint_client,
+            fee_algorithm,
+            MockTransactionCipher,
+            MockTrustedAppHashes::default(),
+        );
+
+        let address = network_ops_client
+            .get_wallet()
+            .new_staking_address(name, passphrase)
+            .unwrap();
+        let change = NetworkParameterChange::UnbondingPeriod(60);
+
+        let transaction = network_ops_client
+            .create_param_change_proposal_transaction(
+                name,
+                passphrase,
+                &address,
+                change,
+                100,
+                StakedStateOpAttributes::new(0),
+            )
+            .unwrap();
+
+        match transaction {
+            TxAux::ParamChangeProposalTx(tx, witness) => {
+                let account_address = verify_tx_recover_address(&witness, &tx.id())
+                    .expect("Unable to verify transaction");
+
+                assert_eq!(account_address, address);
+                assert_eq!(tx.address, address);
+                assert_eq!(tx.change, change);
+                assert_eq!(tx.activation_height, 100);
+            }
+            _ => unreachable!(
+                "`create_param_change_proposal_transaction()` created invalid transaction type"
+            ),
+        }
+    }
+
     #[test]
     fn check_withdraw_unbonded_stake_transaction() {
         let name = "name";
@@ -482,6 +1211,7 @@ mod tests {
             tendermint_client,
             fee_algorithm,
             MockTransactionCipher,
+            MockTrustedAppHashes::default(),
         );
 
         network_ops_client
@@ -539,6 +1269,7 @@ mod tests {
             tendermint_client,
             fee_algorithm,
             MockTransactionCipher,
+            MockTrustedAppHashes::default(),
         );
 
         network_ops_client
@@ -609,6 +1340,7 @@ mod tests {
             tendermint_client,
             fee_algorithm,
             MockTransactionCipher,
+            MockTrustedAppHashes::default(),
         );
 
         network_ops_client
@@ -655,6 +1387,7 @@ mod tests {
             tendermint_client,
             fee_algorithm,
             MockTransactionCipher,
+            MockTrustedAppHashes::default(),
         );
 
         assert_eq!(
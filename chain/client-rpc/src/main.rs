@@ -65,6 +65,13 @@ pub(crate) struct Options {
     )]
     tendermint_url: String,
 
+    #[structopt(
+        name = "trusted-tendermint-url",
+        long,
+        help = "Url of the tendermint RPC of a trusted node the queried staked states are verified with (defaults to the tendermint url)"
+    )]
+    trusted_tendermint_url: Option<String>,
+
     #[structopt(
         name = "websocket-url",
         short,
@@ -73,6 +80,14 @@ pub(crate) struct Options {
         help = "Url for connecting with tendermint websocket RPC"
     )]
     websocket_url: String,
+
+    #[structopt(
+        name = "legacy-tx-height",
+        long,
+        default_value = "0",
+        help = "Last block height with transactions in the legacy format (committed before the transaction format was versioned; 0 if none)"
+    )]
+    legacy_tx_height: u64,
 }
 
 fn main() {
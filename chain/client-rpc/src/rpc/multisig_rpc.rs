@@ -5,6 +5,7 @@ use jsonrpc_derive::rpc;
 use secstr::SecUtf8;
 
 use chain_core::common::{H256, HASH_SIZE_256};
+use chain_core::init::address::CroAddress;
 use chain_core::tx::data::Tx;
 use client_common::{Error, ErrorKind, PublicKey, Result as CommonResult};
 use client_core::{MultiSigWalletClient, WalletClient};
@@ -121,7 +122,9 @@ where
             )
             .map_err(to_rpc_error)?;
 
-        Ok(extended_address.to_string())
+        extended_address
+            .to_cro_for(self.client.chain_params().network())
+            .map_err(|err| rpc_error_from_string(format!("{}", err)))
     }
 
     fn new_session(
@@ -475,7 +478,7 @@ mod test {
             unreachable!("broadcast_transaction")
         }
 
-        fn query(&self, _path: &str, _data: &[u8]) -> CommonResult<QueryResult> {
+        fn query(&self, _path: &str, _data: &[u8], _prove: bool) -> CommonResult<QueryResult> {
             unreachable!("query")
         }
     }
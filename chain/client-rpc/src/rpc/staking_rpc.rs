@@ -4,6 +4,7 @@ use failure::ResultExt;
 use jsonrpc_core::Result;
 use jsonrpc_derive::rpc;
 
+use chain_core::init::address::CroAddress;
 use chain_core::init::coin::Coin;
 use chain_core::state::account::{StakedState, StakedStateAddress, StakedStateOpAttributes};
 use chain_core::tx::data::access::{TxAccess, TxAccessPolicy};
@@ -37,6 +38,14 @@ pub trait StakingRpc: Send + Sync {
         amount: Coin,
     ) -> Result<()>;
 
+    #[rpc(name = "staking_rebondStake")]
+    fn rebond_stake(
+        &self,
+        request: WalletRequest,
+        staking_address: String,
+        amount: Coin,
+    ) -> Result<()>;
+
     #[rpc(name = "staking_withdrawAllUnbondedStake")]
     fn withdraw_all_unbonded_stake(
         &self,
@@ -54,7 +63,6 @@ where
 {
     client: T,
     ops_client: N,
-    network_id: u8,
 }
 
 impl<T, N> StakingRpcImpl<T, N>
@@ -62,12 +70,8 @@ where
     T: WalletClient,
     N: NetworkOpsClient,
 {
-    pub fn new(client: T, ops_client: N, network_id: u8) -> Self {
-        StakingRpcImpl {
-            client,
-            ops_client,
-            network_id,
-        }
+    pub fn new(client: T, ops_client: N) -> Self {
+        StakingRpcImpl { client, ops_client }
     }
 }
 
@@ -86,7 +90,7 @@ where
             .context(ErrorKind::DeserializationError)
             .map_err(Into::into)
             .map_err(to_rpc_error)?;
-        let attr = StakedStateOpAttributes::new(self.network_id);
+        let attr = StakedStateOpAttributes::new(self.client.chain_params().network_id());
         let transaction = self
             .ops_client
             .create_deposit_bonded_stake_transaction(
@@ -117,7 +121,7 @@ where
         staking_address: String,
         amount: Coin,
     ) -> Result<()> {
-        let attr = StakedStateOpAttributes::new(self.network_id);
+        let attr = StakedStateOpAttributes::new(self.client.chain_params().network_id());
         let addr = StakedStateAddress::from_str(&staking_address)
             .context(ErrorKind::DeserializationError)
             .map_err(Into::into)
@@ -141,6 +145,36 @@ where
         Ok(())
     }
 
+    fn rebond_stake(
+        &self,
+        request: WalletRequest,
+        staking_address: String,
+        amount: Coin,
+    ) -> Result<()> {
+        let attr = StakedStateOpAttributes::new(self.client.chain_params().network_id());
+        let addr = StakedStateAddress::from_str(&staking_address)
+            .context(ErrorKind::DeserializationError)
+            .map_err(Into::into)
+            .map_err(to_rpc_error)?;
+
+        let transaction = self
+            .ops_client
+            .create_rebond_stake_transaction(
+                &request.name,
+                &request.passphrase,
+                &addr,
+                amount,
+                attr,
+            )
+            .map_err(to_rpc_error)?;
+
+        self.client
+            .broadcast_transaction(&transaction)
+            .map_err(to_rpc_error)?;
+
+        Ok(())
+    }
+
     fn withdraw_all_unbonded_stake(
         &self,
         request: WalletRequest,
@@ -152,7 +186,8 @@ where
             .context(ErrorKind::DeserializationError)
             .map_err(Into::into)
             .map_err(to_rpc_error)?;
-        let to_address = ExtendedAddr::from_str(&to_address)
+        let chain_params = self.client.chain_params();
+        let to_address = ExtendedAddr::from_cro_for(&to_address, chain_params.network())
             .context(ErrorKind::DeserializationError)
             .map_err(Into::into)
             .map_err(to_rpc_error)?;
@@ -179,7 +214,7 @@ where
             });
         }
 
-        let attributes = TxAttributes::new_with_access(self.network_id, access_policies);
+        let attributes = TxAttributes::new_with_access(chain_params.network_id(), access_policies);
 
         let transaction = self
             .ops_client
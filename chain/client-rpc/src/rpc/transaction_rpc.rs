@@ -1,8 +1,14 @@
 use jsonrpc_core::Result;
 use jsonrpc_derive::rpc;
+use serde::ser::{Error as SerError, SerializeStruct};
 use serde::{Deserialize, Serialize, Serializer};
 
+use chain_core::common::Timespec;
+use chain_core::init::address::CroAddress;
+use chain_core::init::coin::Coin;
+use chain_core::init::network::{ChainParams, Network};
 use chain_core::tx::data::access::{TxAccess, TxAccessPolicy};
+use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::attribute::TxAttributes;
 use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
@@ -10,21 +16,78 @@ use chain_core::tx::data::{Tx, TxId};
 use chain_core::tx::TransactionId;
 use client_common::PublicKey;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::server::rpc_error_from_string;
+
+#[derive(Debug, Deserialize)]
 pub struct RawTransaction {
     tx: Tx,
-    #[serde(serialize_with = "serialize_transaction_id")]
     tx_id: TxId,
+    /// the network the output addresses are formatted for
+    #[serde(skip)]
+    chain_params: ChainParams,
+}
+
+/// `TxOut` with the address in the textual format of the given network
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RawOutput {
+    address: String,
+    value: Coin,
+    valid_from: Option<Timespec>,
+}
+
+impl RawOutput {
+    fn into_output(self, network: Network) -> Result<TxOut> {
+        let address = ExtendedAddr::from_cro_for(&self.address, network)
+            .map_err(|err| rpc_error_from_string(format!("{}", err)))?;
+        Ok(TxOut {
+            address,
+            value: self.value,
+            valid_from: self.valid_from,
+        })
+    }
+}
+
+/// `Tx` with the output addresses in the textual format of the given network
+#[derive(Serialize)]
+struct RawTx<'a> {
+    attributes: &'a TxAttributes,
+    inputs: &'a [TxoPointer],
+    outputs: Vec<RawOutput>,
 }
 
-fn serialize_transaction_id<S>(
-    transaction_id: &TxId,
-    serializer: S,
-) -> std::result::Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    serializer.serialize_str(&hex::encode(transaction_id))
+impl Serialize for RawTransaction {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let network = self.chain_params.network();
+        let outputs = self
+            .tx
+            .outputs
+            .iter()
+            .map(|output| {
+                Ok(RawOutput {
+                    address: output
+                        .address
+                        .to_cro_for(network)
+                        .map_err(S::Error::custom)?,
+                    value: output.value,
+                    valid_from: output.valid_from,
+                })
+            })
+            .collect::<std::result::Result<Vec<_>, S::Error>>()?;
+        let mut state = serializer.serialize_struct("RawTransaction", 2)?;
+        state.serialize_field(
+            "tx",
+            &RawTx {
+                attributes: &self.tx.attributes,
+                inputs: &self.tx.inputs,
+                outputs,
+            },
+        )?;
+        state.serialize_field("tx_id", &hex::encode(self.tx_id))?;
+        state.end()
+    }
 }
 
 #[rpc]
@@ -33,18 +96,18 @@ pub trait TransactionRpc: Send + Sync {
     fn create_raw(
         &self,
         inputs: Vec<TxoPointer>,
-        outputs: Vec<TxOut>,
+        outputs: Vec<RawOutput>,
         view_keys: Vec<PublicKey>,
     ) -> Result<RawTransaction>;
 }
 
 pub struct TransactionRpcImpl {
-    network_id: u8,
+    chain_params: ChainParams,
 }
 
 impl TransactionRpcImpl {
-    pub fn new(network_id: u8) -> Self {
-        TransactionRpcImpl { network_id }
+    pub fn new(chain_params: ChainParams) -> Self {
+        TransactionRpcImpl { chain_params }
     }
 }
 
@@ -52,9 +115,14 @@ impl TransactionRpc for TransactionRpcImpl {
     fn create_raw(
         &self,
         inputs: Vec<TxoPointer>,
-        outputs: Vec<TxOut>,
+        outputs: Vec<RawOutput>,
         view_keys: Vec<PublicKey>,
     ) -> Result<RawTransaction> {
+        // the output addresses are expected in the textual format of the server's network
+        let outputs = outputs
+            .into_iter()
+            .map(|output| output.into_output(self.chain_params.network()))
+            .collect::<Result<Vec<_>>>()?;
         let mut access_policies: Vec<TxAccessPolicy> = vec![];
 
         for key in view_keys.iter() {
@@ -64,7 +132,8 @@ impl TransactionRpc for TransactionRpcImpl {
             });
         }
 
-        let attributes = TxAttributes::new_with_access(self.network_id, access_policies);
+        let attributes =
+            TxAttributes::new_with_access(self.chain_params.network_id(), access_policies);
 
         let tx = Tx {
             inputs,
@@ -73,32 +142,37 @@ impl TransactionRpc for TransactionRpcImpl {
         };
         let tx_id = tx.id();
 
-        Ok(RawTransaction { tx, tx_id })
+        Ok(RawTransaction {
+            tx,
+            tx_id,
+            chain_params: self.chain_params,
+        })
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use chain_core::init::address::CroAddress;
-    use chain_core::init::coin::Coin;
-    use chain_core::tx::data::address::ExtendedAddr;
     use client_common::PrivateKey;
+    use std::str::FromStr;
 
     #[test]
     fn create_raw_flow() {
-        let chain_id = hex::decode("AB").unwrap()[0];
-        let transaction_rpc = TransactionRpcImpl::new(chain_id);
+        let chain_params = ChainParams::new(Network::Testnet, 0xab);
+        let transaction_rpc = TransactionRpcImpl::new(chain_params);
 
         let inputs = vec![TxoPointer::new([0; 32], 0), TxoPointer::new([1; 32], 0)];
 
-        let outputs = vec![TxOut::new(
-            ExtendedAddr::from_cro(
-                "dcro1zz30nheum6vnug3mjs0j4kw4w739tca8cuqae2kdjmt8suhv693qcs3qyn",
-            )
-            .unwrap(),
-            Coin::new(750).unwrap(),
-        )];
+        let address = ExtendedAddr::from_str(
+            "dcro1zz30nheum6vnug3mjs0j4kw4w739tca8cuqae2kdjmt8suhv693qcs3qyn",
+        )
+        .unwrap();
+        let outputs = vec![TxOut::new(address.clone(), Coin::new(750).unwrap())];
+        let raw_outputs = vec![RawOutput {
+            address: address.to_cro_for(Network::Testnet).unwrap(),
+            value: Coin::new(750).unwrap(),
+            valid_from: None,
+        }];
 
         let view_key_1 = PublicKey::from(&PrivateKey::new().unwrap());
         let view_key_2 = PublicKey::from(&PrivateKey::new().unwrap());
@@ -106,7 +180,7 @@ mod test {
         let view_keys = vec![view_key_1.clone(), view_key_2.clone()];
 
         let raw_transaction = transaction_rpc
-            .create_raw(inputs.clone(), outputs.clone(), view_keys.clone())
+            .create_raw(inputs.clone(), raw_outputs, view_keys.clone())
             .expect("create_raw does not work for valid parameters");
 
         assert_eq!(
@@ -119,7 +193,8 @@ mod test {
         );
 
         assert_eq!(
-            raw_transaction.tx.attributes.chain_hex_id, chain_id,
+            raw_transaction.tx.attributes.chain_hex_id,
+            chain_params.network_id(),
             "Returned raw transaction should have same chain_id as network"
         );
         assert_eq!(
@@ -143,5 +218,27 @@ mod test {
             },
             "Returned raw transaction should have the same view key from parameter"
         );
+
+        let serialized = serde_json::to_value(&raw_transaction).unwrap();
+        assert_eq!(
+            serialized["tx"]["outputs"][0]["address"],
+            outputs[0].address.to_cro_for(Network::Testnet).unwrap(),
+            "Returned raw transaction should have output addresses in the network's format"
+        );
+    }
+
+    #[test]
+    fn create_raw_should_reject_output_addresses_of_other_networks() {
+        let transaction_rpc = TransactionRpcImpl::new(ChainParams::new(Network::Testnet, 0xab));
+
+        let outputs = vec![RawOutput {
+            address: "dcro1zz30nheum6vnug3mjs0j4kw4w739tca8cuqae2kdjmt8suhv693qcs3qyn".to_owned(),
+            value: Coin::new(750).unwrap(),
+            valid_from: None,
+        }];
+
+        assert!(transaction_rpc
+            .create_raw(vec![TxoPointer::new([0; 32], 0)], outputs, vec![])
+            .is_err());
     }
 }
@@ -1,7 +1,10 @@
+use chrono::{DateTime, Utc};
 use jsonrpc_core::Result;
 use jsonrpc_derive::rpc;
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
+use chain_core::init::address::CroAddress;
 use chain_core::init::coin::Coin;
 use chain_core::tx::data::access::{TxAccess, TxAccessPolicy};
 use chain_core::tx::data::address::ExtendedAddr;
@@ -9,12 +12,24 @@ use chain_core::tx::data::attribute::TxAttributes;
 use chain_core::tx::data::output::TxOut;
 use chain_core::tx::data::TxId;
 use chain_core::tx::TxAux;
-use client_common::balance::TransactionChange;
+use client_common::balance::{BalanceChange, TransactionChange};
 use client_common::{PublicKey, Result as CommonResult};
 use client_core::{MultiSigWalletClient, WalletClient};
 
 use crate::server::{rpc_error_from_string, to_rpc_error, WalletRequest};
 
+/// Balance change in a wallet transaction (`TransactionChange` with the address
+/// in the textual format of the wallet's network)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalletTransaction {
+    transaction_id: String,
+    address: String,
+    #[serde(flatten)]
+    balance_change: BalanceChange,
+    block_height: u64,
+    block_time: DateTime<Utc>,
+}
+
 #[rpc]
 pub trait WalletRpc: Send + Sync {
     #[rpc(name = "wallet_balance")]
@@ -54,7 +69,7 @@ pub trait WalletRpc: Send + Sync {
     ) -> Result<String>;
 
     #[rpc(name = "wallet_transactions")]
-    fn transactions(&self, request: WalletRequest) -> Result<Vec<TransactionChange>>;
+    fn transactions(&self, request: WalletRequest) -> Result<Vec<WalletTransaction>>;
 }
 
 pub struct WalletRpcImpl<T>
@@ -62,15 +77,21 @@ where
     T: WalletClient,
 {
     client: T,
-    network_id: u8,
 }
 
 impl<T> WalletRpcImpl<T>
 where
     T: WalletClient,
 {
-    pub fn new(client: T, network_id: u8) -> Self {
-        WalletRpcImpl { client, network_id }
+    pub fn new(client: T) -> Self {
+        WalletRpcImpl { client }
+    }
+
+    /// encodes the transfer address in the textual format of the wallet's network
+    fn encode_address(&self, address: &ExtendedAddr) -> Result<String> {
+        address
+            .to_cro_for(self.client.chain_params().network())
+            .map_err(|err| rpc_error_from_string(format!("{}", err)))
     }
 }
 
@@ -102,7 +123,8 @@ where
     fn create_staking_address(&self, request: WalletRequest) -> Result<String> {
         self.client
             .new_staking_address(&request.name, &request.passphrase)
-            .map(|extended_addr| extended_addr.to_string())
+            // staking addresses are hex-encoded (the same on all networks)
+            .map(|staking_address| staking_address.to_string())
             .map_err(to_rpc_error)
     }
 
@@ -112,7 +134,7 @@ where
             .new_transfer_address(&request.name, &request.passphrase)
             .map_err(to_rpc_error)?;
 
-        Ok(extended_address.to_string())
+        self.encode_address(&extended_address)
     }
 
     fn get_view_key(&self, request: WalletRequest) -> Result<String> {
@@ -151,8 +173,10 @@ where
     fn list_transfer_addresses(&self, request: WalletRequest) -> Result<Vec<String>> {
         self.client
             .transfer_addresses(&request.name, &request.passphrase)
-            .map(|addresses| addresses.iter().map(ToString::to_string).collect())
-            .map_err(to_rpc_error)
+            .map_err(to_rpc_error)?
+            .iter()
+            .map(|address| self.encode_address(address))
+            .collect()
     }
 
     fn send_to_address(
@@ -162,8 +186,8 @@ where
         amount: Coin,
         view_keys: Vec<String>,
     ) -> Result<String> {
-        let address = to_address
-            .parse::<ExtendedAddr>()
+        let chain_params = self.client.chain_params();
+        let address = ExtendedAddr::from_cro_for(&to_address, chain_params.network())
             .map_err(|err| rpc_error_from_string(format!("{}", err)))?;
         let tx_out = TxOut::new(address, amount);
 
@@ -190,7 +214,7 @@ where
             });
         }
 
-        let attributes = TxAttributes::new_with_access(self.network_id, access_policies);
+        let attributes = TxAttributes::new_with_access(chain_params.network_id(), access_policies);
 
         let return_address = self
             .client
@@ -222,10 +246,21 @@ where
         }
     }
 
-    fn transactions(&self, request: WalletRequest) -> Result<Vec<TransactionChange>> {
+    fn transactions(&self, request: WalletRequest) -> Result<Vec<WalletTransaction>> {
         self.client
             .history(&request.name, &request.passphrase)
-            .map_err(to_rpc_error)
+            .map_err(to_rpc_error)?
+            .into_iter()
+            .map(|change: TransactionChange| {
+                Ok(WalletTransaction {
+                    transaction_id: hex::encode(&change.transaction_id),
+                    address: self.encode_address(&change.address)?,
+                    balance_change: change.balance_change,
+                    block_height: change.block_height,
+                    block_time: change.block_time,
+                })
+            })
+            .collect()
     }
 }
 
@@ -240,6 +275,7 @@ pub mod tests {
     use parity_scale_codec::Encode;
 
     use chain_core::init::coin::CoinError;
+    use chain_core::init::network::{ChainParams, Network};
     use chain_core::tx::data::input::{TxoIndex, TxoPointer};
     use chain_core::tx::data::{Tx, TxId};
     use chain_core::tx::fee::{Fee, FeeAlgorithm};
@@ -457,7 +493,7 @@ pub mod tests {
             unreachable!("broadcast_transaction")
         }
 
-        fn query(&self, _path: &str, _data: &[u8]) -> CommonResult<QueryResult> {
+        fn query(&self, _path: &str, _data: &[u8], _prove: bool) -> CommonResult<QueryResult> {
             unreachable!("query")
         }
     }
@@ -593,6 +629,34 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn transfer_addresses_should_be_encoded_for_the_wallet_network() {
+        let wallet_client = make_test_wallet_client(
+            MemoryStorage::default(),
+            ChainParams::new(Network::Testnet, 0x42),
+        );
+        let wallet_rpc = WalletRpcImpl::new(wallet_client);
+        let wallet_request = create_wallet_request("Default", "123456");
+
+        wallet_rpc.create(wallet_request.clone()).unwrap();
+        let address = wallet_rpc
+            .create_transfer_address(wallet_request.clone())
+            .unwrap();
+        assert!(address.starts_with("tcro1"));
+        assert!(wallet_rpc
+            .list_transfer_addresses(wallet_request.clone())
+            .unwrap()
+            .contains(&address));
+        let devnet_address = ExtendedAddr::from_cro_for(&address, Network::Testnet)
+            .unwrap()
+            .to_cro_for(Network::Devnet)
+            .unwrap();
+        let error = wallet_rpc
+            .send_to_address(wallet_request, devnet_address, Coin::unit(), vec![])
+            .unwrap_err();
+        assert_eq!(error.message, "Address belonging to different network");
+    }
+
     #[test]
     fn get_view_key_should_return_public_key() {
         let wallet_rpc = setup_wallet_rpc();
@@ -637,16 +701,15 @@ pub mod tests {
         let wallet_request = create_wallet_request("Default", "123456");
 
         wallet_rpc.create(wallet_request.clone()).unwrap();
-        assert_eq!(
-            1,
-            wallet_rpc
-                .transactions(wallet_request.clone())
-                .unwrap()
-                .len()
-        )
+        let transactions = wallet_rpc.transactions(wallet_request.clone()).unwrap();
+        assert_eq!(1, transactions.len());
+        assert!(transactions[0].address.starts_with("dcro1"));
     }
 
-    fn make_test_wallet_client(storage: MemoryStorage) -> TestWalletClient {
+    fn make_test_wallet_client(
+        storage: MemoryStorage,
+        chain_params: ChainParams,
+    ) -> TestWalletClient {
         let signer = DefaultSigner::new(storage.clone());
         DefaultWalletClient::builder()
             .with_wallet(storage)
@@ -656,6 +719,7 @@ pub mod tests {
                 ZeroFeeAlgorithm::default(),
                 MockTransactionCipher,
             ))
+            .with_chain_params(chain_params)
             .build()
             .unwrap()
     }
@@ -663,10 +727,10 @@ pub mod tests {
     fn setup_wallet_rpc() -> WalletRpcImpl<TestWalletClient> {
         let storage = MemoryStorage::default();
 
-        let wallet_client = make_test_wallet_client(storage.clone());
-        let chain_id = 171u8;
+        let chain_params = ChainParams::new(Network::Devnet, 171);
+        let wallet_client = make_test_wallet_client(storage.clone(), chain_params);
 
-        WalletRpcImpl::new(wallet_client, chain_id)
+        WalletRpcImpl::new(wallet_client)
     }
 
     fn create_wallet_request(name: &str, passphrase: &str) -> WalletRequest {
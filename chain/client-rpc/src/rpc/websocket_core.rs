@@ -66,6 +66,7 @@ where
     wallets: Vec<WalletInfo>,
     current_wallet: usize,
     wallet_client: T,
+    legacy_tx_height: u64,
 }
 
 impl<S, C, H, T> WebsocketCore<S, C, H, T>
@@ -82,6 +83,7 @@ where
         block_handler: H,
         wallets: WalletInfos,
         wallet_client: T,
+        legacy_tx_height: u64,
     ) -> Self {
         let gss = GlobalStateService::new(storage);
 
@@ -105,6 +107,7 @@ where
             current_wallet: 0,
 
             wallet_client,
+            legacy_tx_height,
         }
     }
 
@@ -152,6 +155,7 @@ where
 
         let transaction_ids = block_results.transaction_ids()?;
         let block_filter = block_results.block_filter()?;
+        let tx_filters = block_results.tx_filters()?;
 
         let wallet = self.get_current_wallet();
         let unencrypted_transactions = self.check_unencrypted_transactions(
@@ -166,6 +170,7 @@ where
             block_time,
             transaction_ids,
             block_filter,
+            tx_filters,
             unencrypted_transactions,
         };
 
@@ -387,7 +392,7 @@ where
     ) -> Result<Vec<Transaction>> {
         for staking_address in staking_addresses {
             if block_filter.check_staked_state_address(staking_address) {
-                return block.unencrypted_transactions();
+                return block.unencrypted_transactions(self.legacy_tx_height);
             }
         }
 
@@ -95,6 +95,7 @@ impl WebsocketRpc {
         storage: S,
         block_handler: H,
         wallet_client: T,
+        legacy_tx_height: u64,
     ) {
         let channel = mpsc::channel(0);
         // tx, rx
@@ -109,6 +110,7 @@ impl WebsocketRpc {
             block_handler,
             wallets,
             wallet_client,
+            legacy_tx_height,
         );
         // save send_queue to communicate with core
         self.core = Some(core.get_queue());
@@ -9,6 +9,7 @@ use chain_core::tx::fee::LinearFee;
 use client_common::error::{Error, ErrorKind, Result};
 use client_common::storage::SledStorage;
 use client_common::tendermint::{Client, RpcClient};
+use client_common::TrustedNode;
 use client_core::signer::DefaultSigner;
 use client_core::transaction_builder::DefaultTransactionBuilder;
 use client_core::wallet::DefaultWalletClient;
@@ -25,16 +26,20 @@ use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use std::thread;
 
-use chain_core::init::network::{
-    get_network, get_network_id, init_chain_id, MAINNET_CHAIN_ID, TESTNET_CHAIN_ID,
-};
+use chain_core::init::network::{ChainParams, Network};
 type AppSigner = DefaultSigner<SledStorage>;
 type AppIndex = DefaultIndex<SledStorage, RpcClient>;
 type AppTransactionCipher = MockAbciTransactionObfuscation<RpcClient>;
 type AppTxBuilder = DefaultTransactionBuilder<AppSigner, LinearFee, AppTransactionCipher>;
 type AppWalletClient = DefaultWalletClient<SledStorage, AppIndex, AppTxBuilder>;
-type AppOpsClient =
-    DefaultNetworkOpsClient<AppWalletClient, AppSigner, RpcClient, LinearFee, AppTransactionCipher>;
+type AppOpsClient = DefaultNetworkOpsClient<
+    AppWalletClient,
+    AppSigner,
+    RpcClient,
+    LinearFee,
+    AppTransactionCipher,
+    TrustedNode<RpcClient>,
+>;
 type AppTransactionHandler = DefaultTransactionHandler<SledStorage>;
 type AppBlockHandler =
     DefaultBlockHandler<AppTransactionCipher, AppTransactionHandler, SledStorage>;
@@ -43,10 +48,12 @@ use websocket::OwnedMessage;
 pub(crate) struct Server {
     host: String,
     port: u16,
-    network_id: u8,
+    chain_params: ChainParams,
     storage_dir: String,
     tendermint_url: String,
+    trusted_tendermint_url: String,
     websocket_url: String,
+    legacy_tx_height: u64,
     websocket_queue: Option<std::sync::mpsc::Sender<OwnedMessage>>,
 }
 
@@ -55,27 +62,32 @@ impl Server {
         let network_id =
             hex::decode(&options.network_id).context(ErrorKind::SerializationError)?[0];
         let network_type = options.network_type;
-        if network_type.len() < 4 {
-            init_chain_id(&format!("dev-{}", options.network_id))
+        let network = if network_type.len() < 4 {
+            Network::Devnet
         } else {
             match &network_type[..4] {
-                "main" => init_chain_id(MAINNET_CHAIN_ID),
-                "test" => init_chain_id(TESTNET_CHAIN_ID),
-                _ => init_chain_id(&format!("dev-{}", options.network_id)),
+                "main" => Network::Mainnet,
+                "test" => Network::Testnet,
+                _ => Network::Devnet,
             }
-        }
+        };
+        let chain_params = ChainParams::new(network, network_id);
         println!(
             "Network type {:?}  id {:02X}",
-            get_network(),
-            get_network_id()
+            chain_params.network(),
+            chain_params.network_id()
         );
         Ok(Server {
             host: options.host,
             port: options.port,
-            network_id,
+            chain_params,
             storage_dir: options.storage_dir,
+            trusted_tendermint_url: options
+                .trusted_tendermint_url
+                .unwrap_or_else(|| options.tendermint_url.clone()),
             tendermint_url: options.tendermint_url,
             websocket_url: options.websocket_url,
+            legacy_tx_height: options.legacy_tx_height,
             websocket_queue: None,
         })
     }
@@ -94,6 +106,7 @@ impl Server {
             .with_wallet(storage)
             .with_transaction_read(index)
             .with_transaction_write(transaction_builder)
+            .with_chain_params(self.chain_params)
             .build()
             .unwrap()
     }
@@ -110,6 +123,7 @@ impl Server {
             tendermint_client,
             fee_algorithm,
             transaction_cipher,
+            TrustedNode::new(RpcClient::new(&self.trusted_tendermint_url)),
         )
     }
 
@@ -121,6 +135,7 @@ impl Server {
             DefaultBlockHandler::new(transaction_cipher, transaction_handler, storage.clone());
 
         ManualSynchronizer::new(storage, tendermint_client, block_handler)
+            .with_legacy_tx_height(self.legacy_tx_height)
     }
     pub fn start_websocket(&mut self, storage: SledStorage) -> Result<()> {
         log::info!("start_websocket");
@@ -142,6 +157,7 @@ impl Server {
             storage.clone(),
             block_handler,
             wallet_client,
+            self.legacy_tx_height,
         );
         assert!(web.core.is_some());
         self.websocket_queue = Some(web.core.as_mut().unwrap().clone());
@@ -159,12 +175,11 @@ impl Server {
         let multisig_rpc_wallet_client = self.make_wallet_client(storage.clone());
         let multisig_rpc = MultiSigRpcImpl::new(multisig_rpc_wallet_client);
 
-        let transaction_rpc = TransactionRpcImpl::new(self.network_id);
+        let transaction_rpc = TransactionRpcImpl::new(self.chain_params);
 
         let staking_rpc_wallet_client = self.make_wallet_client(storage.clone());
         let ops_client = self.make_ops_client(storage.clone());
-        let staking_rpc =
-            StakingRpcImpl::new(staking_rpc_wallet_client, ops_client, self.network_id);
+        let staking_rpc = StakingRpcImpl::new(staking_rpc_wallet_client, ops_client);
 
         let sync_rpc_wallet_client = self.make_wallet_client(storage.clone());
         let synchronizer = self.make_synchronizer(storage.clone());
@@ -174,7 +189,7 @@ impl Server {
         let sync_rpc = SyncRpcImpl::new(sync_rpc_wallet_client, synchronizer, Some(newone));
 
         let wallet_rpc_wallet_client = self.make_wallet_client(storage.clone());
-        let wallet_rpc = WalletRpcImpl::new(wallet_rpc_wallet_client, self.network_id);
+        let wallet_rpc = WalletRpcImpl::new(wallet_rpc_wallet_client);
 
         io.extend_with(multisig_rpc.to_delegate());
         io.extend_with(transaction_rpc.to_delegate());
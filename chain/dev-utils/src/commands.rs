@@ -5,7 +5,9 @@ mod run_command;
 mod stop_command;
 
 pub use genesis_command::GenesisCommand;
-pub use genesis_dev_config::{GenesisDevConfig, InitialFeePolicy};
+pub use genesis_dev_config::{
+    GenesisDevConfig, InitialFeePolicy, InitialRewardsConfig, InitialSlashingConfig,
+};
 pub use init_command::InitCommand;
 pub use run_command::RunCommand;
 pub use stop_command::StopCommand;
@@ -4,12 +4,14 @@ use failure::{format_err, Error, ResultExt};
 use hex::encode_upper;
 use structopt::StructOpt;
 
+use chain_abci::app::ChainNodeState;
 use chain_abci::storage::account::{AccountStorage, AccountWrapper};
 use chain_abci::storage::tx::StarlingFixedKey;
 use chain_abci::storage::Storage;
-use chain_core::common::MerkleTree;
 use chain_core::compute_app_hash;
-use chain_core::init::config::{AccountType, InitNetworkParameters};
+use chain_core::init::config::{
+    AccountType, InitNetworkParameters, RewardsParameters, SlashRatio, SlashingParameters,
+};
 use chain_core::init::{address::RedeemAddress, coin::Coin, config::InitConfig};
 use chain_core::state::account::StakedState;
 use chain_core::tx::fee::{LinearFee, Milli};
@@ -58,10 +60,32 @@ impl GenesisCommand {
         let coefficient_fee = Milli::from_str(&genesis_dev.initial_fee_policy.per_byte_fee)
             .context(format_err!("Invalid per byte fee"))?;
         let fee_policy = LinearFee::new(constant_fee, coefficient_fee);
+        let slashing = &genesis_dev.slashing_config;
+        let byzantine_slash_ratio = Milli::from_str(&slashing.byzantine_slash_ratio)
+            .ok()
+            .and_then(SlashRatio::new)
+            .ok_or_else(|| format_err!("Invalid byzantine slash ratio"))?;
+        let liveness_slash_ratio = Milli::from_str(&slashing.liveness_slash_ratio)
+            .ok()
+            .and_then(SlashRatio::new)
+            .ok_or_else(|| format_err!("Invalid liveness slash ratio"))?;
+        let reward_rate = Milli::from_str(&genesis_dev.rewards_config.reward_rate)
+            .context(format_err!("Invalid reward rate"))?;
         let params = InitNetworkParameters {
             initial_fee_policy: fee_policy,
             required_council_node_stake: genesis_dev.required_council_node_stake,
             unbonding_period: genesis_dev.unbonding_period,
+            slashing_config: SlashingParameters {
+                jail_duration: slashing.jail_duration,
+                byzantine_slash_ratio,
+                liveness_slash_ratio,
+                block_signing_window: slashing.block_signing_window,
+                missed_block_threshold: slashing.missed_block_threshold,
+            },
+            rewards_config: RewardsParameters {
+                epoch_length: genesis_dev.rewards_config.epoch_length,
+                reward_rate,
+            },
         };
         let config = InitConfig::new(
             dist,
@@ -70,10 +94,12 @@ impl GenesisCommand {
             genesis_dev.long_term_incentive,
             params,
             genesis_dev.council_nodes.clone(),
+            genesis_dev.vesting.clone(),
+            genesis_dev.initial_utxos.clone(),
         );
         let result = config.validate_config_get_genesis(genesis_dev.genesis_time.timestamp());
-        if let Ok((accounts, rp, _nodes)) = result {
-            let tx_tree = MerkleTree::empty();
+        if let Ok((accounts, rp, nodes)) = result {
+            let tx_tree = config.get_genesis_tx_tree();
             let mut account_tree =
                 AccountStorage::new(Storage::new_db(Arc::new(create(1))), 20).expect("account db");
 
@@ -85,7 +111,21 @@ impl GenesisCommand {
                 .insert(None, &mut keys, &wrapped)
                 .expect("initial insert");
 
-            let genesis_app_hash = compute_app_hash(&tx_tree, &new_account_root, &rp);
+            let genesis_state = ChainNodeState::genesis(
+                [0u8; 32],
+                genesis_dev.genesis_time.timestamp(),
+                new_account_root,
+                rp,
+                config.network_params.clone(),
+                nodes,
+            );
+            let genesis_app_hash = compute_app_hash(
+                &tx_tree,
+                &new_account_root,
+                &genesis_state.rewards_pool,
+                &genesis_state.network_params,
+                &genesis_state.node_state_hash(),
+            );
             println!("\"app_hash\": \"{}\",", encode_upper(genesis_app_hash));
             let config_str =
                 serde_json::to_string(&config).context(format_err!("Invalid config"))?;
@@ -1,5 +1,6 @@
-use chain_core::init::config::InitialValidator;
+use chain_core::init::config::{InitialValidator, VestingSchedule};
 use chain_core::init::{address::RedeemAddress, coin::Coin};
+use chain_core::tx::data::output::TxOut;
 use chrono::offset::Utc;
 use chrono::DateTime;
 use serde::{Deserialize, Serialize};
@@ -12,7 +13,13 @@ pub struct GenesisDevConfig {
     pub unbonding_period: u32,
     pub required_council_node_stake: Coin,
     pub initial_fee_policy: InitialFeePolicy,
+    pub slashing_config: InitialSlashingConfig,
+    pub rewards_config: InitialRewardsConfig,
     pub council_nodes: Vec<InitialValidator>,
+    #[serde(default)]
+    pub vesting: BTreeMap<RedeemAddress, VestingSchedule>,
+    #[serde(default)]
+    pub initial_utxos: Vec<TxOut>,
     pub launch_incentive_from: RedeemAddress,
     pub launch_incentive_to: RedeemAddress,
     pub long_term_incentive: RedeemAddress,
@@ -30,7 +37,20 @@ impl GenesisDevConfig {
                 base_fee: "1.1".to_string(),
                 per_byte_fee: "1.25".to_string(),
             },
+            slashing_config: InitialSlashingConfig {
+                jail_duration: 86400,
+                byzantine_slash_ratio: "0.200".to_string(),
+                liveness_slash_ratio: "0.010".to_string(),
+                block_signing_window: 100,
+                missed_block_threshold: 50,
+            },
+            rewards_config: InitialRewardsConfig {
+                epoch_length: 100,
+                reward_rate: "0.100".to_string(),
+            },
             council_nodes: vec![],
+            vesting: BTreeMap::new(),
+            initial_utxos: vec![],
             launch_incentive_from: RedeemAddress::from_str(
                 "0x35f517cab9a37bc31091c2f155d965af84e0bc85",
             )
@@ -53,3 +73,18 @@ pub struct InitialFeePolicy {
     pub base_fee: String,
     pub per_byte_fee: String,
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InitialSlashingConfig {
+    pub jail_duration: u32,
+    pub byzantine_slash_ratio: String,
+    pub liveness_slash_ratio: String,
+    pub block_signing_window: u16,
+    pub missed_block_threshold: u16,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InitialRewardsConfig {
+    pub epoch_length: u32,
+    pub reward_rate: String,
+}
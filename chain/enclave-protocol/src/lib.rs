@@ -19,9 +19,12 @@ use chain_core::state::account::DepositBondTx;
 use chain_core::state::account::StakedState;
 use chain_core::state::account::StakedStateOpWitness;
 use chain_core::state::account::WithdrawUnbondedTx;
+use chain_core::state::proof::AppHashParts;
+use chain_core::tx::data::access::TxAccess;
+use chain_core::tx::data::input::TxoIndex;
 use chain_core::tx::data::{txid_hash, Tx, TxId};
 use chain_core::tx::witness::TxWitness;
-use chain_core::tx::{fee::Fee, TxAux};
+use chain_core::tx::{fee::Fee, RedactedTx, TxAux};
 use chain_core::ChainInfo;
 use chain_tx_validation::TxWithOutputs;
 use secp256k1::{
@@ -39,10 +42,11 @@ const TOKEN_LEN: usize = 1024;
 pub enum EnclaveRequest {
     /// a sanity check (sends the chain network ID -- last byte / two hex digits convention)
     /// during InitChain or startup (to test one connected to the correct process)
-    /// and the last processed app hash
-    /// FIXME: test genesis hash etc.
+    /// and the last processed app hash (if there is none, the enclave accepts the genesis transaction
+    /// committed to in the genesis app hash)
     CheckChain {
         chain_hex_id: u8,
+        genesis_app_hash: H256,
         last_app_hash: Option<H256>,
     },
     /// "stateless" transaction validation requests (sends transaction + all required information)
@@ -66,6 +70,15 @@ pub enum EnclaveRequest {
     },
     /// request to get tx data sealed to "mrsigner" (requested by TDQE -- they should be on the same machine)
     GetSealedTxData { txids: Vec<TxId> },
+    /// request to store the synthetic genesis transaction (creating the initial UTXOs) during InitChain,
+    /// so that its outputs can be spent
+    /// (sends the genesis account trie root and the other app hash components,
+    /// so that the enclave can check the transaction is committed to in the genesis app hash)
+    StoreGenesisTx {
+        tx: Tx,
+        account_root: H256,
+        app_hash_parts: AppHashParts,
+    },
 }
 
 /// reponses sent from enclave wrapper server to chain-abci app
@@ -84,6 +97,9 @@ pub enum EnclaveResponse {
     UpdateCachedLaunchToken(Result<(), ()>),
     /// returns Some(sealed data payloads) or None (if any TXID was not found / invalid)
     GetSealedTxData(Option<Vec<Vec<u8>>>),
+    /// returns if the genesis transaction was stored (the enclave only accepts it once after InitChain
+    /// of a chain without any committed block)
+    StoreGenesisTx(Result<(), ()>),
     /// response if unsupported tx type is sent (e.g. unbondtx) -- TODO: probably unnecessary if there is a data type with a subset of TxAux
     UnsupportedTxType,
     /// response if the enclave failed to parse the request
@@ -231,10 +247,61 @@ impl Decode for DecryptionRequest {
     }
 }
 
+/// Transaction data a view key is allowed to see
+#[derive(Encode, Clone)]
+pub enum DecryptedTx {
+    /// the whole transaction (`TxAccess::AllData`)
+    Full(TxWithOutputs),
+    /// only the granted outputs (`TxAccess::Output`)
+    Redacted(RedactedTx),
+}
+
+impl Decode for DecryptedTx {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        // full transactions are the stored ones, which may be legacy transactions
+        match input.read_byte()? {
+            0 => Ok(DecryptedTx::Full(TxWithOutputs::decode_stored(input)?)),
+            1 => Ok(DecryptedTx::Redacted(RedactedTx::decode(input)?)),
+            _ => Err("No such variant in enum DecryptedTx".into()),
+        }
+    }
+}
+
+impl DecryptedTx {
+    /// Applies the transaction's access policies for the view key.
+    /// Returns None if the view key isn't listed in them (or was only granted non-existent outputs).
+    /// TODO: const eq?
+    pub fn filter(tx: TxWithOutputs, view_key: &PublicKey) -> Option<Self> {
+        let mut all_data = false;
+        let mut indices: Vec<u64> = Vec::new();
+        for policy in tx.attributes().allowed_view.iter() {
+            if policy.view_key != *view_key {
+                continue;
+            }
+            match policy.access {
+                TxAccess::AllData => all_data = true,
+                TxAccess::Output(index) => indices.push(index),
+            }
+        }
+        if all_data {
+            return Some(DecryptedTx::Full(tx));
+        }
+        indices.sort_unstable();
+        indices.dedup();
+        let disclosed: Vec<TxoIndex> = indices
+            .into_iter()
+            .filter(|index| *index < tx.outputs().len() as u64)
+            .map(|index| index as TxoIndex)
+            .collect();
+        // the outputs of the transactions that don't commit to them can't be disclosed separately
+        RedactedTx::new(&tx, &disclosed).map(DecryptedTx::Redacted)
+    }
+}
+
 /// Response in direct communication (over one-side attested TLS) from TDQE
 #[derive(Encode, Decode)]
 pub struct DecryptionResponse {
-    pub txs: Vec<TxWithOutputs>,
+    pub txs: Vec<DecryptedTx>,
 }
 
 #[cfg(test)]
@@ -253,6 +320,67 @@ pub mod tests {
         assert!(decoded_req.verify(&secp, [2u8; 32]).is_ok());
     }
 
+    #[test]
+    fn check_view_policies_filter() {
+        use chain_core::init::coin::Coin;
+        use chain_core::tx::data::access::TxAccessPolicy;
+        use chain_core::tx::data::address::ExtendedAddr;
+        use chain_core::tx::data::attribute::TxAttributes;
+        use chain_core::tx::data::output::TxOut;
+
+        let secp = Secp256k1::new();
+        let view_key =
+            |byte| PublicKey::from_secret_key(&secp, &SecretKey::from_slice(&[byte; 32]).unwrap());
+        let (full, partial, other) = (view_key(0xcd), view_key(0xce), view_key(0xcf));
+        let outputs = (0..3)
+            .map(|i| TxOut::new(ExtendedAddr::OrTree([i; 32]), Coin::one()))
+            .collect();
+        let tx = Tx::new_with(
+            Vec::new(),
+            outputs,
+            TxAttributes::new_with_access(
+                0,
+                vec![
+                    TxAccessPolicy::new(full.clone(), TxAccess::AllData),
+                    TxAccessPolicy::new(partial.clone(), TxAccess::Output(1)),
+                    TxAccessPolicy::new(partial.clone(), TxAccess::Output(7)),
+                ],
+            ),
+        );
+        let txid = tx.id();
+        let tx = TxWithOutputs::Transfer(tx);
+
+        match DecryptedTx::filter(tx.clone(), &full) {
+            Some(DecryptedTx::Full(full_tx)) => assert_eq!(txid, full_tx.id()),
+            _ => panic!("expected full transaction"),
+        }
+        match DecryptedTx::filter(tx.clone(), &partial) {
+            Some(DecryptedTx::Redacted(redacted)) => {
+                assert_eq!(txid, redacted.txid);
+                assert_eq!(1, redacted.outputs.len());
+                assert_eq!(1, redacted.outputs[0].0);
+                assert_eq!(ExtendedAddr::OrTree([1; 32]), redacted.outputs[0].1.address);
+                assert!(redacted.verify());
+                // the disclosed outputs can't be changed or moved
+                let mut changed = redacted.clone();
+                changed.outputs[0].1.value = Coin::zero();
+                assert!(!changed.verify());
+                let mut moved = redacted;
+                moved.outputs[0].0 = 2;
+                assert!(!moved.verify());
+            }
+            _ => panic!("expected redacted transaction"),
+        }
+        assert!(DecryptedTx::filter(tx.clone(), &other).is_none());
+        // transactions before the outputs commitment only disclose all data
+        let mut tx_v1 = match tx {
+            TxWithOutputs::Transfer(tx) => tx,
+            _ => unreachable!(),
+        };
+        tx_v1.attributes.version = 1;
+        assert!(DecryptedTx::filter(TxWithOutputs::Transfer(tx_v1), &partial).is_none());
+    }
+
     #[test]
     fn check_wrong_challenge_not_verify() {
         let secp = Secp256k1::new();
@@ -24,7 +24,7 @@ describe("Staking", () => {
 		client = newZeroFeeRpcClient();
 	});
 
-	it("should support staking, unbonding and withdrawing", async function() {
+	it("should support staking, unbonding, rebonding and withdrawing", async function() {
 		this.timeout(30000);
 		const defaultWalletRequest = newWalletRequest("Default", "123456");
 
@@ -124,6 +124,36 @@ describe("Staking", () => {
 			"Staking state is incorrect after unbond stake",
 		);
 
+		const rebondAmount = "1000";
+		console.log(`[Log] Rebond ${rebondAmount} base unit stake to staking address "${stakingAddress}"`);
+		const bondedAmountAfterRebond = new BigNumber(remainingBondedAmount)
+			.plus(rebondAmount)
+			.toString(10);
+		const unbondedAmountAfterRebond = new BigNumber(unbondAmount)
+			.minus(rebondAmount)
+			.toString(10);
+		await expect(
+			client.request("staking_rebondStake", [
+				walletRequest,
+				stakingAddress,
+				rebondAmount,
+			]),
+		).to.eventually.eq(null, "Rebond stake should work");
+		await sleep(1000);
+		const stakingStateAfterRebond = await client.request("staking_state", [
+			walletRequest,
+			stakingAddress,
+		]);
+		assertStakingState(
+			stakingStateAfterRebond,
+			{
+				address: stakingAddress,
+				bonded: bondedAmountAfterRebond,
+				unbonded: unbondedAmountAfterRebond,
+			},
+			"Staking state is incorrect after rebond stake",
+		);
+
 		console.log(
 			`[Log] Withdraw all unbonded stake from staking address "${stakingAddress}" to address "${transferAddress}"`,
 		);
@@ -160,7 +190,7 @@ describe("Staking", () => {
 			stakingStateAfterWithdraw,
 			{
 				address: stakingAddress,
-				bonded: remainingBondedAmount,
+				bonded: bondedAmountAfterRebond,
 				unbonded: "0",
 			},
 			"Staking state is incorrect after withdraw stake",
@@ -169,31 +199,48 @@ describe("Staking", () => {
 		return expect(
 			client.request("wallet_balance", [walletRequest]),
 		).to.eventually.deep.eq(
-			unbondAmount,
+			unbondedAmountAfterRebond,
 			"Wallet balance should be credited after withdraw stake",
 		);
 	});
 
 	const assertStakingState = (
 		actualState: StakingState,
-		expectedState: Omit<StakingState, "unbonded_from">,
+		expectedState: ExpectedStakingState,
 		errorMessage: string = "Staking state does not match",
 	) => {
+		const unbonded = actualState.unbonding
+			.reduce(
+				(sum, entry) => sum.plus(entry.amount),
+				new BigNumber(0),
+			)
+			.toString(10);
+		const actual = { ...actualState, unbonded };
 		Object.keys(expectedState).forEach((prop) => {
-			expect(actualState[prop]).to.deep.eq(
+			expect(actual[prop]).to.deep.eq(
 				expectedState[prop],
 				`${errorMessage}: "${prop}"`,
 			);
 		});
 	};
 
-	type Omit<T, K> = Pick<T, Exclude<keyof T, K>>;
+	interface UnbondingEntry {
+		amount: string;
+		release_time: number;
+	}
 
 	interface StakingState {
 		address?: string;
 		bonded?: string;
 		nonce?: number;
+		unbonding: UnbondingEntry[];
+	}
+
+	interface ExpectedStakingState {
+		address?: string;
+		bonded?: string;
+		nonce?: number;
+		// total amount of the unbonding entries
 		unbonded?: string;
-		unbonded_from: number;
 	}
 });
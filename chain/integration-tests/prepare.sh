@@ -139,6 +139,17 @@ DEV_CONF=$(cat << EOF
         "base_fee": "{BASE_FEE}",
         "per_byte_fee": "{PER_BYTE_FEE}"
     },
+    "slashing_config": {
+        "jail_duration": 60,
+        "byzantine_slash_ratio": "0.200",
+        "liveness_slash_ratio": "0.010",
+        "block_signing_window": 100,
+        "missed_block_threshold": 50
+    },
+    "rewards_config": {
+        "epoch_length": 100,
+        "reward_rate": "0.100"
+    },
     "council_nodes": [
         {
             "staking_account_address": "0x3ae55c16800dc4bd0e3397a9d7806fb1f11639de",
@@ -151,6 +151,7 @@ pub fn test_integration() {
         min_fee_computed: Fee::new(Coin::zero()),
         chain_hex_id: TEST_NETWORK_ID,
         previous_block_time: 1,
+        last_block_height: 0,
         unbonding_period: 0,
     };
 
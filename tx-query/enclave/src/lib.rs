@@ -11,12 +11,9 @@ extern crate sgx_tstd as std;
 use sgx_rand::*;
 use sgx_types::*;
 
-use chain_core::state::account::WithdrawUnbondedTx;
-use chain_core::tx::data::attribute::TxAttributes;
-use chain_core::tx::data::Tx;
 use chain_core::tx::data::TxId;
 use chain_core::tx::TxWithOutputs;
-use enclave_protocol::{DecryptionRequest, DecryptionRequestBody, DecryptionResponse};
+use enclave_protocol::{DecryptedTx, DecryptionRequest, DecryptionRequestBody, DecryptionResponse};
 use parity_scale_codec::{Decode, Encode};
 use secp256k1::{key::PublicKey, Secp256k1};
 use sgx_tseal::SgxSealedData;
@@ -49,7 +46,7 @@ fn check_unseal(
     view_key: PublicKey,
     txids: &[TxId],
     mut sealed_logs: Vec<Vec<u8>>,
-) -> Option<Vec<TxWithOutputs>> {
+) -> Option<Vec<DecryptedTx>> {
     let mut return_result = Vec::with_capacity(sealed_logs.len());
     for (txid, sealed_log) in txids.iter().zip(sealed_logs.iter_mut()) {
         if sealed_log.len() >= (std::u32::MAX as usize) {
@@ -74,35 +71,25 @@ fn check_unseal(
                 return None;
             }
         };
-        if unsealed_data.get_additional_txt() != txid {
-            unsealed_data.decrypt.zeroize();
-            return None;
-        }
-        let otx = TxWithOutputs::decode(&mut unsealed_data.get_decrypt_txt());
-        let push: bool;
-        match &otx {
-            Ok(TxWithOutputs::Transfer(Tx {
-                attributes: TxAttributes { allowed_view, .. },
-                ..
-            })) => {
-                // TODO: policy != alldata + const eq?
-                push = allowed_view.iter().any(|x| x.view_key == view_key);
-            }
-            Ok(TxWithOutputs::StakeWithdraw(WithdrawUnbondedTx {
-                attributes: TxAttributes { allowed_view, .. },
-                ..
-            })) => {
-                // TODO: policy != alldata + const eq?
-                push = allowed_view.iter().any(|x| x.view_key == view_key);
+        // sealed transactions are checked against the transaction ID in their additional data
+        // (and the ones sealed before the format was versioned are in the legacy layout)
+        let otx = TxWithOutputs::decode_unsealed(
+            unsealed_data.get_additional_txt(),
+            unsealed_data.get_decrypt_txt(),
+        )
+        .filter(|tx| tx.id() == *txid);
+        match otx {
+            Some(tx) => {
+                // only the parts allowed by the view key's access policies are returned
+                if let Some(decrypted) = DecryptedTx::filter(tx, &view_key) {
+                    return_result.push(decrypted);
+                }
             }
             _ => {
                 unsealed_data.decrypt.zeroize();
                 return None;
             }
         }
-        if push {
-            return_result.push(otx.unwrap());
-        }
         unsealed_data.decrypt.zeroize();
     }
     Some(return_result)
@@ -145,7 +132,7 @@ fn process_request(body: &DecryptionRequestBody) -> Option<DecryptionResponse> {
 /// 5. if OK, it processes it:
 /// - asks the tx-validation enclave to send back sealed transaction payloads
 /// - unseales the transactions and checks if the metadata contains the view key in the request
-/// - if so, it includes the transaction in the reply and sends it back
+/// - if so, it includes the transaction (or only the outputs the view key was granted access to) in the reply and sends it back
 #[no_mangle]
 pub extern "C" fn run_server(socket_fd: c_int) -> sgx_status_t {
     let mut sess = rustls::ServerSession::new(&attest::get_tls_config());
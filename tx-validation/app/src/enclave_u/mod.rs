@@ -4,8 +4,12 @@ use chain_core::common::H256;
 use chain_core::init::coin::Coin;
 use chain_core::state::account::DepositBondTx;
 use chain_core::state::account::StakedState;
+use chain_core::state::proof::AppHashParts;
+use chain_core::tx::data::Tx;
 use chain_core::tx::fee::Fee;
+use chain_core::tx::TransactionId;
 use chain_core::tx::TxAux;
+use chain_core::tx::TxWithOutputs;
 use chain_core::ChainInfo;
 use chain_tx_validation::Error;
 use enclave_u_common::enclave_u::TOKEN_LEN;
@@ -20,6 +24,8 @@ extern "C" {
         eid: sgx_enclave_id_t,
         retval: *mut sgx_status_t,
         chain_hex_id: u8,
+        genesis_app_hash: *const u8,
+        committed: u8,
     ) -> sgx_status_t;
 
     fn ecall_check_transfer_tx(
@@ -65,6 +71,17 @@ extern "C" {
         account_len: usize,
     ) -> sgx_status_t;
 
+    fn ecall_seal_genesis_tx(
+        eid: sgx_enclave_id_t,
+        retval: *mut sgx_status_t,
+        sealed_log: *mut u8,
+        sealed_log_size: u32,
+        tx: *const u8,
+        tx_len: usize,
+        account_root: *const u8,
+        app_hash_parts: *const u8,
+        app_hash_parts_len: usize,
+    ) -> sgx_status_t;
 }
 
 pub fn get_token(metadb: Arc<Tree>, token_key: &[u8]) -> Option<Vec<u8>> {
@@ -104,10 +121,20 @@ pub fn store_token(metadb: Arc<Tree>, token_key: &[u8], launch_token: Vec<u8>) -
 pub fn check_initchain(
     eid: sgx_enclave_id_t,
     chain_hex_id: u8,
+    genesis_app_hash: H256,
     last_app_hash: Option<H256>,
 ) -> Result<(), Option<H256>> {
     let mut retval: sgx_status_t = sgx_status_t::SGX_SUCCESS;
-    let result = unsafe { ecall_initchain(eid, &mut retval, chain_hex_id) };
+    let committed = last_app_hash.is_some() as u8;
+    let result = unsafe {
+        ecall_initchain(
+            eid,
+            &mut retval,
+            chain_hex_id,
+            genesis_app_hash.as_ptr(),
+            committed,
+        )
+    };
     if retval == sgx_status_t::SGX_SUCCESS && result == retval {
         Ok(())
     } else {
@@ -167,6 +194,9 @@ pub fn check_deposit_tx(
     info: ChainInfo,
     _txdb: Arc<Tree>,
 ) -> Result<(Fee, Option<StakedState>), Error> {
+    if let Some(true) = maccount.as_ref().map(StakedState::is_jailed) {
+        return Err(Error::AccountJailed);
+    }
     let txins_enc: Vec<u8> = txins.encode();
     let txaux_enc: Vec<u8> = txaux.encode();
     let info_enc: Vec<u8> = info.encode();
@@ -255,7 +285,7 @@ pub fn check_withdraw_tx(
         let fee = Fee::new(
             Coin::new(actual_fee_paid).expect("fee should not be larger than coin supply"),
         );
-        account.withdraw();
+        account.withdraw(info.previous_block_time);
         let _ = txdb
             .insert(&txaux.tx_id(), sealed_log)
             .map_err(|_| Error::IoError)?;
@@ -264,3 +294,40 @@ pub fn check_withdraw_tx(
         Err(Error::from(error_code))
     }
 }
+
+pub fn seal_genesis_tx(
+    eid: sgx_enclave_id_t,
+    tx: Tx,
+    account_root: H256,
+    app_hash_parts: AppHashParts,
+    txdb: Arc<Tree>,
+) -> Result<(), ()> {
+    let txid = tx.id();
+    let parts_enc = app_hash_parts.encode();
+    let otx = TxWithOutputs::Transfer(tx);
+    let tx_enc: Vec<u8> = otx.encode();
+    // the transaction ID (and the sealed format) is sealed as the additional (authenticated) data
+    let sealed_log_size =
+        size_of::<sgx_sealed_data_t>() + otx.sealed_additional_data().len() + tx_enc.len();
+    let mut sealed_log: Vec<u8> = vec![0u8; sealed_log_size];
+    let mut retval: sgx_status_t = sgx_status_t::SGX_SUCCESS;
+    let result = unsafe {
+        ecall_seal_genesis_tx(
+            eid,
+            &mut retval,
+            sealed_log.as_mut_ptr(),
+            sealed_log_size as u32,
+            tx_enc.as_ptr(),
+            tx_enc.len(),
+            account_root.as_ptr(),
+            parts_enc.as_ptr(),
+            parts_enc.len(),
+        )
+    };
+    if retval == sgx_status_t::SGX_SUCCESS && result == retval {
+        txdb.insert(&txid, sealed_log).map_err(|_| ())?;
+        Ok(())
+    } else {
+        Err(())
+    }
+}
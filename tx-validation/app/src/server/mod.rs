@@ -1,6 +1,6 @@
 use crate::enclave_u::{
     check_deposit_tx, check_initchain, check_transfertx, check_withdraw_tx, get_token_arr,
-    store_token,
+    seal_genesis_tx, store_token,
 };
 use chain_core::state::account::DepositBondTx;
 use chain_core::tx::data::TxId;
@@ -73,6 +73,7 @@ impl TxValidationServer {
                 let resp = match mcmd {
                     Ok(EnclaveRequest::CheckChain {
                         chain_hex_id,
+                        genesis_app_hash,
                         last_app_hash,
                     }) => {
                         debug!("check chain");
@@ -88,6 +89,7 @@ impl TxValidationServer {
                                     EnclaveResponse::CheckChain(check_initchain(
                                         self.enclave.geteid(),
                                         chain_hex_id,
+                                        genesis_app_hash,
                                         ss,
                                     ))
                                 } else {
@@ -175,6 +177,20 @@ impl TxValidationServer {
                             self.lookup_txids(txids.iter().map(|x| *x)),
                         )
                     }
+                    Ok(EnclaveRequest::StoreGenesisTx {
+                        tx,
+                        account_root,
+                        app_hash_parts,
+                    }) => {
+                        debug!("store genesis tx");
+                        EnclaveResponse::StoreGenesisTx(seal_genesis_tx(
+                            self.enclave.geteid(),
+                            tx,
+                            account_root,
+                            app_hash_parts,
+                            self.txdb.clone(),
+                        ))
+                    }
                     Ok(_) => {
                         debug!("verify other tx");
                         EnclaveResponse::UnsupportedTxType
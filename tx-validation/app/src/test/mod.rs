@@ -1,4 +1,4 @@
-use crate::enclave_u::{check_initchain, check_transfertx, check_withdraw_tx};
+use crate::enclave_u::{check_initchain, check_transfertx, check_withdraw_tx, seal_genesis_tx};
 use crate::enclave_u::{get_token, store_token};
 use chain_core::common::MerkleTree;
 use chain_core::init::address::RedeemAddress;
@@ -6,6 +6,7 @@ use chain_core::init::coin::Coin;
 use chain_core::state::account::{
     StakedState, StakedStateAddress, StakedStateOpWitness, WithdrawUnbondedTx,
 };
+use chain_core::state::proof::AppHashParts;
 use chain_core::tx::fee::Fee;
 use chain_core::tx::witness::tree::RawPubkey;
 use chain_core::tx::witness::EcdsaSignature;
@@ -93,7 +94,62 @@ pub fn test_sealing() {
             return;
         }
     };
-    assert!(check_initchain(enclave.geteid(), TEST_NETWORK_ID, None).is_ok());
+    let genesis_tx = Tx::new_with(
+        Vec::new(),
+        vec![TxOut::new(ExtendedAddr::OrTree([0; 32]), Coin::one())],
+        TxAttributes::new(0),
+    );
+    let genesis_parts = AppHashParts {
+        valid_tx_root: MerkleTree::new(vec![genesis_tx.id()]).root_hash(),
+        rewards_pool_hash: [1; 32],
+        network_params_hash: [2; 32],
+        node_state_hash: [5; 32],
+    };
+    let genesis_app_hash = genesis_parts.app_hash(&[3; 32]);
+    // a transaction not committed to in the genesis app hash isn't sealed
+    assert!(check_initchain(enclave.geteid(), TEST_NETWORK_ID, genesis_app_hash, None).is_ok());
+    assert!(seal_genesis_tx(
+        enclave.geteid(),
+        genesis_tx.clone(),
+        [4; 32],
+        genesis_parts.clone(),
+        txdb.clone()
+    )
+    .is_err());
+    // the genesis transaction is only sealed once
+    assert!(check_initchain(enclave.geteid(), TEST_NETWORK_ID, genesis_app_hash, None).is_ok());
+    assert!(seal_genesis_tx(
+        enclave.geteid(),
+        genesis_tx.clone(),
+        [3; 32],
+        genesis_parts.clone(),
+        txdb.clone()
+    )
+    .is_ok());
+    assert!(seal_genesis_tx(
+        enclave.geteid(),
+        genesis_tx.clone(),
+        [3; 32],
+        genesis_parts.clone(),
+        txdb.clone()
+    )
+    .is_err());
+    // or after a block was committed
+    assert!(check_initchain(
+        enclave.geteid(),
+        TEST_NETWORK_ID,
+        genesis_app_hash,
+        Some(genesis_app_hash)
+    )
+    .is_ok());
+    assert!(seal_genesis_tx(
+        enclave.geteid(),
+        genesis_tx,
+        [3; 32],
+        genesis_parts,
+        txdb.clone()
+    )
+    .is_err());
 
     let secp = Secp256k1::new();
     let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
@@ -128,6 +184,7 @@ pub fn test_sealing() {
         min_fee_computed: Fee::new(Coin::zero()),
         chain_hex_id: TEST_NETWORK_ID,
         previous_block_time: 1,
+        last_block_height: 0,
         unbonding_period: 0,
     };
     let tb = txdb.get(&txid);
@@ -8,7 +8,9 @@
 #[macro_use]
 extern crate sgx_tstd as std;
 
+use chain_core::common::{MerkleTree, H256};
 use chain_core::state::account::StakedState;
+use chain_core::state::proof::AppHashParts;
 use chain_core::tx::TransactionId;
 use chain_core::tx::{data::input::TxoIndex, PlainTxAux, TxAux, TxObfuscated};
 use chain_tx_validation::witness::verify_tx_recover_address;
@@ -17,26 +19,63 @@ use chain_tx_validation::{
     TxWithOutputs,
 };
 use enclave_macro::get_network_id;
+use lazy_static::lazy_static;
 use parity_scale_codec::{Decode, Encode, Error};
 use sgx_tseal::SgxSealedData;
 use sgx_types::{sgx_sealed_data_t, sgx_status_t};
 use std::prelude::v1::Vec;
 use std::slice;
+use std::sync::SgxMutex;
 
 const NETWORK_HEX_ID: u8 = get_network_id!();
 
-/// FIXME: genesis app_hash etc.
+lazy_static! {
+    /// the genesis app hash while the genesis transaction can be sealed:
+    /// set by InitChain of a chain without any committed block and cleared
+    /// once the genesis transaction is sealed or any transaction is verified
+    static ref GENESIS_APP_HASH: SgxMutex<Option<H256>> = SgxMutex::new(None);
+}
+
+/// checks the enclave was built for the network and, if no block was committed yet,
+/// allows sealing the genesis transaction committed to in the given genesis app hash
 #[no_mangle]
-pub extern "C" fn ecall_initchain(chain_hex_id: u8) -> sgx_status_t {
-    if chain_hex_id == NETWORK_HEX_ID {
-        sgx_status_t::SGX_SUCCESS
+pub extern "C" fn ecall_initchain(
+    chain_hex_id: u8,
+    genesis_app_hash: *const u8,
+    committed: u8,
+) -> sgx_status_t {
+    if chain_hex_id != NETWORK_HEX_ID {
+        return sgx_status_t::SGX_ERROR_INVALID_PARAMETER;
+    }
+    let mut genesis = match GENESIS_APP_HASH.lock() {
+        Ok(x) => x,
+        Err(_) => {
+            return sgx_status_t::SGX_ERROR_UNEXPECTED;
+        }
+    };
+    if committed == 0 {
+        let mut app_hash = [0u8; 32];
+        app_hash.copy_from_slice(unsafe { slice::from_raw_parts(genesis_app_hash, 32) });
+        *genesis = Some(app_hash);
     } else {
-        sgx_status_t::SGX_ERROR_INVALID_PARAMETER
+        *genesis = None;
     }
+    sgx_status_t::SGX_SUCCESS
+}
+
+/// returns the genesis app hash if the genesis transaction can still be sealed
+/// and closes that window (it's only sealed once and not after any transaction was verified)
+#[inline]
+fn take_genesis_app_hash() -> Option<H256> {
+    GENESIS_APP_HASH
+        .lock()
+        .ok()
+        .and_then(|mut genesis| genesis.take())
 }
 
 #[inline]
 fn check_chain_info(chain_info: *const u8, chain_info_len: usize) -> Option<ChainInfo> {
+    take_genesis_app_hash();
     let mut chain_info_slice = unsafe { slice::from_raw_parts(chain_info, chain_info_len) };
     let chain_info = ChainInfo::decode(&mut chain_info_slice);
     match chain_info {
@@ -71,12 +110,12 @@ fn unseal(sealed_log: &mut [u8]) -> Option<TxWithOutputs> {
             return None;
         }
     };
-    let otx = TxWithOutputs::decode(&mut unsealed_data.get_decrypt_txt());
-    // TODO: check decoded txid against unsealed_data.get_additional_txt?
-    match otx {
-        Ok(tx) => Some(tx),
-        _ => None,
-    }
+    // sealed transactions are checked against the transaction ID in their additional data
+    // (and the ones sealed before the format was versioned are in the legacy layout)
+    TxWithOutputs::decode_unsealed(
+        unsealed_data.get_additional_txt(),
+        unsealed_data.get_decrypt_txt(),
+    )
 }
 
 #[inline]
@@ -140,8 +179,9 @@ pub extern "C" fn ecall_check_transfer_tx(
                     }
                     return sgx_status_t::SGX_ERROR_INVALID_PARAMETER;
                 }
-                let to_seal = TxWithOutputs::Transfer(tx).encode();
-                let sealing_result = SgxSealedData::<[u8]>::seal_data(&txid, &to_seal);
+                let otx = TxWithOutputs::Transfer(tx);
+                let sealing_result =
+                    SgxSealedData::<[u8]>::seal_data(&otx.sealed_additional_data(), &otx.encode());
                 let sealed_data = match sealing_result {
                     Ok(x) => x,
                     Err(ret) => {
@@ -281,8 +321,9 @@ pub extern "C" fn ecall_check_withdraw_tx(
                     }
                     return sgx_status_t::SGX_ERROR_INVALID_PARAMETER;
                 }
-                let to_seal = TxWithOutputs::StakeWithdraw(tx).encode();
-                let sealing_result = SgxSealedData::<[u8]>::seal_data(&txid, &to_seal);
+                let otx = TxWithOutputs::StakeWithdraw(tx);
+                let sealing_result =
+                    SgxSealedData::<[u8]>::seal_data(&otx.sealed_additional_data(), &otx.encode());
                 let sealed_data = match sealing_result {
                     Ok(x) => x,
                     Err(ret) => {
@@ -310,3 +351,63 @@ pub extern "C" fn ecall_check_withdraw_tx(
         sgx_status_t::SGX_ERROR_INVALID_PARAMETER
     }
 }
+
+/// seals the synthetic genesis transaction (creating the initial UTXOs), so that its outputs can be spent:
+/// only once after InitChain of a chain without any committed block (see `ecall_initchain`)
+/// and only if it's the transaction committed to in the genesis app hash
+/// (the genesis app hash itself is provided by chain-abci, which checks it against its configuration during InitChain)
+#[no_mangle]
+pub extern "C" fn ecall_seal_genesis_tx(
+    sealed_log: *mut u8,
+    sealed_log_size: u32,
+    tx: *const u8,
+    tx_len: usize,
+    account_root: *const u8,
+    app_hash_parts: *const u8,
+    app_hash_parts_len: usize,
+) -> sgx_status_t {
+    let genesis_app_hash = match take_genesis_app_hash() {
+        Some(x) => x,
+        None => {
+            return sgx_status_t::SGX_ERROR_INVALID_STATE;
+        }
+    };
+    let mut account_root_hash = [0u8; 32];
+    account_root_hash.copy_from_slice(unsafe { slice::from_raw_parts(account_root, 32) });
+    let mut parts_slice = unsafe { slice::from_raw_parts(app_hash_parts, app_hash_parts_len) };
+    let parts = match AppHashParts::decode(&mut parts_slice) {
+        Ok(x) => x,
+        Err(_) => {
+            return sgx_status_t::SGX_ERROR_INVALID_PARAMETER;
+        }
+    };
+    let mut tx_slice = unsafe { slice::from_raw_parts(tx, tx_len) };
+    match TxWithOutputs::decode(&mut tx_slice) {
+        Ok(TxWithOutputs::Transfer(tx)) => {
+            if !tx.inputs.is_empty()
+                || parts.valid_tx_root != MerkleTree::new(vec![tx.id()]).root_hash()
+                || parts.app_hash(&account_root_hash) != genesis_app_hash
+            {
+                return sgx_status_t::SGX_ERROR_INVALID_PARAMETER;
+            }
+            let otx = TxWithOutputs::Transfer(tx);
+            let sealing_result =
+                SgxSealedData::<[u8]>::seal_data(&otx.sealed_additional_data(), &otx.encode());
+            let sealed_data = match sealing_result {
+                Ok(x) => x,
+                Err(ret) => {
+                    return ret;
+                }
+            };
+            let sealed_r = unsafe {
+                sealed_data
+                    .to_raw_sealed_data_t(sealed_log as *mut sgx_sealed_data_t, sealed_log_size)
+            };
+            if sealed_r.is_none() {
+                return sgx_status_t::SGX_ERROR_INVALID_PARAMETER;
+            }
+            sgx_status_t::SGX_SUCCESS
+        }
+        _ => sgx_status_t::SGX_ERROR_INVALID_PARAMETER,
+    }
+}